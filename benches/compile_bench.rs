@@ -0,0 +1,52 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use promptforge::Role::{Ai, FewShotPrompt, Human, Placeholder, System};
+use promptforge::{chats, examples, ChatTemplate, FewShotChatTemplate, FewShotTemplate};
+use std::collections::HashMap;
+use std::hint::black_box;
+
+fn build_chat_template() -> ChatTemplate {
+    let examples = examples!(
+        ("{input}: What is 2+2?", "{output}: 4"),
+        ("{input}: What is 2+3?", "{output}: 5"),
+        ("{input}: What is 3+3?", "{output}: 6")
+    );
+    let example_prompt =
+        ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+    let few_shot = FewShotChatTemplate::new(FewShotTemplate::new(examples), example_prompt);
+
+    ChatTemplate::from_messages(chats!(
+        System = "You are {assistant_name}, a helpful assistant for {company}.",
+        FewShotPrompt = few_shot.to_string(),
+        Placeholder = "{history}",
+        Human = "My name is {user_name}. {question}",
+    ))
+    .unwrap()
+}
+
+fn benchmark_compiled_chat_template(c: &mut Criterion) {
+    let chat_template = build_chat_template();
+    let compiled = chat_template.compile().unwrap();
+
+    let history = r#"[
+        {"role": "human", "content": "Hi there!"},
+        {"role": "ai", "content": "Hello! How can I help you today?"}
+    ]"#;
+
+    let mut variables = HashMap::new();
+    variables.insert("assistant_name", "Forge");
+    variables.insert("company", "Acme Corp");
+    variables.insert("history", history);
+    variables.insert("user_name", "Alice");
+    variables.insert("question", "What is 4+4?");
+
+    c.bench_function("format_messages uncompiled chat template", |b| {
+        b.iter(|| black_box(chat_template.format_messages(black_box(&variables))))
+    });
+
+    c.bench_function("format_messages compiled chat template", |b| {
+        b.iter(|| black_box(compiled.format_messages(black_box(&variables))))
+    });
+}
+
+criterion_group!(benches, benchmark_compiled_chat_template);
+criterion_main!(benches);
@@ -1,6 +1,7 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{criterion_group, criterion_main, Criterion};
 use handlebars::Handlebars;
 use std::collections::HashMap;
+use std::hint::black_box;
 
 fn benchmark_complex_handlebars_template(c: &mut Criterion) {
     let mut handlebars = Handlebars::new();
@@ -0,0 +1,284 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use promptforge_syntax::{
+    detection::{
+        check_source, escape_literals, line_column, DetectedFormat, DetectionError,
+        PROGRAMMING_LITERALS,
+    },
+    yaml,
+};
+use quote::quote;
+use regex::Regex;
+use serde_json::Value;
+use syn::{
+    bracketed,
+    parse::{Parse, ParseStream},
+    Ident, LitStr, Token,
+};
+
+// Mirrors `promptforge::LIBRARY_EXTENSIONS`.
+const EXTENSIONS: [&str; 4] = ["toml", "json", "yaml", "yml"];
+
+pub struct EmbedInput {
+    dir: LitStr,
+    programming: bool,
+    literals: Vec<LitStr>,
+}
+
+impl Parse for EmbedInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let dir = input.parse()?;
+        let mut embed = EmbedInput {
+            dir,
+            programming: false,
+            literals: Vec::new(),
+        };
+        if input.parse::<Option<Token![,]>>()?.is_none() || input.is_empty() {
+            return Ok(embed);
+        }
+
+        let key: Ident = input.parse()?;
+        if key != "literals" {
+            return Err(syn::Error::new(key.span(), "expected `literals = [...]`"));
+        }
+        input.parse::<Token![=]>()?;
+        let items;
+        bracketed!(items in input);
+        while !items.is_empty() {
+            if items.peek(LitStr) {
+                embed.literals.push(items.parse()?);
+            } else {
+                let preset: Ident = items.parse()?;
+                if preset != "programming" {
+                    return Err(syn::Error::new(
+                        preset.span(),
+                        "expected a string literal or `programming`",
+                    ));
+                }
+                embed.programming = true;
+            }
+            if items.parse::<Option<Token![,]>>()?.is_none() {
+                break;
+            }
+        }
+        input.parse::<Option<Token![,]>>()?;
+        Ok(embed)
+    }
+}
+
+impl EmbedInput {
+    // Mirrors the `BraceLiterals` the generated code builds.
+    fn patterns(&self) -> Vec<Regex> {
+        let presets = self.programming.then_some(PROGRAMMING_LITERALS);
+        presets
+            .into_iter()
+            .flatten()
+            .map(|pattern| Regex::new(pattern).unwrap())
+            .chain(
+                self.literals
+                    .iter()
+                    .map(|literal| Regex::new(&regex::escape(&literal.value())).unwrap()),
+            )
+            .collect()
+    }
+
+    fn brace_literals(&self) -> proc_macro2::TokenStream {
+        let literals = &self.literals;
+        let base = match self.programming {
+            true => quote! { ::promptforge::BraceLiterals::programming() },
+            false => quote! { ::promptforge::BraceLiterals::new() },
+        };
+        quote! { #base #(.with_literal(#literals))* }
+    }
+}
+
+struct EmbeddedFile {
+    name: String,
+    extension: String,
+    path: PathBuf,
+}
+
+pub fn expand(input: EmbedInput) -> syn::Result<proc_macro2::TokenStream> {
+    let dir = &input.dir;
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR")
+        .map_err(|_| syn::Error::new(dir.span(), "CARGO_MANIFEST_DIR is not set"))?;
+    let root = Path::new(&manifest_dir).join(dir.value());
+    let error = |message: String| syn::Error::new(dir.span(), message);
+    let patterns = input.patterns();
+
+    let mut files = prompt_files(&root).map_err(|e| {
+        error(format!(
+            "Failed to read prompt directory {}: {}",
+            root.display(),
+            e
+        ))
+    })?;
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut failures = Vec::new();
+    for (i, file) in files.iter().enumerate() {
+        if i > 0 && files[i - 1].name == file.name {
+            failures.push(format!("Duplicate prompt name '{}'", file.name));
+        }
+        let checked = fs::read_to_string(&file.path)
+            .map_err(|e| format!("Failed to read prompt file: {}", e))
+            .and_then(|content| check_file(&file.extension, &content, &patterns));
+        if let Err(message) = checked {
+            failures.push(format!("{}: {}", file.path.display(), message));
+        }
+    }
+    if !failures.is_empty() {
+        return Err(error(format!(
+            "{} prompt file(s) in {} are malformed:\n{}",
+            failures.len(),
+            root.display(),
+            failures.join("\n")
+        )));
+    }
+
+    // `include_str!` makes the build track edits to each file; files added
+    // to the directory are only picked up once the invoking crate rebuilds.
+    let entries = files.iter().map(|file| {
+        let name = &file.name;
+        let extension = &file.extension;
+        let path = file.path.display().to_string();
+        quote! { (#name, #extension, ::core::include_str!(#path)) }
+    });
+    let yaml_check = files.iter().any(|file| is_yaml(&file.extension)).then(|| {
+        quote! {
+            const _: () = ::core::assert!(
+                ::promptforge::library::YAML_SUPPORTED,
+                "embedded YAML prompt files need promptforge's `yaml` feature"
+            );
+        }
+    });
+    let literals = input.brace_literals();
+
+    Ok(quote! {
+        {
+            #yaml_check
+            static LIBRARY: ::std::sync::LazyLock<
+                ::core::result::Result<::promptforge::PromptLibrary, ::promptforge::TemplateError>,
+            > = ::std::sync::LazyLock::new(|| {
+                ::promptforge::PromptLibrary::from_embedded(&[#(#entries),*], &#literals)
+            });
+            ::core::result::Result::as_ref(&*LIBRARY).map_err(::core::clone::Clone::clone)
+        }
+    })
+}
+
+fn is_yaml(extension: &str) -> bool {
+    ["yaml", "yml"].contains(&extension.to_ascii_lowercase().as_str())
+}
+
+// Mirrors `promptforge::prompt_name`: `support/greeting.toml` under the root
+// becomes `support/greeting`.
+fn prompt_files(root: &Path) -> std::io::Result<Vec<EmbeddedFile>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let Some(extension) = path.extension().and_then(|e| e.to_str()) else {
+                continue;
+            };
+            if !EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+            {
+                continue;
+            }
+            let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+            let name = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.push(EmbeddedFile {
+                name,
+                extension: extension.to_string(),
+                path,
+            });
+        }
+    }
+    Ok(files)
+}
+
+fn check_file(extension: &str, content: &str, literals: &[Regex]) -> Result<(), String> {
+    let value: Value = match extension.to_ascii_lowercase().as_str() {
+        "json" => {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse JSON: {}", e))?
+        }
+        "toml" => {
+            let table: toml::Table =
+                toml::from_str(content).map_err(|e| format!("Failed to parse TOML: {}", e))?;
+            serde_json::to_value(table).map_err(|e| format!("Failed to parse TOML: {}", e))?
+        }
+        _ => yaml::parse(content)?,
+    };
+
+    let Value::Object(fields) = &value else {
+        return Err("A prompt file must hold a table of fields".to_string());
+    };
+    if !["template", "messages", "examples", "example_prompt"]
+        .iter()
+        .any(|key| fields.contains_key(*key))
+    {
+        return Err(
+            "Cannot tell the prompt type; expected a 'template', 'messages', \
+             'examples' or 'example_prompt' field"
+                .to_string(),
+        );
+    }
+    check_templates(&value, literals)
+}
+
+// Every `template` string in the file, at any depth, gets the checks a
+// `Template` runs on its source, honouring its declared format.
+fn check_templates(value: &Value, literals: &[Regex]) -> Result<(), String> {
+    match value {
+        Value::Object(fields) => {
+            if let Some(Value::String(template)) = fields.get("template") {
+                let declared = fields
+                    .get("template_format")
+                    .and_then(Value::as_str)
+                    .and_then(DetectedFormat::from_name);
+                check_template(template, declared, literals)
+                    .map_err(|message| format!("{} in template {:?}", message, template))?;
+            }
+            fields
+                .values()
+                .try_for_each(|value| check_templates(value, literals))
+        }
+        Value::Array(items) => items
+            .iter()
+            .try_for_each(|value| check_templates(value, literals)),
+        _ => Ok(()),
+    }
+}
+
+fn check_template(
+    template: &str,
+    declared: Option<DetectedFormat>,
+    literals: &[Regex],
+) -> Result<(), String> {
+    let escaped = escape_literals(template, literals);
+    match check_source(&escaped, declared) {
+        Ok(_) => Ok(()),
+        Err(DetectionError::Malformed(Some((offset, reason)))) => {
+            let (line, column) = line_column(&escaped, offset);
+            Err(format!("{} at line {}, column {}", reason, line, column))
+        }
+        Err(DetectionError::Malformed(None)) => Err("unbalanced braces".to_string()),
+        Err(DetectionError::Unsupported) => Err(
+            "unsupported template format; a placeholder may hold more than one word".to_string(),
+        ),
+    }
+}
@@ -0,0 +1,145 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use promptforge_syntax::variables::required_variables;
+use syn::{parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Lit, LitStr};
+
+mod embed;
+
+#[proc_macro_derive(PromptVars, attributes(prompt))]
+pub fn derive_prompt_vars(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+// `embed_prompts!("prompts/")` resolves the directory against the invoking
+// crate's manifest, checks every prompt file while compiling and evaluates
+// to a `Result<&'static PromptLibrary, TemplateError>` that is parsed on
+// first use. The build-time checks cover parsing, prompt types and template
+// syntax, but not everything building a template does (compiling Mustache
+// and Jinja sources, for one), so loading can still fail and every call
+// returns that same error. Brace literals
+// are given as `embed_prompts!("prompts/", literals = [programming, "{id}"])`,
+// where `programming` stands for `BraceLiterals::programming()`.
+#[proc_macro]
+pub fn embed_prompts(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as embed::EmbedInput);
+    embed::expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    name,
+                    "PromptVars can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                name,
+                "PromptVars can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut idents = Vec::new();
+    let mut var_names = Vec::new();
+    for field in fields {
+        let ident = field.ident.clone().expect("named field");
+        let mut var_name = LitStr::new(&ident.to_string(), ident.span());
+
+        for attr in field.attrs.iter().filter(|a| a.path().is_ident("prompt")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    var_name = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported prompt field attribute, expected `rename`"))
+                }
+            })?;
+        }
+
+        idents.push(ident);
+        var_names.push(var_name);
+    }
+
+    let mut template: Option<Expr> = None;
+    for attr in input.attrs.iter().filter(|a| a.path().is_ident("prompt")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("template") {
+                template = Some(meta.value()?.parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported prompt attribute, expected `template`"))
+            }
+        })?;
+    }
+
+    let names: Vec<String> = var_names.iter().map(LitStr::value).collect();
+    let coverage_check = match &template {
+        Some(Expr::Lit(ExprLit {
+            lit: Lit::Str(literal),
+            ..
+        })) => {
+            let template = literal.value();
+            let missing: Vec<&str> = required_variables(&template)
+                .into_iter()
+                .filter(|variable| !names.iter().any(|name| name == variable))
+                .collect();
+            if !missing.is_empty() {
+                return Err(syn::Error::new(
+                    literal.span(),
+                    format!(
+                        "{} is missing fields for template variables: {}",
+                        name,
+                        missing.join(", ")
+                    ),
+                ));
+            }
+            quote! {}
+        }
+        Some(path) => {
+            let message = LitStr::new(
+                &format!("{} does not cover every variable of its template", name),
+                Span::call_site(),
+            );
+            quote! {
+                const _: () = ::core::assert!(
+                    ::promptforge::fields_cover_template(#path, &[#(#var_names),*]),
+                    #message
+                );
+            }
+        }
+        None => quote! {},
+    };
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::promptforge::PromptVars for #name #ty_generics #where_clause {
+            const FIELDS: &'static [&'static str] = &[#(#var_names),*];
+
+            fn into_vars(self) -> ::std::collections::HashMap<::std::string::String, ::std::string::String> {
+                let mut vars = ::std::collections::HashMap::new();
+                #(
+                    vars.insert(
+                        ::std::string::String::from(#var_names),
+                        ::std::string::ToString::to_string(&self.#idents),
+                    );
+                )*
+                vars
+            }
+        }
+
+        #coverage_check
+    })
+}
@@ -0,0 +1,312 @@
+// Brace checks and template format detection, run by `Template` and by
+// `embed_prompts!`.
+
+use regex::Regex;
+use std::borrow::Cow;
+
+pub const ESCAPED_LEFT_BRACE: &str = "\\{";
+pub const ESCAPED_RIGHT_BRACE: &str = "\\}";
+const ESCAPE_MASK: &str = "\0\0";
+
+// Empty braces, positional `{0}` and format specs such as `{:?}` or `{0:>8}`.
+pub const PROGRAMMING_LITERALS: [&str; 3] = [r"\{\}", r"\{\d+\}", r"\{\d*[:!][^{}\s]*\}"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedFormat {
+    PlainText,
+    FmtString,
+    Mustache,
+    Jinja2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectionError {
+    // The byte offset and reason of the first brace that does not pair up,
+    // when one can be pinned down.
+    Malformed(Option<(usize, &'static str)>),
+    Unsupported,
+}
+
+impl DetectedFormat {
+    // The names `TemplateFormat` is serialized under.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "PlainText" => Some(DetectedFormat::PlainText),
+            "FmtString" => Some(DetectedFormat::FmtString),
+            "Mustache" => Some(DetectedFormat::Mustache),
+            "Jinja2" => Some(DetectedFormat::Jinja2),
+            _ => None,
+        }
+    }
+}
+
+// What a template source must pass before it is compiled. Without a declared
+// format Jinja is recognised first and any other format must be detectable;
+// braces must pair up either way.
+pub fn check_source(
+    source: &str,
+    declared: Option<DetectedFormat>,
+) -> Result<DetectedFormat, DetectionError> {
+    if declared.is_none() && is_jinja2(source) {
+        return Ok(DetectedFormat::Jinja2);
+    }
+    check_braces(source)?;
+    declared.map_or_else(|| classify(source), Ok)
+}
+
+pub fn check_braces(s: &str) -> Result<(), DetectionError> {
+    match is_valid_template(s) {
+        true => Ok(()),
+        false => Err(DetectionError::Malformed(find_brace_error(s))),
+    }
+}
+
+// Assumes the braces already pair up.
+pub fn classify(s: &str) -> Result<DetectedFormat, DetectionError> {
+    let masked = mask_escaped_braces(s);
+    if is_jinja2(s) {
+        Ok(DetectedFormat::Jinja2)
+    } else if is_plain_text(&masked) {
+        Ok(DetectedFormat::PlainText)
+    } else if is_mustache(&masked) {
+        Ok(DetectedFormat::Mustache)
+    } else if is_fmtstring(&masked) {
+        Ok(DetectedFormat::FmtString)
+    } else {
+        Err(DetectionError::Unsupported)
+    }
+}
+
+// One-based line and column, counted in characters, of a byte offset.
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+// Text that is already escaped is left alone, so escaping twice is harmless.
+pub fn escape_literals<'a>(source: &'a str, patterns: &[Regex]) -> Cow<'a, str> {
+    let mut matches: Vec<(usize, usize)> = patterns
+        .iter()
+        .flat_map(|pattern| pattern.find_iter(source))
+        .filter(|m| !source[..m.start()].ends_with('\\'))
+        .map(|m| (m.start(), m.end()))
+        .collect();
+    if matches.is_empty() {
+        return Cow::Borrowed(source);
+    }
+    matches.sort_unstable();
+
+    let mut escaped = String::with_capacity(source.len() + 8);
+    let mut last = 0;
+    for (start, end) in matches {
+        if start < last {
+            continue;
+        }
+        escaped.push_str(&source[last..start]);
+        escaped.push_str(&escape_braces(&source[start..end]));
+        last = end;
+    }
+    escaped.push_str(&source[last..]);
+    Cow::Owned(escaped)
+}
+
+pub fn has_escaped_braces(s: &str) -> bool {
+    s.contains(ESCAPED_LEFT_BRACE) || s.contains(ESCAPED_RIGHT_BRACE)
+}
+
+pub fn mask_escaped_braces(s: &str) -> Cow<'_, str> {
+    if !has_escaped_braces(s) {
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(
+        s.replace(ESCAPED_LEFT_BRACE, ESCAPE_MASK)
+            .replace(ESCAPED_RIGHT_BRACE, ESCAPE_MASK),
+    )
+}
+
+pub fn escape_braces(s: &str) -> Cow<'_, str> {
+    if !s.contains(['{', '}']) {
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(
+        s.replace('{', ESCAPED_LEFT_BRACE)
+            .replace('}', ESCAPED_RIGHT_BRACE),
+    )
+}
+
+pub fn unescape_braces(s: &str) -> Cow<'_, str> {
+    if !has_escaped_braces(s) {
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(
+        s.replace(ESCAPED_LEFT_BRACE, "{")
+            .replace(ESCAPED_RIGHT_BRACE, "}"),
+    )
+}
+
+pub fn find_brace_error(s: &str) -> Option<(usize, &'static str)> {
+    let s = mask_escaped_braces(s);
+    let bytes = s.as_bytes();
+    let mut style: Option<usize> = None;
+    let mut open: Option<(usize, usize)> = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+        if c != b'{' && c != b'}' {
+            i += 1;
+            continue;
+        }
+
+        let width = if bytes.get(i + 1) == Some(&c) { 2 } else { 1 };
+        match (c, open) {
+            (b'{', Some((start, _))) => return Some((start, "unclosed brace")),
+            (b'{', None) => open = Some((i, width)),
+            (_, None) => return Some((i, "unmatched closing brace")),
+            (_, Some((start, open_width))) if open_width != width => {
+                return Some((start, "mismatched brace widths"))
+            }
+            (_, Some((start, _))) => {
+                if style.is_some_and(|style| style != width) {
+                    return Some((start, "mixed single and double braces"));
+                }
+                style = Some(width);
+                open = None;
+            }
+        }
+        i += width;
+    }
+
+    open.map(|(start, _)| (start, "unclosed brace"))
+}
+
+pub fn has_multiple_words_between_braces(s: &str) -> bool {
+    let re = Regex::new(r"\{\{?\s*([^}]+)\s*\}?\}").unwrap();
+
+    if let Some(captures) = re.captures(s) {
+        let content = captures[1]
+            .split([':', '|'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+        let words: Vec<&str> = content.split_whitespace().collect();
+        return words.len() > 1;
+    }
+
+    false
+}
+
+pub fn count_left_braces(s: &str) -> usize {
+    s.matches("{").count()
+}
+
+pub fn count_right_braces(s: &str) -> usize {
+    s.matches("}").count()
+}
+
+pub fn has_even_left_braces(s: &str) -> bool {
+    count_left_braces(s).is_multiple_of(2)
+}
+
+pub fn has_even_right_braces(s: &str) -> bool {
+    count_right_braces(s).is_multiple_of(2)
+}
+
+pub fn has_left_brace(s: &str) -> bool {
+    count_left_braces(s) > 0
+}
+
+pub fn has_right_brace(s: &str) -> bool {
+    count_right_braces(s) > 0
+}
+
+pub fn has_consecutive_left_braces(s: &str) -> bool {
+    s.contains("{{")
+}
+
+pub fn has_consecutive_right_braces(s: &str) -> bool {
+    s.contains("}}")
+}
+
+pub fn has_only_single_braces(s: &str) -> bool {
+    has_left_brace(s)
+        && has_right_brace(s)
+        && !has_consecutive_left_braces(s)
+        && !has_consecutive_right_braces(s)
+}
+
+pub fn has_only_double_braces(s: &str) -> bool {
+    has_consecutive_left_braces(s)
+        && has_consecutive_right_braces(s)
+        && has_even_left_braces(s)
+        && has_even_right_braces(s)
+}
+
+pub fn has_no_braces(s: &str) -> bool {
+    !has_left_brace(s) && !has_right_brace(s)
+}
+
+pub fn is_plain_text(s: &str) -> bool {
+    has_no_braces(s)
+}
+
+pub fn is_mustache(s: &str) -> bool {
+    has_only_double_braces(s) && !has_multiple_words_between_braces(s)
+}
+
+pub fn is_fmtstring(s: &str) -> bool {
+    has_only_single_braces(s) && !has_multiple_words_between_braces(s)
+}
+
+pub fn is_jinja2(s: &str) -> bool {
+    s.contains("{%") || s.contains("{#")
+}
+
+pub fn is_valid_template(s: &str) -> bool {
+    let s = mask_escaped_braces(s);
+    let s = s.as_ref();
+
+    if has_no_braces(s) {
+        return true;
+    }
+
+    count_left_braces(s) == count_right_braces(s)
+        && (has_only_double_braces(s) || has_only_single_braces(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_source() {
+        assert_eq!(
+            check_source("Hi {name}", None),
+            Ok(DetectedFormat::FmtString)
+        );
+        assert_eq!(
+            check_source("{% if a %}{a}", None),
+            Ok(DetectedFormat::Jinja2)
+        );
+        assert_eq!(
+            check_source("Hi {first name}", Some(DetectedFormat::FmtString)),
+            Ok(DetectedFormat::FmtString)
+        );
+        assert_eq!(
+            check_source("Hi {first name}", None),
+            Err(DetectionError::Unsupported)
+        );
+        assert_eq!(
+            check_source("Hi\n {name", Some(DetectedFormat::FmtString)),
+            Err(DetectionError::Malformed(Some((4, "unclosed brace"))))
+        );
+        assert_eq!(line_column("Hi\n {name", 4), (2, 2));
+    }
+}
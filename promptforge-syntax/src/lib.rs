@@ -0,0 +1,7 @@
+// The parts of promptforge that its macros also run while compiling, so a
+// check made at build time cannot drift from the one made at runtime. Only
+// std, regex and serde_json may be used here, and errors are plain values.
+
+pub mod detection;
+pub mod variables;
+pub mod yaml;
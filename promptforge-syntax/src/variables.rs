@@ -0,0 +1,128 @@
+// The variables a template cannot render without, as `PromptVars` checks
+// them: placeholders with a default (`{tone:friendly}`) are optional, and
+// escaped braces are skipped. `const` so derived structs can be checked
+// against a template behind a `const` item.
+
+// The byte range of the first required variable at or after `from`.
+pub const fn next_required_variable(template: &str, from: usize) -> Option<(usize, usize)> {
+    let bytes = template.as_bytes();
+    let mut i = from;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\' {
+            i += 2;
+            continue;
+        }
+        if bytes[i] != b'{' {
+            i += 1;
+            continue;
+        }
+
+        while i < bytes.len() && bytes[i] == b'{' {
+            i += 1;
+        }
+        while i < bytes.len() && bytes[i] == b' ' {
+            i += 1;
+        }
+        let start = i;
+        while i < bytes.len()
+            && (bytes[i] == b'_'
+                || bytes[i].is_ascii_alphabetic()
+                || (i > start && bytes[i].is_ascii_digit()))
+        {
+            i += 1;
+        }
+
+        if i > start && i < bytes.len() && matches!(bytes[i], b'}' | b'|' | b' ') {
+            return Some((start, i));
+        }
+    }
+
+    None
+}
+
+pub const fn fields_cover_template(template: &str, fields: &[&str]) -> bool {
+    let bytes = template.as_bytes();
+    let mut from = 0;
+
+    while let Some((start, end)) = next_required_variable(template, from) {
+        if !has_field(bytes, start, end, fields) {
+            return false;
+        }
+        from = end;
+    }
+
+    true
+}
+
+const fn has_field(bytes: &[u8], start: usize, end: usize, fields: &[&str]) -> bool {
+    let mut f = 0;
+    while f < fields.len() {
+        let field = fields[f].as_bytes();
+        if field.len() == end - start {
+            let mut j = 0;
+            while j < field.len() && field[j] == bytes[start + j] {
+                j += 1;
+            }
+            if j == field.len() {
+                return true;
+            }
+        }
+        f += 1;
+    }
+    false
+}
+
+// In order of first appearance, without repeats.
+pub fn required_variables(template: &str) -> Vec<&str> {
+    let mut variables = Vec::new();
+    let mut from = 0;
+
+    while let Some((start, end)) = next_required_variable(template, from) {
+        let variable = &template[start..end];
+        if !variables.contains(&variable) {
+            variables.push(variable);
+        }
+        from = end;
+    }
+
+    variables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fields_cover_template() {
+        const FIELDS: &[&str] = &["name", "order_id"];
+
+        assert!(fields_cover_template(
+            "Hi {name}, order {order_id}.",
+            FIELDS
+        ));
+        assert!(fields_cover_template("Hi {{ name }}!", FIELDS));
+        assert!(fields_cover_template(
+            "{name|upper} {tone:friendly}",
+            FIELDS
+        ));
+        assert!(fields_cover_template(
+            "Literal \\{braces\\} and {name}",
+            FIELDS
+        ));
+        assert!(!fields_cover_template(
+            "Hi {name}, you owe {amount}.",
+            FIELDS
+        ));
+        assert!(!fields_cover_template("{names}", FIELDS));
+    }
+
+    #[test]
+    fn test_required_variables() {
+        assert_eq!(
+            required_variables("{name}, {{ amount }} {name|upper} {tone:x} \\{id\\}"),
+            ["name", "amount"]
+        );
+        assert!(required_variables("No placeholders").is_empty());
+    }
+}
@@ -0,0 +1,556 @@
+// The YAML reader behind promptforge's `from_yaml_str` and the YAML files
+// `embed_prompts!` checks. Errors are reported as text.
+
+use serde_json::{Map, Number, Value};
+
+pub fn parse(source: &str) -> Result<Value, String> {
+    Parser::new(source)?.parse_document()
+}
+
+fn yaml_error(line: usize, message: &str) -> String {
+    format!("YAML line {}: {}", line + 1, message)
+}
+
+pub fn resolve_plain(text: &str) -> Value {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let numeric = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+        && digits
+            .bytes()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'.' | b'e' | b'E' | b'-' | b'+'));
+    if !numeric {
+        return Value::String(text.to_string());
+    }
+    if let Ok(integer) = text.parse::<i64>() {
+        return Value::Number(integer.into());
+    }
+    if let Ok(integer) = text.parse::<u64>() {
+        return Value::Number(integer.into());
+    }
+    text.parse::<f64>()
+        .ok()
+        .and_then(Number::from_f64)
+        .map_or_else(|| Value::String(text.to_string()), Value::Number)
+}
+
+// Names the unsupported node property or alias a node starts with.
+fn unsupported_node(text: &str) -> Option<&'static str> {
+    match text.chars().next()? {
+        '&' => Some("anchors are not supported"),
+        '*' => Some("aliases are not supported"),
+        '!' => Some("tags are not supported"),
+        _ => None,
+    }
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+// Cuts a trailing comment; `#` only starts one outside quotes and after
+// whitespace.
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some('"'), '\\') => {
+                chars.next();
+            }
+            (Some(open), _) if c == open => quote = None,
+            (None, '#') if previous.is_whitespace() => return text[..index].trim_end(),
+            _ => {}
+        }
+        previous = c;
+    }
+    text.trim_end()
+}
+
+// The byte offset of the `:` separating a mapping key from its value.
+fn mapping_colon(text: &str) -> Option<usize> {
+    if text.starts_with("- ") || text == "-" {
+        return None;
+    }
+    let start = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let mut chars = text.char_indices().skip(1);
+            loop {
+                match chars.next()? {
+                    (_, '\\') if quote == '"' => {
+                        chars.next();
+                    }
+                    (index, c) if c == quote => {
+                        if quote == '\'' && text[index + 1..].starts_with('\'') {
+                            chars.next();
+                            continue;
+                        }
+                        break index + 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => 0,
+    };
+    text[start..]
+        .match_indices(':')
+        .map(|(index, _)| start + index)
+        .find(|&index| text[index + 1..].is_empty() || text[index + 1..].starts_with([' ', '\t']))
+}
+
+fn is_sequence_item(text: &str) -> bool {
+    text == "-" || text.starts_with("- ")
+}
+
+fn unescape_double(body: &str, line: usize) -> Result<String, String> {
+    let mut out = String::with_capacity(body.len());
+    let mut chars = body.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let hex = |chars: &mut std::str::Chars<'_>, digits: usize| {
+            let code: String = chars.take(digits).collect();
+            u32::from_str_radix(&code, 16)
+                .ok()
+                .filter(|_| code.len() == digits)
+                .ok_or_else(|| yaml_error(line, "invalid escape sequence"))
+        };
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('0') => '\0',
+            Some('a') => '\u{7}',
+            Some('v') => '\u{b}',
+            Some('e') => '\u{1b}',
+            Some(c @ ('"' | '\\' | '/' | ' ')) => c,
+            Some('x') => char::from_u32(hex(&mut chars, 2)?).unwrap_or('\u{fffd}'),
+            Some('U') => char::from_u32(hex(&mut chars, 8)?).unwrap_or('\u{fffd}'),
+            Some('u') => {
+                let high = hex(&mut chars, 4)?;
+                if (0xd800..0xdc00).contains(&high) {
+                    if chars.next() != Some('\\') || chars.next() != Some('u') {
+                        return Err(yaml_error(line, "unpaired surrogate escape"));
+                    }
+                    let low = hex(&mut chars, 4)?;
+                    char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low.wrapping_sub(0xdc00)))
+                        .unwrap_or('\u{fffd}')
+                } else {
+                    char::from_u32(high).unwrap_or('\u{fffd}')
+                }
+            }
+            _ => return Err(yaml_error(line, "invalid escape sequence")),
+        };
+        out.push(escaped);
+    }
+    Ok(out)
+}
+
+// Parses one quoted scalar at the start of `text`, returning it and the rest.
+fn quoted(text: &str, line: usize) -> Result<(String, &str), String> {
+    let quote = text.chars().next().unwrap_or('"');
+    let mut chars = text.char_indices().skip(1);
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            '\'' if quote == '\'' && text[index + 1..].starts_with('\'') => {
+                chars.next();
+            }
+            c if c == quote => {
+                let body = &text[1..index];
+                let value = if quote == '"' {
+                    unescape_double(body, line)?
+                } else {
+                    body.replace("''", "'")
+                };
+                return Ok((value, &text[index + 1..]));
+            }
+            _ => {}
+        }
+    }
+    Err(yaml_error(line, "unterminated quoted scalar"))
+}
+
+struct Flow<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+impl<'a> Flow<'a> {
+    fn skip_space(&mut self) {
+        self.text = self.text.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        match self.text.strip_prefix(c) {
+            Some(rest) => {
+                self.text = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_space();
+        match self.text.chars().next() {
+            Some('[') => {
+                self.text = &self.text[1..];
+                let mut items = Vec::new();
+                while !self.eat(']') {
+                    items.push(self.value()?);
+                    if !self.eat(',') && !self.text.trim_start().starts_with(']') {
+                        return Err(yaml_error(self.line, "expected ',' or ']'"));
+                    }
+                }
+                Ok(Value::Array(items))
+            }
+            // `{}` is how an empty mapping is written; anything inside the
+            // braces would be a flow mapping.
+            Some('{') => {
+                self.text = &self.text[1..];
+                match self.eat('}') {
+                    true => Ok(Value::Object(Map::new())),
+                    false => Err(yaml_error(self.line, "flow mappings are not supported")),
+                }
+            }
+            Some('"' | '\'') => {
+                let (value, rest) = quoted(self.text, self.line)?;
+                self.text = rest;
+                Ok(Value::String(value))
+            }
+            Some(_) => {
+                if let Some(message) = unsupported_node(self.text) {
+                    return Err(yaml_error(self.line, message));
+                }
+                let end = self
+                    .text
+                    .char_indices()
+                    .find(|&(_, c)| matches!(c, ',' | ']' | '}'))
+                    .map_or(self.text.len(), |(index, _)| index);
+                let plain = self.text[..end].trim();
+                self.text = &self.text[end..];
+                Ok(resolve_plain(plain))
+            }
+            None => Err(yaml_error(self.line, "unterminated flow collection")),
+        }
+    }
+}
+
+struct Parser {
+    lines: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> Result<Self, String> {
+        let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+        let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+        if let Some(first) = lines.iter().position(|line| !is_blank(line))
+            && lines[first].trim_end() == "---"
+        {
+            lines[first].clear();
+        }
+        if let Some(index) = lines
+            .iter()
+            .position(|line| line.trim_end() == "---" || line.trim_end() == "...")
+        {
+            if lines[index + 1..].iter().any(|line| !is_blank(line)) {
+                return Err(yaml_error(index, "multiple documents are not supported"));
+            }
+            lines.truncate(index);
+        }
+        if let Some(index) = lines.iter().position(|line| {
+            line[..indent_of(line)].len() < line.len() && line[indent_of(line)..].starts_with('\t')
+        }) {
+            return Err(yaml_error(index, "tabs are not allowed in indentation"));
+        }
+        Ok(Parser { lines, pos: 0 })
+    }
+
+    fn skip_blank(&mut self) {
+        while self.pos < self.lines.len() && is_blank(&self.lines[self.pos]) {
+            self.pos += 1;
+        }
+    }
+
+    fn current(&self) -> Option<(usize, &str)> {
+        self.lines
+            .get(self.pos)
+            .map(|line| (indent_of(line), &line[indent_of(line)..]))
+    }
+
+    fn parse_document(&mut self) -> Result<Value, String> {
+        self.skip_blank();
+        let Some((indent, _)) = self.current() else {
+            return Ok(Value::Null);
+        };
+        let value = self.parse_block(indent, 0)?;
+        self.skip_blank();
+        match self.current() {
+            Some(_) => Err(yaml_error(self.pos, "unexpected content")),
+            None => Ok(value),
+        }
+    }
+
+    // `min_indent` is how far a plain scalar's continuation lines must be
+    // indented to still belong to it.
+    fn parse_block(&mut self, indent: usize, min_indent: usize) -> Result<Value, String> {
+        let (_, content) = self.current().expect("caller checked for a line");
+        if is_sequence_item(content) {
+            self.parse_sequence(indent)
+        } else if mapping_colon(content).is_some() {
+            self.parse_mapping(indent)
+        } else {
+            let text = strip_comment(content).trim_start().to_string();
+            self.parse_scalar(text, min_indent)
+        }
+    }
+
+    // Parses the scalar starting on the current line and moves past it. A
+    // plain scalar folds each following line indented at least `min_indent`
+    // into one space, or into one newline per blank line in between, until
+    // a comment, a `key:` line or a shallower line ends it.
+    fn parse_scalar(&mut self, text: String, min_indent: usize) -> Result<Value, String> {
+        if text.starts_with(['[', '{', '"', '\'', '&', '*', '!']) {
+            let value = self.parse_inline(text)?;
+            self.pos += 1;
+            return Ok(value);
+        }
+
+        let line = &self.lines[self.pos];
+        let mut ended = strip_comment(line).len() < line.trim_end().len();
+        let mut text = text;
+        let mut next = self.pos + 1;
+        let mut blanks = 0;
+        self.pos += 1;
+        while !ended && let Some(line) = self.lines.get(next) {
+            let content = line.trim();
+            next += 1;
+            if content.is_empty() {
+                blanks += 1;
+                continue;
+            }
+            if indent_of(line) < min_indent
+                || content.starts_with('#')
+                || mapping_colon(content).is_some()
+            {
+                break;
+            }
+            let folded = strip_comment(content);
+            ended = folded.len() < content.len();
+            match blanks {
+                0 => text.push(' '),
+                _ => text.push_str(&"\n".repeat(blanks)),
+            }
+            text.push_str(folded);
+            blanks = 0;
+            self.pos = next;
+        }
+        Ok(resolve_plain(&text))
+    }
+
+    fn parse_inline(&self, text: String) -> Result<Value, String> {
+        let line = self.pos;
+        match text.chars().next() {
+            Some('[' | '{') => {
+                let mut flow = Flow { text: &text, line };
+                let value = flow.value()?;
+                match flow.text.trim() {
+                    "" => Ok(value),
+                    _ => Err(yaml_error(line, "unexpected text after flow collection")),
+                }
+            }
+            Some('"' | '\'') => {
+                let (value, rest) = quoted(&text, line)?;
+                match rest.trim() {
+                    "" => Ok(Value::String(value)),
+                    _ => Err(yaml_error(line, "unexpected text after quoted scalar")),
+                }
+            }
+            _ => match unsupported_node(&text) {
+                Some(message) => Err(yaml_error(line, message)),
+                None => Ok(resolve_plain(&text)),
+            },
+        }
+    }
+
+    // The value of a `key:` or `-` whose line has nothing after it.
+    fn parse_nested(&mut self, indent: usize, allow_sequence: bool) -> Result<Value, String> {
+        self.skip_blank();
+        match self.current() {
+            Some((child, _)) if child > indent => self.parse_block(child, indent + 1),
+            Some((child, content))
+                if allow_sequence && child == indent && is_sequence_item(content) =>
+            {
+                self.parse_sequence(indent)
+            }
+            _ => Ok(Value::Null),
+        }
+    }
+
+    fn parse_sequence(&mut self, indent: usize) -> Result<Value, String> {
+        let mut items = Vec::new();
+        loop {
+            self.skip_blank();
+            let Some((current, content)) = self.current() else {
+                break;
+            };
+            if current < indent || !is_sequence_item(content) {
+                break;
+            }
+            if current > indent {
+                return Err(yaml_error(self.pos, "bad indentation of a sequence item"));
+            }
+
+            let rest = &content[1..];
+            let item = strip_comment(rest.trim_start());
+            if item.is_empty() {
+                self.pos += 1;
+                items.push(self.parse_nested(indent, false)?);
+                continue;
+            }
+            // Re-read the rest of the line as a node of its own, so that
+            // `- key: value` starts a mapping at the item's column.
+            let column = indent + 1 + (rest.len() - rest.trim_start().len());
+            let rest = rest.trim_start().to_string();
+            self.lines[self.pos] = format!("{}{}", " ".repeat(column), rest);
+            items.push(self.parse_block(column, indent + 1)?);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn parse_mapping(&mut self, indent: usize) -> Result<Value, String> {
+        let mut map = Map::new();
+        loop {
+            self.skip_blank();
+            let Some((current, content)) = self.current() else {
+                break;
+            };
+            if current < indent {
+                break;
+            }
+            if current > indent {
+                return Err(yaml_error(self.pos, "bad indentation of a mapping entry"));
+            }
+            let Some(colon) = mapping_colon(content) else {
+                if is_sequence_item(content) {
+                    break;
+                }
+                return Err(yaml_error(self.pos, "expected 'key: value'"));
+            };
+
+            let (raw_key, rest) = (content[..colon].trim(), content[colon + 1..].to_string());
+            if let Some(message) = unsupported_node(raw_key) {
+                return Err(yaml_error(self.pos, message));
+            }
+            let key = match raw_key.chars().next() {
+                Some('"' | '\'') => quoted(raw_key, self.pos)?.0,
+                _ => raw_key.to_string(),
+            };
+            if map.contains_key(&key) {
+                return Err(yaml_error(self.pos, &format!("duplicate key '{}'", key)));
+            }
+
+            let rest = strip_comment(rest.trim_start()).to_string();
+            let value = if rest.is_empty() {
+                self.pos += 1;
+                self.parse_nested(indent, true)?
+            } else if rest.starts_with(['|', '>']) {
+                self.parse_block_scalar(&rest, indent)?
+            } else {
+                self.parse_scalar(rest, indent + 1)?
+            };
+            map.insert(key, value);
+        }
+        Ok(Value::Object(map))
+    }
+
+    fn parse_block_scalar(&mut self, header: &str, indent: usize) -> Result<Value, String> {
+        let header_line = self.pos;
+        let folded = header.starts_with('>');
+        let mut chomp = ' ';
+        for c in header[1..].chars() {
+            match c {
+                '-' | '+' => chomp = c,
+                '1'..='9' => {
+                    return Err(yaml_error(
+                        header_line,
+                        "block scalar indentation indicators are not supported",
+                    ))
+                }
+                _ => return Err(yaml_error(header_line, "invalid block scalar header")),
+            }
+        }
+        self.pos += 1;
+
+        let block_indent = self.lines[self.pos..]
+            .iter()
+            .find(|line| !line.trim().is_empty())
+            .map_or(indent + 1, |line| indent_of(line));
+        if block_indent <= indent {
+            return Ok(Value::String(String::new()));
+        }
+
+        let mut body = Vec::new();
+        while let Some(line) = self.lines.get(self.pos) {
+            if line.trim().is_empty() {
+                body.push(String::new());
+            } else if indent_of(line) >= block_indent {
+                body.push(line[block_indent..].to_string());
+            } else {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let trailing = body.iter().rev().take_while(|line| line.is_empty()).count();
+        let lines = &body[..body.len() - trailing];
+        let mut text = String::new();
+        if folded {
+            let mut previous_text = false;
+            for line in lines {
+                if line.is_empty() {
+                    text.push('\n');
+                    previous_text = false;
+                } else {
+                    if previous_text {
+                        text.push(' ');
+                    }
+                    text.push_str(line);
+                    previous_text = true;
+                }
+            }
+        } else {
+            text = lines.join("\n");
+        }
+
+        match chomp {
+            '-' => {}
+            '+' => text.push_str(&"\n".repeat(trailing + usize::from(!lines.is_empty()))),
+            _ if !lines.is_empty() => text.push('\n'),
+            _ => {}
+        }
+        Ok(Value::String(text))
+    }
+}
+
+fn is_blank(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
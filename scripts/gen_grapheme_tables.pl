@@ -0,0 +1,48 @@
+#!/usr/bin/env perl
+# Regenerates src/text/grapheme_tables.rs from the Unicode data bundled with
+# perl: perl scripts/gen_grapheme_tables.pl > src/text/grapheme_tables.rs
+use strict;
+use warnings;
+use Unicode::UCD;
+
+my @classes = qw(CR LF Control Extend ZWJ Regional_Indicator Prepend SpacingMark L V T);
+my %pattern = map { $_ => qr/\A\p{Grapheme_Cluster_Break=$_}\z/ } @classes;
+
+sub ranges {
+    my ($classify) = @_;
+    my @ranges;
+    for my $cp (0 .. 0x10FFFF) {
+        next if $cp >= 0xD800 && $cp <= 0xDFFF;
+        my $class = $classify->(chr $cp) // next;
+        if (@ranges && $ranges[-1][1] == $cp - 1 && $ranges[-1][2] eq $class) {
+            $ranges[-1][1] = $cp;
+        } else {
+            push @ranges, [ $cp, $cp, $class ];
+        }
+    }
+    return @ranges;
+}
+
+# Hangul syllables (LV and LVT) are computed in `length.rs` rather than listed.
+my @breaks = ranges(sub {
+    my ($c) = @_;
+    for my $class (@classes) {
+        return $class if $c =~ $pattern{$class};
+    }
+    return undef;
+});
+my @pictographic = ranges(sub { $_[0] =~ /\A\p{Extended_Pictographic}\z/ ? 'Pictographic' : undef });
+
+my %variant = (CR => 'Cr', LF => 'Lf', ZWJ => 'Zwj', Regional_Indicator => 'RegionalIndicator');
+my $version = Unicode::UCD::UnicodeVersion();
+print "// Generated by scripts/gen_grapheme_tables.pl from Unicode $version data. Do not edit.\n\n";
+print "use super::length::GraphemeBreak;\n\n";
+print "pub(crate) const GRAPHEME_BREAKS: &[(u32, u32, GraphemeBreak)] = &[\n";
+for my $r (@breaks) {
+    my $class = $variant{ $r->[2] } // $r->[2];
+    printf "    (0x%04X, 0x%04X, GraphemeBreak::%s),\n", $r->[0], $r->[1], $class;
+}
+print "];\n\n";
+print "pub(crate) const EXTENDED_PICTOGRAPHIC: &[(u32, u32)] = &[\n";
+printf "    (0x%04X, 0x%04X),\n", $_->[0], $_->[1] for @pictographic;
+print "];\n";
@@ -0,0 +1,264 @@
+use std::collections::BTreeMap;
+
+use messageforge::{BaseMessage, MessageEnum};
+use regex::Regex;
+
+use crate::{braces::escape_braces, is_valid_identifier, Template, TemplateError};
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Span(String),
+    Pattern(Regex),
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    matcher: Matcher,
+    variable: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ExampleAnonymizer {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnonymizedExample {
+    template: Template,
+    replacements: BTreeMap<String, String>,
+}
+
+impl AnonymizedExample {
+    pub fn template(&self) -> &Template {
+        &self.template
+    }
+
+    pub fn into_template(self) -> Template {
+        self.template
+    }
+
+    pub fn replacements(&self) -> &BTreeMap<String, String> {
+        &self.replacements
+    }
+}
+
+impl ExampleAnonymizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn span(
+        self,
+        text: impl Into<String>,
+        variable: impl Into<String>,
+    ) -> Result<Self, TemplateError> {
+        let text = text.into();
+        if text.is_empty() {
+            return Err(TemplateError::MalformedTemplate(
+                "Anonymized span must not be empty".to_string(),
+                None,
+            ));
+        }
+        self.rule(Matcher::Span(text), variable.into())
+    }
+
+    pub fn pattern(
+        self,
+        pattern: &str,
+        variable: impl Into<String>,
+    ) -> Result<Self, TemplateError> {
+        let regex = Regex::new(pattern).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Invalid anonymization pattern: {}", e), None)
+        })?;
+        self.rule(Matcher::Pattern(regex), variable.into())
+    }
+
+    fn rule(mut self, matcher: Matcher, variable: String) -> Result<Self, TemplateError> {
+        if !is_valid_identifier(&variable) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Invalid variable name: {}", variable),
+                None,
+            ));
+        }
+        self.rules.push(Rule { matcher, variable });
+        Ok(self)
+    }
+
+    pub fn anonymize(&self, messages: &[MessageEnum]) -> Result<AnonymizedExample, TemplateError> {
+        let mut placeholders: BTreeMap<(usize, String), String> = BTreeMap::new();
+        let mut replacements = BTreeMap::new();
+        let mut lines = Vec::with_capacity(messages.len());
+
+        for message in messages {
+            let content = message.content();
+            let mut matches: Vec<(usize, usize, usize)> = Vec::new();
+
+            for (index, rule) in self.rules.iter().enumerate() {
+                let found: Vec<(usize, usize)> = match &rule.matcher {
+                    Matcher::Span(text) => content
+                        .match_indices(text.as_str())
+                        .map(|(start, m)| (start, start + m.len()))
+                        .collect(),
+                    Matcher::Pattern(regex) => regex
+                        .find_iter(content)
+                        .filter(|m| !m.is_empty())
+                        .map(|m| (m.start(), m.end()))
+                        .collect(),
+                };
+
+                for (start, end) in found {
+                    let overlaps = matches.iter().any(|&(s, e, _)| start < e && s < end);
+                    if !overlaps {
+                        matches.push((start, end, index));
+                    }
+                }
+            }
+            matches.sort_unstable();
+
+            let mut line = format!("{}: ", message.role());
+            let mut last = 0;
+            for (start, end, index) in matches {
+                let value = &content[start..end];
+                let key = (index, value.to_string());
+                let placeholder = match placeholders.get(&key) {
+                    Some(placeholder) => placeholder.clone(),
+                    None => {
+                        let placeholder =
+                            self.next_placeholder(&self.rules[index].variable, &replacements);
+                        placeholders.insert(key, placeholder.clone());
+                        replacements.insert(placeholder.clone(), value.to_string());
+                        placeholder
+                    }
+                };
+
+                line.push_str(&escape_braces(&content[last..start]));
+                line.push_str(&format!("{{{}}}", placeholder));
+                last = end;
+            }
+            line.push_str(&escape_braces(&content[last..]));
+            lines.push(line);
+        }
+
+        Ok(AnonymizedExample {
+            template: Template::new(&lines.join("\n"))?,
+            replacements,
+        })
+    }
+
+    pub fn anonymize_all(
+        &self,
+        conversations: &[Vec<MessageEnum>],
+    ) -> Result<Vec<Template>, TemplateError> {
+        conversations
+            .iter()
+            .map(|messages| Ok(self.anonymize(messages)?.into_template()))
+            .collect()
+    }
+
+    fn next_placeholder(&self, variable: &str, taken: &BTreeMap<String, String>) -> String {
+        if !taken.contains_key(variable) {
+            return variable.to_string();
+        }
+
+        (2..)
+            .map(|n| format!("{}_{}", variable, n))
+            .find(|candidate| !taken.contains_key(candidate))
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Formattable, Templatable};
+    use messageforge::{AiMessage, HumanMessage};
+
+    fn conversation() -> Vec<MessageEnum> {
+        vec![
+            MessageEnum::Human(HumanMessage::new(
+                "Hi, I'm Jane Doe. Email me at jane@example.com or jd@work.org.",
+            )),
+            MessageEnum::Ai(AiMessage::new(
+                "Thanks Jane Doe, I'll write to jane@example.com with {\"status\": \"ok\"}.",
+            )),
+        ]
+    }
+
+    #[test]
+    fn test_anonymize_spans_and_patterns() {
+        let example = ExampleAnonymizer::new()
+            .span("Jane Doe", "customer_name")
+            .unwrap()
+            .pattern(r"[\w.]+@[\w.]+\w", "email")
+            .unwrap()
+            .anonymize(&conversation())
+            .unwrap();
+
+        assert_eq!(
+            example.template().template(),
+            "human: Hi, I'm {customer_name}. Email me at {email} or {email_2}.\n\
+             ai: Thanks {customer_name}, I'll write to {email} with \\{\"status\": \"ok\"\\}."
+        );
+        assert_eq!(
+            example.template().input_variables(),
+            vec!["customer_name", "email", "email_2"]
+        );
+        assert_eq!(
+            example.replacements().get("email_2").map(String::as_str),
+            Some("jd@work.org")
+        );
+
+        let formatted = example
+            .template()
+            .format(&vars!(
+                customer_name = "Sam",
+                email = "a@b.c",
+                email_2 = "d@e.f"
+            ))
+            .unwrap();
+        assert!(formatted.ends_with("I'll write to a@b.c with {\"status\": \"ok\"}."));
+    }
+
+    #[test]
+    fn test_earlier_rules_win_on_overlap() {
+        let example = ExampleAnonymizer::new()
+            .span("jane@example.com", "email")
+            .unwrap()
+            .pattern(r"jane", "first_name")
+            .unwrap()
+            .anonymize(&conversation()[..1])
+            .unwrap();
+
+        assert_eq!(
+            example.template().template(),
+            "human: Hi, I'm Jane Doe. Email me at {email} or jd@work.org."
+        );
+    }
+
+    #[test]
+    fn test_anonymize_all_builds_few_shot_examples() {
+        let anonymizer = ExampleAnonymizer::new()
+            .pattern(r"\d{4}", "order_id")
+            .unwrap();
+        let conversations = vec![
+            vec![MessageEnum::Human(HumanMessage::new(
+                "Where is order 1234?",
+            ))],
+            vec![MessageEnum::Human(HumanMessage::new("Cancel order 9876."))],
+        ];
+
+        let examples = anonymizer.anonymize_all(&conversations).unwrap();
+        let few_shot = crate::FewShotTemplate::new(examples);
+        assert_eq!(
+            few_shot.format(&vars!(order_id = "0001")).unwrap(),
+            "human: Where is order 0001?\n\nhuman: Cancel order 0001."
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        assert!(ExampleAnonymizer::new().span("", "name").is_err());
+        assert!(ExampleAnonymizer::new().span("Jane", "not valid").is_err());
+        assert!(ExampleAnonymizer::new().pattern("(", "name").is_err());
+    }
+}
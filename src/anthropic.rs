@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Map, Value};
+
+use crate::{ChatTemplate, ContentPart, ExportedMessage, RoleMap, TemplateError};
+
+// Anthropic takes the system prompt as a top-level field, tool calls as
+// `tool_use` blocks on the assistant turn and their results as `tool_result`
+// blocks in the following user turn.
+pub fn anthropic_request(messages: &[ExportedMessage]) -> Result<Value, TemplateError> {
+    let mut system: Vec<&ExportedMessage> = Vec::new();
+    let mut turns: Vec<Value> = Vec::new();
+
+    for message in messages {
+        match message.role.as_str() {
+            "system" => system.push(message),
+            "assistant" if !message.tool_calls.is_empty() => {
+                let mut blocks = Vec::new();
+                if !message.content.is_empty() {
+                    blocks.push(json!({"type": "text", "text": message.content}));
+                }
+                for call in &message.tool_calls {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": call.arguments()?,
+                    }));
+                }
+                turns.push(json!({"role": "assistant", "content": blocks}));
+            }
+            RoleMap::TOOL_ROLE => {
+                let block = json!({
+                    "type": "tool_result",
+                    "tool_use_id": message.tool_call_id,
+                    "content": message.content,
+                });
+                match turns.last_mut() {
+                    Some(last) if is_tool_result_turn(last) => {
+                        last["content"].as_array_mut().unwrap().push(block)
+                    }
+                    _ => turns.push(json!({"role": "user", "content": [block]})),
+                }
+            }
+            // Anthropic continues a trailing assistant turn, but rejects one
+            // that ends in whitespace.
+            _ if message.prefill => {
+                let content = message.content.trim_end();
+                if !content.is_empty() {
+                    turns.push(json!({"role": "assistant", "content": content}));
+                }
+            }
+            role if !message.content_parts.is_empty() => {
+                let blocks: Vec<Value> = message.content_parts.iter().map(content_block).collect();
+                turns.push(json!({"role": role, "content": blocks}));
+            }
+            role => turns.push(json!({"role": role, "content": message.content})),
+        }
+        // A tool result may have joined the previous turn; either way the
+        // message now ends the last turn.
+        if message.metadata.cache_control.is_some()
+            && message.role != "system"
+            && let Some(turn) = turns.last_mut()
+        {
+            mark_cache_breakpoint(turn);
+        }
+    }
+
+    let mut request = Map::new();
+    if system
+        .iter()
+        .any(|message| message.metadata.cache_control.is_some())
+    {
+        let blocks: Vec<Value> = system
+            .iter()
+            .map(|message| {
+                let mut block = json!({"type": "text", "text": message.content});
+                if message.metadata.cache_control.is_some() {
+                    block["cache_control"] = json!({"type": "ephemeral"});
+                }
+                block
+            })
+            .collect();
+        request.insert("system".to_string(), Value::from(blocks));
+    } else if !system.is_empty() {
+        let system: Vec<&str> = system
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect();
+        request.insert("system".to_string(), Value::from(system.join("\n\n")));
+    }
+    request.insert("messages".to_string(), Value::from(turns));
+    Ok(Value::Object(request))
+}
+
+// Marks the end of a turn as a cache breakpoint, turning plain string
+// content into a single text block first.
+pub(crate) fn mark_cache_breakpoint(turn: &mut Value) {
+    let content = &mut turn["content"];
+    if let Value::String(text) = content {
+        *content = json!([{"type": "text", "text": text}]);
+    }
+    if let Some(last) = content.as_array_mut().and_then(|blocks| blocks.last_mut()) {
+        last["cache_control"] = json!({"type": "ephemeral"});
+    }
+}
+
+fn content_block(part: &ContentPart) -> Value {
+    match (part, part.base64_data()) {
+        (ContentPart::Text { text }, _) => json!({"type": "text", "text": text}),
+        (ContentPart::ImageUrl { .. }, Some((media_type, data))) => json!({
+            "type": "image",
+            "source": {"type": "base64", "media_type": media_type, "data": data},
+        }),
+        (ContentPart::ImageUrl { image_url }, None) => json!({
+            "type": "image",
+            "source": {"type": "url", "url": image_url.url},
+        }),
+    }
+}
+
+fn is_tool_result_turn(turn: &Value) -> bool {
+    turn["role"] == "user"
+        && turn["content"]
+            .as_array()
+            .is_some_and(|blocks| blocks.iter().all(|block| block["type"] == "tool_result"))
+}
+
+impl ChatTemplate {
+    pub fn to_anthropic_request(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Value, TemplateError> {
+        anthropic_request(&self.export(variables, &RoleMap::openai())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, System};
+    use crate::{
+        chats, examples, vars, CacheControl, ExampleTurn, FewShotChatTemplate, FewShotTemplate,
+        MessageLike, MessageMetadata, MultiTurnExample, MultimodalTemplate, Role, Template,
+        ToolCall, ToolUseExample,
+    };
+
+    #[test]
+    fn test_anthropic_request_with_tool_use_examples() {
+        let example: MultiTurnExample = ToolUseExample::new("Weather in Paris and Rome?")
+            .with_call(
+                ToolCall::new("call_1", "get_weather", &json!({"city": "Paris"})),
+                "18°C",
+            )
+            .with_call(
+                ToolCall::new("call_2", "get_weather", &json!({"city": "Rome"})),
+                "24°C",
+            )
+            .with_answer("Paris is 18°C, Rome 24°C.")
+            .into();
+        let few_shot = FewShotChatTemplate::new(
+            FewShotTemplate::new(examples!()),
+            ChatTemplate::from_messages(chats!(Human = "{input}")).unwrap(),
+        )
+        .with_multi_turn_examples(vec![example]);
+
+        let mut chat_template =
+            ChatTemplate::from_messages(chats!(System = "Use tools.", Human = "{question}"))
+                .unwrap();
+        chat_template
+            .messages
+            .insert(1, MessageLike::few_shot_prompt(few_shot));
+
+        let request = chat_template
+            .to_anthropic_request(&vars!(question = "Weather in Oslo?"))
+            .unwrap();
+        assert_eq!(request["system"], "Use tools.");
+
+        let turns = request["messages"].as_array().unwrap();
+        assert_eq!(turns.len(), 5);
+        assert_eq!(turns[1]["content"][1]["type"], "tool_use");
+        assert_eq!(turns[1]["content"][1]["input"], json!({"city": "Rome"}));
+        assert_eq!(turns[2]["role"], "user");
+        assert_eq!(turns[2]["content"].as_array().unwrap().len(), 2);
+        assert_eq!(turns[2]["content"][0]["tool_use_id"], "call_1");
+        assert_eq!(
+            turns[4],
+            json!({"role": "user", "content": "Weather in Oslo?"})
+        );
+    }
+
+    #[test]
+    fn test_anthropic_request_with_images() {
+        let image = MultimodalTemplate::new(Role::Human)
+            .unwrap()
+            .with_text("What is in {name}?")
+            .unwrap()
+            .with_image_base64("image/jpeg", "{data}")
+            .unwrap()
+            .with_image_url("https://img.example/b.png")
+            .unwrap();
+        let chat_template = ChatTemplate {
+            messages: vec![MessageLike::multimodal(image)],
+            prefill: None,
+            sensitive: false,
+        };
+
+        let request = chat_template
+            .to_anthropic_request(&vars!(name = "a.jpg", data = "/9j/4AAQ"))
+            .unwrap();
+        assert_eq!(
+            request["messages"][0]["content"],
+            json!([
+                {"type": "text", "text": "What is in a.jpg?"},
+                {
+                    "type": "image",
+                    "source": {"type": "base64", "media_type": "image/jpeg", "data": "/9j/4AAQ"},
+                },
+                {"type": "image", "source": {"type": "url", "url": "https://img.example/b.png"}},
+            ])
+        );
+    }
+
+    #[test]
+    fn test_anthropic_request_with_cache_control_metadata() {
+        let cached = MessageMetadata::new().with_cache_control(CacheControl::Ephemeral);
+        let chat_template = ChatTemplate {
+            messages: vec![
+                MessageLike::role_prompt_template(System, Template::new("Docs: {docs}").unwrap())
+                    .with_metadata(cached.clone()),
+                MessageLike::role_prompt_template(System, Template::new("Be brief.").unwrap()),
+                MessageLike::role_prompt_template(Human, Template::new("{question}").unwrap())
+                    .with_metadata(cached),
+            ],
+            prefill: None,
+            sensitive: false,
+        };
+
+        let request = chat_template
+            .to_anthropic_request(&vars!(docs = "FAQ", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            request["system"],
+            json!([
+                {"type": "text", "text": "Docs: FAQ", "cache_control": {"type": "ephemeral"}},
+                {"type": "text", "text": "Be brief."},
+            ])
+        );
+        assert_eq!(
+            request["messages"][0]["content"],
+            json!([{"type": "text", "text": "Why?", "cache_control": {"type": "ephemeral"}}])
+        );
+    }
+
+    #[test]
+    fn test_invalid_tool_arguments_are_rejected() {
+        let mut call = ToolCall::new("call_1", "lookup", &json!({}));
+        call.function.arguments = "not json".to_string();
+        let messages = MultiTurnExample::new(vec![ExampleTurn::tool_use(vec![call])])
+            .format_messages(&ChatTemplate {
+                messages: vec![],
+                prefill: None,
+                sensitive: false,
+            })
+            .unwrap();
+
+        assert!(anthropic_request(&RoleMap::openai().export(&messages)).is_err());
+    }
+
+    #[test]
+    fn test_anthropic_request_with_prefill() {
+        let chat_template = ChatTemplate::from_messages(chats!(Human = "List {n} colors."))
+            .unwrap()
+            .with_assistant_prefill(Template::new("[\n  ").unwrap());
+
+        let request = chat_template.to_anthropic_request(&vars!(n = "3")).unwrap();
+        assert_eq!(
+            request["messages"][1],
+            json!({"role": "assistant", "content": "["})
+        );
+        assert_eq!(request["messages"].as_array().unwrap().len(), 2);
+
+        let exported = chat_template
+            .export(&vars!(n = "3"), &RoleMap::openai())
+            .unwrap();
+        assert!(exported[1].prefill);
+        assert!(!exported[0].prefill);
+        assert!(serde_json::to_string(&exported[1])
+            .unwrap()
+            .ends_with(r#""prefill":true}"#));
+    }
+}
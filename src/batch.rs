@@ -0,0 +1,290 @@
+use std::{collections::HashMap, path::Path};
+
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{vars::borrow_vars, Formattable, TemplateError};
+
+pub type Row = HashMap<String, String>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValueType {
+    Text,
+    Integer,
+    Float,
+    Boolean,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BatchSource {
+    columns: HashMap<String, String>,
+    types: HashMap<String, ValueType>,
+}
+
+impl ValueType {
+    // Normalizes a raw cell so that e.g. " 007 ", "TRUE" and "1.50" render the
+    // same way whether they came from CSV text or a JSON number/bool.
+    fn coerce(self, raw: &str) -> Option<String> {
+        let trimmed = raw.trim();
+        match self {
+            ValueType::Text => Some(raw.to_string()),
+            ValueType::Integer => trimmed.parse::<i64>().ok().map(|n| n.to_string()),
+            ValueType::Float => trimmed
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(|n| n.to_string()),
+            ValueType::Boolean => match trimmed.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "1" => Some("true".to_string()),
+                "false" | "no" | "0" => Some("false".to_string()),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl BatchSource {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn map_column(mut self, column: &str, variable: &str) -> Self {
+        self.columns
+            .insert(column.to_string(), variable.to_string());
+        self
+    }
+
+    pub fn coerce(mut self, variable: &str, value_type: ValueType) -> Self {
+        self.types.insert(variable.to_string(), value_type);
+        self
+    }
+
+    pub fn parse_csv(&self, content: &str) -> Result<Vec<Row>, TemplateError> {
+        let mut records = parse_csv_records(content)?.into_iter();
+        let Some(header) = records.next() else {
+            return Ok(Vec::new());
+        };
+
+        records
+            .enumerate()
+            .map(|(index, record)| {
+                let line = index + 2;
+                if record.len() != header.len() {
+                    return Err(row_error(
+                        line,
+                        format!("expected {} fields, found {}", header.len(), record.len()),
+                    ));
+                }
+                self.build_row(line, header.iter().map(String::as_str).zip(record))
+            })
+            .collect()
+    }
+
+    pub fn parse_jsonl(&self, content: &str) -> Result<Vec<Row>, TemplateError> {
+        content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let line_number = index + 1;
+                let value: Value = serde_json::from_str(line)
+                    .map_err(|e| row_error(line_number, format!("invalid JSON: {}", e)))?;
+                let Value::Object(fields) = value else {
+                    return Err(row_error(line_number, "expected a JSON object".to_string()));
+                };
+
+                let cells = fields.into_iter().filter_map(|(name, value)| match value {
+                    Value::Null => None,
+                    Value::String(text) => Some((name, text)),
+                    other => Some((name, other.to_string())),
+                });
+                self.build_row(line_number, cells)
+            })
+            .collect()
+    }
+
+    pub async fn read_csv<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Row>, TemplateError> {
+        self.parse_csv(&read_data_file(path.as_ref()).await?)
+    }
+
+    pub async fn read_jsonl<P: AsRef<Path>>(&self, path: P) -> Result<Vec<Row>, TemplateError> {
+        self.parse_jsonl(&read_data_file(path.as_ref()).await?)
+    }
+
+    fn build_row<K: AsRef<str>>(
+        &self,
+        line: usize,
+        cells: impl Iterator<Item = (K, String)>,
+    ) -> Result<Row, TemplateError> {
+        cells
+            .map(|(column, raw)| {
+                let column = column.as_ref();
+                let variable = self
+                    .columns
+                    .get(column)
+                    .cloned()
+                    .unwrap_or_else(|| column.to_string());
+                let value = match self.types.get(&variable) {
+                    Some(value_type) => value_type.coerce(&raw).ok_or_else(|| {
+                        row_error(
+                            line,
+                            format!(
+                                "cannot read {:?} as {:?} for '{}'",
+                                raw, value_type, variable
+                            ),
+                        )
+                    })?,
+                    None => raw,
+                };
+                Ok((variable, value))
+            })
+            .collect()
+    }
+}
+
+pub fn format_rows<F: Formattable + ?Sized>(
+    template: &F,
+    rows: &[Row],
+) -> Result<Vec<String>, TemplateError> {
+    rows.iter()
+        .map(|row| template.format(&borrow_vars(row)))
+        .collect()
+}
+
+fn row_error(line: usize, message: String) -> TemplateError {
+    TemplateError::MalformedTemplate(format!("Batch line {}: {}", line, message), None)
+}
+
+async fn read_data_file(path: &Path) -> Result<String, TemplateError> {
+    fs::read_to_string(path).await.map_err(|e| {
+        TemplateError::MalformedTemplate(
+            format!("Failed to read batch file {}: {}", path.display(), e),
+            None,
+        )
+    })
+}
+
+// RFC 4180: fields may be quoted, quoted fields may contain separators, line
+// breaks and doubled quotes. Blank lines between records are skipped.
+fn parse_csv_records(content: &str) -> Result<Vec<Vec<String>>, TemplateError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                line += 1;
+                if !record.is_empty() || !field.is_empty() {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+            }
+            (c, _) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+
+    if in_quotes {
+        return Err(row_error(line, "unterminated quoted field".to_string()));
+    }
+    if !record.is_empty() || !field.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Template;
+
+    #[test]
+    fn test_parse_csv_with_mapping_and_quotes() {
+        let csv = "Customer Name,note\r\n\"Doe, Jane\",\"said \"\"hi\"\"\nthen left\"\n\nBob,\n";
+        let rows = BatchSource::new()
+            .map_column("Customer Name", "name")
+            .parse_csv(csv)
+            .unwrap();
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["name"], "Doe, Jane");
+        assert_eq!(rows[0]["note"], "said \"hi\"\nthen left");
+        assert_eq!(rows[1]["name"], "Bob");
+        assert_eq!(rows[1]["note"], "");
+    }
+
+    #[test]
+    fn test_coercion_normalizes_csv_and_jsonl_alike() {
+        let source = BatchSource::new()
+            .coerce("qty", ValueType::Integer)
+            .coerce("price", ValueType::Float)
+            .coerce("gift", ValueType::Boolean);
+
+        let from_csv = source
+            .parse_csv("qty,price,gift\n 007 ,1.50,YES\n")
+            .unwrap();
+        let from_jsonl = source
+            .parse_jsonl("{\"qty\": 7, \"price\": 1.5, \"gift\": true, \"memo\": null}\n")
+            .unwrap();
+
+        assert_eq!(from_csv, from_jsonl);
+        assert_eq!(from_csv[0]["qty"], "7");
+        assert_eq!(from_csv[0]["price"], "1.5");
+        assert_eq!(from_csv[0]["gift"], "true");
+    }
+
+    #[test]
+    fn test_errors_report_line() {
+        let source = BatchSource::new().coerce("qty", ValueType::Integer);
+
+        let err = source.parse_csv("qty\n1\nmany\n").unwrap_err();
+        assert!(err.to_string().contains("Batch line 3"), "{}", err);
+
+        let err = source.parse_csv("a,b\n1\n").unwrap_err();
+        assert!(err.to_string().contains("expected 2 fields"), "{}", err);
+
+        let err = source.parse_jsonl("{\"qty\": 1}\n\n[1]\n").unwrap_err();
+        assert!(err.to_string().contains("Batch line 3"), "{}", err);
+
+        assert!(source.parse_csv("qty\n\"open\n").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_read_csv_and_format_rows() {
+        let path = std::env::temp_dir().join("promptforge_batch.csv");
+        std::fs::write(&path, "topic,n\nrust,3\ncsv,1\n").unwrap();
+
+        let rows = BatchSource::new()
+            .coerce("n", ValueType::Integer)
+            .read_csv(&path)
+            .await
+            .unwrap();
+        let template = Template::new("Write {n} facts about {topic}.").unwrap();
+
+        assert_eq!(
+            format_rows(&template, &rows).unwrap(),
+            vec!["Write 3 facts about rust.", "Write 1 facts about csv."]
+        );
+        assert!(BatchSource::new()
+            .read_jsonl("missing.jsonl")
+            .await
+            .is_err());
+    }
+}
@@ -1,72 +1,105 @@
-use crate::is_even::IsEven;
+use crate::{
+    detection::{escape_literals, PROGRAMMING_LITERALS},
+    TemplateError,
+};
 use regex::Regex;
-
-pub fn has_multiple_words_between_braces(s: &str) -> bool {
-    let re = Regex::new(r"\{\{?\s*([^}]+)\s*\}?\}").unwrap();
-
-    if let Some(captures) = re.captures(s) {
-        let content = &captures[1].trim();
-        let words: Vec<&str> = content.split_whitespace().collect();
-        return words.len() > 1;
-    }
-
-    false
+use std::borrow::Cow;
+
+pub use crate::detection::{
+    count_left_braces, count_right_braces, escape_braces, find_brace_error,
+    has_consecutive_left_braces, has_consecutive_right_braces, has_escaped_braces,
+    has_even_left_braces, has_even_right_braces, has_left_brace, has_multiple_words_between_braces,
+    has_no_braces, has_only_double_braces, has_only_single_braces, has_right_brace,
+    mask_escaped_braces, unescape_braces, ESCAPED_LEFT_BRACE, ESCAPED_RIGHT_BRACE,
+};
+
+// Brace text that should never be read as a placeholder, such as `{}` or
+// `{0}` in a prompt about code. Matches are escaped before the template
+// format is detected, so they render back as written.
+#[derive(Debug, Clone, Default)]
+pub struct BraceLiterals {
+    patterns: Vec<Regex>,
 }
 
-pub fn count_left_braces(s: &str) -> usize {
-    s.matches("{").count()
-}
-
-pub fn count_right_braces(s: &str) -> usize {
-    s.matches("}").count()
-}
-
-pub fn has_even_left_braces(s: &str) -> bool {
-    count_left_braces(s).is_even()
-}
-
-pub fn has_even_right_braces(s: &str) -> bool {
-    count_right_braces(s).is_even()
-}
-
-pub fn has_left_brace(s: &str) -> bool {
-    count_left_braces(s) > 0
-}
-
-pub fn has_right_brace(s: &str) -> bool {
-    count_right_braces(s) > 0
-}
+impl BraceLiterals {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-pub fn has_consecutive_left_braces(s: &str) -> bool {
-    s.contains("{{")
-}
+    // Empty braces, positional `{0}` and format specs such as `{:?}` or `{0:>8}`.
+    pub fn programming() -> Self {
+        BraceLiterals {
+            patterns: PROGRAMMING_LITERALS
+                .into_iter()
+                .map(|pattern| Regex::new(pattern).unwrap())
+                .collect(),
+        }
+    }
 
-pub fn has_consecutive_right_braces(s: &str) -> bool {
-    s.contains("}}")
-}
+    pub fn with_literal(mut self, literal: &str) -> Self {
+        self.patterns
+            .push(Regex::new(&regex::escape(literal)).expect("escaped literal is a valid regex"));
+        self
+    }
 
-pub fn has_only_single_braces(s: &str) -> bool {
-    has_left_brace(s)
-        && has_right_brace(s)
-        && !has_consecutive_left_braces(s)
-        && !has_consecutive_right_braces(s)
-}
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, TemplateError> {
+        let pattern = Regex::new(pattern).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Invalid literal pattern: {}", e), None)
+        })?;
+        self.patterns.push(pattern);
+        Ok(self)
+    }
 
-pub fn has_only_double_braces(s: &str) -> bool {
-    has_consecutive_left_braces(s)
-        && has_consecutive_right_braces(s)
-        && has_even_left_braces(s)
-        && has_even_right_braces(s)
-}
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
 
-pub fn has_no_braces(s: &str) -> bool {
-    !has_left_brace(s) && !has_right_brace(s)
+    pub fn escape<'a>(&self, source: &'a str) -> Cow<'a, str> {
+        escape_literals(source, &self.patterns)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_find_brace_error() {
+        assert_eq!(find_brace_error("Hi {name}"), None);
+        assert_eq!(find_brace_error("{{a}} and {{b}}"), None);
+        assert_eq!(find_brace_error(r"\{ {a} \}"), None);
+
+        assert_eq!(find_brace_error("Hi {name"), Some((3, "unclosed brace")));
+        assert_eq!(find_brace_error("{a {b}"), Some((0, "unclosed brace")));
+        assert_eq!(
+            find_brace_error("a} {b}"),
+            Some((1, "unmatched closing brace"))
+        );
+        assert_eq!(
+            find_brace_error("{{a}"),
+            Some((0, "mismatched brace widths"))
+        );
+        assert_eq!(
+            find_brace_error("{a} and {{b}}"),
+            Some((8, "mixed single and double braces"))
+        );
+    }
+
+    #[test]
+    fn test_escaped_braces() {
+        let template = r#"JSON: \{"name": "{name}"\}"#;
+        assert!(has_escaped_braces(template));
+        assert!(!has_escaped_braces("{name}"));
+
+        let masked = mask_escaped_braces(template);
+        assert_eq!(masked.len(), template.len());
+        assert_eq!(count_left_braces(&masked), 1);
+        assert_eq!(count_right_braces(&masked), 1);
+
+        assert_eq!(unescape_braces(template), r#"JSON: {"name": "{name}"}"#);
+        assert!(matches!(mask_escaped_braces("{name}"), Cow::Borrowed(_)));
+    }
+
     #[test]
     fn test_has_multiple_words_between_braces() {
         assert!(has_multiple_words_between_braces("{one two}"));
@@ -193,4 +226,17 @@ mod tests {
         assert!(!has_no_braces("hello {{world}}"));
         assert!(!has_no_braces("hello {{world}} {{world}}"));
     }
+
+    #[test]
+    fn test_brace_literals_escape() {
+        let literals = BraceLiterals::programming().with_literal("{id}");
+        assert_eq!(
+            literals.escape("fn f() {} and {0}, {:?} or {id} but {name}"),
+            "fn f() \\{\\} and \\{0\\}, \\{:?\\} or \\{id\\} but {name}"
+        );
+        let escaped = literals.escape("use {0} then {}").into_owned();
+        assert_eq!(literals.escape(&escaped), escaped);
+        assert!(matches!(literals.escape("{name}"), Cow::Borrowed(_)));
+        assert!(BraceLiterals::new().with_pattern("(").is_err());
+    }
 }
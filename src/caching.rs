@@ -0,0 +1,290 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::MessageEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    anthropic::{anthropic_request, mark_cache_breakpoint},
+    metrics::estimate_tokens,
+    ChatTemplate, ExportedMessage, MessageLike, Role, RoleMap, Templatable, TemplateError,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheHint {
+    Stable,
+    #[default]
+    Volatile,
+}
+
+// Variables are volatile unless marked stable; literal text and few-shot
+// examples never change between calls and always count as stable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheHints {
+    hints: HashMap<String, CacheHint>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct CacheReport {
+    pub prefix_messages: usize,
+    pub total_messages: usize,
+    pub prefix_tokens: usize,
+    pub total_tokens: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedExport {
+    pub messages: Vec<ExportedMessage>,
+    pub report: CacheReport,
+}
+
+impl CacheHints {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn stable(self, variable: &str) -> Self {
+        self.with_hint(variable, CacheHint::Stable)
+    }
+
+    pub fn volatile(self, variable: &str) -> Self {
+        self.with_hint(variable, CacheHint::Volatile)
+    }
+
+    pub fn with_hint(mut self, variable: &str, hint: CacheHint) -> Self {
+        self.hints.insert(variable.to_string(), hint);
+        self
+    }
+
+    pub fn hint(&self, variable: &str) -> CacheHint {
+        self.hints.get(variable).copied().unwrap_or_default()
+    }
+
+    pub fn is_stable(&self, message_like: &MessageLike) -> bool {
+        match message_like {
+            MessageLike::BaseMessage(_) | MessageLike::FewShotPrompt(_) => true,
+            MessageLike::RolePromptTemplate(_, template) => template
+                .input_variables()
+                .iter()
+                .filter(|var| !template.partial_vars().contains_key(*var))
+                .all(|var| self.hint(var) == CacheHint::Stable),
+            MessageLike::Placeholder(placeholder) => {
+                self.hint(placeholder.variable_name()) == CacheHint::Stable
+            }
+            MessageLike::ToolCallTemplate(tool_call_template) => tool_call_template
+                .input_variables()
+                .iter()
+                .all(|var| self.hint(var) == CacheHint::Stable),
+            MessageLike::Multimodal(multimodal_template) => multimodal_template
+                .input_variables()
+                .iter()
+                .all(|var| self.hint(var) == CacheHint::Stable),
+            MessageLike::ForEach { variable, .. } => self.hint(variable) == CacheHint::Stable,
+            MessageLike::SubTemplate(_, chat_template) => chat_template
+                .messages
+                .iter()
+                .all(|message_like| self.is_stable(message_like)),
+            MessageLike::Annotated(message_like, _) => self.is_stable(message_like),
+        }
+    }
+}
+
+impl CacheReport {
+    pub fn cacheable_fraction(&self) -> f64 {
+        if self.total_tokens == 0 {
+            0.0
+        } else {
+            self.prefix_tokens as f64 / self.total_tokens as f64
+        }
+    }
+}
+
+fn is_system(message_like: &MessageLike) -> bool {
+    match message_like.inner() {
+        MessageLike::BaseMessage(message) => matches!(message.as_ref(), MessageEnum::System(_)),
+        MessageLike::RolePromptTemplate(role, _) => *role == Role::System,
+        _ => false,
+    }
+}
+
+impl ChatTemplate {
+    // Stable system messages are hoisted ahead of everything else; the
+    // conversation itself keeps its order. The report's prefix is the leading
+    // run of stable messages a provider can serve from its prompt cache.
+    pub fn export_cached(
+        &self,
+        variables: &HashMap<&str, &str>,
+        role_map: &RoleMap,
+        hints: &CacheHints,
+    ) -> Result<CachedExport, TemplateError> {
+        let (hoisted, rest): (Vec<&MessageLike>, Vec<&MessageLike>) = self
+            .messages
+            .iter()
+            .partition(|message| is_system(message) && hints.is_stable(message));
+
+        let mut messages: Vec<Arc<MessageEnum>> = Vec::new();
+        let mut prefix_messages = 0;
+        let mut in_prefix = true;
+        for message_like in hoisted.into_iter().chain(rest) {
+            let formatted = Self::format_message_like(message_like, variables)?;
+            in_prefix = in_prefix && hints.is_stable(message_like);
+            if in_prefix {
+                prefix_messages += formatted.len();
+            }
+            messages.extend(formatted);
+        }
+        messages.extend(self.format_prefill(variables)?);
+
+        let messages = role_map.export(&messages);
+        let tokens: Vec<usize> = messages
+            .iter()
+            .map(|message| estimate_tokens(&message.content))
+            .collect();
+
+        Ok(CachedExport {
+            report: CacheReport {
+                prefix_messages,
+                total_messages: messages.len(),
+                prefix_tokens: tokens[..prefix_messages].iter().sum(),
+                total_tokens: tokens.iter().sum(),
+            },
+            messages,
+        })
+    }
+
+    // Places an Anthropic `cache_control` breakpoint at the end of the stable
+    // prefix: on the system prompt, or on the last turn the prefix reaches.
+    pub fn to_anthropic_request_cached(
+        &self,
+        variables: &HashMap<&str, &str>,
+        hints: &CacheHints,
+    ) -> Result<Value, TemplateError> {
+        let export = self.export_cached(variables, &RoleMap::openai(), hints)?;
+        let mut request = anthropic_request(&export.messages)?;
+        let prefix = &export.messages[..export.report.prefix_messages];
+        if prefix.is_empty() {
+            return Ok(request);
+        }
+
+        let prefix_turns = anthropic_request(prefix)?["messages"]
+            .as_array()
+            .map_or(0, Vec::len);
+        match prefix_turns.checked_sub(1) {
+            Some(turn) => mark_cache_breakpoint(&mut request["messages"][turn]),
+            // The prefix ends among the system messages, so the system prompt
+            // is split into one block per message to cache just that part.
+            None => {
+                let blocks: Vec<Value> = export
+                    .messages
+                    .iter()
+                    .filter(|message| message.role == "system")
+                    .map(|message| json!({"type": "text", "text": message.content}))
+                    .collect();
+                request["system"] = Value::from(blocks);
+                request["system"][prefix.len() - 1]["cache_control"] = json!({"type": "ephemeral"});
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, Placeholder, System};
+    use crate::{chats, vars};
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "Today is {date}.",
+            System = "Answer from these docs:\n{docs}",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    fn history() -> String {
+        json!([{"role": "human", "content": "Hi"}, {"role": "ai", "content": "Hello"}]).to_string()
+    }
+
+    #[test]
+    fn test_export_cached_hoists_stable_system_messages() {
+        let history = history();
+        let variables = vars!(
+            date = "Monday",
+            docs = "Refunds take 5 days.",
+            history = history.as_str(),
+            question = "How long do refunds take?"
+        );
+        let hints = CacheHints::new().stable("docs").stable("history");
+
+        let export = chat()
+            .export_cached(&variables, &RoleMap::openai(), &hints)
+            .unwrap();
+        let contents: Vec<&str> = export.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            vec![
+                "Answer from these docs:\nRefunds take 5 days.",
+                "Today is Monday.",
+                "Hi",
+                "Hello",
+                "How long do refunds take?"
+            ]
+        );
+        assert_eq!(export.report.prefix_messages, 1);
+        assert_eq!(export.report.total_messages, 5);
+        assert!(export.report.cacheable_fraction() > 0.0);
+
+        let hints = hints.stable("date");
+        let report = chat()
+            .export_cached(&variables, &RoleMap::openai(), &hints)
+            .unwrap()
+            .report;
+        assert_eq!(report.prefix_messages, 4);
+        assert!(report.prefix_tokens < report.total_tokens);
+    }
+
+    #[test]
+    fn test_anthropic_request_cached_marks_breakpoint() {
+        let history = history();
+        let variables = vars!(
+            date = "Monday",
+            docs = "Refunds take 5 days.",
+            history = history.as_str(),
+            question = "Why?"
+        );
+
+        let request = chat()
+            .to_anthropic_request_cached(&variables, &CacheHints::new().stable("docs"))
+            .unwrap();
+        assert_eq!(request["system"][0]["cache_control"]["type"], "ephemeral");
+        assert_eq!(
+            request["system"][1],
+            json!({"type": "text", "text": "Today is Monday."})
+        );
+        assert!(request["messages"][0]["content"].is_string());
+
+        let hints = CacheHints::new()
+            .stable("docs")
+            .stable("date")
+            .stable("history");
+        let request = chat()
+            .to_anthropic_request_cached(&variables, &hints)
+            .unwrap();
+        assert!(request["system"].is_string());
+        assert_eq!(
+            request["messages"][1]["content"][0],
+            json!({"type": "text", "text": "Hello", "cache_control": {"type": "ephemeral"}})
+        );
+        assert!(request["messages"][2]["content"].is_string());
+
+        let request = chat()
+            .to_anthropic_request_cached(&variables, &CacheHints::new())
+            .unwrap();
+        assert_eq!(request, chat().to_anthropic_request(&variables).unwrap());
+    }
+}
@@ -0,0 +1,170 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use crate::{CanonicalHash, Formattable, TemplateError, TemplateHash};
+
+type ErrorFactory = Arc<dyn Fn() -> TemplateError + Send + Sync>;
+
+pub struct FailureInjector {
+    rate: f64,
+    targets: HashSet<TemplateHash>,
+    error: ErrorFactory,
+    state: AtomicU64,
+    injected: AtomicUsize,
+}
+
+impl fmt::Debug for FailureInjector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FailureInjector")
+            .field("rate", &self.rate)
+            .field("targets", &self.targets.len())
+            .field("injected", &self.injected_count())
+            .finish()
+    }
+}
+
+impl FailureInjector {
+    pub const DEFAULT_SEED: u64 = 0x5eed_cafe;
+
+    pub fn new(rate: f64) -> Result<Self, TemplateError> {
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Failure rate must be between 0 and 1, got {}", rate),
+                None,
+            ));
+        }
+
+        Ok(FailureInjector {
+            rate,
+            targets: HashSet::new(),
+            error: Arc::new(|| {
+                TemplateError::MalformedTemplate("Injected failure".to_string(), None)
+            }),
+            state: AtomicU64::new(Self::DEFAULT_SEED),
+            injected: AtomicUsize::new(0),
+        })
+    }
+
+    pub fn always() -> Self {
+        Self::new(1.0).expect("1.0 is a valid failure rate")
+    }
+
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.state.store(seed, Ordering::Relaxed);
+        self
+    }
+
+    pub fn with_error<F>(mut self, error: F) -> Self
+    where
+        F: Fn() -> TemplateError + Send + Sync + 'static,
+    {
+        self.error = Arc::new(error);
+        self
+    }
+
+    pub fn with_target<T: CanonicalHash>(mut self, template: &T) -> Self {
+        self.targets.insert(template.template_hash());
+        self
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn injected_count(&self) -> usize {
+        self.injected.load(Ordering::Relaxed)
+    }
+
+    pub fn check<T: CanonicalHash>(&self, template: &T) -> Result<(), TemplateError> {
+        if !self.targets.is_empty() && !self.targets.contains(&template.template_hash()) {
+            return Ok(());
+        }
+        if self.rate < 1.0 && self.next_sample() >= self.rate {
+            return Ok(());
+        }
+
+        self.injected.fetch_add(1, Ordering::Relaxed);
+        Err((self.error)())
+    }
+
+    pub fn format<T>(
+        &self,
+        template: &T,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError>
+    where
+        T: Formattable + CanonicalHash,
+    {
+        self.check(template)?;
+        template.format(variables)
+    }
+
+    // splitmix64, so runs are reproducible for a given seed without pulling in an RNG crate.
+    fn next_sample(&self) -> f64 {
+        let mut z = self
+            .state
+            .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+            .wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        (z >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Template};
+
+    #[test]
+    fn test_failure_rate_bounds() {
+        assert!(FailureInjector::new(-0.1).is_err());
+        assert!(FailureInjector::new(1.5).is_err());
+
+        let tmpl = Template::new("Hi {name}").unwrap();
+        let never = FailureInjector::new(0.0).unwrap();
+        assert_eq!(never.format(&tmpl, &vars!(name = "Ada")).unwrap(), "Hi Ada");
+
+        let always = FailureInjector::always();
+        assert!(always.format(&tmpl, &vars!(name = "Ada")).is_err());
+        assert_eq!(always.injected_count(), 1);
+    }
+
+    #[test]
+    fn test_rate_is_reproducible_for_a_seed() {
+        let tmpl = Template::new("Hi {name}").unwrap();
+        let run = |seed| {
+            let injector = FailureInjector::new(0.3).unwrap().with_seed(seed);
+            (0..1000)
+                .map(|_| injector.check(&tmpl).is_err())
+                .collect::<Vec<_>>()
+        };
+
+        let failures = run(7).iter().filter(|failed| **failed).count();
+        assert!((200..400).contains(&failures), "got {} failures", failures);
+        assert_eq!(run(7), run(7));
+    }
+
+    #[test]
+    fn test_targets_and_custom_errors() {
+        let flaky = Template::new("Summarize {document}").unwrap();
+        let stable = Template::new("Hi {name}").unwrap();
+        let injector = FailureInjector::always()
+            .with_target(&flaky)
+            .with_error(|| TemplateError::MissingVariable("document".to_string()));
+
+        assert!(matches!(
+            injector.format(&flaky, &vars!(document = "report")),
+            Err(TemplateError::MissingVariable(name)) if name == "document"
+        ));
+        assert!(injector.format(&stable, &vars!(name = "Ada")).is_ok());
+        assert_eq!(injector.injected_count(), 1);
+    }
+}
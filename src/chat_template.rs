@@ -1,55 +1,194 @@
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, ops::Add, path::Path, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::{Add, AddAssign},
+    path::Path,
+    sync::Arc,
+};
 use tokio::fs;
 
-use messageforge::{BaseMessage, MessageEnum, MessageType};
+use messageforge::{AiMessage, BaseMessage, MessageEnum};
 
 use crate::{
+    braces::has_escaped_braces,
+    encryption::refuse_sensitive,
+    export::{ExportedMessage, RoleMap},
     extract_variables,
     few_shot_chat_template_config::MessageConfig,
-    message_like::{ArcMessageEnumExt, MessageLike},
-    FewShotChatTemplate, Formattable, MessagesPlaceholder, Role, Templatable, Template,
-    TemplateError, TemplateFormat,
+    is_valid_identifier,
+    message_like::{format_for_each, MessageLike},
+    metrics::{estimate_tokens, PromptMetrics},
+    renderer::{ChatRenderer, PlainPrefix},
+    validation::{MessageIssue, ValidationReport},
+    vars::{borrow_vars, serialize_vars},
+    CompiledChatTemplate, FewShotChatTemplate, FormatOptions, Formattable, MessagesPlaceholder,
+    ModelProfile, NormalizationRules, PromptCompression, PromptForgeConfig, Role, Templatable,
+    Template, TemplateError, TemplateFormat,
 };
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatTemplate {
     pub messages: Vec<MessageLike>,
+    // Rendered after every message as the start of the assistant's reply.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefill: Option<Arc<Template>>,
+    #[serde(
+        skip_deserializing,
+        skip_serializing_if = "std::ops::Not::not",
+        serialize_with = "refuse_sensitive"
+    )]
+    pub(crate) sensitive: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormattedMessage {
+    pub message: Arc<MessageEnum>,
+    pub tokens: Option<usize>,
 }
 
 impl ChatTemplate {
+    pub const PREFILL_KEY: &'static str = "prefill";
+
+    pub fn builder() -> ChatTemplateBuilder {
+        ChatTemplateBuilder::new()
+    }
+
+    pub fn compile(&self) -> Result<CompiledChatTemplate, TemplateError> {
+        CompiledChatTemplate::new(self)
+    }
+
     pub fn from_messages<I>(messages: I) -> Result<Self, TemplateError>
     where
         I: IntoIterator<Item = (Role, String)>,
     {
-        let mut result = Vec::new();
+        let messages = messages
+            .into_iter()
+            .map(|(role, source)| MessageLike::from_role_source(role, source))
+            .collect::<Result<Vec<_>, TemplateError>>()?;
 
-        for (role, template_str) in messages {
-            match role {
-                Role::Placeholder => {
-                    let placeholder = MessagesPlaceholder::try_from(template_str)?;
-                    result.push(MessageLike::placeholder(placeholder));
-                }
-                Role::FewShotPrompt => {
-                    let few_shot_template = FewShotChatTemplate::try_from(template_str)?;
-                    result.push(MessageLike::few_shot_prompt(few_shot_template));
-                }
-                _ => {
-                    let prompt_template = Template::from_template(&template_str)?;
-
-                    if prompt_template.template_format() == TemplateFormat::PlainText {
-                        let base_message = role
-                            .to_message(&template_str)
-                            .map_err(|_| TemplateError::InvalidRoleError)?;
-                        result.push(MessageLike::base_message(base_message.unwrap_enum()));
-                    } else {
-                        result.push(MessageLike::role_prompt_template(role, prompt_template));
-                    }
-                }
+        Ok(ChatTemplate {
+            messages,
+            prefill: None,
+            sensitive: false,
+        })
+    }
+
+    // Entry point for callers that build message-likes themselves. Unlike the
+    // public `messages` field, this rejects roles that cannot carry a template.
+    pub fn from_message_likes(messages: Vec<MessageLike>) -> Result<Self, TemplateError> {
+        for message in &messages {
+            Self::check_message_like(message)?;
+        }
+
+        Ok(ChatTemplate {
+            messages,
+            prefill: None,
+            sensitive: false,
+        })
+    }
+
+    fn check_message_like(message: &MessageLike) -> Result<(), TemplateError> {
+        match message.inner() {
+            MessageLike::RolePromptTemplate(role, _) | MessageLike::ForEach { role, .. } => {
+                role.ensure_message_role()
             }
+            MessageLike::SubTemplate(..) => message.check_includes(&mut Vec::new()),
+            _ => Ok(()),
         }
+    }
+
+    // The editing methods index like `Vec` and panic when out of bounds.
+    pub fn push(&mut self, message: MessageLike) -> Result<(), TemplateError> {
+        Self::check_message_like(&message)?;
+        self.messages.push(message);
+        self.refresh();
+        Ok(())
+    }
+
+    pub fn insert(&mut self, index: usize, message: MessageLike) -> Result<(), TemplateError> {
+        Self::check_message_like(&message)?;
+        self.messages.insert(index, message);
+        self.refresh();
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> MessageLike {
+        self.messages.remove(index)
+    }
+
+    pub fn replace(
+        &mut self,
+        index: usize,
+        message: MessageLike,
+    ) -> Result<MessageLike, TemplateError> {
+        Self::check_message_like(&message)?;
+        let previous = std::mem::replace(&mut self.messages[index], message);
+        self.refresh();
+        Ok(previous)
+    }
+
+    pub fn retain<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&MessageLike) -> bool,
+    {
+        self.messages.retain(predicate);
+    }
+
+    pub fn with_assistant_prefill(mut self, template: Template) -> Self {
+        self.prefill = Some(Arc::new(template));
+        self
+    }
+
+    pub fn assistant_prefill(&self) -> Option<&Template> {
+        self.prefill.as_deref()
+    }
+
+    // Prefill messages are AI messages tagged through `additional_kwargs`.
+    pub fn prefill_message(content: &str) -> Arc<MessageEnum> {
+        let mut ai = AiMessage::new(content);
+        ai.base
+            .additional_kwargs
+            .insert(Self::PREFILL_KEY.to_string(), true.to_string());
+        Arc::new(MessageEnum::Ai(ai))
+    }
+
+    pub fn is_prefill(message: &MessageEnum) -> bool {
+        matches!(message, MessageEnum::Ai(ai) if ai.additional_kwargs().contains_key(Self::PREFILL_KEY))
+    }
+
+    pub(crate) fn format_prefill(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Option<Arc<MessageEnum>>, TemplateError> {
+        self.prefill
+            .as_ref()
+            .map(|template| Ok(Self::prefill_message(&template.format(variables)?)))
+            .transpose()
+    }
+
+    // Promotes base messages whose text holds placeholders. The editing
+    // methods, `+=` and the builder call it; after editing `messages`
+    // directly, call it yourself. Literal braces in a base message, such as
+    // JSON or code, must be escaped as `\{` and `\}`, and render unescaped.
+    pub fn refresh(&mut self) {
+        for message_like in &mut self.messages {
+            let MessageLike::BaseMessage(base_message) = message_like else {
+                continue;
+            };
+
+            let role = match Role::of(base_message) {
+                Role::Tool => continue,
+                role => role,
+            };
 
-        Ok(ChatTemplate { messages: result })
+            let content = base_message.content();
+            if let Ok(template) = Template::from_template(content)
+                && (template.template_format() != TemplateFormat::PlainText
+                    || has_escaped_braces(content))
+            {
+                *message_like = MessageLike::role_prompt_template(role, template);
+            }
+        }
     }
 
     pub fn invoke(
@@ -59,107 +198,446 @@ impl ChatTemplate {
         self.format_messages(variables)
     }
 
-    fn deserialize_placeholder_messages(
-        messages_str: &str,
-        n_messages: usize,
+    pub fn format_messages(
+        &self,
+        variables: &HashMap<&str, &str>,
     ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
-        let deserialized_messages: Vec<MessageEnum> =
-            serde_json::from_str(messages_str).map_err(|e| {
-                TemplateError::MalformedTemplate(format!(
-                    "Failed to deserialize placeholder: {}",
-                    e
-                ))
-            })?;
-
-        let limited_messages = if n_messages > 0 {
-            deserialized_messages.into_iter().take(n_messages).collect()
-        } else {
-            deserialized_messages
+        let mut results = Vec::new();
+
+        for message_like in &self.messages {
+            results.extend(Self::format_message_like(message_like, variables)?);
+        }
+        results.extend(self.format_prefill(variables)?);
+
+        Ok(results)
+    }
+
+    pub fn format_messages_with_options(
+        &self,
+        variables: &HashMap<&str, &str>,
+        options: &FormatOptions,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let messages = self.format_messages(variables)?;
+        for message in &messages {
+            options.audit(message.content())?;
+        }
+        Ok(messages)
+    }
+
+    pub fn format_messages_annotated(
+        &self,
+        variables: &HashMap<&str, &str>,
+        options: &FormatOptions,
+    ) -> Result<Vec<FormattedMessage>, TemplateError> {
+        let counter = options.token_counter();
+        Ok(self
+            .format_messages_with_options(variables, options)?
+            .into_iter()
+            .map(|message| FormattedMessage {
+                tokens: counter.map(|counter| counter.count_tokens(message.content())),
+                message,
+            })
+            .collect())
+    }
+
+    pub fn format_with<T: Serialize + ?Sized>(&self, ctx: &T) -> Result<String, TemplateError> {
+        let variables = serialize_vars(ctx)?;
+        self.format(&borrow_vars(&variables))
+    }
+
+    pub fn format_messages_with<T: Serialize + ?Sized>(
+        &self,
+        ctx: &T,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let variables = serialize_vars(ctx)?;
+        self.format_messages(&borrow_vars(&variables))
+    }
+
+    pub fn format_messages_for(
+        &self,
+        variables: &HashMap<&str, &str>,
+        profile: &ModelProfile,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        self.format_messages(&profile.resolve(variables))
+    }
+
+    pub fn export_for(
+        &self,
+        variables: &HashMap<&str, &str>,
+        profile: &ModelProfile,
+    ) -> Result<Vec<ExportedMessage>, TemplateError> {
+        self.export_normalized(variables, profile, NormalizationRules::builtin())
+    }
+
+    pub fn export_normalized(
+        &self,
+        variables: &HashMap<&str, &str>,
+        profile: &ModelProfile,
+        rules: &NormalizationRules,
+    ) -> Result<Vec<ExportedMessage>, TemplateError> {
+        let mut exported = self.export(&profile.resolve(variables), profile.role_map())?;
+        if let Some(family) = profile.family() {
+            rules.normalize_messages(family, &mut exported);
+        }
+        Ok(exported)
+    }
+
+    pub fn export(
+        &self,
+        variables: &HashMap<&str, &str>,
+        role_map: &RoleMap,
+    ) -> Result<Vec<ExportedMessage>, TemplateError> {
+        let messages = self.format_messages(variables)?;
+        Ok(role_map.export(&messages))
+    }
+
+    pub fn to_openai_messages(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<serde_json::Value, TemplateError> {
+        let serialize_error = |e: serde_json::Error| {
+            TemplateError::MalformedTemplate(format!("Failed to serialize messages: {}", e), None)
         };
 
-        Ok(limited_messages.into_iter().map(Arc::new).collect())
+        // Multimodal messages carry their parts as an array in `content`. OpenAI
+        // takes a participant name but has no cache markers or tags.
+        self.export(variables, &RoleMap::openai())?
+            .into_iter()
+            .map(|mut message| {
+                let parts = std::mem::take(&mut message.content_parts);
+                message.metadata.cache_control = None;
+                message.metadata.tags.clear();
+                let mut value = serde_json::to_value(message).map_err(serialize_error)?;
+                if !parts.is_empty() {
+                    value["content"] = serde_json::to_value(parts).map_err(serialize_error)?;
+                }
+                Ok(value)
+            })
+            .collect::<Result<Vec<_>, TemplateError>>()
+            .map(serde_json::Value::from)
     }
 
-    pub fn format_messages(
+    pub fn format_messages_compressed(
         &self,
         variables: &HashMap<&str, &str>,
+        compression: &PromptCompression,
     ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let compressed_values = variables
+            .iter()
+            .filter(|(name, _)| compression.compresses_variable(name))
+            .map(|(&name, value)| Ok((name, compression.compress(value)?)))
+            .collect::<Result<Vec<(&str, String)>, TemplateError>>()?;
+
+        let mut compressed_variables = variables.clone();
+        for (name, value) in &compressed_values {
+            compressed_variables.insert(name, value.as_str());
+        }
+
         let mut results = Vec::new();
 
         for message_like in &self.messages {
-            let messages = match message_like {
-                MessageLike::BaseMessage(base_message) => vec![base_message.clone()],
+            let messages = Self::format_message_like(message_like, &compressed_variables)?;
 
-                MessageLike::RolePromptTemplate(role, template) => {
-                    let formatted_message = template.format(variables)?;
-                    let base_message = role
-                        .to_message(&formatted_message)
-                        .map_err(|_| TemplateError::InvalidRoleError)?;
-                    vec![base_message]
+            if compression.compresses_history()
+                && matches!(message_like.inner(), MessageLike::Placeholder(_))
+            {
+                for message in &messages {
+                    results.push(compression.compress_message(message)?);
                 }
+            } else {
+                results.extend(messages);
+            }
+        }
 
-                MessageLike::Placeholder(placeholder) => {
-                    if placeholder.optional() {
-                        vec![]
-                    } else {
-                        let messages_str =
-                            variables.get(placeholder.variable_name()).ok_or_else(|| {
-                                TemplateError::MissingVariable(
-                                    placeholder.variable_name().to_string(),
-                                )
-                            })?;
-
-                        Self::deserialize_placeholder_messages(
-                            messages_str,
-                            placeholder.n_messages(),
-                        )?
-                    }
-                }
+        Ok(results)
+    }
+
+    pub(crate) fn format_message_like(
+        message_like: &MessageLike,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let messages = match message_like {
+            MessageLike::BaseMessage(base_message) => vec![base_message.clone()],
+
+            MessageLike::RolePromptTemplate(role, template) => {
+                role.ensure_message_role()?;
+                vec![role.to_message(&template.format(variables)?)?]
+            }
+
+            MessageLike::Placeholder(placeholder) => placeholder.format_messages(variables)?,
+
+            MessageLike::FewShotPrompt(few_shot_template) => few_shot_template.format_messages()?,
 
-                MessageLike::FewShotPrompt(few_shot_template) => {
-                    let formatted_examples = few_shot_template.format_examples()?;
-                    let messages =
-                        MessageEnum::parse_messages(&formatted_examples).map_err(|e| {
-                            TemplateError::MalformedTemplate(format!(
-                                "Failed to parse message: {}",
-                                e
-                            ))
-                        })?;
-
-                    messages.into_iter().map(Arc::new).collect()
+            MessageLike::ToolCallTemplate(tool_call_template) => {
+                vec![tool_call_template.format_message(variables)?]
+            }
+
+            MessageLike::Multimodal(multimodal_template) => {
+                vec![multimodal_template.format_message(variables)?]
+            }
+
+            MessageLike::ForEach {
+                variable,
+                item_template,
+                role,
+            } => format_for_each(variable, item_template, role, variables)?,
+
+            MessageLike::SubTemplate(_, chat_template) => {
+                let mut messages = Vec::new();
+                for message_like in &chat_template.messages {
+                    messages.extend(Self::format_message_like(message_like, variables)?);
                 }
-            };
+                messages
+            }
 
-            results.extend(messages);
-        }
+            MessageLike::Annotated(message_like, metadata) => {
+                Self::format_message_like(message_like, variables)?
+                    .into_iter()
+                    .map(|message| metadata.apply(message))
+                    .collect()
+            }
+        };
 
-        Ok(results)
+        Ok(messages)
     }
 
-    pub fn to_variables_map(&self) -> HashMap<&str, &str> {
-        let mut variables = HashMap::new();
+    pub fn to_variables_map(&self) -> HashMap<String, Vec<Role>> {
+        let mut variables: HashMap<String, Vec<Role>> = HashMap::new();
+        let mut insert = |name: String, role: Role| {
+            let roles = variables.entry(name).or_default();
+            if !roles.contains(&role) {
+                roles.push(role);
+            }
+        };
 
-        for message in &self.messages {
+        for message in self.messages.iter().map(MessageLike::inner) {
             match message {
                 MessageLike::RolePromptTemplate(role, template) => {
-                    let extracted_vars = extract_variables(template.template());
-
-                    if let Some(&var) = extracted_vars.first() {
-                        variables.insert(var, role.as_str());
+                    for var in template.input_variables() {
+                        insert(var, role.clone());
                     }
                 }
                 MessageLike::BaseMessage(base_message) => {
-                    if let Some(content) = extract_variables(base_message.content()).first() {
-                        let role_str = base_message.message_type().as_str();
-                        variables.insert(content, role_str);
+                    let role = Role::of(base_message);
+                    for var in extract_variables(base_message.content()) {
+                        insert(var.to_string(), role.clone());
+                    }
+                }
+                MessageLike::Placeholder(placeholder) => {
+                    insert(placeholder.variable_name().to_string(), Role::Placeholder);
+                }
+                MessageLike::FewShotPrompt(few_shot_prompt) => {
+                    for template in few_shot_prompt
+                        .prefix()
+                        .into_iter()
+                        .chain(few_shot_prompt.suffix())
+                    {
+                        for var in template.input_variables() {
+                            insert(var, Role::FewShotPrompt);
+                        }
+                    }
+                }
+                MessageLike::ToolCallTemplate(tool_call_template) => {
+                    for var in tool_call_template.input_variables() {
+                        insert(var, Role::Ai);
+                    }
+                }
+                MessageLike::Multimodal(multimodal_template) => {
+                    for var in multimodal_template.input_variables() {
+                        insert(var, multimodal_template.role().clone());
+                    }
+                }
+                MessageLike::ForEach { variable, role, .. } => {
+                    insert(variable.clone(), role.clone());
+                }
+                MessageLike::SubTemplate(_, chat_template) => {
+                    for (var, roles) in chat_template.to_variables_map() {
+                        for role in roles {
+                            insert(var.clone(), role);
+                        }
+                    }
+                }
+                MessageLike::Annotated(..) => {}
+            }
+        }
+        variables
+    }
+
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        let mut push = |name: String| {
+            if !variables.contains(&name) {
+                variables.push(name);
+            }
+        };
+
+        for message in self.messages.iter().map(MessageLike::inner) {
+            match message {
+                MessageLike::BaseMessage(_) | MessageLike::Annotated(..) => {}
+                MessageLike::RolePromptTemplate(_, template) => {
+                    template
+                        .required_variables()
+                        .into_iter()
+                        .filter(|var| !template.partial_vars().contains_key(var))
+                        .for_each(&mut push);
+                }
+                MessageLike::Placeholder(placeholder) => {
+                    if !placeholder.optional() && !placeholder.has_source() {
+                        push(placeholder.variable_name().to_string());
                     }
                 }
-                _ => {}
+                MessageLike::FewShotPrompt(few_shot_prompt) => {
+                    few_shot_prompt
+                        .input_variables()
+                        .into_iter()
+                        .for_each(&mut push);
+                }
+                MessageLike::ToolCallTemplate(tool_call_template) => {
+                    tool_call_template
+                        .input_variables()
+                        .into_iter()
+                        .for_each(&mut push);
+                }
+                MessageLike::Multimodal(multimodal_template) => {
+                    multimodal_template
+                        .input_variables()
+                        .into_iter()
+                        .for_each(&mut push);
+                }
+                MessageLike::ForEach { variable, .. } => push(variable.clone()),
+                MessageLike::SubTemplate(_, chat_template) => {
+                    chat_template
+                        .input_variables()
+                        .into_iter()
+                        .for_each(&mut push);
+                }
             }
         }
+        if let Some(prefill) = &self.prefill {
+            prefill
+                .required_variables()
+                .into_iter()
+                .filter(|var| !prefill.partial_vars().contains_key(var))
+                .for_each(&mut push);
+        }
+
         variables
     }
 
+    pub fn validate(&self, available_vars: &[&str]) -> Result<ValidationReport, TemplateError> {
+        if let Some(invalid) = available_vars.iter().find(|var| !is_valid_identifier(var)) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Invalid variable name: {}", invalid),
+                None,
+            ));
+        }
+
+        let mut report = ValidationReport::default();
+        for (index, message) in self.messages.iter().enumerate() {
+            let checked =
+                match message.inner() {
+                    MessageLike::RolePromptTemplate(_, template)
+                    | MessageLike::ForEach {
+                        item_template: template,
+                        ..
+                    } => Template::new_with_config(
+                        template.template(),
+                        Some(template.template_format()),
+                        None,
+                    )
+                    .map(|_| ()),
+                    MessageLike::FewShotPrompt(few_shot_prompt) => {
+                        few_shot_prompt.format_messages().map(|_| ())
+                    }
+                    MessageLike::SubTemplate(_, chat_template) => chat_template
+                        .validate(&[])
+                        .and_then(|nested| match nested.malformed.into_iter().next() {
+                            Some(issue) => Err(issue.error),
+                            None => Ok(()),
+                        }),
+                    MessageLike::BaseMessage(_)
+                    | MessageLike::Placeholder(_)
+                    | MessageLike::ToolCallTemplate(_)
+                    | MessageLike::Multimodal(_)
+                    | MessageLike::Annotated(..) => Ok(()),
+                };
+            if let Err(error) = checked {
+                report.malformed.push(MessageIssue { index, error });
+            }
+        }
+
+        report.missing = self
+            .input_variables()
+            .into_iter()
+            .filter(|var| !available_vars.contains(&var.as_str()))
+            .collect();
+
+        let referenced = self.to_variables_map();
+        for var in available_vars {
+            let var = var.to_string();
+            if !referenced.contains_key(&var) && !report.unused.contains(&var) {
+                report.unused.push(var);
+            }
+        }
+
+        Ok(report)
+    }
+
+    pub fn metrics(&self) -> PromptMetrics {
+        let mut variables = HashSet::new();
+        let mut metrics = PromptMetrics {
+            message_count: self.messages.len(),
+            nesting_depth: 1,
+            ..Default::default()
+        };
+
+        for message in self.messages.iter().map(MessageLike::inner) {
+            match message {
+                MessageLike::BaseMessage(base_message) => {
+                    metrics.estimated_tokens += estimate_tokens(base_message.content());
+                }
+                MessageLike::RolePromptTemplate(_, template) => {
+                    variables.extend(template.input_variables());
+                    metrics.estimated_tokens += estimate_tokens(template.template());
+                }
+                MessageLike::Placeholder(placeholder) => {
+                    variables.insert(placeholder.variable_name().to_string());
+                }
+                MessageLike::FewShotPrompt(few_shot_prompt) => {
+                    let nested = few_shot_prompt.metrics();
+                    metrics.few_shot_example_count += nested.few_shot_example_count;
+                    metrics.estimated_tokens += nested.estimated_tokens;
+                    metrics.nesting_depth = metrics.nesting_depth.max(nested.nesting_depth + 1);
+                }
+                MessageLike::ToolCallTemplate(tool_call_template) => {
+                    variables.extend(tool_call_template.input_variables());
+                }
+                MessageLike::Multimodal(multimodal_template) => {
+                    variables.extend(multimodal_template.input_variables());
+                }
+                MessageLike::ForEach {
+                    variable,
+                    item_template,
+                    ..
+                } => {
+                    variables.insert(variable.clone());
+                    metrics.estimated_tokens += estimate_tokens(item_template.template());
+                }
+                MessageLike::SubTemplate(_, chat_template) => {
+                    let nested = chat_template.metrics();
+                    variables.extend(chat_template.input_variables());
+                    metrics.few_shot_example_count += nested.few_shot_example_count;
+                    metrics.estimated_tokens += nested.estimated_tokens;
+                    metrics.nesting_depth = metrics.nesting_depth.max(nested.nesting_depth + 1);
+                }
+                MessageLike::Annotated(..) => {}
+            }
+        }
+
+        metrics.variable_count = variables.len();
+        metrics
+    }
+
     pub async fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
         let toml_content = fs::read_to_string(path).await.map_err(|e| {
             TemplateError::TomlDeserializationError(format!("Failed to read TOML file: {}", e))
@@ -167,49 +645,126 @@ impl ChatTemplate {
 
         ChatTemplate::try_from(toml_content)
     }
+
+    pub fn to_toml_string(&self) -> Result<String, TemplateError> {
+        toml::to_string(self).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Failed to serialize TOML: {}", e), None)
+        })
+    }
+
+    pub async fn save_toml_file<P: AsRef<Path>>(&self, path: P) -> Result<(), TemplateError> {
+        fs::write(path, self.to_toml_string()?)
+            .await
+            .map_err(|e| TemplateError::RuntimeError(format!("Failed to write TOML file: {}", e)))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ChatTemplateBuilder {
+    messages: Vec<MessageLike>,
+}
+
+impl ChatTemplateBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn message(mut self, role: Role, template: Template) -> Self {
+        self.messages
+            .push(MessageLike::role_prompt_template(role, template));
+        self
+    }
+
+    pub fn system(self, template: Template) -> Self {
+        self.message(Role::System, template)
+    }
+
+    pub fn human(self, template: Template) -> Self {
+        self.message(Role::Human, template)
+    }
+
+    pub fn ai(self, template: Template) -> Self {
+        self.message(Role::Ai, template)
+    }
+
+    pub fn placeholder(mut self, placeholder: MessagesPlaceholder) -> Self {
+        self.messages.push(MessageLike::placeholder(placeholder));
+        self
+    }
+
+    pub fn few_shot_prompt(mut self, few_shot_prompt: FewShotChatTemplate) -> Self {
+        self.messages
+            .push(MessageLike::few_shot_prompt(few_shot_prompt));
+        self
+    }
+
+    pub fn message_like(mut self, message_like: MessageLike) -> Self {
+        self.messages.push(message_like);
+        self
+    }
+
+    pub fn build(self) -> ChatTemplate {
+        let mut chat_template = ChatTemplate {
+            messages: self.messages,
+            prefill: None,
+            sensitive: false,
+        };
+        chat_template.refresh();
+        chat_template
+    }
 }
 
 impl Formattable for ChatTemplate {
     fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
         let formatted_messages = self.format_messages(variables)?;
+        PlainPrefix.render(&formatted_messages)
+    }
 
-        let combined_result = formatted_messages
-            .iter()
-            .map(|message| {
-                let role_prefix = match message.message_type() {
-                    MessageType::Human => "human: ",
-                    MessageType::Ai => "ai: ",
-                    MessageType::System => "system: ",
-                    _ => "",
-                };
-                format!("{}{}", role_prefix, message.content())
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
-
-        Ok(combined_result)
+    fn format_with_config(
+        &self,
+        variables: &HashMap<&str, &str>,
+        config: Option<&PromptForgeConfig>,
+    ) -> Result<String, TemplateError> {
+        let Some(config) = config else {
+            return self.format(variables);
+        };
+        let messages = config
+            .prepare_chat(self, variables)?
+            .format_messages_with_options(variables, config.format_options())?;
+        config
+            .format_options()
+            .finish(config.style().join(&messages))
     }
 }
 
 impl Add for ChatTemplate {
     type Output = ChatTemplate;
     fn add(mut self, other: ChatTemplate) -> ChatTemplate {
-        self.messages.extend(other.messages);
+        self += other;
         self
     }
 }
 
+impl AddAssign for ChatTemplate {
+    fn add_assign(&mut self, other: ChatTemplate) {
+        self.messages.extend(other.messages);
+        self.prefill = other.prefill.or(self.prefill.take());
+        self.sensitive = self.sensitive || other.sensitive;
+        self.refresh();
+    }
+}
+
 impl TryFrom<String> for ChatTemplate {
     type Error = TemplateError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         if value.trim().starts_with('{') {
             serde_json::from_str(&value).map_err(|err| {
-                TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", err))
+                TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", err), None)
             })
         } else {
             toml::from_str(&value).map_err(|err| {
-                TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", err))
+                TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", err), None)
             })
         }
     }
@@ -222,30 +777,37 @@ impl TryFrom<Vec<MessageConfig>> for ChatTemplate {
         let messages = configs
             .into_iter()
             .map(|config| {
-                let role = Role::try_from(config.value.role.as_str())
-                    .map_err(|_| TemplateError::InvalidRoleError)?;
-                let content = config.value.content;
-
-                Ok((role, content))
+                MessageLike::from_config(config).map_err(|err| match err {
+                    TemplateError::InvalidRoleError => err,
+                    _ => TemplateError::MalformedTemplate(
+                        "Failed to deserialize TOML into ChatTemplate messages.".to_string(),
+                        None,
+                    ),
+                })
             })
             .collect::<Result<Vec<_>, Self::Error>>()?;
 
-        ChatTemplate::from_messages(messages).map_err(|_| {
-            TemplateError::MalformedTemplate(
-                "Failed to deserialize TOML into ChatTemplate messages.".to_string(),
-            )
+        Ok(ChatTemplate {
+            messages,
+            prefill: None,
+            sensitive: false,
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use messageforge::tool_message::ToolStatus;
+    use messageforge::{AiMessage, HumanMessage, SystemMessage, ToolMessage};
     use serde_json::json;
 
     use super::*;
     use crate::message_like::MessageLike;
     use crate::Role::{Ai, FewShotPrompt, Human, Placeholder, System};
-    use crate::{chats, examples, vars, FewShotChatTemplate, FewShotTemplate};
+    use crate::{
+        chats, examples, vars, CacheControl, ContentPart, FewShotChatTemplate, FewShotTemplate,
+        MessageMetadata, MultimodalTemplate, RoleMapping, TextTransform, ToolCallTemplate,
+    };
 
     #[test]
     fn test_from_messages_plaintext() {
@@ -606,6 +1168,22 @@ human: Can I help you with anything else, Bob?";
         assert_eq!(formatted_output, expected_output);
     }
 
+    #[test]
+    fn test_non_message_roles_fail_with_targeted_errors() {
+        assert!(matches!(
+            ChatTemplate::from_messages(vec![(Role::Tool, "Result: {result}".to_string())]),
+            Err(TemplateError::ToolRoleNotSupported(_))
+        ));
+
+        let chat_template = ChatTemplate::builder()
+            .message(Role::Placeholder, Template::new("{history}").unwrap())
+            .build();
+        assert!(matches!(
+            chat_template.format_messages(&vars!(history = "[]")),
+            Err(TemplateError::PlaceholderNotAllowedHere(_))
+        ));
+    }
+
     #[test]
     fn test_format_with_missing_variable_error() {
         let templates = chats!(
@@ -714,15 +1292,69 @@ human: Thanks, AI.";
     }
 
     #[test]
-    fn test_to_variables_map_with_full_example() {
-        let chat_template = ChatTemplate::from_messages(chats!(
+    fn test_from_message_likes() {
+        let chat_template = ChatTemplate::from_message_likes(vec![
+            MessageLike::base_message(HumanMessage::new("Hi").into()),
+            MessageLike::role_prompt_template(Ai, Template::new("Hello {name}").unwrap()),
+            MessageLike::placeholder(MessagesPlaceholder::new("history".to_string())),
+        ])
+        .unwrap();
+        assert_eq!(chat_template.input_variables(), vec!["name", "history"]);
+
+        let rejected = ChatTemplate::from_message_likes(vec![MessageLike::role_prompt_template(
+            Placeholder,
+            Template::new("{history}").unwrap(),
+        )]);
+        assert!(matches!(
+            rejected,
+            Err(TemplateError::PlaceholderNotAllowedHere(_))
+        ));
+    }
+
+    #[test]
+    fn test_optional_placeholder_renders_when_provided() {
+        let mut chat_template = ChatTemplate::from_messages(chats!(Human = "{question}")).unwrap();
+        chat_template.messages.insert(
+            0,
+            MessageLike::placeholder(MessagesPlaceholder::with_options(
+                "history".to_string(),
+                true,
+                10,
+            )),
+        );
+        let compiled = chat_template.compile().unwrap();
+        let history = json!([{"role": "ai", "content": "Earlier answer."}]).to_string();
+
+        let with_history = vars!(history = history.as_str(), question = "And now?");
+        for messages in [
+            chat_template.format_messages(&with_history).unwrap(),
+            compiled.format_messages(&with_history).unwrap(),
+        ] {
+            assert_eq!(messages.len(), 2);
+            assert_eq!(messages[0].content(), "Earlier answer.");
+        }
+
+        let without_history = vars!(question = "And now?");
+        for messages in [
+            chat_template.format_messages(&without_history).unwrap(),
+            compiled.format_messages(&without_history).unwrap(),
+        ] {
+            assert_eq!(messages.len(), 1);
+            assert_eq!(messages[0].content(), "And now?");
+        }
+    }
+
+    #[test]
+    fn test_to_variables_map_with_full_example() {
+        let chat_template = ChatTemplate::from_messages(chats!(
             System = "You are a helpful AI bot. Your name is {name}.",
             Ai = "I'm doing well, thank you.",
         ))
         .unwrap();
 
         let variables = chat_template.to_variables_map();
-        let expected: HashMap<&str, &str> = [("name", "system")].into_iter().collect();
+        let expected: HashMap<String, Vec<Role>> =
+            [("name".to_string(), vec![System])].into_iter().collect();
         assert_eq!(variables, expected);
     }
 
@@ -735,7 +1367,7 @@ human: Thanks, AI.";
         .unwrap();
 
         let variables = chat_template.to_variables_map();
-        let expected: HashMap<&str, &str> = HashMap::new();
+        let expected: HashMap<String, Vec<Role>> = HashMap::new();
         assert_eq!(variables, expected);
     }
 
@@ -748,7 +1380,8 @@ human: Thanks, AI.";
         .unwrap();
 
         let variables = chat_template.to_variables_map();
-        let expected: HashMap<&str, &str> = [("name", "human")].into_iter().collect();
+        let expected: HashMap<String, Vec<Role>> =
+            [("name".to_string(), vec![Human])].into_iter().collect();
         assert_eq!(variables, expected);
     }
 
@@ -758,21 +1391,157 @@ human: Thanks, AI.";
             ChatTemplate::from_messages(chats!(Human = "{question}", Ai = "{answer}",)).unwrap();
 
         let variables = chat_template.to_variables_map();
-        let expected: HashMap<&str, &str> = [("question", "human"), ("answer", "ai")]
-            .into_iter()
-            .collect();
+        let expected: HashMap<String, Vec<Role>> = [
+            ("question".to_string(), vec![Human]),
+            ("answer".to_string(), vec![Ai]),
+        ]
+        .into_iter()
+        .collect();
         assert_eq!(variables, expected);
     }
 
+    #[test]
+    fn test_to_variables_map_collects_every_variable_and_role() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You help {user} with {topic}.",
+            Placeholder = "{history}",
+            Human = "{question} — thanks, {user}",
+        ))
+        .unwrap();
+
+        let variables = chat_template.to_variables_map();
+        assert_eq!(variables.len(), 4);
+        assert_eq!(variables["user"], vec![System, Human]);
+        assert_eq!(variables["topic"], vec![System]);
+        assert_eq!(variables["history"], vec![Placeholder]);
+        assert_eq!(variables["question"], vec![Human]);
+    }
+
+    #[test]
+    fn test_validate_reports_missing_and_unused() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You help {user} with {topic:anything}.",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let report = chat_template
+            .validate(&["user", "topic", "question", "tone"])
+            .unwrap();
+        assert_eq!(report.missing, vec!["history"]);
+        assert_eq!(report.unused, vec!["tone"]);
+        assert!(report.malformed.is_empty());
+        assert!(!report.is_valid());
+        assert!(matches!(
+            report.into_result(),
+            Err(TemplateError::MissingVariable(name)) if name == "history"
+        ));
+
+        assert!(chat_template
+            .validate(&["user", "history", "question"])
+            .unwrap()
+            .is_valid());
+        assert!(chat_template.validate(&["not valid"]).is_err());
+    }
+
+    #[test]
+    fn test_validate_reports_malformed_nested_templates() {
+        let broken: Template = serde_json::from_value(json!({
+            "template": "Hello {name",
+            "template_format": "FmtString",
+            "input_variables": ["name"],
+        }))
+        .unwrap();
+        let chat_template = ChatTemplate::builder()
+            .system(Template::new("Be brief.").unwrap())
+            .human(broken)
+            .build();
+
+        let report = chat_template.validate(&["name"]).unwrap();
+        assert_eq!(report.malformed.len(), 1);
+        assert_eq!(report.malformed[0].index, 1);
+        assert!(report.malformed[0].error.span().is_some());
+        assert!(report.into_result().is_err());
+    }
+
     #[test]
     fn test_to_variables_map_with_empty_template() {
-        let chat_template = ChatTemplate { messages: vec![] };
+        let chat_template = ChatTemplate {
+            messages: vec![],
+            prefill: None,
+            sensitive: false,
+        };
 
         let variables = chat_template.to_variables_map();
-        let expected: HashMap<&str, &str> = HashMap::new();
+        let expected: HashMap<String, Vec<Role>> = HashMap::new();
         assert_eq!(variables, expected);
     }
 
+    #[test]
+    fn test_input_variables_across_messages() {
+        let few_shot = FewShotChatTemplate::new(
+            FewShotTemplate::builder()
+                .examples(examples!(("{input}: 2+2", "{output}: 4")))
+                .suffix(Template::new("Now solve {problem}").unwrap())
+                .build(),
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap(),
+        );
+        let mut greeting = Template::new("Hi {name}, I am {bot}. Topic: {topic:general}").unwrap();
+        greeting.partial("bot", "Forge");
+
+        let chat_template = ChatTemplate::builder()
+            .system(greeting)
+            .placeholder(MessagesPlaceholder::new("history".to_string()))
+            .placeholder(MessagesPlaceholder::with_options(
+                "scratchpad".to_string(),
+                true,
+                MessagesPlaceholder::DEFAULT_LIMIT,
+            ))
+            .few_shot_prompt(few_shot)
+            .human(Template::new("{question} (from {name})").unwrap())
+            .message_like(MessageLike::base_message(MessageEnum::Ai(AiMessage::new(
+                "Understood.",
+            ))))
+            .build();
+
+        assert_eq!(
+            chat_template.input_variables(),
+            vec!["name", "history", "problem", "question"]
+        );
+        assert!(ChatTemplate {
+            messages: vec![],
+            prefill: None,
+            sensitive: false,
+        }
+        .input_variables()
+        .is_empty());
+    }
+
+    #[test]
+    fn test_format_messages_keeps_most_recent_history() {
+        let chat_template = ChatTemplate::builder()
+            .placeholder(MessagesPlaceholder::with_options(
+                "history".to_string(),
+                false,
+                2,
+            ))
+            .human(Template::new("{question}").unwrap())
+            .build();
+        let history = json!([
+            {"role": "human", "content": "first"},
+            {"role": "ai", "content": "second"},
+            {"role": "human", "content": "third"},
+        ])
+        .to_string();
+
+        let messages = chat_template
+            .format_messages(&vars!(history = history.as_str(), question = "fourth"))
+            .unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["second", "third", "fourth"]);
+    }
+
     #[test]
     fn test_from_messages_with_few_shot_prompt() {
         let examples = examples!(
@@ -898,7 +1667,7 @@ human: What is 4+4?";
 
         let result = ChatTemplate::try_from(invalid_json.to_string());
         assert!(result.is_err());
-        if let Err(TemplateError::MalformedTemplate(error_msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(error_msg, _)) = result {
             assert!(error_msg.contains("Failed to parse JSON"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -915,10 +1684,702 @@ human: What is 4+4?";
 
         let result = ChatTemplate::try_from(invalid_toml.to_string());
         assert!(result.is_err());
-        if let Err(TemplateError::MalformedTemplate(error_msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(error_msg, _)) = result {
             assert!(error_msg.contains("Failed to parse TOML"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
         }
     }
+
+    #[test]
+    fn test_builder_matches_from_messages() {
+        let built = ChatTemplate::builder()
+            .system(Template::new("You are a helpful assistant.").unwrap())
+            .placeholder(MessagesPlaceholder::new("history".to_string()))
+            .human(Template::new("Hello, {name}!").unwrap())
+            .ai(Template::new("Hi {name}, how can I help?").unwrap())
+            .build();
+
+        let from_messages = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Placeholder = "{history}",
+            Human = "Hello, {name}!",
+            Ai = "Hi {name}, how can I help?",
+        ))
+        .unwrap();
+
+        let history = r#"[{"role": "human", "content": "Earlier question"}]"#;
+        let variables = &vars!(name = "Alice", history = history);
+
+        assert_eq!(built.messages.len(), 4);
+        assert_eq!(
+            built.format(variables).unwrap(),
+            from_messages.format(variables).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_refresh_promotes_base_messages_with_variables() {
+        let mut chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Human = "Hello!",
+        ))
+        .unwrap();
+
+        chat_template.messages[1] =
+            MessageLike::base_message(HumanMessage::new("Hello, {name}!").into());
+        assert!(chat_template
+            .format(&vars!(name = "Alice"))
+            .unwrap()
+            .ends_with("Hello, {name}!"));
+
+        chat_template.refresh();
+
+        assert!(matches!(
+            chat_template.messages[0],
+            MessageLike::BaseMessage(_)
+        ));
+        match &chat_template.messages[1] {
+            MessageLike::RolePromptTemplate(role, template) => {
+                assert_eq!(role, &Role::Human);
+                assert_eq!(template.input_variables(), vec!["name".to_string()]);
+            }
+            other => panic!("Expected RolePromptTemplate, got {:?}", other),
+        }
+        assert_eq!(
+            chat_template.format(&vars!(name = "Alice")).unwrap(),
+            "system: You are a helpful assistant.\nhuman: Hello, Alice!"
+        );
+    }
+
+    #[test]
+    fn test_refresh_leaves_malformed_and_tool_messages() {
+        let mut chat_template = ChatTemplate {
+            messages: vec![
+                MessageLike::base_message(AiMessage::new("{not closed").into()),
+                MessageLike::base_message(
+                    ToolMessage::new("{result}", "call_1".to_string(), None, ToolStatus::Success)
+                        .into(),
+                ),
+            ],
+            prefill: None,
+            sensitive: false,
+        };
+
+        chat_template.refresh();
+
+        assert!(chat_template
+            .messages
+            .iter()
+            .all(|message| matches!(message, MessageLike::BaseMessage(_))));
+    }
+
+    #[test]
+    fn test_mutations_refresh_messages() {
+        let json = MessageLike::base_message(AiMessage::new(r#"\{"status": "ok"\}"#).into());
+        let first = ChatTemplate::from_messages(chats!(System = "Be brief.")).unwrap();
+        let second = ChatTemplate::builder()
+            .message_like(json)
+            .message_like(MessageLike::base_message(
+                HumanMessage::new("Explain {code}").into(),
+            ))
+            .build();
+        assert!(matches!(
+            second.messages[1],
+            MessageLike::RolePromptTemplate(..)
+        ));
+
+        let mut combined = first + second;
+        combined
+            .push(MessageLike::base_message(
+                HumanMessage::new("Summarize {topic}").into(),
+            ))
+            .unwrap();
+        assert_eq!(
+            combined
+                .format(&vars!(code = "fn f() {}", topic = "Rust"))
+                .unwrap(),
+            "system: Be brief.\nai: {\"status\": \"ok\"}\nhuman: Explain fn f() {}\nhuman: Summarize Rust"
+        );
+
+        combined
+            .replace(
+                3,
+                MessageLike::base_message(HumanMessage::new("Now {task}").into()),
+            )
+            .unwrap();
+        assert!(combined
+            .format(&vars!(code = "x", task = "review"))
+            .unwrap()
+            .ends_with("human: Now review"));
+    }
+
+    #[test]
+    fn test_editing_methods() {
+        let mut chat_template =
+            ChatTemplate::from_messages(chats!(System = "Be brief.", Human = "{question}"))
+                .unwrap();
+
+        chat_template
+            .insert(
+                0,
+                MessageLike::base_message(SystemMessage::new("DEBUG: trace on").into()),
+            )
+            .unwrap();
+        chat_template
+            .push(MessageLike::role_prompt_template(
+                Ai,
+                Template::new("Answer: {answer}").unwrap(),
+            ))
+            .unwrap();
+        assert!(chat_template
+            .push(MessageLike::role_prompt_template(
+                Placeholder,
+                Template::new("{history}").unwrap(),
+            ))
+            .is_err());
+
+        let replaced = chat_template
+            .replace(
+                1,
+                MessageLike::base_message(SystemMessage::new("Be thorough.").into()),
+            )
+            .unwrap();
+        assert!(matches!(replaced, MessageLike::BaseMessage(m) if m.content() == "Be brief."));
+        assert!(matches!(
+            chat_template.remove(3),
+            MessageLike::RolePromptTemplate(Ai, _)
+        ));
+
+        chat_template += ChatTemplate::from_messages(chats!(Ai = "Sure.")).unwrap();
+        assert_eq!(
+            chat_template.format(&vars!(question = "Why?")).unwrap(),
+            "system: DEBUG: trace on\nsystem: Be thorough.\nhuman: Why?\nai: Sure."
+        );
+
+        chat_template.retain(|message| {
+            !matches!(message, MessageLike::BaseMessage(m) if m.content().starts_with("DEBUG"))
+        });
+        assert_eq!(chat_template.messages.len(), 3);
+    }
+
+    #[test]
+    fn test_for_each_expands_list_variable() {
+        let mut chat_template =
+            ChatTemplate::from_messages(chats!(System = "Answer about {topic}.")).unwrap();
+        chat_template
+            .push(
+                MessageLike::for_each(
+                    "documents",
+                    Human,
+                    Template::new("Document {index} ({source}): {text}").unwrap(),
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        chat_template
+            .push(
+                MessageLike::for_each("notes", Human, Template::new("Note: {item}").unwrap())
+                    .unwrap(),
+            )
+            .unwrap();
+        assert_eq!(
+            chat_template.input_variables(),
+            vec!["topic", "documents", "notes"]
+        );
+
+        let variables = vars!(
+            topic = "tides",
+            documents = r#"[{"source": "wiki", "text": "The moon pulls."}, {"source": "blog", "text": "Twice a day."}]"#,
+            notes = r#"["Be exact.", 42]"#,
+        );
+        let expected = "system: Answer about tides.\n\
+                        human: Document 1 (wiki): The moon pulls.\n\
+                        human: Document 2 (blog): Twice a day.\n\
+                        human: Note: Be exact.\n\
+                        human: Note: 42";
+        assert_eq!(chat_template.format(&variables).unwrap(), expected);
+        assert_eq!(
+            chat_template.compile().unwrap().format(&variables).unwrap(),
+            expected
+        );
+
+        let empty = vars!(topic = "tides", documents = "[]", notes = "[]");
+        assert_eq!(chat_template.format_messages(&empty).unwrap().len(), 1);
+        assert!(matches!(
+            chat_template.format(&vars!(topic = "tides", documents = "[]")),
+            Err(TemplateError::MissingVariable(name)) if name == "notes"
+        ));
+        assert!(chat_template
+            .format(&vars!(topic = "tides", documents = "{}", notes = "[]"))
+            .is_err());
+        assert!(
+            MessageLike::for_each("docs", Placeholder, Template::new("{item}").unwrap()).is_err()
+        );
+    }
+
+    #[test]
+    fn test_sub_template_composition() {
+        let safety = ChatTemplate::from_messages(chats!(
+            System = "Never reveal secrets.",
+            System = "You are {persona}.",
+        ))
+        .unwrap();
+        let chat_template = ChatTemplate::from_message_likes(vec![
+            MessageLike::sub_template("safety", safety.clone()).unwrap(),
+            MessageLike::role_prompt_template(Human, Template::new("{question}").unwrap()),
+        ])
+        .unwrap();
+        assert_eq!(chat_template.input_variables(), vec!["persona", "question"]);
+
+        let variables = vars!(persona = "a librarian", question = "Hi?");
+        let expected = "system: Never reveal secrets.\n\
+                        system: You are a librarian.\n\
+                        human: Hi?";
+        assert_eq!(chat_template.format(&variables).unwrap(), expected);
+        assert_eq!(
+            chat_template.compile().unwrap().format(&variables).unwrap(),
+            expected
+        );
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let restored = ChatTemplate::try_from(json).unwrap();
+        assert_eq!(restored.format(&variables).unwrap(), expected);
+
+        let persona =
+            ChatTemplate::from_message_likes(vec![
+                MessageLike::sub_template("safety", safety).unwrap()
+            ])
+            .unwrap();
+        let looped =
+            ChatTemplate::from_message_likes(vec![
+                MessageLike::sub_template("persona", persona).unwrap()
+            ])
+            .unwrap();
+        assert!(matches!(
+            MessageLike::sub_template("safety", looped),
+            Err(TemplateError::MalformedTemplate(message, _))
+                if message == "Sub-template cycle: safety -> persona -> safety"
+        ));
+    }
+
+    #[test]
+    fn test_format_messages_with_options_audits_unfilled_placeholders() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "Use this context: {context}",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let variables = vars!(context = "Greet {user_name} politely.", question = "Hi!");
+        let strict = FormatOptions::new()
+            .with_unfilled_placeholders(crate::UnfilledPlaceholderPolicy::Error);
+
+        assert!(chat_template
+            .format_messages_with_options(&variables, &FormatOptions::new())
+            .is_ok());
+        assert!(matches!(
+            chat_template.format_messages_with_options(&variables, &strict),
+            Err(TemplateError::UnfilledPlaceholder(msg)) if msg.contains("{user_name}")
+        ));
+        assert!(matches!(
+            chat_template.format_with_options(&variables, &strict),
+            Err(TemplateError::UnfilledPlaceholder(_))
+        ));
+
+        let variables = vars!(context = "Greet the user politely.", question = "Hi!");
+        assert_eq!(
+            chat_template
+                .format_messages_with_options(&variables, &strict)
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_format_messages_annotated_with_token_counter() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let variables = vars!(question = "What is the capital of France?");
+
+        let unannotated = chat_template
+            .format_messages_annotated(&variables, &FormatOptions::new())
+            .unwrap();
+        assert!(unannotated.iter().all(|message| message.tokens.is_none()));
+
+        let options = FormatOptions::new().with_token_counter(crate::EstimatedTokenCounter);
+        let annotated = chat_template
+            .format_messages_annotated(&variables, &options)
+            .unwrap();
+        assert_eq!(
+            annotated[1].message.content(),
+            "What is the capital of France?"
+        );
+        assert_eq!(
+            annotated.iter().map(|m| m.tokens).collect::<Vec<_>>(),
+            vec![Some(7), Some(8)]
+        );
+
+        let words =
+            FormatOptions::new().with_token_counter(|text: &str| text.split_whitespace().count());
+        let annotated = chat_template
+            .format_messages_annotated(&variables, &words)
+            .unwrap();
+        assert_eq!(annotated[1].tokens, Some(6));
+    }
+
+    #[test]
+    fn test_assistant_prefill_renders_last() {
+        let chat_template = ChatTemplate::from_messages(chats!(Human = "{question}"))
+            .unwrap()
+            .with_assistant_prefill(Template::new("Answer in {lang}:").unwrap());
+        assert_eq!(chat_template.input_variables(), vec!["question", "lang"]);
+
+        let variables = vars!(question = "Why?", lang = "French");
+        let messages = chat_template.format_messages(&variables).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(ChatTemplate::is_prefill(&messages[1]));
+        assert_eq!(messages[1].content(), "Answer in French:");
+        assert!(!ChatTemplate::is_prefill(&messages[0]));
+
+        assert_eq!(
+            chat_template
+                .compile()
+                .unwrap()
+                .format_messages(&variables)
+                .unwrap(),
+            messages
+        );
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let restored: ChatTemplate = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.assistant_prefill().unwrap().template(),
+            "Answer in {lang}:"
+        );
+
+        let plain = ChatTemplate::from_messages(chats!(System = "Hi")).unwrap();
+        assert!(!serde_json::to_string(&plain).unwrap().contains("prefill"));
+        let combined = chat_template + plain;
+        assert!(combined.assistant_prefill().is_some());
+    }
+
+    #[test]
+    fn test_tool_call_template_renders_in_chat() {
+        let tool_call = ToolCallTemplate::new()
+            .with_call("call_1", "search", json!({"query": "{question}"}))
+            .unwrap();
+        let chat_template = ChatTemplate {
+            messages: vec![
+                MessageLike::role_prompt_template(
+                    Role::Human,
+                    Template::new("{question}").unwrap(),
+                ),
+                MessageLike::tool_call_template(tool_call),
+            ],
+            prefill: None,
+            sensitive: false,
+        };
+        assert_eq!(chat_template.input_variables(), vec!["question"]);
+
+        let variables = vars!(question = "rust \"async\"");
+        let exported = chat_template
+            .export(&variables, &RoleMap::openai())
+            .unwrap();
+        assert_eq!(exported[1].role, "assistant");
+        assert_eq!(
+            exported[1].tool_calls[0].arguments().unwrap(),
+            json!({"query": "rust \"async\""})
+        );
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let restored: ChatTemplate = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.format_messages(&variables).unwrap(),
+            chat_template
+                .compile()
+                .unwrap()
+                .format_messages(&variables)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multimodal_message_renders_openai_content_parts() {
+        let image = MultimodalTemplate::new(Role::Human)
+            .unwrap()
+            .with_text("Compare with {product}.")
+            .unwrap()
+            .with_part(ContentPart::image_url("{photo_url}").with_detail("low"))
+            .unwrap();
+        let chat_template = ChatTemplate {
+            messages: vec![
+                MessageLike::base_message(MessageEnum::System(SystemMessage::new("Be precise."))),
+                MessageLike::multimodal(image),
+            ],
+            prefill: None,
+            sensitive: false,
+        };
+        assert_eq!(
+            chat_template.input_variables(),
+            vec!["product", "photo_url"]
+        );
+
+        let variables = vars!(
+            product = "the mug",
+            photo_url = "https://img.example/mug.jpg"
+        );
+        let value = chat_template.to_openai_messages(&variables).unwrap();
+        assert_eq!(value[0]["content"], "Be precise.");
+        assert_eq!(
+            value[1],
+            json!({"role": "user", "content": [
+                {"type": "text", "text": "Compare with the mug."},
+                {"type": "image_url", "image_url": {"url": "https://img.example/mug.jpg", "detail": "low"}},
+            ]})
+        );
+        assert_eq!(
+            chat_template.format(&variables).unwrap(),
+            "system: Be precise.\nhuman: Compare with the mug."
+        );
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let restored: ChatTemplate = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            restored.format_messages(&variables).unwrap(),
+            chat_template
+                .compile()
+                .unwrap()
+                .format_messages(&variables)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_message_metadata_survives_rendering_and_export() {
+        let chat_template = ChatTemplate {
+            messages: vec![
+                MessageLike::base_message(MessageEnum::System(SystemMessage::new("Be brief.")))
+                    .with_metadata(
+                        MessageMetadata::new().with_cache_control(CacheControl::Ephemeral),
+                    ),
+                MessageLike::role_prompt_template(
+                    Role::Human,
+                    Template::new("{question}").unwrap(),
+                )
+                .with_metadata(
+                    MessageMetadata::new()
+                        .with_name("alice")
+                        .with_tag("source", "crm"),
+                ),
+            ],
+            prefill: None,
+            sensitive: false,
+        };
+        let variables = vars!(question = "Hi?");
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let restored: ChatTemplate = serde_json::from_str(&json).unwrap();
+        let messages = restored.format_messages(&variables).unwrap();
+        assert_eq!(
+            messages,
+            chat_template
+                .compile()
+                .unwrap()
+                .format_messages(&variables)
+                .unwrap()
+        );
+        assert_eq!(messages[1].name(), Some("alice"));
+        assert_eq!(restored.input_variables(), vec!["question"]);
+
+        let exported = chat_template
+            .export(&variables, &RoleMap::openai())
+            .unwrap();
+        assert_eq!(
+            exported[0].metadata.cache_control,
+            Some(CacheControl::Ephemeral)
+        );
+        assert_eq!(exported[1].metadata.tags["source"], "crm");
+
+        let value = chat_template.to_openai_messages(&variables).unwrap();
+        assert_eq!(
+            value[1],
+            json!({"role": "user", "content": "Hi?", "name": "alice"})
+        );
+        assert_eq!(value[0], json!({"role": "system", "content": "Be brief."}));
+    }
+
+    #[test]
+    fn test_custom_roles_format_and_round_trip() {
+        let chat_template = ChatTemplate::try_from(vec![
+            MessageConfig::new("system", "Review the plan."),
+            MessageConfig::new("custom:planner", "Plan: {plan}"),
+            MessageConfig::new("custom:critic", "Looks fine."),
+        ])
+        .unwrap();
+        let variables = vars!(plan = "ship it");
+
+        assert_eq!(
+            chat_template.format(&variables).unwrap(),
+            "system: Review the plan.\nplanner: Plan: ship it\ncritic: Looks fine."
+        );
+
+        let json = serde_json::to_string(&chat_template).unwrap();
+        let mut restored: ChatTemplate = serde_json::from_str(&json).unwrap();
+        restored.refresh();
+        assert_eq!(
+            restored.format_messages(&variables).unwrap(),
+            chat_template.format_messages(&variables).unwrap()
+        );
+
+        let role_map = RoleMap::openai().map(Role::custom("critic"), RoleMapping::new("assistant"));
+        let exported = chat_template.export(&variables, &role_map).unwrap();
+        assert_eq!(exported[1].role, "planner");
+        assert_eq!(exported[2].role, "assistant");
+    }
+
+    #[test]
+    fn test_export_with_role_map() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let exported = chat_template
+            .export(&vars!(question = "What is Rust?"), &RoleMap::openai())
+            .unwrap();
+        assert_eq!(exported.len(), 2);
+        assert_eq!(exported[1].role, "user");
+        assert_eq!(exported[1].content, "What is Rust?");
+    }
+
+    #[test]
+    fn test_to_openai_messages() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Placeholder = "{history}",
+            Human = "Say \"{word}\"\nthen stop.",
+        ))
+        .unwrap();
+        let history = r#"[{"role":"ai","content":"Hello!"}]"#;
+
+        let value = chat_template
+            .to_openai_messages(&vars!(history = history, word = "hi"))
+            .unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {"role": "system", "content": "You are a helpful assistant."},
+                {"role": "assistant", "content": "Hello!"},
+                {"role": "user", "content": "Say \"hi\"\nthen stop."},
+            ])
+        );
+        assert!(chat_template.to_openai_messages(&vars!()).is_err());
+    }
+
+    #[test]
+    fn test_format_with_serialize_context() {
+        #[derive(Serialize)]
+        struct Context {
+            topic: &'static str,
+            history: Vec<serde_json::Value>,
+            question: &'static str,
+        }
+
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are an expert on {topic}.",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let context = Context {
+            topic: "Rust",
+            history: vec![json!({"role": "human", "content": "Hi"})],
+            question: "What is ownership?",
+        };
+
+        let messages = chat_template.format_messages_with(&context).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1].content(), "Hi");
+        assert_eq!(
+            chat_template.format_with(&context).unwrap(),
+            "system: You are an expert on Rust.\nhuman: Hi\nhuman: What is ownership?"
+        );
+    }
+
+    #[test]
+    fn test_export_for_model_profile() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "Keep answers under {max_words} words.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let profile = ModelProfile::new("text-only-model")
+            .with_role_map(RoleMap::text_only())
+            .with_variable("max_words", 25);
+
+        let exported = chat_template
+            .export_for(&vars!(question = "Why is the sky blue?"), &profile)
+            .unwrap();
+        assert_eq!(exported[0].content, "Keep answers under 25 words.");
+        assert_eq!(exported[1].role, "user");
+
+        let messages = chat_template
+            .format_messages_for(&vars!(question = "Hi", max_words = "5"), &profile)
+            .unwrap();
+        assert_eq!(messages[0].content(), "Keep answers under 5 words.");
+    }
+
+    #[test]
+    fn test_export_normalizes_by_model_family() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "  You are \u{201C}{persona}\u{201D}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let variables = vars!(persona = "Sage", question = "Hi");
+
+        let llama = ModelProfile::new("llama-3-8b").with_family("llama");
+        let exported = chat_template.export_for(&variables, &llama).unwrap();
+        assert_eq!(exported[0].content, "You are \u{201C}Sage\u{201D}.");
+
+        let mut rules = NormalizationRules::new();
+        rules.add("llama", TextTransform::ReplaceSmartQuotes);
+        let exported = chat_template
+            .export_normalized(&variables, &llama, &rules)
+            .unwrap();
+        assert_eq!(exported[0].content, "You are \"Sage\".");
+
+        let unknown = ModelProfile::new("gpt-4o");
+        let exported = chat_template.export_for(&variables, &unknown).unwrap();
+        assert_eq!(exported[0].content, "  You are \u{201C}Sage\u{201D}.");
+    }
+
+    #[tokio::test]
+    async fn test_save_toml_file_round_trips() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "Answer in {language}.",
+            Human = "{question}",
+            Ai = "Sure.",
+        ))
+        .unwrap();
+        let path = std::env::temp_dir().join("promptforge_chat_save.toml");
+        chat_template.save_toml_file(&path).await.unwrap();
+
+        let loaded = ChatTemplate::from_toml_file(&path).await.unwrap();
+        let variables = vars!(language = "French", question = "Why?");
+        assert_eq!(
+            loaded.format(&variables).unwrap(),
+            chat_template.format(&variables).unwrap()
+        );
+        assert_eq!(
+            loaded.to_toml_string().unwrap(),
+            chat_template.to_toml_string().unwrap()
+        );
+    }
 }
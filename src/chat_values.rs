@@ -0,0 +1,179 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::MessageEnum;
+
+use crate::{ChatTemplate, MessageLike, TemplateError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChatValue {
+    Text(String),
+    Messages(Vec<Arc<MessageEnum>>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChatValues {
+    values: HashMap<String, ChatValue>,
+}
+
+impl From<String> for ChatValue {
+    fn from(text: String) -> Self {
+        ChatValue::Text(text)
+    }
+}
+
+impl From<&str> for ChatValue {
+    fn from(text: &str) -> Self {
+        ChatValue::Text(text.to_string())
+    }
+}
+
+impl From<Vec<MessageEnum>> for ChatValue {
+    fn from(messages: Vec<MessageEnum>) -> Self {
+        ChatValue::Messages(messages.into_iter().map(Arc::new).collect())
+    }
+}
+
+impl From<Vec<Arc<MessageEnum>>> for ChatValue {
+    fn from(messages: Vec<Arc<MessageEnum>>) -> Self {
+        ChatValue::Messages(messages)
+    }
+}
+
+impl ChatValues {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: &str, value: impl Into<ChatValue>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: &str, value: impl Into<ChatValue>) -> &mut Self {
+        self.values.insert(name.to_string(), value.into());
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&ChatValue> {
+        self.values.get(name)
+    }
+
+    pub fn text_vars(&self) -> HashMap<&str, &str> {
+        self.values
+            .iter()
+            .filter_map(|(name, value)| match value {
+                ChatValue::Text(text) => Some((name.as_str(), text.as_str())),
+                ChatValue::Messages(_) => None,
+            })
+            .collect()
+    }
+}
+
+impl ChatTemplate {
+    pub fn invoke_with(&self, values: &ChatValues) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let variables = values.text_vars();
+        let mut results = Vec::new();
+
+        for message_like in &self.messages {
+            let MessageLike::Placeholder(placeholder) = message_like.inner() else {
+                results.extend(Self::format_message_like(message_like, &variables)?);
+                continue;
+            };
+
+            let messages = match values.get(placeholder.variable_name()) {
+                Some(ChatValue::Messages(messages)) => placeholder.select_messages(messages)?,
+                _ => placeholder.format_messages(&variables)?,
+            };
+            match message_like.metadata() {
+                Some(metadata) => {
+                    results.extend(messages.into_iter().map(|message| metadata.apply(message)))
+                }
+                None => results.extend(messages),
+            }
+        }
+        results.extend(self.format_prefill(&variables)?);
+
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::{AiMessage, BaseMessage, HumanMessage};
+
+    use super::*;
+    use crate::Role::{Human, Placeholder, System};
+    use crate::{chats, vars, MessagesPlaceholder, TruncationSide};
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    fn history() -> Vec<MessageEnum> {
+        vec![
+            HumanMessage::new("Hi").into(),
+            AiMessage::new("Hello!").into(),
+        ]
+    }
+
+    #[test]
+    fn test_invoke_with_typed_history() {
+        let values = ChatValues::new()
+            .with("persona", "terse")
+            .with("history", history())
+            .with("question", "Why?");
+
+        let messages = chat().invoke_with(&values).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["You are terse.", "Hi", "Hello!", "Why?"]);
+
+        let json = serde_json::to_string(&history()).unwrap();
+        let expected = chat()
+            .invoke(&vars!(
+                persona = "terse",
+                history = json.as_str(),
+                question = "Why?"
+            ))
+            .unwrap();
+        assert_eq!(messages, expected);
+    }
+
+    #[test]
+    fn test_invoke_with_applies_placeholder_window() {
+        let chat = ChatTemplate::builder()
+            .placeholder(
+                MessagesPlaceholder::with_options("history".to_string(), false, 1)
+                    .with_truncation(TruncationSide::Tail),
+            )
+            .build();
+
+        let messages = chat
+            .invoke_with(&ChatValues::new().with("history", history()))
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].content(), "Hello!");
+
+        assert!(matches!(
+            chat.invoke_with(&ChatValues::new()),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_messages_value_does_not_fill_text_variables() {
+        let values = ChatValues::new()
+            .with("persona", history())
+            .with("history", history())
+            .with("question", "Why?");
+
+        assert!(matches!(
+            chat().invoke_with(&values),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+}
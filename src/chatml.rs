@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use crate::{ChatTemplate, ExportedMessage, RoleMap, TemplateError};
+
+pub const IM_START: &str = "<|im_start|>";
+pub const IM_END: &str = "<|im_end|>";
+pub const GENERATION_ROLE: &str = "assistant";
+
+pub fn render_chatml(messages: &[ExportedMessage], add_generation_prompt: bool) -> String {
+    // A prefill opens the assistant turn itself and leaves it unterminated.
+    let mut output: String = messages
+        .iter()
+        .map(|message| {
+            if message.prefill {
+                format!("{}{}\n{}", IM_START, GENERATION_ROLE, message.content)
+            } else {
+                format!(
+                    "{}{}\n{}{}\n",
+                    IM_START, message.role, message.content, IM_END
+                )
+            }
+        })
+        .collect();
+
+    if add_generation_prompt && !messages.last().is_some_and(|message| message.prefill) {
+        output.push_str(&format!("{}{}\n", IM_START, GENERATION_ROLE));
+    }
+    output
+}
+
+impl ChatTemplate {
+    pub fn format_chatml(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let messages = self.export(variables, &RoleMap::openai())?;
+        Ok(render_chatml(&messages, false))
+    }
+
+    pub fn format_chatml_for_generation(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        let messages = self.export(variables, &RoleMap::openai())?;
+        Ok(render_chatml(&messages, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, vars, Template};
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "Hi!",
+            Ai = "Hello.",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_format_chatml() {
+        let rendered = chat()
+            .format_chatml(&vars!(persona = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "<|im_start|>system\nYou are terse.<|im_end|>\n\
+             <|im_start|>user\nHi!<|im_end|>\n\
+             <|im_start|>assistant\nHello.<|im_end|>\n\
+             <|im_start|>user\nWhy?<|im_end|>\n"
+        );
+    }
+
+    #[test]
+    fn test_format_chatml_for_generation() {
+        let rendered = chat()
+            .format_chatml_for_generation(&vars!(persona = "terse", question = "Why?"))
+            .unwrap();
+        assert!(rendered.ends_with("Why?<|im_end|>\n<|im_start|>assistant\n"));
+        assert!(chat().format_chatml(&vars!(persona = "terse")).is_err());
+    }
+
+    #[test]
+    fn test_prefill_leaves_assistant_turn_open() {
+        let chat = chat().with_assistant_prefill(Template::new("Because {reason}").unwrap());
+        let variables = vars!(persona = "terse", question = "Why?", reason = "it");
+
+        let expected = "Why?<|im_end|>\n<|im_start|>assistant\nBecause it";
+        assert!(chat.format_chatml(&variables).unwrap().ends_with(expected));
+        assert!(chat
+            .format_chatml_for_generation(&variables)
+            .unwrap()
+            .ends_with(expected));
+    }
+}
@@ -16,7 +16,7 @@ macro_rules! chats {
 #[cfg(test)]
 mod tests {
     use crate::role::Role::{Ai, FewShotPrompt, Human, System};
-    use crate::{chats, examples, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Role};
+    use crate::{examples, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Role};
 
     #[test]
     fn test_empty_list() {
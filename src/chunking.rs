@@ -0,0 +1,310 @@
+use std::{collections::HashMap, fmt, ops::Range, sync::Arc};
+
+use crate::{
+    batch::Row, format_rows, EstimatedTokenCounter, Formattable, TemplateError, TokenCounter,
+};
+
+pub const CHUNK_INDEX_VARIABLE: &str = "chunk_index";
+pub const CHUNK_COUNT_VARIABLE: &str = "chunk_count";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkSize {
+    Tokens(usize),
+    Sentences(usize),
+}
+
+// Overlap is measured in the same unit as the chunk size. Chunks never split
+// a word (or a sentence when chunking by sentences); a single unit larger
+// than the limit becomes a chunk of its own.
+#[derive(Clone)]
+pub struct Chunker {
+    size: ChunkSize,
+    overlap: usize,
+    counter: Arc<dyn TokenCounter>,
+}
+
+impl fmt::Debug for Chunker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Chunker")
+            .field("size", &self.size)
+            .field("overlap", &self.overlap)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub index: usize,
+    pub text: String,
+    pub range: Range<usize>,
+}
+
+impl Chunker {
+    pub fn new(size: ChunkSize) -> Self {
+        Chunker {
+            size,
+            overlap: 0,
+            counter: Arc::new(EstimatedTokenCounter),
+        }
+    }
+
+    pub fn by_tokens(max_tokens: usize) -> Self {
+        Self::new(ChunkSize::Tokens(max_tokens))
+    }
+
+    pub fn by_sentences(max_sentences: usize) -> Self {
+        Self::new(ChunkSize::Sentences(max_sentences))
+    }
+
+    pub fn with_overlap(mut self, overlap: usize) -> Self {
+        self.overlap = overlap;
+        self
+    }
+
+    pub fn with_token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.counter = Arc::new(counter);
+        self
+    }
+
+    pub fn size(&self) -> ChunkSize {
+        self.size
+    }
+
+    pub fn overlap(&self) -> usize {
+        self.overlap
+    }
+
+    pub fn chunk(&self, text: &str) -> Vec<Chunk> {
+        let (units, max): (Vec<(Range<usize>, usize)>, usize) = match self.size {
+            ChunkSize::Tokens(max_tokens) => (
+                words(text)
+                    .into_iter()
+                    .map(|range| {
+                        let tokens = self.counter.count_tokens(&text[range.clone()]);
+                        (range, tokens)
+                    })
+                    .collect(),
+                max_tokens,
+            ),
+            ChunkSize::Sentences(max_sentences) => (
+                sentences(text)
+                    .into_iter()
+                    .map(|range| (range, 1))
+                    .collect(),
+                max_sentences,
+            ),
+        };
+
+        let mut chunks = Vec::new();
+        let mut first = 0;
+        while first < units.len() {
+            let mut last = first;
+            let mut used = units[first].1;
+            while last + 1 < units.len() && used + units[last + 1].1 <= max {
+                last += 1;
+                used += units[last].1;
+            }
+
+            let range = units[first].0.start..units[last].0.end;
+            chunks.push(Chunk {
+                index: chunks.len(),
+                text: text[range.clone()].to_string(),
+                range,
+            });
+            if last + 1 == units.len() {
+                break;
+            }
+
+            // Step back over trailing units that fit the overlap, but always
+            // move forward by at least one unit.
+            let mut next = last + 1;
+            let mut overlapped = 0;
+            while next - 1 > first && overlapped + units[next - 1].1 <= self.overlap {
+                next -= 1;
+                overlapped += units[next].1;
+            }
+            first = next;
+        }
+
+        chunks
+    }
+
+    // One row per chunk: `variable` holds the chunk text, alongside the
+    // 1-based `chunk_index` and `chunk_count` and every entry of `base`.
+    pub fn chunk_vars<'a>(
+        &self,
+        text: &str,
+        variable: &'a str,
+        base: &'a HashMap<&str, &str>,
+    ) -> impl Iterator<Item = Row> + 'a {
+        let chunks = self.chunk(text);
+        let count = chunks.len().to_string();
+
+        chunks.into_iter().map(move |chunk| {
+            let mut row: Row = base
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            row.insert(variable.to_string(), chunk.text);
+            row.insert(
+                CHUNK_INDEX_VARIABLE.to_string(),
+                (chunk.index + 1).to_string(),
+            );
+            row.insert(CHUNK_COUNT_VARIABLE.to_string(), count.clone());
+            row
+        })
+    }
+}
+
+pub fn format_chunks<F: Formattable + ?Sized>(
+    template: &F,
+    chunker: &Chunker,
+    text: &str,
+    variable: &str,
+    base: &HashMap<&str, &str>,
+) -> Result<Vec<String>, TemplateError> {
+    let rows: Vec<Row> = chunker.chunk_vars(text, variable, base).collect();
+    format_rows(template, &rows)
+}
+
+fn words(text: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (index, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                words.push(begin..index);
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+    if let Some(begin) = start {
+        words.push(begin..text.len());
+    }
+    words
+}
+
+// A sentence ends at `.`, `!` or `?` (plus any closing quotes or brackets)
+// followed by whitespace, or at a blank line.
+fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((index, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let begin = *start.get_or_insert(index);
+
+        let mut end = index + c.len_utf8();
+        let terminal = matches!(c, '.' | '!' | '?');
+        if terminal {
+            while let Some(&(next, closing)) = chars.peek() {
+                if !matches!(
+                    closing,
+                    '.' | '!' | '?' | '"' | '\'' | ')' | ']' | '”' | '’'
+                ) {
+                    break;
+                }
+                end = next + closing.len_utf8();
+                chars.next();
+            }
+        }
+
+        let rest = &text[end..];
+        if rest.trim().is_empty()
+            || (terminal && rest.starts_with(char::is_whitespace))
+            || paragraph_break(rest)
+        {
+            sentences.push(begin..end);
+            start = None;
+        }
+    }
+
+    sentences
+}
+
+fn paragraph_break(rest: &str) -> bool {
+    rest.chars()
+        .take_while(|c| c.is_whitespace())
+        .filter(|&c| c == '\n')
+        .count()
+        >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Template};
+
+    #[test]
+    fn test_chunk_by_sentences_with_overlap() {
+        let text = "One is first. Two says \"hi!\"  Three is next\n\nFour has no end";
+        let chunks = Chunker::by_sentences(2).with_overlap(1).chunk(text);
+
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "One is first. Two says \"hi!\"",
+                "Two says \"hi!\"  Three is next",
+                "Three is next\n\nFour has no end",
+            ]
+        );
+        assert_eq!(&text[chunks[1].range.clone()], chunks[1].text);
+        assert_eq!(chunks[2].index, 2);
+    }
+
+    #[test]
+    fn test_chunk_by_tokens_never_stalls() {
+        let words = |text: &str| text.split_whitespace().count();
+        let text = "a b c d e f g";
+
+        let chunks = Chunker::by_tokens(3)
+            .with_overlap(1)
+            .with_token_counter(words)
+            .chunk(text);
+        let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.text.as_str()).collect();
+        assert_eq!(texts, vec!["a b c", "c d e", "e f g"]);
+
+        let chunks = Chunker::by_tokens(2)
+            .with_overlap(5)
+            .with_token_counter(words)
+            .chunk(text);
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(chunks[5].text, "f g");
+
+        assert_eq!(Chunker::by_tokens(0).chunk("one two").len(), 2);
+        assert!(Chunker::by_tokens(10).chunk("  \n ").is_empty());
+    }
+
+    #[test]
+    fn test_format_chunks_renders_per_chunk() {
+        let template =
+            Template::new("[{chunk_index}/{chunk_count}] Summarize for {audience}: {doc}").unwrap();
+        let chunker = Chunker::by_sentences(1);
+
+        let prompts = format_chunks(
+            &template,
+            &chunker,
+            "Rust is fast. It is safe.",
+            "doc",
+            &vars!(audience = "kids"),
+        )
+        .unwrap();
+        assert_eq!(
+            prompts,
+            vec![
+                "[1/2] Summarize for kids: Rust is fast.",
+                "[2/2] Summarize for kids: It is safe."
+            ]
+        );
+
+        let rows: Vec<Row> = chunker.chunk_vars("Only one.", "doc", &vars!()).collect();
+        assert_eq!(rows[0]["doc"], "Only one.");
+        assert_eq!(rows[0][CHUNK_COUNT_VARIABLE], "1");
+    }
+}
@@ -0,0 +1,476 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::MessageEnum;
+
+use crate::{
+    braces::{mask_escaped_braces, unescape_braces},
+    is_valid_identifier,
+    message_like::format_for_each,
+    placeholder::split_filters,
+    renderer::join_messages,
+    template::check_missing,
+    ChatTemplate, FilterCall, FilterRegistry, Formattable, MessageLike, MessageMetadata,
+    MessagesPlaceholder, MultimodalTemplate, Role, Templatable, Template, TemplateError,
+    TemplateFormat, ToolCallTemplate,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Variable {
+        name: String,
+        default: Option<String>,
+        filters: Vec<FilterCall>,
+    },
+}
+
+#[derive(Debug, Clone)]
+enum CompiledBody {
+    Segments(Vec<Segment>),
+    Engine(Arc<Template>),
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledTemplate {
+    body: CompiledBody,
+    input_variables: Vec<String>,
+    literal_len: usize,
+    filters: Option<FilterRegistry>,
+}
+
+impl CompiledTemplate {
+    pub fn new(template: &Template) -> Result<Self, TemplateError> {
+        let input_variables = template.input_variables();
+        let partials = template.partial_vars();
+
+        let body = match template.template_format() {
+            // Transforms run inside `Template::format`, so those templates keep
+            // rendering through it.
+            _ if !template.transforms().is_empty() => {
+                CompiledBody::Engine(Arc::new(template.clone()))
+            }
+            TemplateFormat::PlainText => CompiledBody::Segments(vec![Segment::Literal(
+                unescape_braces(template.template()).into_owned(),
+            )]),
+            TemplateFormat::FmtString => {
+                for variable in &input_variables {
+                    if !is_valid_identifier(variable) {
+                        return Err(TemplateError::MalformedTemplate(
+                            format!("Invalid variable name: {}", variable),
+                            None,
+                        ));
+                    }
+                }
+                CompiledBody::Segments(Self::parse_fmtstring(
+                    template.template(),
+                    &input_variables,
+                    partials,
+                    &template.variable_defaults(),
+                )?)
+            }
+            TemplateFormat::Mustache | TemplateFormat::Jinja2 => {
+                CompiledBody::Engine(Arc::new(template.clone()))
+            }
+        };
+
+        let literal_len = match &body {
+            CompiledBody::Segments(segments) => segments
+                .iter()
+                .map(|segment| match segment {
+                    Segment::Literal(text) => text.len(),
+                    Segment::Variable { .. } => 0,
+                })
+                .sum(),
+            CompiledBody::Engine(template) => template.template().len(),
+        };
+
+        let has_filters = matches!(&body, CompiledBody::Segments(segments) if segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Variable { filters, .. } if !filters.is_empty())));
+
+        Ok(Self {
+            body,
+            input_variables,
+            literal_len,
+            filters: has_filters.then(|| template.filters().clone()),
+        })
+    }
+
+    fn parse_fmtstring(
+        template: &str,
+        input_variables: &[String],
+        partials: &HashMap<String, String>,
+        defaults: &HashMap<&str, &str>,
+    ) -> Result<Vec<Segment>, TemplateError> {
+        let masked = mask_escaped_braces(template);
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut offset = 0;
+
+        while let Some(start) = masked[offset..].find('{').map(|i| offset + i) {
+            let Some(end) = masked[start..].find('}').map(|i| start + i) else {
+                break;
+            };
+            let (head, filters) = split_filters(&template[start + 1..end]);
+            let (name, inline_default) = match head.split_once(':') {
+                Some((name, default)) => (name, Some(default)),
+                None => (head, None),
+            };
+
+            if input_variables.iter().any(|variable| variable == name) {
+                literal.push_str(&template[offset..start]);
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(
+                        unescape_braces(&std::mem::take(&mut literal)).into_owned(),
+                    ));
+                }
+                segments.push(Segment::Variable {
+                    name: name.to_string(),
+                    default: partials
+                        .get(name)
+                        .map(String::as_str)
+                        .or(inline_default)
+                        .or_else(|| defaults.get(name).copied())
+                        .map(str::to_string),
+                    filters: FilterCall::parse_chain(filters.unwrap_or_default())
+                        .map_err(|e| e.at(template, start))?,
+                });
+            } else {
+                literal.push_str(&template[offset..=end]);
+            }
+
+            offset = end + 1;
+        }
+
+        literal.push_str(&template[offset..]);
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(unescape_braces(&literal).into_owned()));
+        }
+
+        Ok(segments)
+    }
+
+    pub fn input_variables(&self) -> &[String] {
+        &self.input_variables
+    }
+}
+
+impl Formattable for CompiledTemplate {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let segments = match &self.body {
+            CompiledBody::Segments(segments) => segments,
+            CompiledBody::Engine(template) => return template.format(variables),
+        };
+
+        let mut output = String::with_capacity(
+            self.literal_len + variables.values().map(|v| v.len()).sum::<usize>(),
+        );
+        let mut missing: Vec<&String> = Vec::new();
+
+        for segment in segments {
+            match segment {
+                Segment::Literal(text) => output.push_str(text),
+                Segment::Variable {
+                    name,
+                    default,
+                    filters,
+                } => {
+                    let Some(value) = variables.get(name.as_str()).copied().or(default.as_deref())
+                    else {
+                        missing.push(name);
+                        continue;
+                    };
+                    match &self.filters {
+                        Some(registry) if !filters.is_empty() => {
+                            output.push_str(&registry.apply_chain(filters, value)?)
+                        }
+                        _ => output.push_str(value),
+                    }
+                }
+            }
+        }
+
+        if !missing.is_empty() {
+            let missing: Vec<&String> = self
+                .input_variables
+                .iter()
+                .filter(|var| missing.contains(var))
+                .collect();
+            check_missing(&self.input_variables, &missing, variables)?;
+        }
+        Ok(output)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum RenderStep {
+    Static(Vec<Arc<MessageEnum>>),
+    Role(Role, CompiledTemplate),
+    Placeholder(MessagesPlaceholder),
+    Prefill(CompiledTemplate),
+    ToolCall(ToolCallTemplate),
+    Multimodal(MultimodalTemplate),
+    ForEach(String, Arc<Template>, Role),
+    SubTemplate(Box<CompiledChatTemplate>),
+    Annotated(Box<RenderStep>, MessageMetadata),
+}
+
+#[derive(Debug, Clone)]
+pub struct CompiledChatTemplate {
+    plan: Vec<RenderStep>,
+    message_capacity: usize,
+}
+
+impl CompiledChatTemplate {
+    pub fn new(chat_template: &ChatTemplate) -> Result<Self, TemplateError> {
+        let mut plan: Vec<RenderStep> = Vec::with_capacity(chat_template.messages.len());
+
+        for message_like in &chat_template.messages {
+            match (plan.last_mut(), Self::compile_step(message_like)?) {
+                (Some(RenderStep::Static(previous)), RenderStep::Static(messages)) => {
+                    previous.extend(messages)
+                }
+                (_, step) => plan.push(step),
+            }
+        }
+        if let Some(prefill) = &chat_template.prefill {
+            plan.push(RenderStep::Prefill(CompiledTemplate::new(prefill)?));
+        }
+
+        let message_capacity = plan.iter().map(Self::step_capacity).sum();
+
+        Ok(Self {
+            plan,
+            message_capacity,
+        })
+    }
+
+    fn compile_step(message_like: &MessageLike) -> Result<RenderStep, TemplateError> {
+        Ok(match message_like {
+            MessageLike::BaseMessage(base_message) => {
+                RenderStep::Static(vec![Arc::clone(base_message)])
+            }
+            MessageLike::RolePromptTemplate(role, template) => {
+                role.ensure_message_role()?;
+                RenderStep::Role(role.clone(), CompiledTemplate::new(template)?)
+            }
+            MessageLike::Placeholder(placeholder) => RenderStep::Placeholder(placeholder.clone()),
+            MessageLike::FewShotPrompt(_) => RenderStep::Static(ChatTemplate::format_message_like(
+                message_like,
+                &HashMap::new(),
+            )?),
+            MessageLike::ToolCallTemplate(tool_call_template) => {
+                RenderStep::ToolCall(tool_call_template.clone())
+            }
+            MessageLike::Multimodal(multimodal_template) => {
+                RenderStep::Multimodal(multimodal_template.clone())
+            }
+            MessageLike::ForEach {
+                variable,
+                item_template,
+                role,
+            } => RenderStep::ForEach(variable.clone(), Arc::clone(item_template), role.clone()),
+            MessageLike::SubTemplate(_, chat_template) => {
+                RenderStep::SubTemplate(Box::new(CompiledChatTemplate::new(chat_template)?))
+            }
+            // Static messages take their metadata now; anything rendered later
+            // gets it per call.
+            MessageLike::Annotated(message_like, metadata) => {
+                match Self::compile_step(message_like)? {
+                    RenderStep::Static(messages) => RenderStep::Static(
+                        messages
+                            .into_iter()
+                            .map(|message| metadata.apply(message))
+                            .collect(),
+                    ),
+                    step => RenderStep::Annotated(Box::new(step), metadata.clone()),
+                }
+            }
+        })
+    }
+
+    fn step_capacity(step: &RenderStep) -> usize {
+        match step {
+            RenderStep::Static(messages) => messages.len(),
+            RenderStep::Role(..)
+            | RenderStep::Prefill(_)
+            | RenderStep::ToolCall(_)
+            | RenderStep::Multimodal(_) => 1,
+            RenderStep::Placeholder(_) | RenderStep::ForEach(..) => 0,
+            RenderStep::SubTemplate(compiled) => compiled.message_capacity,
+            RenderStep::Annotated(step, _) => Self::step_capacity(step),
+        }
+    }
+
+    pub fn format_messages(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let mut results = Vec::with_capacity(self.message_capacity);
+        for step in &self.plan {
+            Self::render_step(step, variables, &mut results)?;
+        }
+        Ok(results)
+    }
+
+    fn render_step(
+        step: &RenderStep,
+        variables: &HashMap<&str, &str>,
+        results: &mut Vec<Arc<MessageEnum>>,
+    ) -> Result<(), TemplateError> {
+        match step {
+            RenderStep::Static(messages) => results.extend(messages.iter().cloned()),
+            RenderStep::Role(role, template) => {
+                let content = template.format(variables)?;
+                results.push(role.to_message(&content)?);
+            }
+            RenderStep::Placeholder(placeholder) => {
+                results.extend(placeholder.format_messages(variables)?);
+            }
+            RenderStep::Prefill(template) => {
+                results.push(ChatTemplate::prefill_message(&template.format(variables)?));
+            }
+            RenderStep::ToolCall(tool_call_template) => {
+                results.push(tool_call_template.format_message(variables)?);
+            }
+            RenderStep::Multimodal(multimodal_template) => {
+                results.push(multimodal_template.format_message(variables)?);
+            }
+            RenderStep::ForEach(variable, item_template, role) => {
+                results.extend(format_for_each(variable, item_template, role, variables)?);
+            }
+            RenderStep::SubTemplate(compiled) => {
+                for step in &compiled.plan {
+                    Self::render_step(step, variables, results)?;
+                }
+            }
+            RenderStep::Annotated(step, metadata) => {
+                let start = results.len();
+                Self::render_step(step, variables, results)?;
+                let rendered = results.split_off(start);
+                results.extend(rendered.into_iter().map(|message| metadata.apply(message)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Formattable for CompiledChatTemplate {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let formatted_messages = self.format_messages(variables)?;
+        Ok(join_messages(&formatted_messages))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, FewShotPrompt, Human, Placeholder, System};
+    use crate::{chats, examples, vars, FewShotChatTemplate, FewShotTemplate};
+
+    #[test]
+    fn test_compiled_template_matches_uncompiled() {
+        let templates = [
+            "Hello, {name}! You are {age} years old.",
+            "{greeting}{name}",
+            "Hello, {{name}}!",
+            "No variables here.",
+            "{name|upper} is {age|truncate(1)}0-ish.",
+            r#"\{"name": "{name}", "age": {age}\}"#,
+            r"Literal \{braces\} only",
+        ];
+        let variables = vars!(name = "Alice", age = "30", greeting = "Hi ");
+
+        for source in templates {
+            let template = Template::new(source).unwrap();
+            let compiled = template.compile().unwrap();
+            assert_eq!(
+                compiled.format(&variables).unwrap(),
+                template.format(&variables).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_compiled_template_resolves_partials() {
+        let mut template = Template::new("{greeting}, {name}!").unwrap();
+        template.partial("greeting", "Hello");
+        let compiled = template.compile().unwrap();
+
+        assert_eq!(
+            compiled.format(&vars!(name = "Bob")).unwrap(),
+            "Hello, Bob!"
+        );
+        assert_eq!(
+            compiled
+                .format(&vars!(name = "Bob", greeting = "Hey"))
+                .unwrap(),
+            "Hey, Bob!"
+        );
+        assert!(matches!(
+            compiled.format(&vars!(greeting = "Hey")),
+            Err(TemplateError::MissingVariable(msg)) if msg.contains("'name'")
+        ));
+    }
+
+    #[test]
+    fn test_compiled_template_resolves_inline_defaults() {
+        let mut template = Template::new("{greeting:Hello}, {name:Anonymous}!").unwrap();
+        let compiled = template.compile().unwrap();
+        assert_eq!(compiled.format(&vars!()).unwrap(), "Hello, Anonymous!");
+        assert_eq!(
+            compiled.format(&vars!(name = "Bob")).unwrap(),
+            template.format(&vars!(name = "Bob")).unwrap()
+        );
+
+        template.partial("greeting", "Hey");
+        let compiled = template.compile().unwrap();
+        assert_eq!(compiled.format(&vars!()).unwrap(), "Hey, Anonymous!");
+    }
+
+    #[test]
+    fn test_compiled_chat_template_matches_uncompiled() {
+        let examples = examples!(
+            ("{input}: What is 2+2?", "{output}: 4"),
+            ("{input}: What is 2+3?", "{output}: 5")
+        );
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let few_shot = FewShotChatTemplate::new(FewShotTemplate::new(examples), example_prompt);
+
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            FewShotPrompt = few_shot.to_string(),
+            Placeholder = "{history}",
+            Human = "{input}",
+        ))
+        .unwrap();
+        let compiled = chat_template.compile().unwrap();
+
+        let history =
+            r#"[{"role": "human", "content": "Hi"}, {"role": "ai", "content": "Hello!"}]"#;
+        let variables = vars!(history = history, input = "What is 3+3?");
+
+        assert_eq!(
+            compiled.format(&variables).unwrap(),
+            chat_template.format(&variables).unwrap()
+        );
+        assert_eq!(compiled.format_messages(&variables).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_compile_rejects_invalid_roles() {
+        let chat_template = ChatTemplate {
+            messages: vec![MessageLike::role_prompt_template(
+                Role::Tool,
+                Template::new("{result}").unwrap(),
+            )],
+            prefill: None,
+            sensitive: false,
+        };
+        assert!(matches!(
+            chat_template.compile(),
+            Err(TemplateError::ToolRoleNotSupported(_))
+        ));
+    }
+}
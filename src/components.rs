@@ -0,0 +1,142 @@
+use crate::{is_valid_identifier, Template, TemplateError};
+
+pub const DEFAULT_CHAIN_OF_THOUGHT_TRIGGER: &str = "Let's think step by step.";
+
+pub fn json_only_answer(keys: &[&str]) -> Result<Template, TemplateError> {
+    let mut instruction = String::from(
+        "Respond with a single valid JSON object and nothing else. \
+         Do not wrap the JSON in Markdown code fences or add commentary.",
+    );
+
+    if !keys.is_empty() {
+        instruction.push_str(&format!(
+            " The object must contain exactly these keys: {}.",
+            keys.join(", ")
+        ));
+    }
+
+    Template::new(&instruction)
+}
+
+pub fn refusal_policy(
+    disallowed_topics: &[&str],
+    refusal_message: &str,
+) -> Result<Template, TemplateError> {
+    if disallowed_topics.is_empty() {
+        return Err(TemplateError::MalformedTemplate(
+            "A refusal policy needs at least one disallowed topic".to_string(),
+            None,
+        ));
+    }
+
+    Template::new(&format!(
+        "If a request asks for help with any of the following, politely refuse: {}. \
+         When refusing, reply with \"{}\" and do not elaborate further.",
+        disallowed_topics.join(", "),
+        refusal_message
+    ))
+}
+
+pub fn citation_instruction(sources_variable: &str) -> Result<Template, TemplateError> {
+    if !is_valid_identifier(sources_variable) {
+        return Err(TemplateError::MalformedTemplate(
+            format!("Invalid sources variable name: {}", sources_variable),
+            None,
+        ));
+    }
+
+    Template::new(&format!(
+        "Answer only using the sources below. Cite every claim with the number of its source \
+         in square brackets, e.g. [1]. If the sources do not contain the answer, say that you \
+         do not know.\n\nSources:\n{{{}}}",
+        sources_variable
+    ))
+}
+
+pub fn chain_of_thought(trigger: Option<&str>) -> Result<Template, TemplateError> {
+    Template::new(trigger.unwrap_or(DEFAULT_CHAIN_OF_THOUGHT_TRIGGER))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, ChatTemplate, Formattable, Templatable, TemplateFormat};
+    use messageforge::BaseMessage;
+
+    #[test]
+    fn test_json_only_answer() {
+        let template = json_only_answer(&[]).unwrap();
+        assert_eq!(template.template_format(), TemplateFormat::PlainText);
+        assert!(template
+            .template()
+            .starts_with("Respond with a single valid JSON object"));
+
+        let template = json_only_answer(&["answer", "confidence"]).unwrap();
+        assert!(template
+            .template()
+            .ends_with("The object must contain exactly these keys: answer, confidence."));
+    }
+
+    #[test]
+    fn test_refusal_policy() {
+        let template =
+            refusal_policy(&["weapons", "medical dosing"], "I can't help with that.").unwrap();
+        assert_eq!(
+            template.template(),
+            "If a request asks for help with any of the following, politely refuse: \
+             weapons, medical dosing. When refusing, reply with \"I can't help with that.\" \
+             and do not elaborate further."
+        );
+
+        assert!(refusal_policy(&[], "No.").is_err());
+    }
+
+    #[test]
+    fn test_citation_instruction() {
+        let template = citation_instruction("documents").unwrap();
+        assert_eq!(template.template_format(), TemplateFormat::FmtString);
+        assert_eq!(template.input_variables(), vec!["documents".to_string()]);
+
+        let formatted = template
+            .format(&vars!(documents = "[1] Rust 1.0 shipped in May 2015."))
+            .unwrap();
+        assert!(formatted.ends_with("Sources:\n[1] Rust 1.0 shipped in May 2015."));
+
+        assert!(citation_instruction("not valid").is_err());
+    }
+
+    #[test]
+    fn test_chain_of_thought() {
+        assert_eq!(
+            chain_of_thought(None).unwrap().template(),
+            DEFAULT_CHAIN_OF_THOUGHT_TRIGGER
+        );
+        assert_eq!(
+            chain_of_thought(Some("Work through the problem before answering."))
+                .unwrap()
+                .template(),
+            "Work through the problem before answering."
+        );
+    }
+
+    #[test]
+    fn test_components_snap_into_builder() {
+        let chat_template = ChatTemplate::builder()
+            .system(refusal_policy(&["legal advice"], "Please consult a lawyer.").unwrap())
+            .system(citation_instruction("sources").unwrap())
+            .system(json_only_answer(&["answer"]).unwrap())
+            .human(Template::new("{question}").unwrap())
+            .ai(chain_of_thought(None).unwrap())
+            .build();
+
+        let messages = chat_template
+            .format_messages(&vars!(
+                sources = "[1] The sky is blue.",
+                question = "What colour is the sky?"
+            ))
+            .unwrap();
+
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[4].content(), DEFAULT_CHAIN_OF_THOUGHT_TRIGGER);
+    }
+}
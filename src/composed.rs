@@ -0,0 +1,208 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{merge_vars, Formattable, Templatable, Template, TemplateError, TemplateFormat};
+
+// A parent template whose slots are filled by rendering child templates.
+// Children inherit the caller's variables and the parent's partials, so their
+// own inputs surface through the parent's `input_variables`.
+#[derive(Debug, Clone)]
+pub struct ComposedTemplate {
+    parent: Template,
+    children: Vec<(String, ComposedTemplate)>,
+}
+
+impl ComposedTemplate {
+    pub fn new(parent: Template) -> Self {
+        ComposedTemplate {
+            parent,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn include(
+        mut self,
+        slot: &str,
+        child: impl Into<ComposedTemplate>,
+    ) -> Result<Self, TemplateError> {
+        if !self.parent.input_variables().iter().any(|var| var == slot) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Slot '{}' is not a variable of the parent template", slot),
+                None,
+            ));
+        }
+        if self.children.iter().any(|(name, _)| name == slot) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Slot '{}' is already filled by another template", slot),
+                None,
+            ));
+        }
+
+        self.children.push((slot.to_string(), child.into()));
+        Ok(self)
+    }
+
+    pub fn parent(&self) -> &Template {
+        &self.parent
+    }
+
+    pub fn slots(&self) -> Vec<&str> {
+        self.children
+            .iter()
+            .map(|(slot, _)| slot.as_str())
+            .collect()
+    }
+
+    pub fn child(&self, slot: &str) -> Option<&ComposedTemplate> {
+        self.children
+            .iter()
+            .find(|(name, _)| name == slot)
+            .map(|(_, child)| child)
+    }
+
+    pub fn required_variables(&self) -> Vec<String> {
+        self.collect_variables(&HashSet::new(), true)
+    }
+
+    fn is_slot(&self, var: &str) -> bool {
+        self.children.iter().any(|(slot, _)| slot == var)
+    }
+
+    fn collect_variables(&self, inherited: &HashSet<String>, required_only: bool) -> Vec<String> {
+        let own = if required_only {
+            self.parent.required_variables()
+        } else {
+            self.parent.input_variables()
+        };
+        let mut variables: Vec<String> = own
+            .into_iter()
+            .filter(|var| !self.is_slot(var))
+            .filter(|var| !(required_only && self.parent.partial_vars().contains_key(var)))
+            .filter(|var| !inherited.contains(var))
+            .collect();
+
+        let mut satisfied = inherited.clone();
+        satisfied.extend(self.parent.partial_vars().keys().cloned());
+
+        for (_, child) in &self.children {
+            for var in child.collect_variables(&satisfied, required_only) {
+                if !variables.contains(&var) {
+                    variables.push(var);
+                }
+            }
+        }
+        variables
+    }
+}
+
+impl From<Template> for ComposedTemplate {
+    fn from(parent: Template) -> Self {
+        ComposedTemplate::new(parent)
+    }
+}
+
+impl Formattable for ComposedTemplate {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let inherited = merge_vars(self.parent.partial_vars(), variables);
+
+        let rendered = self
+            .children
+            .iter()
+            .map(|(slot, child)| Ok((slot.as_str(), child.format(&inherited)?)))
+            .collect::<Result<Vec<_>, TemplateError>>()?;
+
+        let mut variables = variables.clone();
+        variables.extend(
+            rendered
+                .iter()
+                .map(|(slot, output)| (*slot, output.as_str())),
+        );
+        self.parent.format(&variables)
+    }
+}
+
+impl Templatable for ComposedTemplate {
+    fn template(&self) -> &str {
+        self.parent.template()
+    }
+
+    fn template_format(&self) -> TemplateFormat {
+        self.parent.template_format()
+    }
+
+    fn input_variables(&self) -> Vec<String> {
+        self.collect_variables(&HashSet::new(), false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vars;
+
+    fn composed() -> ComposedTemplate {
+        let mut parent = Template::new("{persona}\n\n{context}\n\nQuestion: {question}").unwrap();
+        parent.partial("tone", "formal");
+
+        let context =
+            ComposedTemplate::new(Template::new("Use a {tone} tone. Sources:\n{sources}").unwrap())
+                .include("sources", Template::new("- {doc} ({lang:en})").unwrap())
+                .unwrap();
+
+        ComposedTemplate::new(parent)
+            .include("context", context)
+            .unwrap()
+            .include("persona", Template::new("You are {name}.").unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_input_variables_inherit_children() {
+        let template = composed();
+
+        assert_eq!(
+            template.input_variables(),
+            vec!["question", "doc", "lang", "name"]
+        );
+        assert_eq!(
+            template.required_variables(),
+            vec!["question", "doc", "name"]
+        );
+        assert_eq!(template.slots(), vec!["context", "persona"]);
+        assert!(template
+            .child("context")
+            .unwrap()
+            .child("sources")
+            .is_some());
+    }
+
+    #[test]
+    fn test_format_renders_children_into_slots() {
+        let rendered = composed()
+            .format(&vars!(question = "Why?", doc = "spec.md", name = "Forge"))
+            .unwrap();
+
+        assert_eq!(
+            rendered,
+            "You are Forge.\n\nUse a formal tone. Sources:\n- spec.md (en)\n\nQuestion: Why?"
+        );
+        assert!(matches!(
+            composed().format(&vars!(question = "Why?", name = "Forge")),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_include_rejects_unknown_or_duplicate_slot() {
+        let parent = Template::new("{a} {b}").unwrap();
+        let child = Template::new("{c}").unwrap();
+
+        assert!(ComposedTemplate::new(parent.clone())
+            .include("missing", child.clone())
+            .is_err());
+        assert!(ComposedTemplate::new(parent)
+            .include("a", child.clone())
+            .unwrap()
+            .include("a", child)
+            .is_err());
+    }
+}
@@ -0,0 +1,304 @@
+use std::{collections::HashSet, fmt, sync::Arc};
+
+use messageforge::{BaseMessage, MessageEnum};
+
+use crate::TemplateError;
+
+pub trait Compressor: Send + Sync {
+    fn compress(&self, text: &str) -> Result<String, TemplateError>;
+}
+
+impl<F> Compressor for F
+where
+    F: Fn(&str) -> Result<String, TemplateError> + Send + Sync,
+{
+    fn compress(&self, text: &str) -> Result<String, TemplateError> {
+        self(text)
+    }
+}
+
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "but", "by", "do", "for", "from", "has", "have",
+    "i", "if", "in", "is", "it", "its", "me", "my", "no", "not", "of", "on", "or", "so", "that",
+    "the", "then", "there", "this", "to", "was", "we", "were", "will", "with", "you", "your",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExtractiveCompressor {
+    keep_ratio: f32,
+    min_sentences: usize,
+}
+
+impl Default for ExtractiveCompressor {
+    fn default() -> Self {
+        Self {
+            keep_ratio: Self::DEFAULT_KEEP_RATIO,
+            min_sentences: 1,
+        }
+    }
+}
+
+impl ExtractiveCompressor {
+    pub const DEFAULT_KEEP_RATIO: f32 = 0.5;
+
+    pub fn new(keep_ratio: f32) -> Self {
+        Self {
+            keep_ratio: keep_ratio.clamp(0.0, 1.0),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_min_sentences(mut self, min_sentences: usize) -> Self {
+        self.min_sentences = min_sentences;
+        self
+    }
+
+    pub fn keep_ratio(&self) -> f32 {
+        self.keep_ratio
+    }
+
+    pub fn min_sentences(&self) -> usize {
+        self.min_sentences
+    }
+
+    fn split_sentences(text: &str) -> Vec<&str> {
+        let mut sentences = Vec::new();
+        let mut start = 0;
+        let mut chars = text.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            let at_boundary = c == '\n'
+                || (matches!(c, '.' | '!' | '?')
+                    && chars.peek().is_none_or(|(_, next)| next.is_whitespace()));
+
+            if at_boundary {
+                let end = i + c.len_utf8();
+                let sentence = text[start..end].trim();
+                if !sentence.is_empty() {
+                    sentences.push(sentence);
+                }
+                start = end;
+            }
+        }
+
+        let rest = text[start..].trim();
+        if !rest.is_empty() {
+            sentences.push(rest);
+        }
+
+        sentences
+    }
+
+    fn information_score(sentence: &str, seen: &HashSet<String>) -> usize {
+        sentence
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .filter(|word| !STOPWORDS.contains(&word.as_str()) && !seen.contains(word))
+            .collect::<HashSet<_>>()
+            .len()
+    }
+}
+
+impl Compressor for ExtractiveCompressor {
+    fn compress(&self, text: &str) -> Result<String, TemplateError> {
+        let sentences = Self::split_sentences(text);
+        if sentences.is_empty() {
+            return Ok(String::new());
+        }
+
+        let target = ((sentences.len() as f32 * self.keep_ratio).ceil() as usize)
+            .max(self.min_sentences)
+            .min(sentences.len());
+
+        let mut seen = HashSet::new();
+        let mut scored: Vec<(usize, usize)> = sentences
+            .iter()
+            .enumerate()
+            .map(|(index, sentence)| {
+                let score = Self::information_score(sentence, &seen);
+                seen.extend(
+                    sentence
+                        .split(|c: char| !c.is_alphanumeric())
+                        .filter(|word| !word.is_empty())
+                        .map(str::to_lowercase),
+                );
+                (index, score)
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let mut kept: Vec<usize> = scored.into_iter().take(target).map(|(i, _)| i).collect();
+        kept.sort_unstable();
+
+        Ok(kept
+            .into_iter()
+            .map(|index| sentences[index])
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+}
+
+#[derive(Clone)]
+pub struct PromptCompression {
+    compressor: Arc<dyn Compressor>,
+    variables: HashSet<String>,
+    history: bool,
+}
+
+impl fmt::Debug for PromptCompression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PromptCompression")
+            .field("variables", &self.variables)
+            .field("history", &self.history)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PromptCompression {
+    pub fn new(compressor: impl Compressor + 'static) -> Self {
+        Self {
+            compressor: Arc::new(compressor),
+            variables: HashSet::new(),
+            history: false,
+        }
+    }
+
+    pub fn variable(mut self, name: impl Into<String>) -> Self {
+        self.variables.insert(name.into());
+        self
+    }
+
+    pub fn history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn compresses_variable(&self, name: &str) -> bool {
+        self.variables.contains(name)
+    }
+
+    pub fn compresses_history(&self) -> bool {
+        self.history
+    }
+
+    pub fn compress(&self, text: &str) -> Result<String, TemplateError> {
+        self.compressor.compress(text)
+    }
+
+    pub fn compress_message(
+        &self,
+        message: &Arc<MessageEnum>,
+    ) -> Result<Arc<MessageEnum>, TemplateError> {
+        let content = self.compress(message.content())?;
+
+        let compressed = match message.as_ref() {
+            MessageEnum::Human(human) => {
+                let mut human = human.clone();
+                human.set_content(&content);
+                MessageEnum::Human(human)
+            }
+            MessageEnum::Ai(ai) => {
+                let mut ai = ai.clone();
+                ai.set_content(&content);
+                MessageEnum::Ai(ai)
+            }
+            MessageEnum::System(system) => {
+                let mut system = system.clone();
+                system.set_content(&content);
+                MessageEnum::System(system)
+            }
+            MessageEnum::Tool(_) => return Ok(Arc::clone(message)),
+        };
+
+        Ok(Arc::new(compressed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, Placeholder, System};
+    use crate::{chats, ChatTemplate};
+
+    #[test]
+    fn test_split_sentences() {
+        let sentences = ExtractiveCompressor::split_sentences(
+            "First sentence. Second one!  Is this third? Version 1.2 is out\nLast line",
+        );
+        assert_eq!(
+            sentences,
+            vec![
+                "First sentence.",
+                "Second one!",
+                "Is this third?",
+                "Version 1.2 is out",
+                "Last line"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extractive_compressor_drops_low_information_sentences() {
+        let text = "The order 4521 shipped from Denver on Monday via FedEx. \
+                    It is what it is. \
+                    The order shipped. \
+                    Customer requested a refund for the damaged blender.";
+
+        let compressed = ExtractiveCompressor::default().compress(text).unwrap();
+        assert_eq!(
+            compressed,
+            "The order 4521 shipped from Denver on Monday via FedEx. \
+             Customer requested a refund for the damaged blender."
+        );
+    }
+
+    #[test]
+    fn test_extractive_compressor_keeps_minimum_sentences() {
+        let compressor = ExtractiveCompressor::new(0.0).with_min_sentences(1);
+        assert_eq!(compressor.compress("Only one.").unwrap(), "Only one.");
+        assert_eq!(compressor.compress("   ").unwrap(), "");
+    }
+
+    #[test]
+    fn test_closure_compressor_hook() {
+        let compressor = |text: &str| -> Result<String, TemplateError> { Ok(text.to_uppercase()) };
+        assert_eq!(compressor.compress("shout").unwrap(), "SHOUT");
+    }
+
+    #[test]
+    fn test_format_messages_compressed() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "Answer using the context: {context}",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let history =
+            r#"[{"role": "human", "content": "Hi there. I ordered a blender last week."}]"#;
+        let context = "Blenders ship in 3 days. Ok. Returns accepted within 30 days.";
+        let question = "Where is my blender?";
+        let variables = crate::vars!(history = history, context = context, question = question);
+
+        let truncate = |text: &str| -> Result<String, TemplateError> {
+            Ok(text.split(". ").last().unwrap_or_default().to_string())
+        };
+        let compression = PromptCompression::new(truncate)
+            .variable("context")
+            .history(true);
+
+        let messages = chat_template
+            .format_messages_compressed(&variables, &compression)
+            .unwrap();
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!(
+            messages[0].content(),
+            "Answer using the context: Returns accepted within 30 days."
+        );
+        assert_eq!(messages[1].content(), "I ordered a blender last week.");
+        assert_eq!(messages[2].content(), "Where is my blender?");
+    }
+}
@@ -0,0 +1,249 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    ChatTemplate, FewShotTemplate, FilterRegistry, FormatOptions, FormatStyle, Formattable,
+    LintDiagnostic, LintProfile, PromptConstants, PromptRegistry, RegisteredPrompt, Templatable,
+    Template, TemplateError, TokenCounter,
+};
+
+// Crate-wide defaults built once and shared behind an `Arc`, so a team can
+// hand the same formatting, separators, filters, constants and lint profile
+// to every call instead of repeating per-call options. Settings made on a
+// template itself, such as its own filter registry, take precedence.
+#[derive(Clone, Default)]
+pub struct PromptForgeConfig {
+    format_options: FormatOptions,
+    style: FormatStyle,
+    example_separator: Option<String>,
+    filters: Option<Arc<FilterRegistry>>,
+    constants: PromptConstants,
+    lint_profile: Option<LintProfile>,
+}
+
+impl fmt::Debug for PromptForgeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PromptForgeConfig")
+            .field("format_options", &self.format_options)
+            .field("style", &self.style)
+            .field("example_separator", &self.example_separator)
+            .field("filters", &self.filters)
+            .field("constants", &self.constants)
+            .field("lint_profile", &self.lint_profile)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptForgeConfigBuilder {
+    config: PromptForgeConfig,
+}
+
+impl PromptForgeConfig {
+    pub fn builder() -> PromptForgeConfigBuilder {
+        PromptForgeConfigBuilder::new()
+    }
+
+    pub fn format_options(&self) -> &FormatOptions {
+        &self.format_options
+    }
+
+    pub fn token_counter(&self) -> Option<&dyn TokenCounter> {
+        self.format_options.token_counter()
+    }
+
+    pub fn style(&self) -> &FormatStyle {
+        &self.style
+    }
+
+    pub fn example_separator(&self) -> &str {
+        self.example_separator
+            .as_deref()
+            .unwrap_or(FewShotTemplate::<Template>::DEFAULT_EXAMPLE_SEPARATOR)
+    }
+
+    pub fn filters(&self) -> &FilterRegistry {
+        self.filters
+            .as_deref()
+            .unwrap_or_else(|| FilterRegistry::builtin())
+    }
+
+    pub fn constants(&self) -> &PromptConstants {
+        &self.constants
+    }
+
+    pub fn lint_profile(&self) -> Option<LintProfile> {
+        self.lint_profile
+    }
+
+    pub fn few_shot<T>(&self, examples: Vec<T>) -> FewShotTemplate<T>
+    where
+        T: Templatable + Formattable + DeserializeOwned + TryFrom<String, Error = TemplateError>,
+    {
+        FewShotTemplate::with_options(examples, None, None, self.example_separator())
+    }
+
+    pub fn registry(&self) -> PromptRegistry {
+        let registry = PromptRegistry::new().with_constants(self.constants.clone());
+        match self.lint_profile {
+            Some(profile) => registry.with_lint_profile(profile),
+            None => registry,
+        }
+    }
+
+    pub fn lint(&self, prompt: &RegisteredPrompt) -> Vec<LintDiagnostic> {
+        prompt.lint(self.lint_profile)
+    }
+
+    pub(crate) fn prepare_template(
+        &self,
+        template: &Template,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Template, TemplateError> {
+        self.constants.check_values(variables)?;
+        let template = self.constants.resolve_template(template)?;
+        Ok(match &self.filters {
+            Some(filters) => template.or_filters(filters),
+            None => template,
+        })
+    }
+
+    // Filters are only applied to top-level templates; chat messages keep
+    // whatever registry their own templates carry.
+    pub(crate) fn prepare_chat(
+        &self,
+        chat_template: &ChatTemplate,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<ChatTemplate, TemplateError> {
+        self.constants.check_values(variables)?;
+        if self.constants.is_empty() {
+            return Ok(chat_template.clone());
+        }
+        match self
+            .constants
+            .resolve_prompt(&RegisteredPrompt::Chat(chat_template.clone()))?
+        {
+            RegisteredPrompt::Chat(chat_template) => Ok(chat_template),
+            RegisteredPrompt::Template(_) => unreachable!("constants keep the prompt kind"),
+        }
+    }
+}
+
+impl PromptForgeConfigBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format_options(mut self, format_options: FormatOptions) -> Self {
+        self.config.format_options = format_options;
+        self
+    }
+
+    pub fn token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.config.format_options = self.config.format_options.with_token_counter(counter);
+        self
+    }
+
+    pub fn style(mut self, style: FormatStyle) -> Self {
+        self.config.style = style;
+        self
+    }
+
+    pub fn message_separator(mut self, separator: &str) -> Self {
+        self.config.style = self.config.style.with_separator(separator);
+        self
+    }
+
+    pub fn example_separator(mut self, separator: impl Into<String>) -> Self {
+        self.config.example_separator = Some(separator.into());
+        self
+    }
+
+    pub fn filters(mut self, filters: FilterRegistry) -> Self {
+        self.config.filters = Some(Arc::new(filters));
+        self
+    }
+
+    pub fn constants(mut self, constants: PromptConstants) -> Self {
+        self.config.constants = constants;
+        self
+    }
+
+    pub fn lint_profile(mut self, profile: LintProfile) -> Self {
+        self.config.lint_profile = Some(profile);
+        self
+    }
+
+    pub fn build(self) -> Arc<PromptForgeConfig> {
+        Arc::new(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, System};
+    use crate::{chats, examples, vars, OutputEncoding};
+
+    fn config() -> Arc<PromptForgeConfig> {
+        let mut filters = FilterRegistry::new();
+        filters.register("shout", |value, _| Ok(format!("{}!", value.to_uppercase())));
+        PromptForgeConfig::builder()
+            .format_options(FormatOptions::new().with_output_encoding(OutputEncoding::JsonString))
+            .token_counter(|text: &str| text.split_whitespace().count())
+            .message_separator("\n---\n")
+            .example_separator(" | ")
+            .filters(filters)
+            .constants(PromptConstants::new().with_constant("product", "Forge"))
+            .lint_profile(LintProfile::Concise)
+            .build()
+    }
+
+    #[test]
+    fn test_config_applies_to_formatting() {
+        let config = config();
+        let template = Template::new("{@const.product} says \"{name|shout}\"").unwrap();
+        assert_eq!(
+            template
+                .format_with_config(&vars!(name = "hi"), Some(&config))
+                .unwrap(),
+            r#"Forge says \"HI!\""#
+        );
+        assert!(template
+            .format_with_config(&vars!(name = "hi"), None)
+            .is_err());
+
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You support {@const.product}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        assert_eq!(
+            chat_template
+                .format_with_config(&vars!(question = "Why?"), Some(&config))
+                .unwrap(),
+            r"system: You support Forge.\n---\nhuman: Why?"
+        );
+        assert_eq!(config.token_counter().unwrap().count_tokens("a b c"), 3);
+    }
+
+    #[test]
+    fn test_config_seeds_few_shot_and_registry() {
+        let config = config();
+        let few_shot = config.few_shot(examples!(("Q1", "A1"), ("Q2", "A2")));
+        assert_eq!(few_shot.format(&vars!()).unwrap(), "Q1\nA1 | Q2\nA2");
+
+        let registry = config
+            .registry()
+            .with_prompt("pitch", Template::new("Try {@const.product}").unwrap());
+        assert_eq!(registry.lint_profile(), Some(LintProfile::Concise));
+        assert_eq!(registry.format("pitch", &vars!()).unwrap(), "Try Forge");
+        assert!(PromptForgeConfig::default()
+            .lint(&Template::new("STOP SHOUTING AT THE MODEL").unwrap().into())
+            .is_empty());
+        assert!(!config
+            .lint(&Template::new("STOP SHOUTING AT THE MODEL").unwrap().into())
+            .is_empty());
+    }
+}
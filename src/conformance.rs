@@ -0,0 +1,225 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatTemplate, Formattable, Role, Template, TemplateError, TemplateFormat};
+
+// The fixture set lives in `conformance/cases.json` so ports in other
+// languages can read it directly; errors are named by `TemplateError`'s
+// serialized `kind`.
+const BUILTIN_CASES: &str = include_str!("../conformance/cases.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseKind {
+    Template,
+    Chat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConformanceMessage {
+    pub role: String,
+    pub template: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceCase {
+    pub name: String,
+    pub kind: CaseKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    // Left out to let the format be detected from the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<TemplateFormat>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub messages: Vec<ConformanceMessage>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    // Exactly one of `expected` and `error` is set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConformanceSuite {
+    pub version: u32,
+    pub cases: Vec<ConformanceCase>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConformanceFailure {
+    pub name: String,
+    pub expected: Result<String, String>,
+    pub actual: Result<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConformanceReport {
+    pub passed: usize,
+    pub failures: Vec<ConformanceFailure>,
+}
+
+pub fn error_kind(error: &TemplateError) -> String {
+    serde_json::to_value(error)
+        .ok()
+        .and_then(|value| value.get("kind")?.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+impl ConformanceCase {
+    pub fn expectation(&self) -> Result<Result<String, String>, TemplateError> {
+        match (&self.expected, &self.error) {
+            (Some(expected), None) => Ok(Ok(expected.clone())),
+            (None, Some(error)) => Ok(Err(error.clone())),
+            _ => Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Conformance case '{}' needs exactly one of 'expected' and 'error'",
+                    self.name
+                ),
+                None,
+            )),
+        }
+    }
+
+    // The reference rendering every port is compared against.
+    pub fn render(&self) -> Result<String, TemplateError> {
+        let variables = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        match self.kind {
+            CaseKind::Template => {
+                let source = self.template.as_deref().ok_or_else(|| {
+                    TemplateError::MalformedTemplate(
+                        format!("Conformance case '{}' has no 'template'", self.name),
+                        None,
+                    )
+                })?;
+                Template::new_with_config(source, self.format.clone(), None)?.format(&variables)
+            }
+            CaseKind::Chat => {
+                let messages = self
+                    .messages
+                    .iter()
+                    .map(|message| {
+                        Ok((
+                            Role::try_from(message.role.as_str())?,
+                            message.template.clone(),
+                        ))
+                    })
+                    .collect::<Result<Vec<_>, TemplateError>>()?;
+                ChatTemplate::from_messages(messages)?.format(&variables)
+            }
+        }
+    }
+}
+
+impl ConformanceSuite {
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_CASES).expect("the bundled conformance cases are valid")
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, TemplateError> {
+        let suite: ConformanceSuite = serde_json::from_str(json).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", e), None)
+        })?;
+        for case in &suite.cases {
+            let _ = case.expectation()?;
+        }
+        Ok(suite)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("conformance cases always serialize")
+    }
+
+    pub fn run(&self) -> ConformanceReport {
+        self.run_with(|case| case.render().map_err(|error| error_kind(&error)))
+    }
+
+    // `render` stands in for another implementation: it returns the rendered
+    // text, or the error kind it failed with.
+    pub fn run_with<F>(&self, mut render: F) -> ConformanceReport
+    where
+        F: FnMut(&ConformanceCase) -> Result<String, String>,
+    {
+        let mut report = ConformanceReport::default();
+        for case in &self.cases {
+            let expected = case
+                .expectation()
+                .unwrap_or_else(|error| Err(error_kind(&error)));
+            let actual = render(case);
+            if actual == expected {
+                report.passed += 1;
+            } else {
+                report.failures.push(ConformanceFailure {
+                    name: case.name.clone(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        report
+    }
+}
+
+impl ConformanceReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} passed, {} failed", self.passed, self.failures.len())?;
+        for failure in &self.failures {
+            write!(
+                f,
+                "\n{}: expected {:?}, got {:?}",
+                failure.name, failure.expected, failure.actual
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_suite_passes_against_reference() {
+        let suite = ConformanceSuite::builtin();
+        let report = suite.run();
+        assert!(report.is_success(), "{}", report);
+        assert_eq!(report.passed, suite.cases.len());
+        assert_eq!(
+            ConformanceSuite::from_json(&suite.to_json()).unwrap(),
+            suite
+        );
+    }
+
+    #[test]
+    fn test_run_with_reports_divergent_port() {
+        let suite = ConformanceSuite::builtin();
+        // A naive port that skips Mustache's HTML escaping and never fails.
+        let report =
+            suite.run_with(|case| Ok(case.render().unwrap_or_default().replace("&lt;", "<")));
+
+        let failed: Vec<&str> = report.failures.iter().map(|f| f.name.as_str()).collect();
+        assert!(failed.contains(&"mustache/html-escaping"));
+        assert!(failed.contains(&"fmtstring/missing-variable"));
+        assert!(!failed.contains(&"fmtstring/basic"));
+        assert!(report
+            .to_string()
+            .starts_with(&format!("{} passed", report.passed)));
+
+        assert!(ConformanceSuite::from_json(
+            r#"{"version": 1, "cases": [{"name": "x", "kind": "template", "template": "x"}]}"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+    sync::Arc,
+};
+
+use lazy_static::lazy_static;
+use messageforge::BaseMessage;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    braces::escape_braces, ChatTemplate, MessageLike, RegisteredPrompt, Role, Templatable,
+    Template, TemplateError, TemplateFormat,
+};
+
+lazy_static! {
+    static ref CONSTANT_RE: Regex = Regex::new(r"\{@const\.([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+}
+
+// Loaded from a constants.toml with a `[const]` table of shared text and an
+// `[enum]` table listing the values each enum-typed variable accepts.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptConstants {
+    #[serde(default, rename = "const", skip_serializing_if = "BTreeMap::is_empty")]
+    constants: BTreeMap<String, String>,
+    #[serde(default, rename = "enum", skip_serializing_if = "BTreeMap::is_empty")]
+    enums: BTreeMap<String, Vec<String>>,
+}
+
+impl PromptConstants {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_constant(mut self, name: &str, value: &str) -> Self {
+        self.constants.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_enum<I, S>(mut self, variable: &str, values: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.enums.insert(
+            variable.to_string(),
+            values.into_iter().map(Into::into).collect(),
+        );
+        self
+    }
+
+    pub fn constant(&self, name: &str) -> Option<&str> {
+        self.constants.get(name).map(String::as_str)
+    }
+
+    pub fn allowed_values(&self, variable: &str) -> Option<&[String]> {
+        self.enums.get(variable).map(Vec::as_slice)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constants.is_empty() && self.enums.is_empty()
+    }
+
+    pub async fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        let content = fs::read_to_string(path).await.map_err(|e| {
+            TemplateError::TomlDeserializationError(format!("Failed to read TOML file: {}", e))
+        })?;
+        Self::try_from(content)
+    }
+
+    // Replaces every `{@const.name}`. In `{var}` and plain templates the value
+    // is brace-escaped so it is never read as a variable.
+    pub fn resolve(
+        &self,
+        source: &str,
+        template_format: TemplateFormat,
+    ) -> Result<String, TemplateError> {
+        let mut resolved = String::with_capacity(source.len());
+        let mut last = 0;
+        for cap in CONSTANT_RE.captures_iter(source) {
+            let (whole, name) = (cap.get(0).unwrap(), &cap[1]);
+            let value = self
+                .constant(name)
+                .ok_or_else(|| TemplateError::MissingVariable(format!("@const.{}", name)))?;
+
+            resolved.push_str(&source[last..whole.start()]);
+            match template_format {
+                TemplateFormat::FmtString | TemplateFormat::PlainText => {
+                    resolved.push_str(&escape_braces(value))
+                }
+                TemplateFormat::Mustache | TemplateFormat::Jinja2 => resolved.push_str(value),
+            }
+            last = whole.end();
+        }
+        resolved.push_str(&source[last..]);
+        Ok(resolved)
+    }
+
+    pub fn resolve_template(&self, template: &Template) -> Result<Template, TemplateError> {
+        if !CONSTANT_RE.is_match(template.template()) {
+            return Ok(template.clone());
+        }
+        let source = self.resolve(template.template(), template.template_format())?;
+        template.with_source(
+            &source,
+            template.template_format(),
+            template.input_variables(),
+        )
+    }
+
+    pub fn resolve_prompt(
+        &self,
+        prompt: &RegisteredPrompt,
+    ) -> Result<RegisteredPrompt, TemplateError> {
+        match prompt {
+            RegisteredPrompt::Template(template) => {
+                Ok(RegisteredPrompt::from(self.resolve_template(template)?))
+            }
+            RegisteredPrompt::Chat(chat_template) => Ok(RegisteredPrompt::Chat(ChatTemplate {
+                messages: chat_template
+                    .messages
+                    .iter()
+                    .map(|message_like| self.resolve_message_like(message_like))
+                    .collect::<Result<_, _>>()?,
+                prefill: chat_template
+                    .prefill
+                    .as_deref()
+                    .map(|prefill| self.resolve_template(prefill).map(Arc::new))
+                    .transpose()?,
+                sensitive: chat_template.sensitive,
+            })),
+        }
+    }
+
+    fn resolve_message_like(
+        &self,
+        message_like: &MessageLike,
+    ) -> Result<MessageLike, TemplateError> {
+        match message_like {
+            MessageLike::RolePromptTemplate(role, template) => Ok(MessageLike::RolePromptTemplate(
+                role.clone(),
+                Arc::new(self.resolve_template(template)?),
+            )),
+            MessageLike::BaseMessage(message)
+                if Role::of(message) != Role::Tool && CONSTANT_RE.is_match(message.content()) =>
+            {
+                let content = self.resolve(message.content(), TemplateFormat::PlainText)?;
+                MessageLike::from_role_source(Role::of(message), content)
+            }
+            MessageLike::ForEach {
+                variable,
+                item_template,
+                role,
+            } => Ok(MessageLike::ForEach {
+                variable: variable.clone(),
+                item_template: Arc::new(self.resolve_template(item_template)?),
+                role: role.clone(),
+            }),
+            MessageLike::SubTemplate(name, chat_template) => Ok(MessageLike::SubTemplate(
+                name.clone(),
+                Arc::new(ChatTemplate {
+                    messages: chat_template
+                        .messages
+                        .iter()
+                        .map(|message_like| self.resolve_message_like(message_like))
+                        .collect::<Result<_, _>>()?,
+                    prefill: None,
+                    sensitive: false,
+                }),
+            )),
+            MessageLike::Annotated(message_like, metadata) => Ok(MessageLike::Annotated(
+                Box::new(self.resolve_message_like(message_like)?),
+                metadata.clone(),
+            )),
+            other => Ok(other.clone()),
+        }
+    }
+
+    // Only variables that are both enum-typed and supplied are checked.
+    pub fn check_values(&self, variables: &HashMap<&str, &str>) -> Result<(), TemplateError> {
+        for (variable, allowed) in &self.enums {
+            if let Some(value) = variables.get(variable.as_str())
+                && !allowed.iter().any(|allowed| allowed == value)
+            {
+                return Err(TemplateError::InvalidValue(format!(
+                    "'{}' is not allowed for '{}', expected one of: {}",
+                    value,
+                    variable,
+                    allowed.join(", ")
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<String> for PromptConstants {
+    type Error = TemplateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        toml::from_str(&value).map_err(|e| {
+            TemplateError::TomlDeserializationError(format!("Failed to parse constants: {}", e))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chats, vars, Formattable, Role::Human, Role::System};
+
+    const CONSTANTS: &str = r#"
+        [const]
+        product_name = "Acme {Cloud}"
+
+        [enum]
+        tone = ["formal", "casual"]
+    "#;
+
+    #[test]
+    fn test_resolves_constant_references() {
+        let constants = PromptConstants::try_from(CONSTANTS.to_string()).unwrap();
+        let template = constants
+            .resolve_template(
+                &Template::new("Write a {tone} note about {@const.product_name}.").unwrap(),
+            )
+            .unwrap();
+        assert_eq!(template.input_variables(), vec!["tone"]);
+        assert_eq!(
+            template.format(&vars!(tone = "casual")).unwrap(),
+            "Write a casual note about Acme {Cloud}."
+        );
+
+        let chat = ChatTemplate::from_messages(chats!(
+            System = "You support {@const.product_name}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let resolved = constants.resolve_prompt(&chat.into()).unwrap();
+        assert_eq!(
+            resolved.format(&vars!(question = "Hi")).unwrap(),
+            "system: You support Acme {Cloud}.\nhuman: Hi"
+        );
+
+        assert!(matches!(
+            constants.resolve("{@const.missing}", TemplateFormat::FmtString),
+            Err(TemplateError::MissingVariable(name)) if name == "@const.missing"
+        ));
+    }
+
+    #[test]
+    fn test_check_values_enforces_enums() {
+        let constants = PromptConstants::try_from(CONSTANTS.to_string()).unwrap();
+        assert_eq!(
+            constants.allowed_values("tone"),
+            Some(&["formal".to_string(), "casual".to_string()][..])
+        );
+        assert!(constants
+            .check_values(&vars!(tone = "formal", other = "x"))
+            .is_ok());
+        assert!(matches!(
+            constants.check_values(&vars!(tone = "angry")),
+            Err(TemplateError::InvalidValue(_))
+        ));
+    }
+}
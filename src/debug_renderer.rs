@@ -0,0 +1,262 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{CanonicalHash, Formattable, TemplateError, TemplateHash};
+
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RenderHistory {
+    previous: Option<String>,
+    latest: String,
+}
+
+#[derive(Debug, Default)]
+pub struct DebugRenderer {
+    history: Mutex<HashMap<TemplateHash, RenderHistory>>,
+}
+
+impl DebugRenderer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn render<T>(
+        &self,
+        template: &T,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError>
+    where
+        T: Formattable + CanonicalHash,
+    {
+        let output = template.format(variables)?;
+        self.record(template.template_hash(), output.clone());
+        Ok(output)
+    }
+
+    pub fn record(&self, hash: TemplateHash, output: String) {
+        let mut history = self.history();
+        match history.get_mut(&hash) {
+            Some(entry) => {
+                entry.previous = Some(std::mem::replace(&mut entry.latest, output));
+            }
+            None => {
+                history.insert(
+                    hash,
+                    RenderHistory {
+                        previous: None,
+                        latest: output,
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn last_render<T: CanonicalHash>(&self, template: &T) -> Option<String> {
+        self.history()
+            .get(&template.template_hash())
+            .map(|entry| entry.latest.clone())
+    }
+
+    pub fn diff<T: CanonicalHash>(&self, template: &T) -> Option<String> {
+        let history = self.history();
+        let entry = history.get(&template.template_hash())?;
+        let previous = entry.previous.as_deref()?;
+        Some(unified_diff(previous, &entry.latest))
+    }
+
+    pub fn clear(&self) {
+        self.history().clear();
+    }
+
+    fn history(&self) -> MutexGuard<'_, HashMap<TemplateHash, RenderHistory>> {
+        self.history
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal,
+    Delete,
+    Insert,
+}
+
+pub fn unified_diff(old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+
+    let changes: Vec<usize> = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != DiffOp::Equal)
+        .map(|(index, _)| index)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for &index in &changes {
+        match groups.last_mut() {
+            Some((_, last)) if index - *last <= 2 * DIFF_CONTEXT + 1 => *last = index,
+            _ => groups.push((index, index)),
+        }
+    }
+
+    let mut out = String::from("--- previous\n+++ current\n");
+    for (first, last) in groups {
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = (last + DIFF_CONTEXT + 1).min(ops.len());
+
+        let old_before = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != DiffOp::Insert)
+            .count();
+        let new_before = ops[..start]
+            .iter()
+            .filter(|(op, _)| *op != DiffOp::Delete)
+            .count();
+        let hunk = &ops[start..end];
+        let old_count = hunk.iter().filter(|(op, _)| *op != DiffOp::Insert).count();
+        let new_count = hunk.iter().filter(|(op, _)| *op != DiffOp::Delete).count();
+
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_before, old_count),
+            hunk_range(new_before, new_count)
+        ));
+        for (op, line) in hunk {
+            let marker = match op {
+                DiffOp::Equal => ' ',
+                DiffOp::Delete => '-',
+                DiffOp::Insert => '+',
+            };
+            out.push(marker);
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+fn hunk_range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, count),
+    }
+}
+
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(DiffOp, &'a str)> {
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old.len() + new.len());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            ops.push((DiffOp::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+            ops.push((DiffOp::Delete, old[i]));
+            i += 1;
+        } else {
+            ops.push((DiffOp::Insert, new[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old[i..].iter().map(|line| (DiffOp::Delete, *line)));
+    ops.extend(new[j..].iter().map(|line| (DiffOp::Insert, *line)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Template};
+
+    #[test]
+    fn test_diff_between_consecutive_renders() {
+        let renderer = DebugRenderer::new();
+        let tmpl =
+            Template::new("You are a {tone} assistant.\nAnswer the question.\n{question}").unwrap();
+
+        renderer
+            .render(&tmpl, &vars!(tone = "friendly", question = "Why?"))
+            .unwrap();
+        assert_eq!(renderer.diff(&tmpl), None);
+
+        renderer
+            .render(&tmpl, &vars!(tone = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            renderer.diff(&tmpl).unwrap(),
+            "--- previous\n+++ current\n@@ -1,3 +1,3 @@\n\
+             -You are a friendly assistant.\n\
+             +You are a terse assistant.\n \
+             Answer the question.\n \
+             Why?\n"
+        );
+        assert_eq!(
+            renderer.last_render(&tmpl).as_deref(),
+            Some("You are a terse assistant.\nAnswer the question.\nWhy?")
+        );
+
+        renderer
+            .render(&tmpl, &vars!(tone = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(renderer.diff(&tmpl).as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_history_is_kept_per_template() {
+        let renderer = DebugRenderer::new();
+        let first = Template::new("Hello {name}").unwrap();
+        let second = Template::new("Bye {name}").unwrap();
+
+        renderer.render(&first, &vars!(name = "Ada")).unwrap();
+        renderer.render(&second, &vars!(name = "Bob")).unwrap();
+        assert_eq!(renderer.diff(&first), None);
+        assert_eq!(renderer.last_render(&second).as_deref(), Some("Bye Bob"));
+
+        renderer.clear();
+        assert_eq!(renderer.last_render(&first), None);
+    }
+
+    #[test]
+    fn test_unified_diff_hunks() {
+        let old: Vec<String> = (1..=20).map(|n| format!("line {}", n)).collect();
+        let mut new = old.clone();
+        new[1] = "changed 2".to_string();
+        new.remove(17);
+        new.push("line 21".to_string());
+
+        let diff = unified_diff(&old.join("\n"), &new.join("\n"));
+        assert_eq!(
+            diff,
+            "--- previous\n+++ current\n\
+             @@ -1,5 +1,5 @@\n line 1\n-line 2\n+changed 2\n line 3\n line 4\n line 5\n\
+             @@ -15,6 +15,6 @@\n line 15\n line 16\n line 17\n-line 18\n line 19\n line 20\n+line 21\n"
+        );
+        assert_eq!(unified_diff("same", "same"), "");
+        assert_eq!(
+            unified_diff("", "new"),
+            "--- previous\n+++ current\n@@ -0,0 +1 @@\n+new\n"
+        );
+    }
+}
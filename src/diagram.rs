@@ -0,0 +1,346 @@
+use messageforge::BaseMessage;
+
+use crate::{ChatTemplate, MessageLike, Role, Templatable, Template};
+
+const PREVIEW_CHARS: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Message,
+    Placeholder,
+    FewShot,
+    SubTemplate,
+    Variable,
+    Partial,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    id: String,
+    label: String,
+    kind: NodeKind,
+}
+
+// Dashed edges feed a variable or partial into a message; solid edges give
+// the rendering order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Edge {
+    from: String,
+    to: String,
+    label: Option<&'static str>,
+    dashed: bool,
+}
+
+#[derive(Debug, Default)]
+struct Diagram {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+fn preview(source: &str) -> String {
+    let flat = source.split_whitespace().collect::<Vec<_>>().join(" ");
+    match flat.char_indices().nth(PREVIEW_CHARS) {
+        Some((end, _)) => format!("{}...", &flat[..end]),
+        None => flat,
+    }
+}
+
+impl Diagram {
+    fn of(chat_template: &ChatTemplate) -> Self {
+        let mut diagram = Diagram::default();
+        let mut previous: Option<String> = None;
+
+        for (index, message_like) in chat_template.messages.iter().enumerate() {
+            let id = format!("m{}", index);
+            diagram.link(previous.replace(id.clone()), &id);
+            diagram.add_message(&id, message_like.inner());
+        }
+        if let Some(prefill) = chat_template.assistant_prefill() {
+            let id = "prefill".to_string();
+            diagram.node(
+                &id,
+                format!("ai prefill: {}", preview(prefill.template())),
+                NodeKind::Message,
+            );
+            diagram.link(previous, &id);
+            diagram.add_template_variables(&id, prefill);
+        }
+
+        diagram
+    }
+
+    fn node(&mut self, id: &str, label: String, kind: NodeKind) {
+        if !self.nodes.iter().any(|node| node.id == id) {
+            self.nodes.push(Node {
+                id: id.to_string(),
+                label,
+                kind,
+            });
+        }
+    }
+
+    fn link(&mut self, from: Option<String>, to: &str) {
+        if let Some(from) = from {
+            self.edges.push(Edge {
+                from,
+                to: to.to_string(),
+                label: None,
+                dashed: false,
+            });
+        }
+    }
+
+    fn feed(&mut self, from: String, to: &str, label: Option<&'static str>) {
+        self.edges.push(Edge {
+            from,
+            to: to.to_string(),
+            label,
+            dashed: true,
+        });
+    }
+
+    fn add_variables(&mut self, id: &str, variables: Vec<String>) {
+        for variable in variables {
+            let var_id = format!("v_{}", variable);
+            self.node(&var_id, variable, NodeKind::Variable);
+            self.feed(var_id, id, None);
+        }
+    }
+
+    fn add_template_variables(&mut self, id: &str, template: &Template) {
+        let (partials, variables): (Vec<String>, Vec<String>) = template
+            .input_variables()
+            .into_iter()
+            .partition(|var| template.partial_vars().contains_key(var));
+        for partial in partials {
+            let partial_id = format!("{}_{}", id, partial);
+            self.node(
+                &partial_id,
+                format!(
+                    "{} = {}",
+                    partial,
+                    preview(&template.partial_vars()[&partial])
+                ),
+                NodeKind::Partial,
+            );
+            self.feed(partial_id, id, Some("partial"));
+        }
+        self.add_variables(id, variables);
+    }
+
+    fn add_message(&mut self, id: &str, message_like: &MessageLike) {
+        match message_like {
+            MessageLike::BaseMessage(message) => self.node(
+                id,
+                format!("{}: {}", Role::of(message), preview(message.content())),
+                NodeKind::Message,
+            ),
+            MessageLike::RolePromptTemplate(role, template) => {
+                self.node(
+                    id,
+                    format!("{}: {}", role, preview(template.template())),
+                    NodeKind::Message,
+                );
+                self.add_template_variables(id, template);
+            }
+            MessageLike::Placeholder(placeholder) => {
+                let optional = if placeholder.optional() {
+                    " (optional)"
+                } else {
+                    ""
+                };
+                self.node(
+                    id,
+                    format!("placeholder: {}{}", placeholder.variable_name(), optional),
+                    NodeKind::Placeholder,
+                );
+                self.add_variables(id, vec![placeholder.variable_name().to_string()]);
+            }
+            MessageLike::FewShotPrompt(few_shot_prompt) => {
+                let examples =
+                    few_shot_prompt.examples().len() + few_shot_prompt.multi_turn_examples().len();
+                self.node(
+                    id,
+                    format!("few-shot: {} examples", examples),
+                    NodeKind::FewShot,
+                );
+                self.add_variables(id, few_shot_prompt.input_variables());
+            }
+            MessageLike::ToolCallTemplate(tool_call_template) => {
+                let names: Vec<&str> = tool_call_template
+                    .calls()
+                    .iter()
+                    .map(|call| call.name.as_str())
+                    .collect();
+                self.node(
+                    id,
+                    format!("ai tool calls: {}", names.join(", ")),
+                    NodeKind::Message,
+                );
+                self.add_variables(id, tool_call_template.input_variables());
+            }
+            MessageLike::Multimodal(multimodal_template) => {
+                self.node(
+                    id,
+                    format!(
+                        "{}: {} content parts",
+                        multimodal_template.role(),
+                        multimodal_template.parts().len()
+                    ),
+                    NodeKind::Message,
+                );
+                self.add_variables(id, multimodal_template.input_variables());
+            }
+            MessageLike::ForEach {
+                variable,
+                item_template,
+                role,
+            } => {
+                self.node(
+                    id,
+                    format!(
+                        "for each {}: {}: {}",
+                        variable,
+                        role,
+                        preview(item_template.template())
+                    ),
+                    NodeKind::Message,
+                );
+                self.add_variables(id, vec![variable.clone()]);
+            }
+            MessageLike::SubTemplate(name, chat_template) => {
+                self.node(
+                    id,
+                    format!(
+                        "include {}: {} messages",
+                        name,
+                        chat_template.messages.len()
+                    ),
+                    NodeKind::SubTemplate,
+                );
+                self.add_variables(id, chat_template.input_variables());
+            }
+            MessageLike::Annotated(message_like, _) => self.add_message(id, message_like),
+        }
+    }
+
+    fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart TD\n");
+        for node in &self.nodes {
+            let label = node.label.replace('"', "#quot;");
+            let shape = match node.kind {
+                NodeKind::Message => format!("[\"{}\"]", label),
+                NodeKind::Placeholder => format!("[/\"{}\"/]", label),
+                NodeKind::FewShot => format!("[[\"{}\"]]", label),
+                NodeKind::SubTemplate => format!("[(\"{}\")]", label),
+                NodeKind::Variable => format!("([\"{}\"])", label),
+                NodeKind::Partial => format!("{{{{\"{}\"}}}}", label),
+            };
+            out.push_str(&format!("    {}{}\n", node.id, shape));
+        }
+        for edge in &self.edges {
+            let arrow = match (edge.dashed, edge.label) {
+                (false, _) => "-->".to_string(),
+                (true, None) => "-.->".to_string(),
+                (true, Some(label)) => format!("-. {} .->", label),
+            };
+            out.push_str(&format!("    {} {} {}\n", edge.from, arrow, edge.to));
+        }
+        out
+    }
+
+    fn to_dot(&self) -> String {
+        let mut out = String::from("digraph chat_template {\n    rankdir=TB;\n");
+        for node in &self.nodes {
+            let label = node.label.replace('\\', "\\\\").replace('"', "\\\"");
+            let shape = match node.kind {
+                NodeKind::Message => "box",
+                NodeKind::Placeholder => "parallelogram",
+                NodeKind::FewShot => "box3d",
+                NodeKind::SubTemplate => "component",
+                NodeKind::Variable => "ellipse",
+                NodeKind::Partial => "hexagon",
+            };
+            out.push_str(&format!(
+                "    {} [label=\"{}\", shape={}];\n",
+                node.id, label, shape
+            ));
+        }
+        for edge in &self.edges {
+            let attributes = match (edge.dashed, edge.label) {
+                (false, _) => String::new(),
+                (true, None) => " [style=dashed]".to_string(),
+                (true, Some(label)) => format!(" [style=dashed, label=\"{}\"]", label),
+            };
+            out.push_str(&format!(
+                "    {} -> {}{};\n",
+                edge.from, edge.to, attributes
+            ));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+impl ChatTemplate {
+    pub fn to_mermaid(&self) -> String {
+        Diagram::of(self).to_mermaid()
+    }
+
+    pub fn to_dot(&self) -> String {
+        Diagram::of(self).to_dot()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chats;
+    use crate::Role::{Human, Placeholder, System};
+
+    fn chat() -> ChatTemplate {
+        let mut question = Template::new("{lang}: {question}").unwrap();
+        question.partial("lang", "en");
+
+        let mut chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are \"helpful\".",
+            Placeholder = "{history}",
+        ))
+        .unwrap();
+        chat_template
+            .push(MessageLike::role_prompt_template(Human, question))
+            .unwrap();
+        chat_template
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        assert_eq!(
+            chat().to_mermaid(),
+            "flowchart TD\n\
+             \x20   m0[\"system: You are #quot;helpful#quot;.\"]\n\
+             \x20   m1[/\"placeholder: history\"/]\n\
+             \x20   v_history([\"history\"])\n\
+             \x20   m2[\"human: {lang}: {question}\"]\n\
+             \x20   m2_lang{{\"lang = en\"}}\n\
+             \x20   v_question([\"question\"])\n\
+             \x20   m0 --> m1\n\
+             \x20   v_history -.-> m1\n\
+             \x20   m1 --> m2\n\
+             \x20   m2_lang -. partial .-> m2\n\
+             \x20   v_question -.-> m2\n"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let dot = chat()
+            .with_assistant_prefill(Template::new("Sure, {question}").unwrap())
+            .to_dot();
+        assert!(dot.starts_with("digraph chat_template {\n"));
+        assert!(dot.contains("    m0 [label=\"system: You are \\\"helpful\\\".\", shape=box];\n"));
+        assert!(dot.contains("    m2_lang -> m2 [style=dashed, label=\"partial\"];\n"));
+        assert!(dot.contains("    m2 -> prefill;\n    v_question -> prefill [style=dashed];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+}
@@ -0,0 +1,306 @@
+use std::ops::Range;
+
+use crate::{
+    braces::mask_escaped_braces, placeholder::FMTSTRING_PLACEHOLDER_RE,
+    template_format::validate_template, SourceSpan, Templatable, Template, TemplateError,
+    TemplateFormat,
+};
+
+// Nodes keep their exact source text, so untouched parts of a template
+// (escapes, defaults, filter chains) are re-emitted byte for byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceNode {
+    Literal(String),
+    Variable { name: String, source: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct TemplateEditor {
+    base: Template,
+    nodes: Vec<SourceNode>,
+}
+
+impl SourceNode {
+    pub fn source(&self) -> &str {
+        match self {
+            SourceNode::Literal(source) => source,
+            SourceNode::Variable { source, .. } => source,
+        }
+    }
+}
+
+impl Template {
+    pub fn editor(&self) -> Result<TemplateEditor, TemplateError> {
+        match self.template_format() {
+            TemplateFormat::FmtString | TemplateFormat::PlainText => Ok(TemplateEditor {
+                nodes: parse_nodes(self.template())?,
+                base: self.clone(),
+            }),
+            _ => Err(TemplateError::UnsupportedFormat(
+                "Template editing is only supported for FmtString templates".to_string(),
+            )),
+        }
+    }
+}
+
+impl TemplateEditor {
+    pub fn nodes(&self) -> &[SourceNode] {
+        &self.nodes
+    }
+
+    pub fn source(&self) -> String {
+        self.nodes.iter().map(SourceNode::source).collect()
+    }
+
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for node in &self.nodes {
+            if let SourceNode::Variable { name, .. } = node
+                && !variables.contains(name)
+            {
+                variables.push(name.clone());
+            }
+        }
+        variables
+    }
+
+    // Fragments are template source: they may introduce new `{variables}`, and
+    // literal braces in them must be escaped.
+    pub fn insert_before(self, variable: &str, fragment: &str) -> Result<Self, TemplateError> {
+        self.wrap(variable, fragment, "")
+    }
+
+    pub fn insert_after(self, variable: &str, fragment: &str) -> Result<Self, TemplateError> {
+        self.wrap(variable, "", fragment)
+    }
+
+    pub fn wrap(
+        mut self,
+        variable: &str,
+        before: &str,
+        after: &str,
+    ) -> Result<Self, TemplateError> {
+        let before = parse_nodes(before)?;
+        let after = parse_nodes(after)?;
+        if !self.contains_variable(variable) {
+            return Err(TemplateError::MissingVariable(variable.to_string()));
+        }
+
+        let mut nodes = Vec::with_capacity(self.nodes.len() + before.len() + after.len());
+        for node in self.nodes {
+            if matches!(&node, SourceNode::Variable { name, .. } if name == variable) {
+                nodes.extend(before.iter().cloned());
+                nodes.push(node);
+                nodes.extend(after.iter().cloned());
+            } else {
+                nodes.push(node);
+            }
+        }
+
+        self.nodes = merge_literals(nodes);
+        Ok(self)
+    }
+
+    // `range` is a byte range of the current `source()` and must lie inside a
+    // single literal run; variables can only be moved through `wrap`.
+    pub fn replace_literal(
+        mut self,
+        range: Range<usize>,
+        fragment: &str,
+    ) -> Result<Self, TemplateError> {
+        let replacement = parse_nodes(fragment)?;
+        let source = self.source();
+
+        let mut start = 0;
+        let target = self.nodes.iter().position(|node| {
+            let end = start + node.source().len();
+            let found = matches!(node, SourceNode::Literal(_))
+                && start <= range.start
+                && range.start <= range.end
+                && range.end <= end;
+            if !found {
+                start = end;
+            }
+            found
+        });
+
+        let Some(index) = target
+            .filter(|_| source.is_char_boundary(range.start) && source.is_char_boundary(range.end))
+        else {
+            return Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Range {}..{} does not fall inside a single literal span",
+                    range.start, range.end
+                ),
+                Some(SourceSpan::locate(&source, range.start)),
+            ));
+        };
+
+        let literal = self.nodes[index].source();
+        let (head, tail) = (
+            literal[..range.start - start].to_string(),
+            literal[range.end - start..].to_string(),
+        );
+        let spliced = [SourceNode::Literal(head)]
+            .into_iter()
+            .chain(replacement)
+            .chain([SourceNode::Literal(tail)]);
+
+        self.nodes.splice(index..=index, spliced);
+        self.nodes = merge_literals(std::mem::take(&mut self.nodes));
+        Ok(self)
+    }
+
+    pub fn finish(self) -> Result<Template, TemplateError> {
+        let source = self.source();
+        let input_variables = self.input_variables();
+        let template_format = if input_variables.is_empty() {
+            TemplateFormat::PlainText
+        } else {
+            TemplateFormat::FmtString
+        };
+
+        self.base
+            .with_source(&source, template_format, input_variables)
+    }
+
+    fn contains_variable(&self, variable: &str) -> bool {
+        self.nodes
+            .iter()
+            .any(|node| matches!(node, SourceNode::Variable { name, .. } if name == variable))
+    }
+}
+
+fn parse_nodes(source: &str) -> Result<Vec<SourceNode>, TemplateError> {
+    if source.is_empty() {
+        return Ok(Vec::new());
+    }
+    validate_template(source)?;
+
+    let masked = mask_escaped_braces(source);
+    let mut nodes = Vec::new();
+    let mut last = 0;
+
+    for cap in FMTSTRING_PLACEHOLDER_RE.captures_iter(&masked) {
+        let (whole, name) = (cap.get(0).unwrap(), cap.get(1).unwrap());
+        if whole.start() > last {
+            nodes.push(SourceNode::Literal(source[last..whole.start()].to_string()));
+        }
+        nodes.push(SourceNode::Variable {
+            name: source[name.range()].to_string(),
+            source: source[whole.range()].to_string(),
+        });
+        last = whole.end();
+    }
+    if last < source.len() {
+        nodes.push(SourceNode::Literal(source[last..].to_string()));
+    }
+
+    Ok(nodes)
+}
+
+fn merge_literals(nodes: Vec<SourceNode>) -> Vec<SourceNode> {
+    let mut merged: Vec<SourceNode> = Vec::with_capacity(nodes.len());
+    for node in nodes {
+        match (merged.last_mut(), node) {
+            (_, SourceNode::Literal(text)) if text.is_empty() => {}
+            (Some(SourceNode::Literal(previous)), SourceNode::Literal(text)) => {
+                previous.push_str(&text)
+            }
+            (_, node) => merged.push(node),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Formattable};
+
+    #[test]
+    fn test_wrap_preserves_untouched_source() {
+        let mut template =
+            Template::new("Answer {question|trim} using \\{json\\}. Tone: {tone:neutral}").unwrap();
+        template.partial("tone", "warm");
+
+        let edited = template
+            .editor()
+            .unwrap()
+            .wrap("question", "<q>", "</q>")
+            .unwrap()
+            .finish()
+            .unwrap();
+
+        assert_eq!(
+            edited.template(),
+            "Answer <q>{question|trim}</q> using \\{json\\}. Tone: {tone:neutral}"
+        );
+        assert_eq!(edited.input_variables(), vec!["question", "tone"]);
+        assert_eq!(
+            edited.format(&vars!(question = " Why? ")).unwrap(),
+            "Answer <q>Why?</q> using {json}. Tone: warm"
+        );
+    }
+
+    #[test]
+    fn test_insert_fragments_add_variables() {
+        let editor = Template::new("Summarize {doc}.")
+            .unwrap()
+            .editor()
+            .unwrap()
+            .insert_before("doc", "the {kind} ")
+            .unwrap()
+            .insert_after("doc", " in {n} words")
+            .unwrap();
+
+        assert_eq!(editor.source(), "Summarize the {kind} {doc} in {n} words.");
+        assert_eq!(editor.input_variables(), vec!["kind", "doc", "n"]);
+        assert_eq!(editor.nodes().len(), 7);
+
+        assert!(matches!(
+            editor.clone().insert_after("missing", "!"),
+            Err(TemplateError::MissingVariable(_))
+        ));
+        assert!(editor.insert_after("doc", "{ oops").is_err());
+    }
+
+    #[test]
+    fn test_replace_literal_span() {
+        let template = Template::new("Be brief. Answer {question}. Be brief.").unwrap();
+        let editor = template.editor().unwrap();
+
+        let edited = editor
+            .clone()
+            .replace_literal(0..9, "Be thorough, cite {source}.")
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(
+            edited.template(),
+            "Be thorough, cite {source}. Answer {question}. Be brief."
+        );
+        assert_eq!(edited.input_variables(), vec!["source", "question"]);
+
+        assert!(matches!(
+            editor.replace_literal(10..20, "Reply"),
+            Err(TemplateError::MalformedTemplate(_, Some(_)))
+        ));
+    }
+
+    #[test]
+    fn test_plain_text_becomes_fmtstring_and_rejects_other_formats() {
+        let edited = Template::new("Hello there")
+            .unwrap()
+            .editor()
+            .unwrap()
+            .replace_literal(6..11, "{name}")
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(edited.template_format(), TemplateFormat::FmtString);
+        assert_eq!(edited.format(&vars!(name = "Ada")).unwrap(), "Hello Ada");
+
+        assert!(Template::new("Hello {{name}}").unwrap().editor().is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use std::{fmt, path::Path, sync::Arc};
+
+use serde::{ser::Error as _, Serializer};
+
+use crate::{
+    ChatTemplate, FromSource, LoaderOptions, PromptRegistry, RegisteredPrompt, Template,
+    TemplateError,
+};
+
+pub type KeyProvider = Arc<dyn Fn(&Path) -> Result<Vec<u8>, TemplateError> + Send + Sync>;
+
+// Implemented by callers on top of their cipher of choice (age, a KMS client,
+// ...); promptforge only moves bytes between disk and the cipher and ships
+// no cryptography of its own.
+pub trait Cipher: Send + Sync {
+    fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, TemplateError>;
+}
+
+impl<F> Cipher for F
+where
+    F: Fn(&[u8], &[u8]) -> Result<Vec<u8>, TemplateError> + Send + Sync,
+{
+    fn decrypt(&self, key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, TemplateError> {
+        self(key, ciphertext)
+    }
+}
+
+// Prompts decrypted in memory are marked sensitive. Serializing one fails,
+// so no save or export path can write the plaintext back to disk. The mark
+// carries over to clones and cannot be cleared.
+pub trait Sensitive {
+    fn mark_sensitive(&mut self);
+    fn is_sensitive(&self) -> bool;
+}
+
+impl Sensitive for Template {
+    fn mark_sensitive(&mut self) {
+        self.sensitive = true;
+    }
+
+    fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+}
+
+impl Sensitive for ChatTemplate {
+    fn mark_sensitive(&mut self) {
+        self.sensitive = true;
+    }
+
+    fn is_sensitive(&self) -> bool {
+        self.sensitive
+    }
+}
+
+impl Sensitive for RegisteredPrompt {
+    fn mark_sensitive(&mut self) {
+        match self {
+            RegisteredPrompt::Template(template) => Arc::make_mut(template).mark_sensitive(),
+            RegisteredPrompt::Chat(chat_template) => chat_template.mark_sensitive(),
+        }
+    }
+
+    fn is_sensitive(&self) -> bool {
+        match self {
+            RegisteredPrompt::Template(template) => template.is_sensitive(),
+            RegisteredPrompt::Chat(chat_template) => chat_template.is_sensitive(),
+        }
+    }
+}
+
+pub(crate) fn sensitive_error() -> TemplateError {
+    TemplateError::RuntimeError(
+        "Refusing to write a sensitive prompt decrypted from an encrypted file".to_string(),
+    )
+}
+
+// Only called for a set flag; unmarked prompts skip the field entirely.
+pub(crate) fn refuse_sensitive<S: Serializer>(_: &bool, _: S) -> Result<S::Ok, S::Error> {
+    Err(S::Error::custom(sensitive_error()))
+}
+
+#[derive(Clone)]
+pub struct EncryptedLoader {
+    cipher: Arc<dyn Cipher>,
+    key_provider: KeyProvider,
+    options: LoaderOptions,
+}
+
+impl fmt::Debug for EncryptedLoader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EncryptedLoader")
+            .field("options", &self.options)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EncryptedLoader {
+    pub fn new<C, K>(cipher: C, key_provider: K) -> Self
+    where
+        C: Cipher + 'static,
+        K: Fn(&Path) -> Result<Vec<u8>, TemplateError> + Send + Sync + 'static,
+    {
+        EncryptedLoader {
+            cipher: Arc::new(cipher),
+            key_provider: Arc::new(key_provider),
+            options: LoaderOptions::default(),
+        }
+    }
+
+    pub fn with_options(mut self, options: LoaderOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    pub async fn load<T, P>(&self, path: P) -> Result<T, TemplateError>
+    where
+        T: FromSource + Sensitive,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let ciphertext = self.options.read_bytes(path).await?;
+        let key = (self.key_provider)(path)?;
+        let plaintext = self.cipher.decrypt(&key, &ciphertext)?;
+        let content = String::from_utf8(plaintext).map_err(|_| {
+            TemplateError::MalformedTemplate(
+                format!("Decrypted content of {} is not valid UTF-8", path.display()),
+                None,
+            )
+        })?;
+
+        let mut prompt = T::from_source(content, self.options.brace_literals())?;
+        prompt.mark_sensitive();
+        Ok(prompt)
+    }
+}
+
+impl PromptRegistry {
+    pub async fn load_encrypted<T, P>(
+        &mut self,
+        name: impl Into<String>,
+        path: P,
+        loader: &EncryptedLoader,
+    ) -> Result<(), TemplateError>
+    where
+        T: FromSource + Sensitive + Into<RegisteredPrompt>,
+        P: AsRef<Path>,
+    {
+        let prompt: T = loader.load(path).await?;
+        self.register_sensitive(name, prompt);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::System;
+    use crate::{chats, vars, ChatTemplate, Template};
+
+    // Stand-in cipher for tests only; real callers plug in age or AES-GCM.
+    fn xor(key: &[u8], data: &[u8]) -> Result<Vec<u8>, TemplateError> {
+        if key.is_empty() {
+            return Err(TemplateError::MalformedTemplate(
+                "empty key".to_string(),
+                None,
+            ));
+        }
+        Ok(data
+            .iter()
+            .zip(key.iter().cycle())
+            .map(|(byte, k)| byte ^ k)
+            .collect())
+    }
+
+    fn write_encrypted(name: &str, plaintext: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, xor(b"secret", plaintext.as_bytes()).unwrap()).unwrap();
+        path
+    }
+
+    fn loader() -> EncryptedLoader {
+        EncryptedLoader::new(
+            |key: &[u8], data: &[u8]| xor(key, data),
+            |_: &Path| Ok(b"secret".to_vec()),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_load_encrypted_template() {
+        let path = write_encrypted("promptforge_encrypted.bin", "Hi {name}, code {code}");
+        let template: Template = loader().load(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            crate::Formattable::format(&template, &vars!(name = "Ada", code = "7")).unwrap(),
+            "Hi Ada, code 7"
+        );
+
+        let path = write_encrypted(
+            "promptforge_encrypted_literals.bin",
+            "Print {} for {{lang}}",
+        );
+        let template: Template = loader()
+            .with_options(
+                LoaderOptions::new().with_brace_literals(crate::BraceLiterals::programming()),
+            )
+            .load(&path)
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(
+            crate::Formattable::format(&template, &vars!(lang = "Rust")).unwrap(),
+            "Print {} for Rust"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_key_and_decrypt_failures_surface() {
+        let path = write_encrypted("promptforge_encrypted_fail.bin", "Hi {name}");
+        let no_key = EncryptedLoader::new(
+            |key: &[u8], data: &[u8]| xor(key, data),
+            |path: &Path| {
+                Err(TemplateError::MalformedTemplate(
+                    format!("no key for {}", path.display()),
+                    None,
+                ))
+            },
+        );
+        assert!(no_key.load::<Template, _>(&path).await.is_err());
+
+        let wrong_key = EncryptedLoader::new(
+            |key: &[u8], data: &[u8]| xor(key, data),
+            |_: &Path| Ok(vec![0xFF]),
+        );
+        assert!(wrong_key.load::<Template, _>(&path).await.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_registry_marks_encrypted_prompts_sensitive() {
+        let chat_template =
+            ChatTemplate::from_messages(chats!(System = "Rules for {tenant}")).unwrap();
+        let path = write_encrypted(
+            "promptforge_encrypted_chat.bin",
+            &serde_json::to_string(&chat_template).unwrap(),
+        );
+        let mut registry = PromptRegistry::new();
+        registry
+            .load_encrypted::<ChatTemplate, _>("rules", &path, &loader())
+            .await
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(registry.is_sensitive("rules"));
+        assert_eq!(
+            registry.format("rules", &vars!(tenant = "acme")).unwrap(),
+            "system: Rules for acme"
+        );
+
+        let Some(RegisteredPrompt::Chat(decrypted)) = registry.get("rules") else {
+            panic!("expected a chat prompt");
+        };
+        let copy =
+            decrypted.clone() + ChatTemplate::from_messages(chats!(System = "Be brief")).unwrap();
+        assert!(copy.is_sensitive());
+        assert!(copy.to_toml_string().is_err());
+        assert!(serde_json::to_string(decrypted).is_err());
+        let out = std::env::temp_dir().join("promptforge_decrypted_chat.toml");
+        assert!(decrypted.save_toml_file(&out).await.is_err());
+        assert!(!out.exists());
+
+        registry.register("rules", chat_template);
+        assert!(!registry.is_sensitive("rules"));
+    }
+
+    #[tokio::test]
+    async fn test_loaded_template_refuses_serialization() {
+        let path = write_encrypted("promptforge_encrypted_refuse.bin", "Hi {name}");
+        let template: Template = loader().load(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(template.is_sensitive());
+        let err = serde_json::to_string(&template).unwrap_err();
+        assert!(err.to_string().contains("sensitive"), "{}", err);
+        assert!(serde_json::to_string(&Template::new("Hi {name}").unwrap()).is_ok());
+    }
+}
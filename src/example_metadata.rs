@@ -0,0 +1,197 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::TemplateError;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+        }
+    }
+}
+
+impl TryFrom<&str> for Difficulty {
+    type Error = TemplateError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(TemplateError::MalformedTemplate(
+                format!("Unknown example difficulty: {}", value),
+                None,
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExampleMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<Difficulty>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negative: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rationale: Option<String>,
+}
+
+impl ExampleMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    pub fn with_difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulty = Some(difficulty);
+        self
+    }
+
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.source = Some(source.into());
+        self
+    }
+
+    pub fn with_created_at(mut self, created_at: impl Into<String>) -> Self {
+        self.created_at = Some(created_at.into());
+        self
+    }
+
+    pub fn with_negative(mut self, rationale: impl Into<String>) -> Self {
+        self.negative = true;
+        self.rationale = Some(rationale.into());
+        self
+    }
+
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn difficulty(&self) -> Option<Difficulty> {
+        self.difficulty
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    pub fn created_at(&self) -> Option<&str> {
+        self.created_at.as_deref()
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    pub fn rationale(&self) -> Option<&str> {
+        self.rationale.as_deref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Template;
+
+    #[test]
+    fn test_difficulty_try_from_str() {
+        assert_eq!(Difficulty::try_from("easy").unwrap(), Difficulty::Easy);
+        assert_eq!(Difficulty::try_from("Medium").unwrap(), Difficulty::Medium);
+        assert_eq!(Difficulty::try_from("HARD").unwrap(), Difficulty::Hard);
+        assert!(Difficulty::try_from("impossible").is_err());
+    }
+
+    #[test]
+    fn test_difficulty_to_string() {
+        assert_eq!(Difficulty::Easy.to_string(), "easy");
+        assert_eq!(Difficulty::Medium.to_string(), "medium");
+        assert_eq!(Difficulty::Hard.to_string(), "hard");
+    }
+
+    #[test]
+    fn test_metadata_builder() {
+        let metadata = ExampleMetadata::new()
+            .with_label("math")
+            .with_difficulty(Difficulty::Hard)
+            .with_source("textbook")
+            .with_created_at("2024-09-01");
+
+        assert_eq!(metadata.label(), Some("math"));
+        assert_eq!(metadata.difficulty(), Some(Difficulty::Hard));
+        assert_eq!(metadata.source(), Some("textbook"));
+        assert_eq!(metadata.created_at(), Some("2024-09-01"));
+        assert!(!metadata.is_negative());
+        assert_eq!(metadata.rationale(), None);
+    }
+
+    #[test]
+    fn test_negative_metadata_serialization() {
+        let metadata = ExampleMetadata::new().with_negative("Adds instead of multiplying.");
+        assert!(metadata.is_negative());
+        assert_eq!(metadata.rationale(), Some("Adds instead of multiplying."));
+
+        let serialized = serde_json::to_string(&metadata).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"negative":true,"rationale":"Adds instead of multiplying."}"#
+        );
+
+        let deserialized: ExampleMetadata = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, metadata);
+    }
+
+    #[test]
+    fn test_template_metadata_survives_serialization() {
+        let template = Template::new("{input}: What is 2 + 2?\n{output}: 4")
+            .unwrap()
+            .with_metadata(
+                ExampleMetadata::new()
+                    .with_label("math")
+                    .with_difficulty(Difficulty::Easy),
+            );
+
+        let serialized = serde_json::to_string(&template).unwrap();
+        assert!(serialized.contains(r#""metadata":{"label":"math","difficulty":"Easy"}"#));
+
+        let deserialized: Template = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.metadata(), template.metadata());
+    }
+
+    #[test]
+    fn test_template_without_metadata_serialization_unchanged() {
+        let template = Template::new("Hello, {name}!").unwrap();
+        let serialized = serde_json::to_string(&template).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"template":"Hello, {name}!","template_format":"FmtString","input_variables":["name"]}"#
+        );
+        assert!(template.metadata().is_none());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{example_metadata::Difficulty, ExampleMetadata, Template};
+
+pub trait ExampleSelector {
+    fn select<'a>(&self, examples: &'a [Template]) -> Vec<&'a Template>;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetadataFilterSelector {
+    labels: Vec<String>,
+    difficulties: Vec<Difficulty>,
+    sources: Vec<String>,
+    negative: Option<bool>,
+    max_examples: Option<usize>,
+}
+
+impl MetadataFilterSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.labels.push(label.into());
+        self
+    }
+
+    pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+        self.difficulties.push(difficulty);
+        self
+    }
+
+    pub fn source(mut self, source: impl Into<String>) -> Self {
+        self.sources.push(source.into());
+        self
+    }
+
+    pub fn negative(mut self, negative: bool) -> Self {
+        self.negative = Some(negative);
+        self
+    }
+
+    pub fn max_examples(mut self, max_examples: usize) -> Self {
+        self.max_examples = Some(max_examples);
+        self
+    }
+
+    pub fn matches(&self, metadata: Option<&ExampleMetadata>) -> bool {
+        let label = metadata.and_then(ExampleMetadata::label);
+        let difficulty = metadata.and_then(ExampleMetadata::difficulty);
+        let source = metadata.and_then(ExampleMetadata::source);
+        let negative = metadata.is_some_and(ExampleMetadata::is_negative);
+
+        (self.labels.is_empty() || label.is_some_and(|l| self.labels.iter().any(|x| x == l)))
+            && (self.difficulties.is_empty()
+                || difficulty.is_some_and(|d| self.difficulties.contains(&d)))
+            && (self.sources.is_empty()
+                || source.is_some_and(|s| self.sources.iter().any(|x| x == s)))
+            && self.negative.is_none_or(|n| n == negative)
+    }
+}
+
+impl ExampleSelector for MetadataFilterSelector {
+    fn select<'a>(&self, examples: &'a [Template]) -> Vec<&'a Template> {
+        examples
+            .iter()
+            .filter(|example| self.matches(example.metadata()))
+            .take(self.max_examples.unwrap_or(usize::MAX))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StratifiedExampleSelector {
+    max_examples: Option<usize>,
+}
+
+impl StratifiedExampleSelector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_examples(mut self, max_examples: usize) -> Self {
+        self.max_examples = Some(max_examples);
+        self
+    }
+}
+
+impl ExampleSelector for StratifiedExampleSelector {
+    fn select<'a>(&self, examples: &'a [Template]) -> Vec<&'a Template> {
+        let mut strata: Vec<(Option<&str>, Vec<usize>)> = Vec::new();
+        let positives_first = examples
+            .iter()
+            .enumerate()
+            .filter(|(_, example)| !example.is_negative_example())
+            .chain(
+                examples
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, example)| example.is_negative_example()),
+            );
+
+        for (index, example) in positives_first {
+            let label = example.metadata().and_then(ExampleMetadata::label);
+            match strata.iter_mut().find(|(l, _)| *l == label) {
+                Some((_, indices)) => indices.push(index),
+                None => strata.push((label, vec![index])),
+            }
+        }
+
+        let budget = self.max_examples.unwrap_or(examples.len());
+        let mut selected = Vec::with_capacity(budget.min(examples.len()));
+        let mut round = 0;
+
+        while selected.len() < budget {
+            let mut picked_any = false;
+            for (_, indices) in &strata {
+                if selected.len() == budget {
+                    break;
+                }
+                if let Some(&index) = indices.get(round) {
+                    selected.push(index);
+                    picked_any = true;
+                }
+            }
+            if !picked_any {
+                break;
+            }
+            round += 1;
+        }
+
+        selected.sort_unstable();
+        selected.into_iter().map(|index| &examples[index]).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Templatable, Template};
+
+    fn example(text: &str, label: &str, difficulty: Difficulty, source: &str) -> Template {
+        Template::new(text).unwrap().with_metadata(
+            ExampleMetadata::new()
+                .with_label(label)
+                .with_difficulty(difficulty)
+                .with_source(source),
+        )
+    }
+
+    fn corpus() -> Vec<Template> {
+        vec![
+            example("easy math", "math", Difficulty::Easy, "textbook"),
+            example("hard math", "math", Difficulty::Hard, "olympiad"),
+            example("hard history", "history", Difficulty::Hard, "textbook"),
+            Template::new("no metadata").unwrap(),
+        ]
+    }
+
+    fn templates<'a>(selected: &[&'a Template]) -> Vec<&'a str> {
+        selected.iter().map(|t| t.template()).collect()
+    }
+
+    #[test]
+    fn test_empty_filter_selects_everything() {
+        let examples = corpus();
+        let selected = MetadataFilterSelector::new().select(&examples);
+        assert_eq!(selected.len(), 4);
+    }
+
+    #[test]
+    fn test_filter_by_difficulty() {
+        let examples = corpus();
+        let selected = MetadataFilterSelector::new()
+            .difficulty(Difficulty::Hard)
+            .select(&examples);
+        assert_eq!(templates(&selected), vec!["hard math", "hard history"]);
+    }
+
+    #[test]
+    fn test_filter_by_label_and_source() {
+        let examples = corpus();
+        let selected = MetadataFilterSelector::new()
+            .label("math")
+            .source("textbook")
+            .select(&examples);
+        assert_eq!(templates(&selected), vec!["easy math"]);
+    }
+
+    #[test]
+    fn test_filter_multiple_values_and_limit() {
+        let examples = corpus();
+        let selected = MetadataFilterSelector::new()
+            .label("math")
+            .label("history")
+            .max_examples(2)
+            .select(&examples);
+        assert_eq!(templates(&selected), vec!["easy math", "hard math"]);
+    }
+
+    fn labeled(text: &str, label: &str) -> Template {
+        Template::new(text)
+            .unwrap()
+            .with_metadata(ExampleMetadata::new().with_label(label))
+    }
+
+    #[test]
+    fn test_filter_by_negative_flag() {
+        let mut examples = corpus();
+        examples.push(
+            Template::new("wrong math")
+                .unwrap()
+                .with_metadata(ExampleMetadata::new().with_negative("off by one")),
+        );
+
+        let positives = MetadataFilterSelector::new()
+            .negative(false)
+            .select(&examples);
+        assert_eq!(positives.len(), 4);
+
+        let negatives = MetadataFilterSelector::new()
+            .negative(true)
+            .select(&examples);
+        assert_eq!(templates(&negatives), vec!["wrong math"]);
+    }
+
+    #[test]
+    fn test_stratified_prefers_positive_examples() {
+        let examples = vec![
+            Template::new("refund wrong").unwrap().with_metadata(
+                ExampleMetadata::new()
+                    .with_label("refund")
+                    .with_negative("wrong intent"),
+            ),
+            labeled("refund 1", "refund"),
+            labeled("shipping 1", "shipping"),
+        ];
+
+        let selected = StratifiedExampleSelector::new()
+            .max_examples(2)
+            .select(&examples);
+        assert_eq!(templates(&selected), vec!["refund 1", "shipping 1"]);
+    }
+
+    #[test]
+    fn test_stratified_covers_every_label_first() {
+        let examples = vec![
+            labeled("refund 1", "refund"),
+            labeled("refund 2", "refund"),
+            labeled("refund 3", "refund"),
+            labeled("shipping 1", "shipping"),
+            labeled("cancel 1", "cancel"),
+            labeled("shipping 2", "shipping"),
+        ];
+
+        let selected = StratifiedExampleSelector::new()
+            .max_examples(3)
+            .select(&examples);
+        assert_eq!(
+            templates(&selected),
+            vec!["refund 1", "shipping 1", "cancel 1"]
+        );
+
+        let selected = StratifiedExampleSelector::new()
+            .max_examples(5)
+            .select(&examples);
+        assert_eq!(
+            templates(&selected),
+            vec![
+                "refund 1",
+                "refund 2",
+                "shipping 1",
+                "cancel 1",
+                "shipping 2"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stratified_budget_smaller_than_labels() {
+        let examples = corpus();
+        let selected = StratifiedExampleSelector::new()
+            .max_examples(1)
+            .select(&examples);
+        assert_eq!(templates(&selected), vec!["easy math"]);
+    }
+
+    #[test]
+    fn test_stratified_without_budget_selects_everything() {
+        let examples = corpus();
+        let selected = StratifiedExampleSelector::new().select(&examples);
+        assert_eq!(
+            templates(&selected),
+            vec!["easy math", "hard math", "hard history", "no metadata"]
+        );
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use messageforge::{BaseMessage, MessageEnum};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatTemplate, ContentPart, MessageMetadata, Role, TemplateError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleMapping {
+    pub role: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+}
+
+impl RoleMapping {
+    pub fn new(role: impl Into<String>) -> Self {
+        RoleMapping {
+            role: role.into(),
+            prefix: None,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleMap {
+    pub system: RoleMapping,
+    pub human: RoleMapping,
+    pub ai: RoleMapping,
+    pub tool: RoleMapping,
+    // Custom roles export under their own name unless mapped here.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub custom: BTreeMap<String, RoleMapping>,
+}
+
+impl Default for RoleMap {
+    fn default() -> Self {
+        RoleMap {
+            system: RoleMapping::new(Role::System.as_str()),
+            human: RoleMapping::new(Role::Human.as_str()),
+            ai: RoleMapping::new(Role::Ai.as_str()),
+            tool: RoleMapping::new(Role::Tool.as_str()),
+            custom: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolFunction {
+    pub name: String,
+    pub arguments: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCall {
+    pub id: String,
+    #[serde(rename = "type", default = "ToolCall::function_type")]
+    pub kind: String,
+    pub function: ToolFunction,
+}
+
+impl ToolCall {
+    pub const KWARGS_KEY: &'static str = "tool_calls";
+
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        arguments: &serde_json::Value,
+    ) -> Self {
+        ToolCall {
+            id: id.into(),
+            kind: Self::function_type(),
+            function: ToolFunction {
+                name: name.into(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn function_type() -> String {
+        "function".to_string()
+    }
+
+    pub fn arguments(&self) -> Result<serde_json::Value, TemplateError> {
+        serde_json::from_str(&self.function.arguments).map_err(|e| {
+            TemplateError::MalformedTemplate(
+                format!("Invalid arguments for tool call '{}': {}", self.id, e),
+                None,
+            )
+        })
+    }
+
+    // Tool calls travel on messages through `additional_kwargs`, which only
+    // holds strings, so they are stored as a JSON array.
+    pub fn from_kwargs(additional_kwargs: &HashMap<String, String>) -> Vec<ToolCall> {
+        additional_kwargs
+            .get(Self::KWARGS_KEY)
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn to_kwarg(tool_calls: &[ToolCall]) -> String {
+        serde_json::to_string(tool_calls).expect("tool calls serialize")
+    }
+
+    fn to_prose(&self) -> String {
+        format!("{}({})", self.function.name, self.function.arguments)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedMessage {
+    pub role: String,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub prefill: bool,
+    // Set for multimodal messages; `content` then holds only their text.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub content_parts: Vec<ContentPart>,
+    #[serde(flatten)]
+    pub metadata: MessageMetadata,
+}
+
+impl RoleMap {
+    pub const TOOL_ROLE: &'static str = "tool";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn openai() -> Self {
+        RoleMap {
+            system: RoleMapping::new("system"),
+            human: RoleMapping::new("user"),
+            ai: RoleMapping::new("assistant"),
+            tool: RoleMapping::new(Self::TOOL_ROLE),
+            custom: BTreeMap::new(),
+        }
+    }
+
+    pub fn text_only() -> Self {
+        RoleMap {
+            tool: RoleMapping::new("user").with_prefix("Tool result: "),
+            ..Self::openai()
+        }
+    }
+
+    pub fn map(mut self, role: Role, mapping: RoleMapping) -> Self {
+        match role {
+            Role::System => self.system = mapping,
+            Role::Human => self.human = mapping,
+            Role::Ai => self.ai = mapping,
+            Role::Tool => self.tool = mapping,
+            Role::Custom(name) => {
+                self.custom.insert(name, mapping);
+            }
+            Role::Placeholder | Role::FewShotPrompt => {}
+        }
+        self
+    }
+
+    pub fn mapping(&self, role: Role) -> Option<&RoleMapping> {
+        match role {
+            Role::System => Some(&self.system),
+            Role::Human => Some(&self.human),
+            Role::Ai => Some(&self.ai),
+            Role::Tool => Some(&self.tool),
+            Role::Custom(name) => self.custom.get(&name),
+            Role::Placeholder | Role::FewShotPrompt => None,
+        }
+    }
+
+    pub fn export_message(&self, message: &MessageEnum) -> ExportedMessage {
+        let custom = match Role::of(message) {
+            Role::Custom(name) => Some(
+                self.custom
+                    .get(&name)
+                    .cloned()
+                    .unwrap_or_else(|| RoleMapping::new(name)),
+            ),
+            _ => None,
+        };
+        let (mapping, tool_call_id) = match (message, &custom) {
+            (_, Some(mapping)) => (mapping, None),
+            (MessageEnum::System(_), None) => (&self.system, None),
+            (MessageEnum::Human(_), None) => (&self.human, None),
+            (MessageEnum::Ai(_), None) => (&self.ai, None),
+            (MessageEnum::Tool(tool), None) => (&self.tool, Some(tool.tool_call_id().to_string())),
+        };
+
+        let mut content = match &mapping.prefix {
+            Some(prefix) => format!("{}{}", prefix, message.content()),
+            None => message.content().to_string(),
+        };
+
+        // Targets without a native tool role get the calls spelled out as prose.
+        let mut tool_calls = match message {
+            MessageEnum::Ai(ai) => ToolCall::from_kwargs(ai.additional_kwargs()),
+            _ => Vec::new(),
+        };
+        if self.tool.role != Self::TOOL_ROLE && !tool_calls.is_empty() {
+            let prose: Vec<String> = tool_calls.drain(..).map(|call| call.to_prose()).collect();
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(&prose.join("\n"));
+        }
+
+        let mut content_parts = ContentPart::from_kwargs(message.additional_kwargs());
+        if let Some(prefix) = &mapping.prefix
+            && let Some(ContentPart::Text { text }) = content_parts
+                .iter_mut()
+                .find(|part| matches!(part, ContentPart::Text { .. }))
+        {
+            text.insert_str(0, prefix);
+        }
+
+        ExportedMessage {
+            role: mapping.role.clone(),
+            content,
+            tool_call_id: tool_call_id.filter(|_| mapping.role == Self::TOOL_ROLE),
+            tool_calls,
+            prefill: ChatTemplate::is_prefill(message),
+            content_parts,
+            metadata: MessageMetadata::of(message),
+        }
+    }
+
+    pub fn export(&self, messages: &[Arc<MessageEnum>]) -> Vec<ExportedMessage> {
+        messages
+            .iter()
+            .map(|message| self.export_message(message))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use messageforge::tool_message::ToolStatus;
+    use messageforge::{AiMessage, HumanMessage, SystemMessage, ToolMessage};
+
+    fn conversation() -> Vec<Arc<MessageEnum>> {
+        vec![
+            Arc::new(MessageEnum::System(SystemMessage::new("Be brief."))),
+            Arc::new(MessageEnum::Human(HumanMessage::new("Weather in Oslo?"))),
+            Arc::new(MessageEnum::Ai(AiMessage::new("get_weather(Oslo)"))),
+            Arc::new(MessageEnum::Tool(ToolMessage::new(
+                "-3°C",
+                "call_1".to_string(),
+                None,
+                ToolStatus::Success,
+            ))),
+        ]
+    }
+
+    fn roles(exported: &[ExportedMessage]) -> Vec<&str> {
+        exported.iter().map(|m| m.role.as_str()).collect()
+    }
+
+    #[test]
+    fn test_default_role_map_is_identity() {
+        let exported = RoleMap::new().export(&conversation());
+        assert_eq!(roles(&exported), vec!["system", "human", "ai", "tool"]);
+        assert_eq!(exported[3].tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[test]
+    fn test_openai_role_map() {
+        let exported = RoleMap::openai().export(&conversation());
+        assert_eq!(
+            roles(&exported),
+            vec!["system", "user", "assistant", "tool"]
+        );
+        assert_eq!(exported[3].content, "-3°C");
+    }
+
+    #[test]
+    fn test_text_only_role_map_folds_tool_messages() {
+        let exported = RoleMap::text_only().export(&conversation());
+        assert_eq!(
+            roles(&exported),
+            vec!["system", "user", "assistant", "user"]
+        );
+        assert_eq!(exported[3].content, "Tool result: -3°C");
+        assert_eq!(exported[3].tool_call_id, None);
+    }
+
+    #[test]
+    fn test_tool_calls_export_natively_or_as_prose() {
+        let call = ToolCall::new(
+            "call_1",
+            "get_weather",
+            &serde_json::json!({"city": "Oslo"}),
+        );
+        let mut ai = AiMessage::new("");
+        ai.base.additional_kwargs.insert(
+            ToolCall::KWARGS_KEY.to_string(),
+            ToolCall::to_kwarg(std::slice::from_ref(&call)),
+        );
+        let message = MessageEnum::Ai(ai);
+
+        let exported = RoleMap::openai().export_message(&message);
+        assert_eq!(exported.tool_calls, vec![call.clone()]);
+        assert_eq!(
+            serde_json::to_value(&exported).unwrap()["tool_calls"][0],
+            serde_json::json!({
+                "id": "call_1",
+                "type": "function",
+                "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+            })
+        );
+        assert_eq!(call.arguments().unwrap()["city"], "Oslo");
+
+        let exported = RoleMap::text_only().export_message(&message);
+        assert!(exported.tool_calls.is_empty());
+        assert_eq!(exported.content, r#"get_weather({"city":"Oslo"})"#);
+    }
+
+    #[test]
+    fn test_custom_mapping_and_serialization() {
+        let role_map = RoleMap::openai().map(
+            Role::System,
+            RoleMapping::new("user").with_prefix("[instructions] "),
+        );
+        assert_eq!(role_map.mapping(Role::System).unwrap().role, "user");
+        assert!(role_map.mapping(Role::Placeholder).is_none());
+
+        let json = serde_json::to_string(&role_map).unwrap();
+        let deserialized: RoleMap = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, role_map);
+
+        let exported = deserialized.export(&conversation()[..1]);
+        assert_eq!(exported[0].content, "[instructions] Be brief.");
+    }
+}
@@ -1,39 +1,267 @@
-use std::{collections::HashMap, fmt, path::Path, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::Path,
+    sync::Arc,
+};
 
+use lazy_static::lazy_static;
+use messageforge::{BaseMessage, MessageEnum};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
 use crate::{
-    ChatTemplate, FewShotChatTemplateConfig, FewShotTemplate, Formattable, Template, TemplateError,
+    encryption::{sensitive_error, Sensitive},
+    example_selector::ExampleSelector,
+    few_shot_chat_template_config::{MessageConfig, TemplateConfig},
+    metrics::{estimate_tokens, PromptMetrics},
+    renderer::join_messages,
+    vars::borrow_vars,
+    ChatTemplate, ConsistencyIssue, ConsistencyReport, FewShotChatTemplateConfig, FewShotTemplate,
+    Formattable, MultiTurnExample, Role, Templatable, Template, TemplateError, TemplateFormat,
 };
 
+lazy_static! {
+    static ref FIELD_LABEL_RE: Regex =
+        Regex::new(r"^\s*([A-Za-z][A-Za-z0-9 _-]{0,31}):(?:\s|$)").unwrap();
+}
+
+type MessageShape = (String, Vec<String>);
+
+fn parse_messages(text: &str) -> Result<Vec<MessageEnum>, TemplateError> {
+    MessageEnum::parse_messages(text).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("Failed to parse message: {}", e), None)
+    })
+}
+
+fn message_shapes<M: BaseMessage>(messages: &[M]) -> Vec<MessageShape> {
+    messages
+        .iter()
+        .map(|message| {
+            let labels = FIELD_LABEL_RE
+                .captures(message.content())
+                .map(|captures| vec![captures[1].trim().to_string()])
+                .unwrap_or_default();
+            (message.role().to_string(), labels)
+        })
+        .collect()
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NegativeExamplePolicy {
+    #[default]
+    Interleaved,
+    AfterPositives,
+    BeforePositives,
+    Omit,
+}
+
+impl NegativeExamplePolicy {
+    pub(crate) fn is_default(&self) -> bool {
+        *self == NegativeExamplePolicy::default()
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FewShotChatTemplate {
     examples: FewShotTemplate<Template>,
     example_prompt: Arc<ChatTemplate>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    negative_example_prompt: Option<Arc<ChatTemplate>>,
+    #[serde(default, skip_serializing_if = "NegativeExamplePolicy::is_default")]
+    negative_example_policy: NegativeExamplePolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    multi_turn_examples: Vec<MultiTurnExample>,
+    // Which role each example variable renders as; variables left out are
+    // inferred from the example prompt.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    example_variable_mapping: BTreeMap<String, Role>,
 }
 
 impl FewShotChatTemplate {
+    pub const RATIONALE_VARIABLE: &'static str = "rationale";
+
     pub fn new(examples: FewShotTemplate<Template>, example_prompt: ChatTemplate) -> Self {
         FewShotChatTemplate {
             examples,
             example_prompt: Arc::new(example_prompt),
+            negative_example_prompt: None,
+            negative_example_policy: NegativeExamplePolicy::default(),
+            multi_turn_examples: Vec::new(),
+            example_variable_mapping: BTreeMap::new(),
         }
     }
 
+    pub fn with_multi_turn_examples(mut self, multi_turn_examples: Vec<MultiTurnExample>) -> Self {
+        self.multi_turn_examples = multi_turn_examples;
+        self
+    }
+
+    pub fn with_example_variable_mapping(mut self, mapping: BTreeMap<String, Role>) -> Self {
+        self.example_variable_mapping = mapping;
+        self
+    }
+
+    pub fn with_example_variable(mut self, variable: &str, role: Role) -> Self {
+        self.example_variable_mapping
+            .insert(variable.to_string(), role);
+        self
+    }
+
+    pub fn extend_examples(mut self, examples: impl IntoIterator<Item = Template>) -> Self {
+        self.examples = self.examples.extend_examples(examples);
+        self
+    }
+
+    pub fn with_negative_example_prompt(mut self, negative_example_prompt: ChatTemplate) -> Self {
+        self.negative_example_prompt = Some(Arc::new(negative_example_prompt));
+        self
+    }
+
+    pub fn with_negative_example_policy(mut self, policy: NegativeExamplePolicy) -> Self {
+        self.negative_example_policy = policy;
+        self
+    }
+
     pub fn format_examples(&self) -> Result<String, TemplateError> {
-        let variables = self.example_prompt.to_variables_map();
-        self.format(&variables)
+        let variables = self.example_role_variables();
+        self.format(&borrow_vars(&variables))
+    }
+
+    pub fn format_messages(&self) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let variables = self.example_role_variables();
+        let (prefix, examples, suffix) = self.format_parts(&borrow_vars(&variables))?;
+
+        let mut messages: Vec<Arc<MessageEnum>> = parse_messages(&prefix)?
+            .into_iter()
+            .chain(parse_messages(&examples)?)
+            .map(Arc::new)
+            .collect();
+        for example in self.ordered_multi_turn_examples() {
+            messages.extend(example.format_messages(&self.example_prompt)?);
+        }
+        messages.extend(parse_messages(&suffix)?.into_iter().map(Arc::new));
+
+        Ok(messages)
+    }
+
+    // Every example is compared against the first one; the final user turn in the
+    // suffix must reuse the field labels the examples taught. Multi-turn examples
+    // have their own turn structure and are not compared, nor are negative examples,
+    // which are either left out or rendered through the negative prompt.
+    pub fn verify_consistency(&self) -> Result<ConsistencyReport, TemplateError> {
+        let variables = self.example_role_variables();
+        let variables = borrow_vars(&variables);
+        let mut report = ConsistencyReport::default();
+        let mut reference: Option<Vec<MessageShape>> = None;
+
+        for (index, example) in self.examples().iter().enumerate() {
+            if example.is_negative_example() {
+                continue;
+            }
+            let shapes = message_shapes(&parse_messages(&example.format(&variables)?)?);
+            report.examples += 1;
+
+            let Some(expected) = &reference else {
+                reference = Some(shapes);
+                continue;
+            };
+
+            let roles = |shapes: &[MessageShape]| -> Vec<String> {
+                shapes.iter().map(|(role, _)| role.clone()).collect()
+            };
+            if roles(expected) != roles(&shapes) {
+                report.issues.push(ConsistencyIssue::RoleSequence {
+                    example: index,
+                    expected: roles(expected),
+                    found: roles(&shapes),
+                });
+                continue;
+            }
+            for (message, ((_, expected), (_, found))) in expected.iter().zip(&shapes).enumerate() {
+                if expected != found {
+                    report.issues.push(ConsistencyIssue::FieldLabels {
+                        example: index,
+                        message,
+                        expected: expected.clone(),
+                        found: found.clone(),
+                    });
+                }
+            }
+        }
+
+        let final_turn = self
+            .suffix()
+            .and_then(|suffix| parse_messages(suffix.template()).ok())
+            .and_then(|messages| {
+                message_shapes(&messages)
+                    .into_iter()
+                    .rfind(|(role, _)| role == "human")
+            });
+        if let (Some(reference), Some((_, found))) = (&reference, final_turn) {
+            let expected = reference
+                .iter()
+                .find(|(role, _)| role == "human")
+                .map(|(_, labels)| labels.clone())
+                .unwrap_or_default();
+            let taught =
+                |label: &String| reference.iter().any(|(_, labels)| labels.contains(label));
+            if !expected.iter().all(|label| found.contains(label)) || !found.iter().all(taught) {
+                report
+                    .issues
+                    .push(ConsistencyIssue::FinalTurnLabels { expected, found });
+            }
+        }
+
+        Ok(report)
+    }
+
+    // Examples are written as "{input}: ..." lines, so each variable renders as the
+    // role it is mapped to, or else the first role it is bound to in the example
+    // prompt.
+    pub fn example_variable_mapping(&self) -> BTreeMap<String, Role> {
+        let mut mapping: BTreeMap<String, Role> = self
+            .example_prompt
+            .to_variables_map()
+            .into_iter()
+            .filter_map(|(var, roles)| Some((var, roles.into_iter().next()?)))
+            .collect();
+        mapping.extend(self.example_variable_mapping.clone());
+        mapping
+    }
+
+    pub(crate) fn explicit_example_variables(&self) -> &BTreeMap<String, Role> {
+        &self.example_variable_mapping
+    }
+
+    pub(crate) fn example_role_variables(&self) -> HashMap<String, String> {
+        self.example_variable_mapping()
+            .into_iter()
+            .map(|(var, role)| (var, role.as_str().to_string()))
+            .collect()
     }
 
     pub fn examples(&self) -> &[Template] {
         self.examples.examples()
     }
 
+    pub fn multi_turn_examples(&self) -> &[MultiTurnExample] {
+        &self.multi_turn_examples
+    }
+
     pub fn example_prompt(&self) -> &ChatTemplate {
         &self.example_prompt
     }
 
+    pub fn negative_example_prompt(&self) -> Option<&ChatTemplate> {
+        self.negative_example_prompt.as_deref()
+    }
+
+    pub fn negative_example_policy(&self) -> NegativeExamplePolicy {
+        self.negative_example_policy
+    }
+
     pub fn example_separator(&self) -> &str {
         self.examples.example_separator()
     }
@@ -46,19 +274,174 @@ impl FewShotChatTemplate {
         self.examples.suffix()
     }
 
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for template in self.prefix().into_iter().chain(self.suffix()) {
+            for var in template.required_variables() {
+                if !variables.contains(&var) && !template.partial_vars().contains_key(&var) {
+                    variables.push(var);
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn select_examples(&self, selector: &dyn ExampleSelector) -> Self {
+        FewShotChatTemplate {
+            examples: self.examples.select_examples(selector),
+            example_prompt: Arc::clone(&self.example_prompt),
+            negative_example_prompt: self.negative_example_prompt.clone(),
+            negative_example_policy: self.negative_example_policy,
+            multi_turn_examples: self.multi_turn_examples.clone(),
+            example_variable_mapping: self.example_variable_mapping.clone(),
+        }
+    }
+
+    // Without a negative example prompt nothing would set negatives apart
+    // from positives, so they are left out whatever the policy.
+    fn ordered_examples(&self) -> Vec<&Template> {
+        let shown = self.examples().iter().filter(|example| {
+            !example.is_negative_example() || self.negative_example_prompt.is_some()
+        });
+        let (negatives, positives): (Vec<&Template>, Vec<&Template>) = shown
+            .clone()
+            .partition(|example| example.is_negative_example());
+
+        match self.negative_example_policy {
+            NegativeExamplePolicy::Interleaved => shown.collect(),
+            NegativeExamplePolicy::AfterPositives => {
+                positives.into_iter().chain(negatives).collect()
+            }
+            NegativeExamplePolicy::BeforePositives => {
+                negatives.into_iter().chain(positives).collect()
+            }
+            NegativeExamplePolicy::Omit => positives,
+        }
+    }
+
+    // A negative multi-turn example is only shown through its own example
+    // prompt; the shared one would render it as a positive.
+    fn ordered_multi_turn_examples(&self) -> Vec<&MultiTurnExample> {
+        let shown = self
+            .multi_turn_examples
+            .iter()
+            .filter(|example| !example.is_negative_example() || example.example_prompt().is_some());
+        let (negatives, positives): (Vec<&MultiTurnExample>, Vec<&MultiTurnExample>) = shown
+            .clone()
+            .partition(|example| example.is_negative_example());
+
+        match self.negative_example_policy {
+            NegativeExamplePolicy::Interleaved => shown.collect(),
+            NegativeExamplePolicy::AfterPositives => {
+                positives.into_iter().chain(negatives).collect()
+            }
+            NegativeExamplePolicy::BeforePositives => {
+                negatives.into_iter().chain(positives).collect()
+            }
+            NegativeExamplePolicy::Omit => positives,
+        }
+    }
+
+    fn format_parts(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<(String, String, String), TemplateError> {
+        let ordered_examples = self.ordered_examples();
+        self.examples
+            .format_parts(variables, &ordered_examples, |example| {
+                match &self.negative_example_prompt {
+                    Some(negative_example_prompt) if example.is_negative_example() => {
+                        self.format_negative_example(negative_example_prompt, example)
+                    }
+                    _ => example.format(variables),
+                }
+            })
+    }
+
+    fn format_negative_example(
+        &self,
+        negative_example_prompt: &ChatTemplate,
+        example: &Template,
+    ) -> Result<String, TemplateError> {
+        let mut fields = self.example_fields(example)?;
+        let rationale = example
+            .metadata()
+            .and_then(|metadata| metadata.rationale())
+            .unwrap_or_default();
+        fields.insert(Self::RATIONALE_VARIABLE.to_string(), rationale.to_string());
+
+        negative_example_prompt.format(&borrow_vars(&fields))
+    }
+
+    // Renders the example with a marker in place of each role variable, so
+    // the text is unescaped exactly as for a positive example, and reads each
+    // field from its marker up to the next one. Values may span lines and
+    // contain colons.
+    fn example_fields(&self, example: &Template) -> Result<HashMap<String, String>, TemplateError> {
+        const MARKER: char = '\u{1}';
+        let markers: HashMap<String, String> = self
+            .example_variable_mapping()
+            .into_keys()
+            .map(|variable| {
+                let marker = format!("{MARKER}{variable}{MARKER}");
+                (variable, marker)
+            })
+            .collect();
+        let rendered = example.format(&borrow_vars(&markers))?;
+
+        let mut segments = rendered.split(MARKER);
+        let leading = segments.next().unwrap_or_default();
+        if !leading.trim().is_empty() {
+            return Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Negative example must start with a role variable: {}",
+                    example.template()
+                ),
+                None,
+            ));
+        }
+
+        let mut fields = HashMap::new();
+        while let (Some(variable), Some(value)) = (segments.next(), segments.next()) {
+            let value = value.trim_start();
+            let value = value.strip_prefix(':').unwrap_or(value).trim();
+            fields.insert(variable.to_string(), value.to_string());
+        }
+        Ok(fields)
+    }
+
+    pub fn metrics(&self) -> PromptMetrics {
+        let estimated_tokens = self
+            .prefix()
+            .into_iter()
+            .chain(self.examples())
+            .chain(self.suffix())
+            .map(|template| estimate_tokens(template.template()))
+            .sum();
+
+        PromptMetrics {
+            variable_count: 0,
+            message_count: self.example_prompt.messages.len(),
+            few_shot_example_count: self.examples().len() + self.multi_turn_examples.len(),
+            nesting_depth: self.example_prompt.metrics().nesting_depth + 1,
+            estimated_tokens,
+        }
+    }
+
     fn try_from_json(value: &str) -> Result<Self, TemplateError> {
         if let Ok(template) = serde_json::from_str::<FewShotChatTemplate>(value) {
             return Ok(template);
         }
 
         let deserialized: HashMap<String, String> = serde_json::from_str(value).map_err(|e| {
-            TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", e))
+            TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", e), None)
         })?;
 
         let examples_str = deserialized
             .get("examples")
             .ok_or(TemplateError::MalformedTemplate(
                 "Missing 'examples' field".to_string(),
+                None,
             ))?;
         let examples = FewShotTemplate::try_from(examples_str.clone())?;
 
@@ -67,6 +450,7 @@ impl FewShotChatTemplate {
                 .get("example_prompt")
                 .ok_or(TemplateError::MalformedTemplate(
                     "Missing 'example_prompt' field".to_string(),
+                    None,
                 ))?;
         let example_prompt = ChatTemplate::try_from(example_prompt_str.clone())?;
 
@@ -75,16 +459,19 @@ impl FewShotChatTemplate {
 
     fn try_from_toml(value: &str) -> Result<Self, TemplateError> {
         let toml_parsed: HashMap<String, String> = toml::from_str(value).map_err(|e| {
-            TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", e))
+            TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", e), None)
         })?;
 
         let examples_str = toml_parsed.get("examples").ok_or_else(|| {
-            TemplateError::MalformedTemplate("Missing 'examples' field in TOML".to_string())
+            TemplateError::MalformedTemplate("Missing 'examples' field in TOML".to_string(), None)
         })?;
         let examples = FewShotTemplate::try_from(examples_str.clone())?;
 
         let example_prompt_str = toml_parsed.get("example_prompt").ok_or_else(|| {
-            TemplateError::MalformedTemplate("Missing 'example_prompt' field in TOML".to_string())
+            TemplateError::MalformedTemplate(
+                "Missing 'example_prompt' field in TOML".to_string(),
+                None,
+            )
         })?;
         let example_prompt = ChatTemplate::try_from(example_prompt_str.clone())?;
 
@@ -97,16 +484,44 @@ impl FewShotChatTemplate {
         })?;
 
         let config: FewShotChatTemplateConfig = toml::from_str(&toml_content).map_err(|e| {
-            TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", e))
+            TemplateError::MalformedTemplate(format!("Failed to parse TOML: {}", e), None)
         })?;
 
         FewShotChatTemplate::try_from(config)
     }
+
+    // Written in the config layout `from_toml_file` reads.
+    pub fn to_toml_string(&self) -> Result<String, TemplateError> {
+        let config = FewShotChatTemplateConfig::try_from(self)?;
+        toml::to_string(&config).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Failed to serialize TOML: {}", e), None)
+        })
+    }
+
+    pub async fn save_toml_file<P: AsRef<Path>>(&self, path: P) -> Result<(), TemplateError> {
+        fs::write(path, self.to_toml_string()?)
+            .await
+            .map_err(|e| TemplateError::RuntimeError(format!("Failed to write TOML file: {}", e)))
+    }
 }
 
 impl Formattable for FewShotChatTemplate {
     fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
-        let examples = self.examples.format(variables)?;
+        let (prefix, examples, suffix) = self.format_parts(variables)?;
+
+        let mut parts = vec![prefix, examples];
+        for example in self.ordered_multi_turn_examples() {
+            parts.push(join_messages(
+                &example.format_messages(&self.example_prompt)?,
+            ));
+        }
+        parts.push(suffix);
+
+        let examples = parts
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join(self.example_separator());
         if examples.is_empty() {
             Ok(String::new())
         } else {
@@ -135,6 +550,70 @@ impl TryFrom<String> for FewShotChatTemplate {
     }
 }
 
+impl TryFrom<&FewShotChatTemplate> for FewShotChatTemplateConfig {
+    type Error = TemplateError;
+
+    fn try_from(template: &FewShotChatTemplate) -> Result<Self, Self::Error> {
+        // The config copies template text directly instead of serializing the
+        // templates, so sensitive ones are caught here.
+        let mut templates = template
+            .prefix()
+            .into_iter()
+            .chain(template.suffix())
+            .chain(template.examples());
+        if template.example_prompt().is_sensitive() || templates.any(Sensitive::is_sensitive) {
+            return Err(sensitive_error());
+        }
+        let messages = |chat_template: &ChatTemplate| {
+            chat_template
+                .messages
+                .iter()
+                .map(MessageConfig::try_from)
+                .collect::<Result<Vec<_>, TemplateError>>()
+        };
+        // The config always carries a prefix and suffix; an empty one renders
+        // nothing and leaves the separators alone.
+        let part = |part: Option<&Template>| match part {
+            Some(part) => TemplateConfig::from(part),
+            None => TemplateConfig {
+                template: String::new(),
+                template_format: TemplateFormat::PlainText.as_str().to_string(),
+                input_variables: Vec::new(),
+                metadata: None,
+                transforms: BTreeMap::new(),
+            },
+        };
+        let separator = |separator: &str| {
+            (separator != template.example_separator()).then(|| separator.to_string())
+        };
+
+        Ok(FewShotChatTemplateConfig {
+            example_separator: template.example_separator().to_string(),
+            prefix_separator: separator(template.examples.prefix_separator()),
+            suffix_separator: separator(template.examples.suffix_separator()),
+            prefix: part(template.prefix()),
+            suffix: part(template.suffix()),
+            examples: template
+                .examples()
+                .iter()
+                .map(TemplateConfig::from)
+                .collect(),
+            messages: messages(&template.example_prompt)?,
+            negative_messages: match template.negative_example_prompt() {
+                Some(negative_example_prompt) => messages(negative_example_prompt)?,
+                None => Vec::new(),
+            },
+            negative_example_policy: template.negative_example_policy,
+            multi_turn_examples: template.multi_turn_examples.clone(),
+            example_variable_mapping: template
+                .example_variable_mapping
+                .iter()
+                .map(|(variable, role)| (variable.clone(), role.as_str().to_string()))
+                .collect(),
+        })
+    }
+}
+
 impl TryFrom<FewShotChatTemplateConfig> for FewShotChatTemplate {
     type Error = TemplateError;
 
@@ -142,12 +621,14 @@ impl TryFrom<FewShotChatTemplateConfig> for FewShotChatTemplate {
         let prefix = Some(config.prefix.try_into().map_err(|_| {
             TemplateError::MalformedTemplate(
                 "Failed to parse 'prefix' in FewShotChatTemplateConfig.".to_string(),
+                None,
             )
         })?);
 
         let suffix = Some(config.suffix.try_into().map_err(|_| {
             TemplateError::MalformedTemplate(
                 "Failed to parse 'suffix' in FewShotChatTemplateConfig.".to_string(),
+                None,
             )
         })?);
 
@@ -158,21 +639,57 @@ impl TryFrom<FewShotChatTemplateConfig> for FewShotChatTemplate {
                 example.try_into().map_err(|_| {
                     TemplateError::MalformedTemplate(
                         "Failed to parse an example in FewShotChatTemplateConfig.".to_string(),
+                        None,
                     )
                 })
             })
             .collect::<Result<Vec<Template>, Self::Error>>()?;
 
-        let few_shot_template =
+        let mut few_shot_template =
             FewShotTemplate::with_options(examples, prefix, suffix, config.example_separator);
+        if let Some(prefix_separator) = config.prefix_separator {
+            few_shot_template = few_shot_template.with_prefix_separator(prefix_separator);
+        }
+        if let Some(suffix_separator) = config.suffix_separator {
+            few_shot_template = few_shot_template.with_suffix_separator(suffix_separator);
+        }
 
         let example_prompt = ChatTemplate::try_from(config.messages).map_err(|_| {
             TemplateError::MalformedTemplate(
                 "Failed to parse 'messages' in FewShotChatTemplateConfig.".to_string(),
+                None,
             )
         })?;
 
-        Ok(FewShotChatTemplate::new(few_shot_template, example_prompt))
+        let example_variable_mapping = config
+            .example_variable_mapping
+            .into_iter()
+            .map(|(var, role)| {
+                let role =
+                    Role::try_from(role.as_str()).map_err(|_| TemplateError::InvalidRoleError)?;
+                role.ensure_message_role()?;
+                Ok((var, role))
+            })
+            .collect::<Result<BTreeMap<String, Role>, Self::Error>>()?;
+
+        let few_shot_chat_template = FewShotChatTemplate::new(few_shot_template, example_prompt)
+            .with_negative_example_policy(config.negative_example_policy)
+            .with_multi_turn_examples(config.multi_turn_examples)
+            .with_example_variable_mapping(example_variable_mapping);
+
+        if config.negative_messages.is_empty() {
+            return Ok(few_shot_chat_template);
+        }
+
+        let negative_example_prompt =
+            ChatTemplate::try_from(config.negative_messages).map_err(|_| {
+                TemplateError::MalformedTemplate(
+                    "Failed to parse 'negative_messages' in FewShotChatTemplateConfig.".to_string(),
+                    None,
+                )
+            })?;
+
+        Ok(few_shot_chat_template.with_negative_example_prompt(negative_example_prompt))
     }
 }
 
@@ -182,8 +699,8 @@ mod tests {
 
     use super::*;
     use crate::{
-        chats, examples, ChatTemplate, MessageLike,
-        Role::{Ai, Human},
+        chats, examples, ChatTemplate, ExampleMetadata, ExampleTurn, MessageLike,
+        Role::{Ai, Human, Placeholder, System},
     };
 
     #[test]
@@ -261,7 +778,7 @@ mod tests {
         let format_result = few_shot_chat_template.format_examples();
         assert!(matches!(
             format_result,
-            Err(TemplateError::MissingVariable(_))
+            Err(TemplateError::MissingVariables(names)) if names == ["input", "output"]
         ));
     }
 
@@ -292,7 +809,7 @@ mod tests {
         let result = FewShotChatTemplate::try_from(invalid_json_data.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             println!("{}", msg);
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -310,7 +827,7 @@ mod tests {
         let result = FewShotChatTemplate::try_from(missing_fields_json.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             assert!(msg.contains("Missing 'examples' field"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -324,7 +841,7 @@ mod tests {
         let result = FewShotChatTemplate::try_from(empty_json_data.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             assert!(msg.contains("Missing 'examples' field"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -341,7 +858,7 @@ mod tests {
         let result = ChatTemplate::try_from(invalid_json_data.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             assert!(msg.contains("Failed to parse JSON"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -359,7 +876,7 @@ mod tests {
         let result = ChatTemplate::try_from(json_data_missing_field.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             assert!(msg.contains("missing field"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -373,7 +890,7 @@ mod tests {
         let result = ChatTemplate::try_from(empty_json_data.to_string());
         assert!(result.is_err());
 
-        if let Err(TemplateError::MalformedTemplate(msg)) = result {
+        if let Err(TemplateError::MalformedTemplate(msg, _)) = result {
             assert!(msg.contains("missing field"));
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
@@ -421,6 +938,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_verify_consistency_flags_structural_drift() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let consistent = FewShotTemplate::with_options(
+            examples!(
+                ("{input}: Question: 2+2?", "{output}: Answer: 4"),
+                ("{input}: Question: 2+3?", "{output}: Answer: 5"),
+            ),
+            None,
+            Some(Template::new("human: Question: {question}").unwrap()),
+            "\n\n",
+        );
+        let report = FewShotChatTemplate::new(consistent, example_prompt.clone())
+            .verify_consistency()
+            .unwrap();
+        assert!(report.is_consistent());
+        assert_eq!(report.examples, 2);
+
+        let drifting = FewShotTemplate::with_options(
+            examples!(
+                ("{input}: Question: 2+2?", "{output}: Answer: 4"),
+                ("{input}: Q: 2+3?", "{output}: Answer: 5"),
+                ("{input}: Question: 3+3?", "{input}: Question: again?"),
+            ),
+            None,
+            Some(Template::new("human: Query: {question}").unwrap()),
+            "\n\n",
+        );
+        let report = FewShotChatTemplate::new(drifting, example_prompt)
+            .verify_consistency()
+            .unwrap();
+        assert_eq!(
+            report.issues,
+            vec![
+                ConsistencyIssue::FieldLabels {
+                    example: 1,
+                    message: 0,
+                    expected: vec!["Question".to_string()],
+                    found: vec!["Q".to_string()],
+                },
+                ConsistencyIssue::RoleSequence {
+                    example: 2,
+                    expected: vec!["human".to_string(), "ai".to_string()],
+                    found: vec!["human".to_string(), "human".to_string()],
+                },
+                ConsistencyIssue::FinalTurnLabels {
+                    expected: vec!["Question".to_string()],
+                    found: vec!["Query".to_string()],
+                },
+            ]
+        );
+    }
+
     #[test]
     fn test_format_few_shot_chat_template() {
         let examples = examples!(
@@ -465,4 +1036,295 @@ ai: 5
             panic!("Expected an Ai message as the second message");
         }
     }
+
+    fn negative_examples_fixture() -> FewShotTemplate<Template> {
+        let wrong = Template::new("{input}: What is 3 * 3?\n{output}: 6")
+            .unwrap()
+            .with_metadata(ExampleMetadata::new().with_negative("It adds instead of multiplying."));
+
+        FewShotTemplate::new(vec![
+            Template::new("{input}: What is 2 * 2?\n{output}: 4").unwrap(),
+            wrong,
+            Template::new("{input}: What is 2 * 3?\n{output}: 6").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn test_negative_examples_use_negative_prompt() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let negative_example_prompt = ChatTemplate::from_messages(chats!(
+            Human = "{input}",
+            Ai = "Incorrect: {output} Why it's wrong: {rationale}",
+        ))
+        .unwrap();
+
+        let few_shot_chat_template =
+            FewShotChatTemplate::new(negative_examples_fixture(), example_prompt)
+                .with_negative_example_prompt(negative_example_prompt);
+
+        let formatted = few_shot_chat_template.format_examples().unwrap();
+        assert_eq!(
+            formatted,
+            "human: What is 2 * 2?\nai: 4\n\n\
+             human: What is 3 * 3?\nai: Incorrect: 6 Why it's wrong: It adds instead of multiplying.\n\n\
+             human: What is 2 * 3?\nai: 6\n\n"
+        );
+
+        let messages = MessageEnum::parse_messages(&formatted).unwrap();
+        assert_eq!(messages.len(), 6);
+
+        let multi_line = Template::new("{input}: Sort: b, a\nKeep case.\n{output}: Sorted:\nB, A")
+            .unwrap()
+            .with_metadata(ExampleMetadata::new().with_negative("Changes the case."));
+        let few_shot_chat_template = few_shot_chat_template.clone().extend_examples([multi_line]);
+        let formatted = few_shot_chat_template.format_examples().unwrap();
+        assert!(
+            formatted.ends_with(
+                "human: Sort: b, a\nKeep case.\n\
+                 ai: Incorrect: Sorted:\nB, A Why it's wrong: Changes the case.\n\n"
+            ),
+            "{}",
+            formatted
+        );
+    }
+
+    #[test]
+    fn test_negative_examples_need_a_negative_prompt() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let few_shot_chat_template =
+            FewShotChatTemplate::new(negative_examples_fixture(), example_prompt)
+                .with_multi_turn_examples(vec![MultiTurnExample::new(vec![
+                    ExampleTurn::human("What is 4 * 4?"),
+                    ExampleTurn::ai("8"),
+                ])
+                .with_metadata(ExampleMetadata::new().with_negative("Halves it."))]);
+
+        assert_eq!(
+            few_shot_chat_template.format_examples().unwrap(),
+            "human: What is 2 * 2?\nai: 4\n\nhuman: What is 2 * 3?\nai: 6\n\n"
+        );
+    }
+
+    #[test]
+    fn test_negative_example_policies() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let negative_example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "Incorrect: {output}",))
+                .unwrap();
+        let few_shot_chat_template =
+            FewShotChatTemplate::new(negative_examples_fixture(), example_prompt)
+                .with_negative_example_prompt(negative_example_prompt);
+
+        let after = few_shot_chat_template
+            .clone()
+            .with_negative_example_policy(NegativeExamplePolicy::AfterPositives)
+            .format_examples()
+            .unwrap();
+        assert!(after.ends_with("human: What is 3 * 3?\nai: Incorrect: 6\n\n"));
+
+        let before = few_shot_chat_template
+            .clone()
+            .with_negative_example_policy(NegativeExamplePolicy::BeforePositives)
+            .format_examples()
+            .unwrap();
+        assert!(before.starts_with("human: What is 3 * 3?\nai: Incorrect: 6\n\n"));
+
+        let omitted = few_shot_chat_template
+            .with_negative_example_policy(NegativeExamplePolicy::Omit)
+            .format_examples()
+            .unwrap();
+        assert_eq!(
+            omitted,
+            "human: What is 2 * 2?\nai: 4\n\nhuman: What is 2 * 3?\nai: 6\n\n"
+        );
+    }
+
+    #[test]
+    fn test_negative_example_prompt_survives_serialization() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let negative_example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "Incorrect: {output}"))
+                .unwrap();
+        let few_shot_chat_template =
+            FewShotChatTemplate::new(negative_examples_fixture(), example_prompt)
+                .with_negative_example_prompt(negative_example_prompt)
+                .with_negative_example_policy(NegativeExamplePolicy::AfterPositives);
+
+        let deserialized =
+            FewShotChatTemplate::try_from(few_shot_chat_template.to_string()).unwrap();
+        assert!(deserialized.negative_example_prompt().is_some());
+        assert_eq!(
+            deserialized.negative_example_policy(),
+            NegativeExamplePolicy::AfterPositives
+        );
+        assert_eq!(
+            deserialized.format_examples().unwrap(),
+            few_shot_chat_template.format_examples().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_multi_turn_examples_in_chat_template() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let few_shot = FewShotChatTemplate::new(
+            FewShotTemplate::new(examples!(("{input}: What is 2+2?", "{output}: 4"))),
+            example_prompt,
+        )
+        .with_multi_turn_examples(vec![MultiTurnExample::new(vec![
+            crate::ExampleTurn::human("Convert 3 km to miles."),
+            crate::ExampleTurn::tool_call("convert(3, km, mi)", "call_7"),
+            crate::ExampleTurn::tool_result("1.86", "call_7"),
+            crate::ExampleTurn::ai("3 km is about 1.86 miles."),
+        ])]);
+
+        assert_eq!(
+            few_shot.format_examples().unwrap(),
+            "human: What is 2+2?\nai: 4\n\nhuman: Convert 3 km to miles.\nai: convert(3, km, mi)\n1.86\nai: 3 km is about 1.86 miles.\n\n"
+        );
+
+        let chat_template = ChatTemplate {
+            messages: vec![MessageLike::few_shot_prompt(few_shot.clone())],
+            prefill: None,
+            sensitive: false,
+        };
+        let messages = chat_template.format_messages(&HashMap::new()).unwrap();
+        assert_eq!(messages.len(), 6);
+        assert!(
+            matches!(messages[4].as_ref(), MessageEnum::Tool(tool) if tool.tool_call_id() == "call_7")
+        );
+
+        let json = serde_json::to_string(&few_shot).unwrap();
+        let deserialized: FewShotChatTemplate = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized.multi_turn_examples().len(), 1);
+        assert_eq!(deserialized.metrics().few_shot_example_count, 2);
+    }
+
+    #[test]
+    fn test_example_variable_mapping_overrides_inference() {
+        let example_prompt = ChatTemplate::from_messages(chats!(
+            System = "Continue from {prompt}.",
+            Human = "{prompt}",
+            Ai = "{reply}",
+        ))
+        .unwrap();
+        let few_shot = FewShotChatTemplate::new(
+            FewShotTemplate::new(examples!(("{prompt}: Hi", "{reply}: Hello!"))),
+            example_prompt,
+        );
+        assert_eq!(few_shot.example_variable_mapping()["prompt"], System);
+
+        let few_shot = few_shot.with_example_variable("prompt", Human);
+        assert_eq!(
+            few_shot.example_variable_mapping(),
+            BTreeMap::from([("prompt".to_string(), Human), ("reply".to_string(), Ai)])
+        );
+        assert_eq!(
+            few_shot.format_examples().unwrap(),
+            "human: Hi\nai: Hello!\n\n"
+        );
+
+        let restored = FewShotChatTemplate::try_from(few_shot.to_string()).unwrap();
+        assert_eq!(
+            restored.example_variable_mapping(),
+            few_shot.example_variable_mapping()
+        );
+    }
+
+    #[test]
+    fn test_multi_turn_examples_from_config() {
+        let toml_str = r#"
+        example_separator = "\n"
+        examples = []
+
+        [prefix]
+        template = "system: Here are some examples."
+        template_format = "PlainText"
+        input_variables = []
+
+        [suffix]
+        template = "system: Now it is your turn."
+        template_format = "PlainText"
+        input_variables = []
+
+        [[messages]]
+        type = "RolePromptTemplate"
+        [messages.value]
+        role = "human"
+        content = "User: {input}"
+
+        [[messages]]
+        type = "RolePromptTemplate"
+        [messages.value]
+        role = "ai"
+        content = "Bot: {output}"
+
+        [example_variable_mapping]
+        output = "assistant"
+
+        [[multi_turn_examples]]
+        turns = [
+            { role = "Human", content = "Hi" },
+            { role = "Ai", content = "Hello!" },
+            { role = "Human", content = "Bye" },
+            { role = "Ai", content = "Goodbye!" },
+        ]
+        "#;
+
+        let config: FewShotChatTemplateConfig = toml::from_str(toml_str).unwrap();
+        let few_shot = FewShotChatTemplate::try_from(config).unwrap();
+        assert_eq!(few_shot.example_variable_mapping()["output"], Ai);
+        let messages = few_shot.format_messages().unwrap();
+
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(
+            contents,
+            [
+                "Here are some examples.",
+                "User: Hi",
+                "Bot: Hello!",
+                "User: Bye",
+                "Bot: Goodbye!",
+                "Now it is your turn."
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_save_toml_file_round_trips() {
+        let examples = FewShotTemplate::with_options(
+            examples!(
+                ("{input}: 2+2", "{output}: 4"),
+                ("{input}: 3+3", "{output}: 6")
+            ),
+            Some(Template::new("Solve these.").unwrap()),
+            None,
+            "\n",
+        )
+        .with_prefix_separator("\n\n");
+        let few_shot = FewShotChatTemplate::new(
+            examples,
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap(),
+        )
+        .with_example_variable("output", Ai);
+
+        let path = std::env::temp_dir().join("promptforge_few_shot_chat_save.toml");
+        few_shot.save_toml_file(&path).await.unwrap();
+        let loaded = FewShotChatTemplate::from_toml_file(&path).await.unwrap();
+        assert_eq!(
+            loaded.format_examples().unwrap(),
+            few_shot.format_examples().unwrap()
+        );
+        assert_eq!(loaded.example_variable_mapping()["output"], Ai);
+
+        let placeholder = FewShotChatTemplate::new(
+            FewShotTemplate::new(vec![]),
+            ChatTemplate::from_messages(chats!(Placeholder = "{history}")).unwrap(),
+        );
+        assert!(placeholder.to_toml_string().is_err());
+    }
 }
@@ -1,35 +1,112 @@
-use crate::{extract_variables, Template, TemplateError, TemplateFormat};
-use serde::Deserialize;
-
-#[derive(Debug, Deserialize)]
+use crate::{
+    extract_variables, ExampleMetadata, MessageLike, MultiTurnExample, NegativeExamplePolicy, Role,
+    Templatable, Template, TemplateError, TemplateFormat,
+};
+use messageforge::BaseMessage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FewShotChatTemplateConfig {
     pub example_separator: String,
+    // Both fall back to `example_separator` when left out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_separator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suffix_separator: Option<String>,
     pub prefix: TemplateConfig,
     pub suffix: TemplateConfig,
+    #[serde(default)]
     pub examples: Vec<TemplateConfig>,
     pub messages: Vec<MessageConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub negative_messages: Vec<MessageConfig>,
+    #[serde(default, skip_serializing_if = "NegativeExamplePolicy::is_default")]
+    pub negative_example_policy: NegativeExamplePolicy,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub multi_turn_examples: Vec<MultiTurnExample>,
+    // Variable name to role name, e.g. `input = "human"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub example_variable_mapping: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TemplateConfig {
     pub template: String,
     pub template_format: String,
     pub input_variables: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<ExampleMetadata>,
+    // Variable name to filter chain, e.g. `due = "format_date(%B %-d, UTC)"`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub transforms: BTreeMap<String, String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MessageConfig {
     #[serde(rename = "type")]
     pub message_type: String,
     pub value: MessageValue,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MessageValue {
     pub role: String,
     pub content: String,
 }
 
+impl MessageConfig {
+    pub fn new(role: &str, content: impl Into<String>) -> Self {
+        MessageConfig {
+            message_type: "BaseMessage".to_string(),
+            value: MessageValue {
+                role: role.to_string(),
+                content: content.into(),
+            },
+        }
+    }
+}
+
+impl From<&Template> for TemplateConfig {
+    fn from(template: &Template) -> Self {
+        TemplateConfig {
+            template: template.template().to_string(),
+            template_format: template.template_format().as_str().to_string(),
+            input_variables: template.input_variables(),
+            metadata: template.metadata().cloned(),
+            transforms: template
+                .transforms()
+                .iter()
+                .map(|(variable, chain)| (variable.clone(), chain.clone()))
+                .collect(),
+        }
+    }
+}
+
+// Only fixed messages and role templates have a config form; anything else
+// would be silently dropped on the way back in.
+impl TryFrom<&MessageLike> for MessageConfig {
+    type Error = TemplateError;
+
+    fn try_from(message_like: &MessageLike) -> Result<Self, Self::Error> {
+        match message_like.inner() {
+            MessageLike::BaseMessage(message) => Ok(MessageConfig::new(
+                Role::of(message).as_str(),
+                message.content(),
+            )),
+            MessageLike::RolePromptTemplate(role, template) => Ok(MessageConfig {
+                message_type: "RolePromptTemplate".to_string(),
+                ..MessageConfig::new(role.as_str(), template.template())
+            }),
+            _ => Err(TemplateError::MalformedTemplate(
+                "Only fixed messages and role templates can be written to a few-shot config"
+                    .to_string(),
+                None,
+            )),
+        }
+    }
+}
+
 impl TryInto<Template> for TemplateConfig {
     type Error = TemplateError;
 
@@ -43,13 +120,22 @@ impl TryInto<Template> for TemplateConfig {
                 .collect::<Vec<String>>(),
         );
 
-        Template::new_with_config(&self.template, Some(template_format), input_variables)
+        let mut template =
+            Template::new_with_config(&self.template, Some(template_format), input_variables)?;
+        for (variable, chain) in &self.transforms {
+            template = template.with_transform(variable, chain)?;
+        }
+
+        Ok(match self.metadata {
+            Some(metadata) => template.with_metadata(metadata),
+            None => template,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{Templatable, TemplateFormat};
+    use crate::{Formattable, Templatable, TemplateFormat};
 
     use super::*;
     use std::convert::TryInto;
@@ -163,6 +249,8 @@ mod tests {
             template: "{name} is learning Rust!".to_string(),
             template_format: "FmtString".to_string(),
             input_variables: vec!["name".to_string()],
+            metadata: None,
+            transforms: BTreeMap::new(),
         };
 
         let template: Result<Template, TemplateError> = config.try_into();
@@ -181,6 +269,8 @@ mod tests {
             template: "Hello, {{name}}!".to_string(),
             template_format: "Mustache".to_string(),
             input_variables: vec!["name".to_string()],
+            metadata: None,
+            transforms: BTreeMap::new(),
         };
 
         let template: Result<Template, TemplateError> = config.try_into();
@@ -199,6 +289,8 @@ mod tests {
             template: "This format is unsupported: <<<var>>>".to_string(),
             template_format: "UnknownFormat".to_string(),
             input_variables: vec!["var".to_string()],
+            metadata: None,
+            transforms: BTreeMap::new(),
         };
 
         let result: Result<Template, TemplateError> = config.try_into();
@@ -217,6 +309,8 @@ mod tests {
             template: "This is a test without variables.".to_string(),
             template_format: "PlainText".to_string(),
             input_variables: vec![],
+            metadata: None,
+            transforms: BTreeMap::new(),
         };
 
         let template: Result<Template, TemplateError> = config.try_into();
@@ -235,6 +329,8 @@ mod tests {
             template: "Hello, {user}!".to_string(),
             template_format: "FmtString".to_string(),
             input_variables: vec!["user".to_string()],
+            metadata: None,
+            transforms: BTreeMap::new(),
         };
 
         let template: Result<Template, TemplateError> = config.try_into();
@@ -246,4 +342,53 @@ mod tests {
         assert_eq!(template.template_format(), TemplateFormat::FmtString);
         assert_eq!(template.input_variables(), vec!["user".to_string()]);
     }
+
+    #[test]
+    fn test_try_into_template_with_metadata() {
+        let toml_str = r#"
+        template = "Q: {question}"
+        template_format = "FmtString"
+        input_variables = ["question"]
+
+        [metadata]
+        label = "math"
+        difficulty = "Hard"
+        source = "olympiad"
+        "#;
+
+        let config: TemplateConfig = toml::from_str(toml_str).unwrap();
+        let template: Template = config.try_into().unwrap();
+
+        let metadata = template.metadata().unwrap();
+        assert_eq!(metadata.label(), Some("math"));
+        assert_eq!(metadata.difficulty(), Some(crate::Difficulty::Hard));
+        assert_eq!(metadata.source(), Some("olympiad"));
+        assert_eq!(metadata.created_at(), None);
+    }
+
+    #[test]
+    fn test_try_into_template_with_transforms() {
+        let toml_str = r#"
+        template = "Due {due}, total {total}"
+        template_format = "FmtString"
+        input_variables = ["due", "total"]
+
+        [transforms]
+        due = 'format_date("%B %-d, %Y %H:%M", +02:00)'
+        total = "format_number(de-DE, 2)"
+        "#;
+
+        let config: TemplateConfig = toml::from_str(toml_str).unwrap();
+        let template: Template = config.try_into().unwrap();
+        let variables = crate::vars!(due = "2024-03-01T22:30:00Z", total = "1234.5");
+
+        assert_eq!(
+            template.format(&variables).unwrap(),
+            "Due March 2, 2024 00:30, total 1.234,50"
+        );
+        assert_eq!(
+            template.compile().unwrap().format(&variables).unwrap(),
+            template.format(&variables).unwrap()
+        );
+    }
 }
@@ -2,6 +2,7 @@ use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use tokio::fs;
 
+use crate::example_selector::ExampleSelector;
 use crate::template_format::TemplateError;
 use crate::{Formattable, Templatable, Template};
 use std::collections::HashMap;
@@ -11,6 +12,10 @@ use std::path::Path;
 pub struct FewShotTemplate<T: Templatable + Formattable> {
     examples: Vec<T>,
     example_separator: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prefix_separator: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    suffix_separator: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     prefix: Option<T>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -25,6 +30,8 @@ where
         Self {
             examples: Vec::new(),
             example_separator: Self::DEFAULT_EXAMPLE_SEPARATOR.to_string(),
+            prefix_separator: None,
+            suffix_separator: None,
             prefix: None,
             suffix: None,
         }
@@ -53,11 +60,28 @@ where
         FewShotTemplate {
             examples,
             example_separator: example_separator.into(),
+            prefix_separator: None,
+            suffix_separator: None,
             prefix,
             suffix,
         }
     }
 
+    pub fn with_prefix_separator(mut self, prefix_separator: impl Into<String>) -> Self {
+        self.prefix_separator = Some(prefix_separator.into());
+        self
+    }
+
+    pub fn with_suffix_separator(mut self, suffix_separator: impl Into<String>) -> Self {
+        self.suffix_separator = Some(suffix_separator.into());
+        self
+    }
+
+    pub fn extend_examples(mut self, examples: impl IntoIterator<Item = T>) -> Self {
+        self.examples.extend(examples);
+        self
+    }
+
     pub fn builder() -> FewShotTemplateBuilder<T> {
         FewShotTemplateBuilder::new()
     }
@@ -70,6 +94,18 @@ where
         &self.example_separator
     }
 
+    pub fn prefix_separator(&self) -> &str {
+        self.prefix_separator
+            .as_deref()
+            .unwrap_or(&self.example_separator)
+    }
+
+    pub fn suffix_separator(&self) -> &str {
+        self.suffix_separator
+            .as_deref()
+            .unwrap_or(&self.example_separator)
+    }
+
     pub fn prefix(&self) -> Option<&T> {
         self.prefix.as_ref()
     }
@@ -87,8 +123,53 @@ where
     }
 }
 
-impl Formattable for FewShotTemplate<Template> {
-    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+impl FewShotTemplate<Template> {
+    pub fn select_examples(&self, selector: &dyn ExampleSelector) -> Self {
+        FewShotTemplate {
+            examples: selector
+                .select(&self.examples)
+                .into_iter()
+                .cloned()
+                .collect(),
+            example_separator: self.example_separator.clone(),
+            prefix_separator: self.prefix_separator.clone(),
+            suffix_separator: self.suffix_separator.clone(),
+            prefix: self.prefix.clone(),
+            suffix: self.suffix.clone(),
+        }
+    }
+
+    pub(crate) fn format_with<F>(
+        &self,
+        variables: &HashMap<&str, &str>,
+        examples: &[&Template],
+        format_example: F,
+    ) -> Result<String, TemplateError>
+    where
+        F: Fn(&Template) -> Result<String, TemplateError>,
+    {
+        let (prefix_str, examples_str, suffix_str) =
+            self.format_parts(variables, examples, format_example)?;
+
+        let body = join_part(prefix_str, &examples_str, self.prefix_separator());
+        let separator = if examples_str.is_empty() {
+            self.prefix_separator()
+        } else {
+            self.suffix_separator()
+        };
+
+        Ok(join_part(body, &suffix_str, separator))
+    }
+
+    pub(crate) fn format_parts<F>(
+        &self,
+        variables: &HashMap<&str, &str>,
+        examples: &[&Template],
+        format_example: F,
+    ) -> Result<(String, String, String), TemplateError>
+    where
+        F: Fn(&Template) -> Result<String, TemplateError>,
+    {
         let prefix_str = if let Some(ref prefix_template) = self.prefix {
             prefix_template.format(variables)?
         } else {
@@ -97,8 +178,8 @@ impl Formattable for FewShotTemplate<Template> {
 
         let mut formatted_examples = Vec::new();
 
-        for example in &self.examples {
-            let formatted_example = example.format(variables)?;
+        for example in examples {
+            let formatted_example = format_example(example)?;
             formatted_examples.push(formatted_example);
         }
 
@@ -110,21 +191,32 @@ impl Formattable for FewShotTemplate<Template> {
             String::new()
         };
 
-        let mut result_parts = Vec::new();
+        Ok((prefix_str, examples_str, suffix_str))
+    }
+}
 
-        if !prefix_str.is_empty() {
-            result_parts.push(prefix_str);
-        }
-        if !examples_str.is_empty() {
-            result_parts.push(examples_str);
-        }
-        if !suffix_str.is_empty() {
-            result_parts.push(suffix_str);
-        }
+// Joins two rendered parts, skipping the separator when either side is empty
+// and emitting it only once when a part already ends or starts with it.
+fn join_part(mut left: String, right: &str, separator: &str) -> String {
+    if left.is_empty() {
+        return right.to_string();
+    }
+    if right.is_empty() {
+        return left;
+    }
 
-        let result = result_parts.join(&self.example_separator);
+    let right = right.strip_prefix(separator).unwrap_or(right);
+    if separator.is_empty() || !left.ends_with(separator) {
+        left.push_str(separator);
+    }
+    left.push_str(right);
+    left
+}
 
-        Ok(result)
+impl Formattable for FewShotTemplate<Template> {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let examples: Vec<&Template> = self.examples.iter().collect();
+        self.format_with(variables, &examples, |example| example.format(variables))
     }
 }
 
@@ -135,6 +227,8 @@ where
 {
     examples: Vec<T>,
     example_separator: String,
+    prefix_separator: Option<String>,
+    suffix_separator: Option<String>,
     prefix: Option<T>,
     suffix: Option<T>,
 }
@@ -148,6 +242,8 @@ where
             prefix: None,
             suffix: None,
             example_separator: FewShotTemplate::<T>::DEFAULT_EXAMPLE_SEPARATOR.to_string(),
+            prefix_separator: None,
+            suffix_separator: None,
             examples: Vec::new(),
         }
     }
@@ -176,6 +272,16 @@ where
         self
     }
 
+    pub fn prefix_separator(mut self, prefix_separator: impl Into<String>) -> Self {
+        self.prefix_separator = Some(prefix_separator.into());
+        self
+    }
+
+    pub fn suffix_separator(mut self, suffix_separator: impl Into<String>) -> Self {
+        self.suffix_separator = Some(suffix_separator.into());
+        self
+    }
+
     pub fn example(mut self, example: T) -> Self {
         self.examples.push(example);
         self
@@ -193,6 +299,8 @@ where
         FewShotTemplate {
             examples: self.examples,
             example_separator: self.example_separator,
+            prefix_separator: self.prefix_separator,
+            suffix_separator: self.suffix_separator,
             prefix: self.prefix,
             suffix: self.suffix,
         }
@@ -208,11 +316,11 @@ where
     fn try_from(value: String) -> Result<Self, Self::Error> {
         if value.trim().starts_with('{') {
             serde_json::from_str(&value).map_err(|e| {
-                TemplateError::MalformedTemplate(format!("JSON deserialization error: {}", e))
+                TemplateError::MalformedTemplate(format!("JSON deserialization error: {}", e), None)
             })
         } else {
             toml::from_str(&value).map_err(|e| {
-                TemplateError::MalformedTemplate(format!("TOML deserialization error: {}", e))
+                TemplateError::MalformedTemplate(format!("TOML deserialization error: {}", e), None)
             })
         }
     }
@@ -797,7 +905,7 @@ Question: Who was the father of Mary Ball Washington?
             FewShotTemplate::<Template>::try_from(invalid_json_data.to_string()).unwrap_err();
 
         match error {
-            TemplateError::MalformedTemplate(msg) => {
+            TemplateError::MalformedTemplate(msg, _) => {
                 println!("Error message: {}", msg);
             }
             _ => {
@@ -808,4 +916,100 @@ Question: Who was the father of Mary Ball Washington?
             }
         }
     }
+
+    #[test]
+    fn test_select_examples_by_metadata() {
+        use crate::{Difficulty, ExampleMetadata, MetadataFilterSelector};
+
+        let easy = Template::new("Q: 1+1\nA: 2").unwrap().with_metadata(
+            ExampleMetadata::new()
+                .with_label("math")
+                .with_difficulty(Difficulty::Easy),
+        );
+        let hard = Template::new("Q: 17*23\nA: 391").unwrap().with_metadata(
+            ExampleMetadata::new()
+                .with_label("math")
+                .with_difficulty(Difficulty::Hard),
+        );
+
+        let few_shot_template = FewShotTemplate::builder()
+            .prefix(Template::new("Solve the following:").unwrap())
+            .examples(vec![easy, hard])
+            .example_separator("\n---\n")
+            .build();
+
+        let selector = MetadataFilterSelector::new().difficulty(Difficulty::Hard);
+        let selected = few_shot_template.select_examples(&selector);
+
+        assert_eq!(selected.examples().len(), 1);
+        assert_eq!(
+            selected.format(&vars!()).unwrap(),
+            "Solve the following:\n---\nQ: 17*23\nA: 391"
+        );
+    }
+
+    #[test]
+    fn test_prefix_and_suffix_separators() {
+        let template = FewShotTemplate::builder()
+            .prefix(Template::new("Examples:").unwrap())
+            .suffix(Template::new("Now answer: {question}").unwrap())
+            .example_separator("\n---\n")
+            .prefix_separator("\n")
+            .suffix_separator("\n\n")
+            .examples(vec![
+                Template::new("Q: 1+1\nA: 2").unwrap(),
+                Template::new("Q: 2+2\nA: 4").unwrap(),
+            ])
+            .build();
+
+        assert_eq!(template.prefix_separator(), "\n");
+        assert_eq!(
+            template.format(&vars!(question = "3+3")).unwrap(),
+            "Examples:\nQ: 1+1\nA: 2\n---\nQ: 2+2\nA: 4\n\nNow answer: 3+3"
+        );
+
+        let no_examples = FewShotTemplate::with_options(
+            vec![],
+            Some(Template::new("Examples:").unwrap()),
+            Some(Template::new("Go.").unwrap()),
+            "\n\n",
+        )
+        .with_prefix_separator(" ");
+        assert_eq!(no_examples.suffix_separator(), "\n\n");
+        assert_eq!(no_examples.format(&vars!()).unwrap(), "Examples: Go.");
+    }
+
+    #[test]
+    fn test_separator_not_duplicated_at_part_edges() {
+        let template = FewShotTemplate::with_options(
+            vec![
+                Template::new("Q: 1+1\nA: 2").unwrap(),
+                Template::new("Q: 2+2\nA: 4\n\n").unwrap(),
+            ],
+            Some(Template::new("Examples:\n\n").unwrap()),
+            Some(Template::new("\n\nYour turn.").unwrap()),
+            "\n\n",
+        );
+
+        assert_eq!(
+            template.format(&vars!()).unwrap(),
+            "Examples:\n\nQ: 1+1\nA: 2\n\nQ: 2+2\nA: 4\n\nYour turn."
+        );
+    }
+
+    #[test]
+    fn test_separators_deserialize_and_fall_back() {
+        let template: FewShotTemplate<Template> = FewShotTemplate::try_from(
+            r#"{
+                "examples": [],
+                "example_separator": "\n---\n",
+                "suffix_separator": "\n"
+            }"#
+            .to_string(),
+        )
+        .unwrap();
+
+        assert_eq!(template.prefix_separator(), "\n---\n");
+        assert_eq!(template.suffix_separator(), "\n");
+    }
 }
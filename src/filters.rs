@@ -0,0 +1,300 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use lazy_static::lazy_static;
+
+use crate::{
+    text::{
+        datetime::{parse_offset, Timestamp},
+        length::{grapheme_len, truncate_graphemes},
+        number,
+    },
+    TemplateError,
+};
+
+pub type FilterFn = Arc<dyn Fn(&str, &[&str]) -> Result<String, TemplateError> + Send + Sync>;
+
+lazy_static! {
+    static ref BUILTIN_FILTERS: FilterRegistry = FilterRegistry::new();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterCall {
+    name: String,
+    args: Vec<String>,
+}
+
+impl FilterCall {
+    pub fn parse(call: &str) -> Result<Self, TemplateError> {
+        let call = call.trim();
+        let (name, args) = match call.split_once('(') {
+            Some((name, rest)) => {
+                let args = rest.strip_suffix(')').ok_or_else(|| {
+                    TemplateError::MalformedTemplate(
+                        format!("Unclosed filter arguments: {}", call),
+                        None,
+                    )
+                })?;
+                let args = split_args(args)
+                    .into_iter()
+                    .map(|arg| arg.trim().trim_matches('"').to_string())
+                    .filter(|arg| !arg.is_empty())
+                    .collect();
+                (name.trim(), args)
+            }
+            None => (call, Vec::new()),
+        };
+
+        if name.is_empty() {
+            return Err(TemplateError::MalformedTemplate(
+                "Empty filter name".to_string(),
+                None,
+            ));
+        }
+
+        Ok(FilterCall {
+            name: name.to_string(),
+            args,
+        })
+    }
+
+    pub fn parse_chain(chain: &str) -> Result<Vec<Self>, TemplateError> {
+        chain
+            .split('|')
+            .filter(|call| !call.trim().is_empty())
+            .map(Self::parse)
+            .collect()
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn args(&self) -> &[String] {
+        &self.args
+    }
+}
+
+// Commas inside double quotes belong to the argument, e.g. a date pattern.
+fn split_args(args: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (index, c) in args.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&args[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&args[start..]);
+    parts
+}
+
+#[derive(Clone, Default)]
+pub struct FilterRegistry {
+    filters: HashMap<String, FilterFn>,
+}
+
+impl fmt::Debug for FilterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.filters.keys().collect();
+        names.sort();
+        f.debug_struct("FilterRegistry")
+            .field("filters", &names)
+            .finish()
+    }
+}
+
+impl FilterRegistry {
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register("upper", |value, _| Ok(value.to_uppercase()));
+        registry.register("lower", |value, _| Ok(value.to_lowercase()));
+        registry.register("trim", |value, _| Ok(value.trim().to_string()));
+        registry.register("truncate", truncate);
+        registry.register("json_escape", json_escape);
+        registry.register("format_date", format_date);
+        registry.register("format_number", format_number);
+        registry
+    }
+
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn builtin() -> &'static FilterRegistry {
+        &BUILTIN_FILTERS
+    }
+
+    pub fn register<F>(&mut self, name: impl Into<String>, filter: F) -> &mut Self
+    where
+        F: Fn(&str, &[&str]) -> Result<String, TemplateError> + Send + Sync + 'static,
+    {
+        self.filters.insert(name.into(), Arc::new(filter));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    pub fn apply(&self, call: &FilterCall, value: &str) -> Result<String, TemplateError> {
+        let filter = self.filters.get(call.name()).ok_or_else(|| {
+            TemplateError::MalformedTemplate(format!("Unknown filter: {}", call.name()), None)
+        })?;
+        let args: Vec<&str> = call.args().iter().map(String::as_str).collect();
+        filter(value, &args)
+    }
+
+    pub fn apply_chain(&self, calls: &[FilterCall], value: &str) -> Result<String, TemplateError> {
+        calls
+            .iter()
+            .try_fold(value.to_string(), |value, call| self.apply(call, &value))
+    }
+}
+
+fn truncate(value: &str, args: &[&str]) -> Result<String, TemplateError> {
+    let length = args
+        .first()
+        .ok_or_else(|| {
+            TemplateError::MalformedTemplate("truncate needs a length argument".to_string(), None)
+        })?
+        .parse::<usize>()
+        .map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Invalid truncate length: {}", e), None)
+        })?;
+
+    if grapheme_len(value) <= length {
+        return Ok(value.to_string());
+    }
+
+    let mut truncated = truncate_graphemes(value, length).to_string();
+    if let Some(ellipsis) = args.get(1) {
+        truncated.push_str(ellipsis);
+    }
+    Ok(truncated)
+}
+
+fn json_escape(value: &str, _: &[&str]) -> Result<String, TemplateError> {
+    let quoted = serde_json::to_string(value).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("Failed to escape JSON: {}", e), None)
+    })?;
+    Ok(quoted[1..quoted.len() - 1].to_string())
+}
+
+// format_date(pattern[, tz]): without a zone the timestamp keeps its own offset.
+fn format_date(value: &str, args: &[&str]) -> Result<String, TemplateError> {
+    let pattern = args.first().ok_or_else(|| {
+        TemplateError::MalformedTemplate("format_date needs a pattern argument".to_string(), None)
+    })?;
+    let timestamp = Timestamp::parse(value)?;
+    let timestamp = match args.get(1) {
+        Some(tz) => timestamp.with_offset(parse_offset(tz).ok_or_else(|| {
+            TemplateError::MalformedTemplate(format!("Unsupported time zone: {}", tz), None)
+        })?),
+        None => timestamp,
+    };
+    timestamp.format(pattern)
+}
+
+// format_number(locale[, decimals])
+fn format_number(value: &str, args: &[&str]) -> Result<String, TemplateError> {
+    let locale = args.first().ok_or_else(|| {
+        TemplateError::MalformedTemplate("format_number needs a locale argument".to_string(), None)
+    })?;
+    let decimals = args
+        .get(1)
+        .map(|decimals| {
+            decimals.parse::<usize>().map_err(|e| {
+                TemplateError::MalformedTemplate(format!("Invalid decimal places: {}", e), None)
+            })
+        })
+        .transpose()?;
+    number::format_number(value, locale, decimals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(chain: &str, value: &str) -> Result<String, TemplateError> {
+        FilterRegistry::new().apply_chain(&FilterCall::parse_chain(chain)?, value)
+    }
+
+    #[test]
+    fn test_parse_filter_calls() {
+        assert_eq!(
+            FilterCall::parse_chain("trim|truncate(10, \"...\")|upper").unwrap(),
+            vec![
+                FilterCall {
+                    name: "trim".to_string(),
+                    args: vec![]
+                },
+                FilterCall {
+                    name: "truncate".to_string(),
+                    args: vec!["10".to_string(), "...".to_string()]
+                },
+                FilterCall {
+                    name: "upper".to_string(),
+                    args: vec![]
+                },
+            ]
+        );
+        assert!(FilterCall::parse("truncate(10").is_err());
+        assert!(FilterCall::parse(" ").is_err());
+    }
+
+    #[test]
+    fn test_builtin_filters() {
+        assert_eq!(apply("upper", "Ada").unwrap(), "ADA");
+        assert_eq!(apply("lower", "Ada").unwrap(), "ada");
+        assert_eq!(apply("trim", "  Ada \n").unwrap(), "Ada");
+        assert_eq!(apply("truncate(3)", "Lovelace").unwrap(), "Lov");
+        assert_eq!(apply("truncate(3, …)", "Lovelace").unwrap(), "Lov…");
+        assert_eq!(apply("truncate(20)", "Lovelace").unwrap(), "Lovelace");
+        assert_eq!(apply("truncate(2)", "héllo").unwrap(), "hé");
+        assert_eq!(apply("truncate(2)", "he\u{301}llo").unwrap(), "he\u{301}");
+        assert_eq!(apply("truncate(1, …)", "👍🏽👍🏽").unwrap(), "👍🏽…");
+        assert_eq!(
+            apply("json_escape", "say \"hi\"\nnow").unwrap(),
+            r#"say \"hi\"\nnow"#
+        );
+        assert_eq!(
+            apply(
+                "format_date(\"%b %-d, %Y\", -05:00)",
+                "2024-03-01T02:00:00Z"
+            )
+            .unwrap(),
+            "Feb 29, 2024"
+        );
+        assert_eq!(
+            apply("format_number(en-US)", "1234567").unwrap(),
+            "1,234,567"
+        );
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert!(matches!(
+            apply("shout", "Ada"),
+            Err(TemplateError::MalformedTemplate(msg, _)) if msg == "Unknown filter: shout"
+        ));
+        assert!(apply("truncate", "Ada").is_err());
+        assert!(apply("truncate(many)", "Ada").is_err());
+    }
+
+    #[test]
+    fn test_custom_filters() {
+        let mut registry = FilterRegistry::new();
+        registry.register("repeat", |value, args| {
+            let times = args.first().and_then(|n| n.parse().ok()).unwrap_or(2);
+            Ok(value.repeat(times))
+        });
+
+        let calls = FilterCall::parse_chain("repeat(3)|upper").unwrap();
+        assert_eq!(registry.apply_chain(&calls, "ab").unwrap(), "ABABAB");
+        assert!(!FilterRegistry::empty().contains("upper"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::{AiMessage, HumanMessage, MessageEnum, SystemMessage};
+
+use crate::Role::{Ai, Human, Placeholder, System};
+use crate::{chats, examples, vars, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Template};
+
+pub const GREETING_TEMPLATE: &str = "Hello, {name}! Your order {order_id} has shipped.";
+pub const GREETING_OUTPUT: &str = "Hello, Alice! Your order 12345 has shipped.";
+
+pub fn greeting_template() -> Template {
+    Template::new(GREETING_TEMPLATE).expect("Failed to create Template")
+}
+
+pub fn greeting_vars() -> HashMap<&'static str, &'static str> {
+    vars!(name = "Alice", order_id = "12345")
+}
+
+pub fn mustache_template() -> Template {
+    Template::new("Hello, {{name}}! Your favorite color is {{color}}.")
+        .expect("Failed to create Template")
+}
+
+pub fn mustache_vars() -> HashMap<&'static str, &'static str> {
+    vars!(name = "Bob", color = "blue")
+}
+
+pub fn support_chat() -> ChatTemplate {
+    ChatTemplate::from_messages(chats!(
+        System = "You are a support agent for {company}. Be concise.",
+        Placeholder = "{history}",
+        Human = "{question}",
+    ))
+    .expect("Failed to create ChatTemplate")
+}
+
+pub fn support_vars() -> HashMap<&'static str, &'static str> {
+    vars!(
+        company = "Acme",
+        history = HISTORY_JSON,
+        question = "Can I change my shipping address?",
+    )
+}
+
+pub const HISTORY_JSON: &str = r#"[{"role":"human","content":"Where is my order?"},{"role":"ai","content":"It shipped yesterday and arrives Friday."}]"#;
+
+pub fn history() -> Vec<Arc<MessageEnum>> {
+    vec![
+        Arc::new(MessageEnum::Human(HumanMessage::new("Where is my order?"))),
+        Arc::new(MessageEnum::Ai(AiMessage::new(
+            "It shipped yesterday and arrives Friday.",
+        ))),
+    ]
+}
+
+pub fn long_history(turns: usize) -> Vec<Arc<MessageEnum>> {
+    let mut messages = vec![Arc::new(MessageEnum::System(SystemMessage::new(
+        "You are a helpful assistant.",
+    )))];
+    for turn in 1..=turns {
+        messages.push(Arc::new(MessageEnum::Human(HumanMessage::new(&format!(
+            "Question {}: what is {} + {}?",
+            turn, turn, turn
+        )))));
+        messages.push(Arc::new(MessageEnum::Ai(AiMessage::new(&format!(
+            "Answer {}: {}",
+            turn,
+            turn * 2
+        )))));
+    }
+    messages
+}
+
+pub fn math_examples() -> Vec<Template> {
+    examples!(
+        ("{input}: What is 2+2?", "{output}: 4"),
+        ("{input}: What is 3+5?", "{output}: 8"),
+        ("{input}: What is 10-7?", "{output}: 3"),
+    )
+}
+
+pub fn math_few_shot() -> FewShotTemplate<Template> {
+    FewShotTemplate::builder()
+        .prefix(
+            Template::new("Answer the arithmetic question.").expect("Failed to create Template"),
+        )
+        .examples(examples!(
+            ("Q: What is 2+2?", "A: 4"),
+            ("Q: What is 3+5?", "A: 8"),
+            ("Q: What is 10-7?", "A: 3"),
+        ))
+        .suffix(Template::new("Q: {question}\nA:").expect("Failed to create Template"))
+        .build()
+}
+
+pub fn math_few_shot_chat() -> FewShotChatTemplate {
+    FewShotChatTemplate::new(
+        FewShotTemplate::new(math_examples()),
+        ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}"))
+            .expect("Failed to create ChatTemplate"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Formattable;
+    use messageforge::BaseMessage;
+
+    #[test]
+    fn test_template_fixtures_render() {
+        assert_eq!(
+            greeting_template().format(&greeting_vars()).unwrap(),
+            GREETING_OUTPUT
+        );
+        assert_eq!(
+            mustache_template().format(&mustache_vars()).unwrap(),
+            "Hello, Bob! Your favorite color is blue."
+        );
+        assert_eq!(
+            math_few_shot()
+                .format(&vars!(question = "What is 6+1?"))
+                .unwrap(),
+            "Answer the arithmetic question.\n\nQ: What is 2+2?\nA: 4\n\nQ: What is 3+5?\nA: 8\n\n\
+             Q: What is 10-7?\nA: 3\n\nQ: What is 6+1?\nA:"
+        );
+    }
+
+    #[test]
+    fn test_chat_fixtures_render() {
+        let messages = support_chat().format_messages(&support_vars()).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        let history = history();
+        let history_contents: Vec<&str> = history.iter().map(|m| m.content()).collect();
+
+        assert_eq!(messages.len(), 4);
+        assert_eq!(&contents[1..3], history_contents.as_slice());
+
+        assert_eq!(math_few_shot_chat().format_messages().unwrap().len(), 6);
+        assert_eq!(long_history(3).len(), 7);
+        assert_eq!(long_history(3)[6].content(), "Answer 3: 6");
+    }
+}
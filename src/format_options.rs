@@ -0,0 +1,318 @@
+use std::{
+    fmt, io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{metrics::TokenCounter, TemplateError};
+
+lazy_static! {
+    static ref UNFILLED_PLACEHOLDER_RE: Regex =
+        Regex::new(r"\{\{?\s*[a-zA-Z_][a-zA-Z0-9_]*(?::[^{}]*)?\s*\}\}?").unwrap();
+}
+
+pub type WarningHandler = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnfilledPlaceholderPolicy {
+    #[default]
+    Ignore,
+    Warn,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    #[default]
+    None,
+    Url,
+    JsonString,
+}
+
+impl OutputEncoding {
+    pub fn encode(&self, output: &str) -> String {
+        match self {
+            OutputEncoding::None => output.to_string(),
+            OutputEncoding::Url => percent_encode(output),
+            OutputEncoding::JsonString => {
+                let quoted = serde_json::Value::from(output).to_string();
+                quoted[1..quoted.len() - 1].to_string()
+            }
+        }
+    }
+}
+
+fn percent_encode(output: &str) -> String {
+    let mut encoded = String::with_capacity(output.len());
+    for byte in output.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderDeadline {
+    at: Instant,
+    timeout: Duration,
+}
+
+impl RenderDeadline {
+    pub fn after(timeout: Duration) -> Self {
+        RenderDeadline {
+            at: Instant::now() + timeout,
+            timeout,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Instant::now() >= self.at
+    }
+
+    pub fn check(&self) -> Result<(), TemplateError> {
+        if self.is_expired() {
+            return Err(self.error());
+        }
+        Ok(())
+    }
+
+    pub fn error(&self) -> TemplateError {
+        TemplateError::Timeout(format!(
+            "render exceeded its {}ms budget",
+            self.timeout.as_millis()
+        ))
+    }
+}
+
+// Handlebars and MiniJinja emit output piece by piece, so checking the clock
+// on every write aborts a runaway loop without a watchdog thread.
+pub(crate) struct DeadlineWriter {
+    pub(crate) output: Vec<u8>,
+    deadline: RenderDeadline,
+}
+
+impl DeadlineWriter {
+    pub(crate) fn new(deadline: RenderDeadline) -> Self {
+        DeadlineWriter {
+            output: Vec::new(),
+            deadline,
+        }
+    }
+
+    pub(crate) fn into_string(self) -> Result<String, TemplateError> {
+        String::from_utf8(self.output)
+            .map_err(|e| TemplateError::RuntimeError(format!("Rendered invalid UTF-8: {}", e)))
+    }
+}
+
+impl io::Write for DeadlineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.deadline.is_expired() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "render deadline"));
+        }
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FormatOptions {
+    unfilled_placeholders: UnfilledPlaceholderPolicy,
+    warning_handler: Option<WarningHandler>,
+    output_encoding: OutputEncoding,
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    render_timeout: Option<Duration>,
+}
+
+impl fmt::Debug for FormatOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FormatOptions")
+            .field("unfilled_placeholders", &self.unfilled_placeholders)
+            .field("output_encoding", &self.output_encoding)
+            .field("token_counter", &self.token_counter.is_some())
+            .field("render_timeout", &self.render_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FormatOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_unfilled_placeholders(mut self, policy: UnfilledPlaceholderPolicy) -> Self {
+        self.unfilled_placeholders = policy;
+        self
+    }
+
+    pub fn with_warning_handler(mut self, handler: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        self.warning_handler = Some(Arc::new(handler));
+        self
+    }
+
+    pub fn with_output_encoding(mut self, encoding: OutputEncoding) -> Self {
+        self.output_encoding = encoding;
+        self
+    }
+
+    pub fn with_token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.token_counter = Some(Arc::new(counter));
+        self
+    }
+
+    pub fn with_render_timeout(mut self, timeout: Duration) -> Self {
+        self.render_timeout = Some(timeout);
+        self
+    }
+
+    pub fn unfilled_placeholders(&self) -> UnfilledPlaceholderPolicy {
+        self.unfilled_placeholders
+    }
+
+    pub fn output_encoding(&self) -> OutputEncoding {
+        self.output_encoding
+    }
+
+    pub fn token_counter(&self) -> Option<&dyn TokenCounter> {
+        self.token_counter.as_deref()
+    }
+
+    pub fn render_timeout(&self) -> Option<Duration> {
+        self.render_timeout
+    }
+
+    pub fn deadline(&self) -> Option<RenderDeadline> {
+        self.render_timeout.map(RenderDeadline::after)
+    }
+
+    pub fn finish(&self, output: String) -> Result<String, TemplateError> {
+        self.audit(&output)?;
+        match self.output_encoding {
+            OutputEncoding::None => Ok(output),
+            encoding => Ok(encoding.encode(&output)),
+        }
+    }
+
+    // Warnings only go to the warning handler; the library never writes to
+    // stderr, so `Warn` without a handler checks nothing.
+    pub fn audit(&self, output: &str) -> Result<(), TemplateError> {
+        let handler = match (self.unfilled_placeholders, &self.warning_handler) {
+            (UnfilledPlaceholderPolicy::Ignore, _) | (UnfilledPlaceholderPolicy::Warn, None) => {
+                return Ok(())
+            }
+            (UnfilledPlaceholderPolicy::Warn, Some(handler)) => Some(handler),
+            (UnfilledPlaceholderPolicy::Error, _) => None,
+        };
+
+        let unfilled = find_unfilled_placeholders(output);
+        if unfilled.is_empty() {
+            return Ok(());
+        }
+
+        let message = format!("output still contains {}", unfilled.join(", "));
+        match handler {
+            Some(handler) => {
+                handler(&message);
+                Ok(())
+            }
+            None => Err(TemplateError::UnfilledPlaceholder(message)),
+        }
+    }
+}
+
+pub fn find_unfilled_placeholders(output: &str) -> Vec<&str> {
+    let mut found: Vec<&str> = Vec::new();
+
+    for placeholder in UNFILLED_PLACEHOLDER_RE.find_iter(output) {
+        if !found.contains(&placeholder.as_str()) {
+            found.push(placeholder.as_str());
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_find_unfilled_placeholders() {
+        assert_eq!(
+            find_unfilled_placeholders("Hi {name}, {{ topic }} and {name} again {mood:calm}"),
+            vec!["{name}", "{{ topic }}", "{mood:calm}"]
+        );
+        assert!(find_unfilled_placeholders(r#"{"answer": 42} and {} or {1st}"#).is_empty());
+    }
+
+    #[test]
+    fn test_audit_policies() {
+        let output = "Summarise {document} please.";
+
+        assert!(FormatOptions::new().audit(output).is_ok());
+
+        let result = FormatOptions::new()
+            .with_unfilled_placeholders(UnfilledPlaceholderPolicy::Error)
+            .audit(output);
+        assert!(matches!(
+            result,
+            Err(TemplateError::UnfilledPlaceholder(msg)) if msg.contains("{document}")
+        ));
+
+        let unhandled =
+            FormatOptions::new().with_unfilled_placeholders(UnfilledPlaceholderPolicy::Warn);
+        assert!(unhandled.audit(output).is_ok());
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&warnings);
+        let options = FormatOptions::new()
+            .with_unfilled_placeholders(UnfilledPlaceholderPolicy::Warn)
+            .with_warning_handler(move |msg| sink.lock().unwrap().push(msg.to_string()));
+
+        assert!(options.audit(output).is_ok());
+        assert!(options.audit("Nothing left over.").is_ok());
+        assert_eq!(
+            *warnings.lock().unwrap(),
+            vec!["output still contains {document}".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_output_encodings() {
+        let output = "Café & \"tea\"\n?";
+        assert_eq!(OutputEncoding::None.encode(output), output);
+        assert_eq!(
+            OutputEncoding::Url.encode(output),
+            "Caf%C3%A9%20%26%20%22tea%22%0A%3F"
+        );
+        assert_eq!(
+            OutputEncoding::JsonString.encode(output),
+            r#"Café & \"tea\"\n?"#
+        );
+    }
+
+    #[test]
+    fn test_finish_audits_before_encoding() {
+        let strict = FormatOptions::new()
+            .with_unfilled_placeholders(UnfilledPlaceholderPolicy::Error)
+            .with_output_encoding(OutputEncoding::Url);
+
+        assert_eq!(strict.finish("a b".to_string()).unwrap(), "a%20b");
+        assert!(strict.finish("Hi {name}".to_string()).is_err());
+    }
+}
@@ -1,8 +1,34 @@
+use crate::config::PromptForgeConfig;
+use crate::format_options::FormatOptions;
 use crate::template_format::{TemplateError, TemplateFormat};
 use std::collections::HashMap;
 
 pub trait Formattable {
     fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError>;
+
+    fn format_with_options(
+        &self,
+        variables: &HashMap<&str, &str>,
+        options: &FormatOptions,
+    ) -> Result<String, TemplateError> {
+        let deadline = options.deadline();
+        let output = self.format(variables)?;
+        if let Some(deadline) = deadline {
+            deadline.check()?;
+        }
+        options.finish(output)
+    }
+
+    fn format_with_config(
+        &self,
+        variables: &HashMap<&str, &str>,
+        config: Option<&PromptForgeConfig>,
+    ) -> Result<String, TemplateError> {
+        match config {
+            Some(config) => self.format_with_options(variables, config.format_options()),
+            None => self.format(variables),
+        }
+    }
 }
 
 pub trait Templatable: Formattable {
@@ -0,0 +1,467 @@
+use std::fmt;
+
+use messageforge::{BaseMessage, MessageEnum};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    ChatTemplate, FewShotChatTemplate, FewShotTemplate, MessageLike, NegativeExamplePolicy,
+    RegisteredPrompt, Templatable, Template, TemplateFormat, ToolCall, TruncationSide,
+};
+
+// Must be bumped by any change to the canonical encoding, including a new
+// field, so hashes from different schemes never compare equal.
+pub const TEMPLATE_HASH_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TemplateHash {
+    version: u32,
+    digest: [u8; 32],
+}
+
+impl TemplateHash {
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn digest(&self) -> &[u8; 32] {
+        &self.digest
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+}
+
+impl fmt::Display for TemplateHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "v{}:{}", self.version, self.to_hex())
+    }
+}
+
+pub trait CanonicalHash {
+    fn write_canonical(&self, out: &mut Vec<u8>);
+
+    fn canonical_bytes(&self) -> Vec<u8> {
+        let mut out = format!("promptforge:v{}\n", TEMPLATE_HASH_VERSION).into_bytes();
+        self.write_canonical(&mut out);
+        out
+    }
+
+    fn template_hash(&self) -> TemplateHash {
+        TemplateHash {
+            version: TEMPLATE_HASH_VERSION,
+            digest: Sha256::digest(self.canonical_bytes()).into(),
+        }
+    }
+}
+
+fn write_field(out: &mut Vec<u8>, value: &str) {
+    out.extend_from_slice(value.len().to_string().as_bytes());
+    out.push(b':');
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn write_optional<T: CanonicalHash>(out: &mut Vec<u8>, value: Option<&T>) {
+    match value {
+        Some(value) => {
+            write_field(out, "some");
+            value.write_canonical(out);
+        }
+        None => write_field(out, "none"),
+    }
+}
+
+fn format_id(format: TemplateFormat) -> &'static str {
+    match format {
+        TemplateFormat::PlainText => "plaintext",
+        TemplateFormat::FmtString => "fmtstring",
+        TemplateFormat::Mustache => "mustache",
+        TemplateFormat::Jinja2 => "jinja2",
+    }
+}
+
+fn message_role_id(message: &MessageEnum) -> &'static str {
+    match message {
+        MessageEnum::System(_) => "system",
+        MessageEnum::Human(_) => "human",
+        MessageEnum::Ai(_) => "ai",
+        MessageEnum::Tool(_) => "tool",
+    }
+}
+
+fn policy_id(policy: NegativeExamplePolicy) -> &'static str {
+    match policy {
+        NegativeExamplePolicy::Interleaved => "interleaved",
+        NegativeExamplePolicy::AfterPositives => "after_positives",
+        NegativeExamplePolicy::BeforePositives => "before_positives",
+        NegativeExamplePolicy::Omit => "omit",
+    }
+}
+
+fn truncation_id(truncation: TruncationSide) -> &'static str {
+    match truncation {
+        TruncationSide::Head => "head",
+        TruncationSide::Tail => "tail",
+    }
+}
+
+impl CanonicalHash for Template {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        let mut input_variables = self.input_variables();
+        input_variables.sort();
+        input_variables.dedup();
+
+        write_field(out, "template");
+        write_field(out, format_id(self.template_format()));
+        write_field(out, self.template());
+        write_field(out, &input_variables.len().to_string());
+        for variable in &input_variables {
+            write_field(out, variable);
+        }
+        if !self.transforms().is_empty() {
+            let mut transforms: Vec<_> = self.transforms().iter().collect();
+            transforms.sort();
+            write_field(out, "transforms");
+            for (variable, chain) in transforms {
+                write_field(out, variable);
+                write_field(out, chain);
+            }
+        }
+        if !self.partial_vars().is_empty() {
+            let mut partials: Vec<_> = self.partial_vars().iter().collect();
+            partials.sort();
+            write_field(out, "partials");
+            write_field(out, &partials.len().to_string());
+            for (variable, value) in partials {
+                write_field(out, variable);
+                write_field(out, value);
+            }
+        }
+    }
+}
+
+impl CanonicalHash for FewShotTemplate<Template> {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_field(out, "few_shot_template");
+        write_field(out, self.example_separator());
+        if self.prefix_separator() != self.example_separator()
+            || self.suffix_separator() != self.example_separator()
+        {
+            write_field(out, "separators");
+            write_field(out, self.prefix_separator());
+            write_field(out, self.suffix_separator());
+        }
+        write_optional(out, self.prefix());
+        write_field(out, &self.examples().len().to_string());
+        for example in self.examples() {
+            example.write_canonical(out);
+            match example.metadata().filter(|metadata| metadata.is_negative()) {
+                Some(metadata) => {
+                    write_field(out, "negative");
+                    write_field(out, metadata.rationale().unwrap_or_default());
+                }
+                None => write_field(out, "positive"),
+            }
+        }
+        write_optional(out, self.suffix());
+    }
+}
+
+impl CanonicalHash for FewShotChatTemplate {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_field(out, "few_shot_chat");
+        FewShotTemplate::with_options(
+            self.examples().to_vec(),
+            self.prefix().cloned(),
+            self.suffix().cloned(),
+            self.example_separator(),
+        )
+        .write_canonical(out);
+        self.example_prompt().write_canonical(out);
+        write_optional(out, self.negative_example_prompt());
+        write_field(out, policy_id(self.negative_example_policy()));
+        if !self.explicit_example_variables().is_empty() {
+            write_field(out, "variable_mapping");
+            for (variable, role) in self.explicit_example_variables() {
+                write_field(out, variable);
+                write_field(out, role.as_str());
+            }
+        }
+
+        if !self.multi_turn_examples().is_empty() {
+            write_field(out, "multi_turn");
+            write_field(out, &self.multi_turn_examples().len().to_string());
+            for example in self.multi_turn_examples() {
+                write_field(out, &example.turns().len().to_string());
+                for turn in example.turns() {
+                    write_field(out, turn.role.as_str());
+                    write_field(out, &turn.content);
+                    write_field(out, turn.tool_call_id.as_deref().unwrap_or_default());
+                    if !turn.tool_calls.is_empty() {
+                        write_field(out, &ToolCall::to_kwarg(&turn.tool_calls));
+                    }
+                }
+                write_optional(out, example.example_prompt());
+                write_field(out, &example.is_negative_example().to_string());
+            }
+        }
+    }
+}
+
+impl CanonicalHash for ChatTemplate {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        write_field(out, "chat");
+        write_field(out, &self.messages.len().to_string());
+
+        for message in &self.messages {
+            if let Some(metadata) = message.metadata() {
+                write_field(out, "metadata");
+                write_field(out, &serde_json::to_string(metadata).unwrap_or_default());
+            }
+            match message.inner() {
+                MessageLike::BaseMessage(base_message) => {
+                    write_field(out, "base");
+                    write_field(out, message_role_id(base_message));
+                    write_field(out, base_message.content());
+                }
+                MessageLike::RolePromptTemplate(role, template) => {
+                    write_field(out, "role");
+                    write_field(out, role.as_str());
+                    template.write_canonical(out);
+                }
+                MessageLike::Placeholder(placeholder) => {
+                    write_field(out, "placeholder");
+                    write_field(out, placeholder.variable_name());
+                    write_field(out, &placeholder.optional().to_string());
+                    write_field(out, &placeholder.n_messages().to_string());
+                    let limits = placeholder.limits();
+                    write_field(out, &limits.max_bytes.to_string());
+                    write_field(out, &limits.max_messages.to_string());
+                    write_field(out, truncation_id(placeholder.truncation()));
+                    match placeholder.max_tokens() {
+                        Some(max_tokens) => write_field(out, &max_tokens.to_string()),
+                        None => write_field(out, "none"),
+                    }
+                    let role_aliases = placeholder.role_aliases();
+                    write_field(out, &role_aliases.iter().count().to_string());
+                    for (alias, role) in role_aliases.iter() {
+                        write_field(out, alias);
+                        write_field(out, role.as_str());
+                    }
+                }
+                MessageLike::FewShotPrompt(few_shot_prompt) => {
+                    write_field(out, "few_shot");
+                    few_shot_prompt.write_canonical(out);
+                }
+                MessageLike::ToolCallTemplate(tool_call_template) => {
+                    write_field(out, "tool_call");
+                    write_field(
+                        out,
+                        &serde_json::to_string(tool_call_template).unwrap_or_default(),
+                    );
+                }
+                MessageLike::Multimodal(multimodal_template) => {
+                    write_field(out, "multimodal");
+                    write_field(
+                        out,
+                        &serde_json::to_string(multimodal_template).unwrap_or_default(),
+                    );
+                }
+                MessageLike::ForEach {
+                    variable,
+                    item_template,
+                    role,
+                } => {
+                    write_field(out, "for_each");
+                    write_field(out, variable);
+                    write_field(out, role.as_str());
+                    item_template.write_canonical(out);
+                }
+                MessageLike::SubTemplate(name, chat_template) => {
+                    write_field(out, "sub_template");
+                    write_field(out, name);
+                    chat_template.write_canonical(out);
+                }
+                MessageLike::Annotated(..) => {}
+            }
+        }
+        if let Some(prefill) = &self.prefill {
+            write_field(out, "prefill");
+            prefill.write_canonical(out);
+        }
+    }
+}
+
+impl CanonicalHash for RegisteredPrompt {
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        match self {
+            RegisteredPrompt::Template(template) => template.write_canonical(out),
+            RegisteredPrompt::Chat(chat_template) => chat_template.write_canonical(out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, Placeholder, System};
+    use crate::{
+        chats, examples, Difficulty, ExampleMetadata, MessagesPlaceholder, PlaceholderLimits, Role,
+        RoleAliases,
+    };
+
+    #[test]
+    fn test_template_canonical_bytes() {
+        let template = Template::new("Hello, {name}!").unwrap();
+        assert_eq!(
+            template.canonical_bytes(),
+            b"promptforge:v1\n8:template9:fmtstring14:Hello, {name}!1:14:name".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_template_hash_vectors() {
+        let vectors = [
+            (
+                "Hello, {name}!",
+                "v1:cd7c7f9f01d655dc64415e0a6b85f9e6e5e57dee33df6f2ab2c1050a5f639194",
+            ),
+            (
+                "Hello, {{name}}!",
+                "v1:b9a043576d1babd2cc9b1bd93f53253460045e57357f8d5250f839df08308861",
+            ),
+            (
+                "You are a helpful assistant.",
+                "v1:e5bf03dc09db84d5d4a9b906b3a1dee7ca11ea8a4bd5ec5700e8a217c5557afd",
+            ),
+        ];
+
+        for (source, expected) in vectors {
+            let hash = Template::new(source).unwrap().template_hash();
+            assert_eq!(hash.to_string(), expected, "hash of {:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_chat_template_hash_vector() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        assert_eq!(
+            chat_template.template_hash().to_string(),
+            "v1:75ed31ef65a55a9cb14d45121ec55e47fb985c05e8458f2dd219f0cc190e20a1"
+        );
+    }
+
+    #[test]
+    fn test_hash_ignores_non_content_changes() {
+        let template = Template::new("Q: {question}").unwrap();
+        let with_metadata = template
+            .clone()
+            .with_metadata(ExampleMetadata::new().with_difficulty(Difficulty::Hard));
+        assert_eq!(template.template_hash(), with_metadata.template_hash());
+
+        let reordered = Template::new_with_config(
+            "{a} and {b}",
+            None,
+            Some(vec!["b".to_string(), "a".to_string()]),
+        )
+        .unwrap();
+        assert_eq!(
+            reordered.template_hash(),
+            Template::new("{a} and {b}").unwrap().template_hash()
+        );
+    }
+
+    #[test]
+    fn test_hash_covers_partials() {
+        let template = Template::new("{greeting}, {name}!").unwrap();
+        let mut partial = template.clone();
+        partial.partial("greeting", "Hello");
+        let mut other = template.clone();
+        other.partial("greeting", "Howdy");
+
+        assert_ne!(template.template_hash(), partial.template_hash());
+        assert_ne!(partial.template_hash(), other.template_hash());
+        partial.clear_partials();
+        assert_eq!(template.template_hash(), partial.template_hash());
+    }
+
+    #[test]
+    fn test_hash_covers_placeholder_options() {
+        let chat = |placeholder: MessagesPlaceholder| {
+            ChatTemplate::from_message_likes(vec![MessageLike::placeholder(placeholder)])
+                .unwrap()
+                .template_hash()
+        };
+        let base = chat(MessagesPlaceholder::new("history".to_string()));
+
+        assert_ne!(
+            base,
+            chat(
+                MessagesPlaceholder::new("history".to_string())
+                    .with_limits(PlaceholderLimits::new(1024, 10))
+            )
+        );
+        assert_ne!(
+            base,
+            chat(MessagesPlaceholder::new("history".to_string()).with_max_tokens(500))
+        );
+        assert_ne!(
+            base,
+            chat(
+                MessagesPlaceholder::new("history".to_string())
+                    .with_truncation(TruncationSide::Head)
+            )
+        );
+        assert_ne!(
+            base,
+            chat(
+                MessagesPlaceholder::new("history".to_string())
+                    .with_role_aliases(RoleAliases::new().with_alias("bot", Role::Ai))
+            )
+        );
+    }
+
+    #[test]
+    fn test_hash_covers_prefill() {
+        let chat_template = ChatTemplate::from_messages(chats!(Human = "{question}")).unwrap();
+        let prefilled = chat_template
+            .clone()
+            .with_assistant_prefill(Template::new("Answer in {lang}:").unwrap());
+        let other = chat_template
+            .clone()
+            .with_assistant_prefill(Template::new("Answer briefly:").unwrap());
+
+        assert_ne!(chat_template.template_hash(), prefilled.template_hash());
+        assert_ne!(prefilled.template_hash(), other.template_hash());
+    }
+
+    #[test]
+    fn test_hash_changes_with_content() {
+        let first =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let second =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{answer}")).unwrap();
+        let swapped =
+            ChatTemplate::from_messages(chats!(Ai = "{output}", Human = "{input}")).unwrap();
+
+        assert_ne!(first.template_hash(), second.template_hash());
+        assert_ne!(first.template_hash(), swapped.template_hash());
+
+        let examples = examples!(("{input}: 2+2?", "{output}: 4"));
+        let few_shot =
+            FewShotChatTemplate::new(FewShotTemplate::new(examples.clone()), first.clone());
+        let same = FewShotChatTemplate::new(FewShotTemplate::new(examples), first);
+        let negative = few_shot
+            .clone()
+            .with_negative_example_policy(NegativeExamplePolicy::Omit);
+
+        assert_eq!(few_shot.template_hash(), same.template_hash());
+        assert_ne!(few_shot.template_hash(), negative.template_hash());
+    }
+}
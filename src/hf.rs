@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use minijinja::{syntax::SyntaxConfig, value::from_args, Environment, Error, ErrorKind, Value};
+use serde::Deserialize;
+
+use crate::{ChatTemplate, RoleMap, TemplateError};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HfChatTemplate {
+    template: String,
+    bos_token: String,
+    eos_token: String,
+    add_generation_prompt: bool,
+}
+
+// tokenizer_config.json stores special tokens either as plain strings or as
+// serialized `AddedToken` objects, and `chat_template` either as one string or
+// as a list of named templates.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SpecialToken {
+    Plain(String),
+    Added { content: String },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ChatTemplateField {
+    Single(String),
+    Named(Vec<NamedTemplate>),
+}
+
+#[derive(Deserialize)]
+struct NamedTemplate {
+    name: String,
+    template: String,
+}
+
+#[derive(Deserialize)]
+struct TokenizerConfig {
+    chat_template: Option<ChatTemplateField>,
+    bos_token: Option<SpecialToken>,
+    eos_token: Option<SpecialToken>,
+}
+
+impl SpecialToken {
+    fn into_content(self) -> String {
+        match self {
+            SpecialToken::Plain(content) | SpecialToken::Added { content } => content,
+        }
+    }
+}
+
+impl HfChatTemplate {
+    pub const DEFAULT_TEMPLATE_NAME: &'static str = "default";
+
+    pub fn new(template: impl Into<String>) -> Self {
+        HfChatTemplate {
+            template: template.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn from_tokenizer_config(json: &str) -> Result<Self, TemplateError> {
+        let config: TokenizerConfig = serde_json::from_str(json).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Invalid tokenizer_config.json: {}", e), None)
+        })?;
+
+        let template = match config.chat_template {
+            Some(ChatTemplateField::Single(template)) => template,
+            Some(ChatTemplateField::Named(templates)) => templates
+                .into_iter()
+                .find(|named| named.name == Self::DEFAULT_TEMPLATE_NAME)
+                .map(|named| named.template)
+                .ok_or_else(|| {
+                    TemplateError::MalformedTemplate(
+                        "tokenizer_config.json has no default chat_template".to_string(),
+                        None,
+                    )
+                })?,
+            None => {
+                return Err(TemplateError::MalformedTemplate(
+                    "tokenizer_config.json has no chat_template".to_string(),
+                    None,
+                ))
+            }
+        };
+
+        Ok(HfChatTemplate {
+            template,
+            bos_token: config
+                .bos_token
+                .map(SpecialToken::into_content)
+                .unwrap_or_default(),
+            eos_token: config
+                .eos_token
+                .map(SpecialToken::into_content)
+                .unwrap_or_default(),
+            add_generation_prompt: false,
+        })
+    }
+
+    pub fn with_bos_token(mut self, bos_token: impl Into<String>) -> Self {
+        self.bos_token = bos_token.into();
+        self
+    }
+
+    pub fn with_eos_token(mut self, eos_token: impl Into<String>) -> Self {
+        self.eos_token = eos_token.into();
+        self
+    }
+
+    pub fn with_add_generation_prompt(mut self, add_generation_prompt: bool) -> Self {
+        self.add_generation_prompt = add_generation_prompt;
+        self
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    pub fn bos_token(&self) -> &str {
+        &self.bos_token
+    }
+
+    pub fn eos_token(&self) -> &str {
+        &self.eos_token
+    }
+
+    pub fn add_generation_prompt(&self) -> bool {
+        self.add_generation_prompt
+    }
+
+    pub fn render(
+        &self,
+        chat_template: &ChatTemplate,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        let messages: Vec<Value> = chat_template
+            .export(variables, &RoleMap::openai())?
+            .into_iter()
+            .map(|message| {
+                let mut fields = vec![
+                    ("role", Value::from(message.role)),
+                    ("content", Value::from(message.content)),
+                ];
+                if let Some(tool_call_id) = message.tool_call_id {
+                    fields.push(("tool_call_id", Value::from(tool_call_id)));
+                }
+                Value::from_pairs(fields)
+            })
+            .collect();
+
+        let context = Value::from_pairs([
+            ("messages", Value::from(messages)),
+            ("bos_token", Value::from(self.bos_token.as_str())),
+            ("eos_token", Value::from(self.eos_token.as_str())),
+            (
+                "add_generation_prompt",
+                Value::from(self.add_generation_prompt),
+            ),
+        ]);
+
+        environment()
+            .render_str(&self.template, context)
+            .map_err(|e| TemplateError::RuntimeError(e.to_string()))
+    }
+}
+
+// Matches the environment transformers renders chat templates in: trimmed
+// blocks, `raise_exception`, and the handful of Python string methods that
+// published templates rely on.
+fn environment() -> Environment<'static> {
+    let mut environment = Environment::new();
+    let syntax = SyntaxConfig::builder()
+        .trim_blocks(true)
+        .lstrip_blocks(true)
+        .build()
+        .expect("default delimiters are valid");
+    environment.set_syntax(syntax);
+    environment.add_function(
+        "raise_exception",
+        |message: String| -> Result<Value, Error> {
+            Err(Error::new(ErrorKind::InvalidOperation, message))
+        },
+    );
+    environment.set_unknown_method_callback(|_, value, method, args| {
+        let Some(text) = value.as_str() else {
+            return Err(Error::from(ErrorKind::UnknownMethod));
+        };
+        match method {
+            "strip" => from_args::<()>(args).map(|_| Value::from(text.trim())),
+            "lstrip" => from_args::<()>(args).map(|_| Value::from(text.trim_start())),
+            "rstrip" => from_args::<()>(args).map(|_| Value::from(text.trim_end())),
+            "startswith" => {
+                from_args::<(&str,)>(args).map(|(prefix,)| Value::from(text.starts_with(prefix)))
+            }
+            "endswith" => {
+                from_args::<(&str,)>(args).map(|(suffix,)| Value::from(text.ends_with(suffix)))
+            }
+            _ => Err(Error::from(ErrorKind::UnknownMethod)),
+        }
+    });
+    environment
+}
+
+impl ChatTemplate {
+    pub fn render_with_hf_template(
+        &self,
+        chat_template_jinja: &str,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        HfChatTemplate::new(chat_template_jinja).render(self, variables)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, vars};
+
+    const ZEPHYR: &str = "{% for message in messages %}\n\
+        {% if message['role'] == 'user' %}\n\
+        {{ '<|user|>\\n' + message['content'].strip() + eos_token }}\n\
+        {% elif message['role'] == 'system' %}\n\
+        {{ '<|system|>\\n' + message['content'] + eos_token }}\n\
+        {% elif message['role'] == 'assistant' %}\n\
+        {{ '<|assistant|>\\n' + message['content'] + eos_token }}\n\
+        {% endif %}\n\
+        {% if loop.last and add_generation_prompt %}\n\
+        {{ '<|assistant|>' }}\n\
+        {% endif %}\n\
+        {% endfor %}";
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "  {question} ",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_with_hf_template() {
+        let rendered = chat()
+            .render_with_hf_template(ZEPHYR, &vars!(persona = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(rendered, "<|system|>\nYou are terse.\n<|user|>\nWhy?\n");
+    }
+
+    #[test]
+    fn test_tokenizer_config_tokens_and_generation_prompt() {
+        let config = serde_json::json!({
+            "chat_template": [{"name": "default", "template": ZEPHYR}],
+            "bos_token": {"__type": "AddedToken", "content": "<s>"},
+            "eos_token": "</s>",
+        });
+        let hf = HfChatTemplate::from_tokenizer_config(&config.to_string())
+            .unwrap()
+            .with_add_generation_prompt(true);
+        assert_eq!(hf.bos_token(), "<s>");
+
+        let rendered = hf
+            .render(&chat(), &vars!(persona = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "<|system|>\nYou are terse.</s>\n<|user|>\nWhy?</s>\n<|assistant|>\n"
+        );
+        assert!(HfChatTemplate::from_tokenizer_config("{}").is_err());
+    }
+
+    #[test]
+    fn test_raise_exception_surfaces_as_runtime_error() {
+        let template = "{% if messages[0]['role'] != 'user' %}\
+            {{ raise_exception('Conversation must start with user') }}{% endif %}";
+        let chat_template =
+            ChatTemplate::from_messages(chats!(Ai = "Hello", Human = "Hi")).unwrap();
+
+        let error = chat_template
+            .render_with_hf_template(template, &vars!())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            TemplateError::RuntimeError(message) if message.contains("must start with user")
+        ));
+    }
+}
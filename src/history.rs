@@ -0,0 +1,158 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use messageforge::{tool_message::ToolStatus, AiMessage, HumanMessage, MessageEnum, ToolMessage};
+
+use crate::TemplateError;
+
+pub trait MessageSource: Send + Sync {
+    fn messages(&self) -> Vec<Arc<MessageEnum>>;
+}
+
+// Condenses history a placeholder had to cut; typically backed by an LLM call.
+pub trait Summarizer: Send + Sync {
+    fn summarize(&self, overflow: &[Arc<MessageEnum>]) -> Result<String, TemplateError>;
+}
+
+impl<F> Summarizer for F
+where
+    F: Fn(&[Arc<MessageEnum>]) -> Result<String, TemplateError> + Send + Sync,
+{
+    fn summarize(&self, overflow: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        self(overflow)
+    }
+}
+
+// Clones share one buffer, so a placeholder holding a clone sees every turn
+// the application pushes afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct ChatHistory {
+    messages: Arc<Mutex<VecDeque<Arc<MessageEnum>>>>,
+    window: Option<usize>,
+}
+
+impl ChatHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_window(mut self, window: usize) -> Self {
+        self.window = Some(window);
+        self.evict(&mut self.buffer());
+        self
+    }
+
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+
+    pub fn push(&self, message: MessageEnum) {
+        let mut buffer = self.buffer();
+        buffer.push_back(Arc::new(message));
+        self.evict(&mut buffer);
+    }
+
+    pub fn push_human(&self, content: &str) {
+        self.push(HumanMessage::new(content).into());
+    }
+
+    pub fn push_ai(&self, content: &str) {
+        self.push(AiMessage::new(content).into());
+    }
+
+    pub fn push_tool(&self, content: &str, tool_call_id: &str) {
+        self.push(MessageEnum::Tool(ToolMessage::new(
+            content,
+            tool_call_id.to_string(),
+            None,
+            ToolStatus::Success,
+        )));
+    }
+
+    pub fn clear(&self) {
+        self.buffer().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.buffer().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffer().is_empty()
+    }
+
+    fn evict(&self, buffer: &mut VecDeque<Arc<MessageEnum>>) {
+        if let Some(window) = self.window {
+            while buffer.len() > window {
+                buffer.pop_front();
+            }
+        }
+    }
+
+    fn buffer(&self) -> MutexGuard<'_, VecDeque<Arc<MessageEnum>>> {
+        self.messages
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl MessageSource for ChatHistory {
+    fn messages(&self) -> Vec<Arc<MessageEnum>> {
+        self.buffer().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::BaseMessage;
+
+    use super::*;
+    use crate::Role::{Human, Placeholder};
+    use crate::{chats, vars, ChatTemplate, MessageLike, MessagesPlaceholder};
+
+    #[test]
+    fn test_history_window_and_clear() {
+        let history = ChatHistory::new().with_window(2);
+        history.push_human("one");
+        history.push_ai("two");
+        history.push_tool("three", "call_1");
+
+        let messages = history.messages();
+        assert_eq!(history.len(), 2);
+        assert_eq!(messages[0].content(), "two");
+        assert!(matches!(messages[1].as_ref(), MessageEnum::Tool(_)));
+
+        history.clear();
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_placeholder_reads_from_history() {
+        let history = ChatHistory::new();
+        let mut chat = ChatTemplate::from_messages(chats!(Human = "{question}")).unwrap();
+        chat.messages.insert(
+            0,
+            MessageLike::placeholder(
+                MessagesPlaceholder::new("history".to_string()).with_source(history.clone()),
+            ),
+        );
+        assert_eq!(chat.input_variables(), vec!["question"]);
+
+        history.push_human("Hi");
+        history.push_ai("Hello!");
+        let messages = chat.format_messages(&vars!(question = "Why?")).unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(contents, vec!["Hi", "Hello!", "Why?"]);
+
+        let explicit = r#"[{"role": "ai", "content": "Override"}]"#;
+        let messages = chat
+            .format_messages(&vars!(history = explicit, question = "Why?"))
+            .unwrap();
+        assert_eq!(messages[0].content(), "Override");
+
+        let placeholder = ChatTemplate::from_messages(chats!(Placeholder = "{history}")).unwrap();
+        assert!(placeholder.format_messages(&vars!()).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use messageforge::{AiMessage, HumanMessage, MessageEnum, SystemMessage};
+use serde_json::{Map, Value};
+
+use crate::{
+    braces::escape_braces, vars::borrow_vars, ChatTemplate, FewShotTemplate, Formattable,
+    MessageLike, MessagesPlaceholder, Role, Template, TemplateError, TemplateFormat,
+};
+
+struct LcObject<'a> {
+    name: &'a str,
+    kwargs: &'a Map<String, Value>,
+}
+
+fn parse_json(json: &str) -> Result<Value, TemplateError> {
+    serde_json::from_str(json).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("Failed to parse LangChain JSON: {}", e), None)
+    })
+}
+
+fn lc_object(value: &Value) -> Result<LcObject<'_>, TemplateError> {
+    let name = value
+        .get("id")
+        .and_then(Value::as_array)
+        .and_then(|id| id.last())
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                "LangChain object is missing its 'id'".to_string(),
+                None,
+            )
+        })?;
+    let kwargs = value
+        .get("kwargs")
+        .and_then(Value::as_object)
+        .ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                format!("LangChain {} is missing 'kwargs'", name),
+                None,
+            )
+        })?;
+
+    Ok(LcObject { name, kwargs })
+}
+
+fn unsupported(name: &str) -> TemplateError {
+    TemplateError::UnsupportedFormat(format!("Unsupported LangChain object: {}", name))
+}
+
+fn str_field<'a>(object: &LcObject<'a>, field: &str) -> Result<&'a str, TemplateError> {
+    object
+        .kwargs
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                format!("LangChain {} is missing '{}'", object.name, field),
+                None,
+            )
+        })
+}
+
+fn role_for(label: &str) -> Result<Role, TemplateError> {
+    match label.to_lowercase().as_str() {
+        "system" => Ok(Role::System),
+        "human" | "user" => Ok(Role::Human),
+        "ai" | "assistant" => Ok(Role::Ai),
+        _ => Err(TemplateError::InvalidRoleError),
+    }
+}
+
+// Python f-strings escape literal braces by doubling them.
+fn convert_f_string(template: &str) -> String {
+    template
+        .replace("{{", &escape_braces("{"))
+        .replace("}}", &escape_braces("}"))
+}
+
+fn prompt_template(value: &Value) -> Result<Template, TemplateError> {
+    let object = lc_object(value)?;
+    if object.name != "PromptTemplate" {
+        return Err(unsupported(object.name));
+    }
+
+    let source = str_field(&object, "template")?;
+    let format = object
+        .kwargs
+        .get("template_format")
+        .and_then(Value::as_str)
+        .unwrap_or("f-string");
+    let mut template = match format {
+        "f-string" => Template::new(&convert_f_string(source))?,
+        "mustache" => Template::new_with_config(source, Some(TemplateFormat::Mustache), None)?,
+        "jinja2" => Template::new_with_config(source, Some(TemplateFormat::Jinja2), None)?,
+        other => {
+            return Err(TemplateError::UnsupportedFormat(format!(
+                "Unsupported LangChain template_format: {}",
+                other
+            )))
+        }
+    };
+
+    if let Some(partials) = object
+        .kwargs
+        .get("partial_variables")
+        .and_then(Value::as_object)
+    {
+        for (name, value) in partials {
+            if let Some(value) = value.as_str() {
+                template.partial(name, value);
+            }
+        }
+    }
+
+    Ok(template)
+}
+
+fn chat_message(value: &Value) -> Result<MessageLike, TemplateError> {
+    let object = lc_object(value)?;
+    let role = match object.name {
+        "SystemMessagePromptTemplate" => Some(Role::System),
+        "HumanMessagePromptTemplate" => Some(Role::Human),
+        "AIMessagePromptTemplate" => Some(Role::Ai),
+        "ChatMessagePromptTemplate" => Some(role_for(str_field(&object, "role")?)?),
+        _ => None,
+    };
+    if let Some(role) = role {
+        let prompt = object.kwargs.get("prompt").ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                format!("LangChain {} is missing 'prompt'", object.name),
+                None,
+            )
+        })?;
+        return Ok(MessageLike::role_prompt_template(
+            role,
+            prompt_template(prompt)?,
+        ));
+    }
+
+    match object.name {
+        "MessagesPlaceholder" => {
+            let optional = object
+                .kwargs
+                .get("optional")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+            let n_messages = object
+                .kwargs
+                .get("n_messages")
+                .and_then(Value::as_u64)
+                .map_or(MessagesPlaceholder::DEFAULT_LIMIT, |n| n as usize);
+            Ok(MessageLike::placeholder(MessagesPlaceholder::with_options(
+                str_field(&object, "variable_name")?.to_string(),
+                optional,
+                n_messages,
+            )))
+        }
+        "SystemMessage" => Ok(MessageLike::base_message(MessageEnum::System(
+            SystemMessage::new(str_field(&object, "content")?),
+        ))),
+        "HumanMessage" => Ok(MessageLike::base_message(MessageEnum::Human(
+            HumanMessage::new(str_field(&object, "content")?),
+        ))),
+        "AIMessage" => Ok(MessageLike::base_message(MessageEnum::Ai(AiMessage::new(
+            str_field(&object, "content")?,
+        )))),
+        other => Err(unsupported(other)),
+    }
+}
+
+fn few_shot_template(value: &Value) -> Result<FewShotTemplate<Template>, TemplateError> {
+    let object = lc_object(value)?;
+    if object.name != "FewShotPromptTemplate" {
+        return Err(unsupported(object.name));
+    }
+
+    let example_prompt =
+        prompt_template(object.kwargs.get("example_prompt").ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                "LangChain FewShotPromptTemplate is missing 'example_prompt'".to_string(),
+                None,
+            )
+        })?)?;
+    let examples = object
+        .kwargs
+        .get("examples")
+        .and_then(Value::as_array)
+        .ok_or_else(|| {
+            TemplateError::UnsupportedFormat(
+                "LangChain FewShotPromptTemplate without inline 'examples'".to_string(),
+            )
+        })?
+        .iter()
+        .map(|example| {
+            let variables: HashMap<String, String> = serde_json::from_value(example.clone())
+                .map_err(|e| {
+                    TemplateError::MalformedTemplate(
+                        format!("Invalid LangChain example: {}", e),
+                        None,
+                    )
+                })?;
+            let rendered = example_prompt.format(&borrow_vars(&variables))?;
+            Template::new(&escape_braces(&rendered))
+        })
+        .collect::<Result<Vec<_>, TemplateError>>()?;
+
+    let optional_template = |field: &str| -> Result<Option<Template>, TemplateError> {
+        match object.kwargs.get(field).and_then(Value::as_str) {
+            Some(text) if !text.is_empty() => Ok(Some(Template::new(&convert_f_string(text))?)),
+            _ => Ok(None),
+        }
+    };
+
+    let mut builder = FewShotTemplate::builder().examples(examples);
+    if let Some(prefix) = optional_template("prefix")? {
+        builder = builder.prefix(prefix);
+    }
+    if let Some(suffix) = optional_template("suffix")? {
+        builder = builder.suffix(suffix);
+    }
+    if let Some(separator) = object
+        .kwargs
+        .get("example_separator")
+        .and_then(Value::as_str)
+    {
+        builder = builder.example_separator(separator);
+    }
+
+    Ok(builder.build())
+}
+
+impl Template {
+    pub fn from_langchain_json(json: &str) -> Result<Self, TemplateError> {
+        prompt_template(&parse_json(json)?)
+    }
+}
+
+impl FewShotTemplate<Template> {
+    pub fn from_langchain_json(json: &str) -> Result<Self, TemplateError> {
+        few_shot_template(&parse_json(json)?)
+    }
+}
+
+impl ChatTemplate {
+    pub fn from_langchain_json(json: &str) -> Result<Self, TemplateError> {
+        let value = parse_json(json)?;
+        let object = lc_object(&value)?;
+        if object.name != "ChatPromptTemplate" {
+            return Err(unsupported(object.name));
+        }
+
+        let messages = object
+            .kwargs
+            .get("messages")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                TemplateError::MalformedTemplate(
+                    "LangChain ChatPromptTemplate is missing 'messages'".to_string(),
+                    None,
+                )
+            })?
+            .iter()
+            .map(chat_message)
+            .collect::<Result<Vec<_>, TemplateError>>()?;
+
+        Ok(ChatTemplate {
+            messages,
+            prefill: None,
+            sensitive: false,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Templatable};
+    use messageforge::BaseMessage;
+
+    fn prompt(template: &str) -> String {
+        format!(
+            r#"{{"lc":1,"type":"constructor","id":["langchain","prompts","prompt","PromptTemplate"],
+               "kwargs":{{"input_variables":[],"template":{},"template_format":"f-string"}}}}"#,
+            serde_json::to_string(template).unwrap()
+        )
+    }
+
+    #[test]
+    fn test_prompt_template_import() {
+        let template =
+            Template::from_langchain_json(&prompt("Tell me a {adjective} joke {{ok}}")).unwrap();
+        assert_eq!(template.input_variables(), vec!["adjective"]);
+        assert_eq!(
+            template.format(&vars!(adjective = "dad")).unwrap(),
+            "Tell me a dad joke {ok}"
+        );
+
+        let mustache = r#"{"lc":1,"type":"constructor","id":["langchain","prompts","prompt","PromptTemplate"],
+            "kwargs":{"template":"Hi {{name}}","template_format":"mustache","partial_variables":{"name":"Ada"}}}"#;
+        let template = Template::from_langchain_json(mustache).unwrap();
+        assert_eq!(template.format(&vars!()).unwrap(), "Hi Ada");
+    }
+
+    #[test]
+    fn test_chat_prompt_template_import() {
+        let json = format!(
+            r#"{{"lc":1,"type":"constructor","id":["langchain","prompts","chat","ChatPromptTemplate"],
+               "kwargs":{{"input_variables":["topic","history","question"],"messages":[
+                 {{"lc":1,"type":"constructor","id":["langchain","prompts","chat","SystemMessagePromptTemplate"],"kwargs":{{"prompt":{}}}}},
+                 {{"lc":1,"type":"constructor","id":["langchain","prompts","chat","MessagesPlaceholder"],"kwargs":{{"variable_name":"history","optional":true}}}},
+                 {{"lc":1,"type":"constructor","id":["langchain_core","messages","ai","AIMessage"],"kwargs":{{"content":"Ready."}}}},
+                 {{"lc":1,"type":"constructor","id":["langchain","prompts","chat","HumanMessagePromptTemplate"],"kwargs":{{"prompt":{}}}}}
+               ]}}}}"#,
+            prompt("You are an expert on {topic}."),
+            prompt("{question}")
+        );
+
+        let chat_template = ChatTemplate::from_langchain_json(&json).unwrap();
+        assert_eq!(chat_template.messages.len(), 4);
+        assert!(matches!(
+            &chat_template.messages[1],
+            MessageLike::Placeholder(placeholder) if placeholder.optional()
+        ));
+
+        let messages = chat_template
+            .format_messages(&vars!(topic = "Rust", question = "Why?"))
+            .unwrap();
+        let contents: Vec<&str> = messages.iter().map(|m| m.content()).collect();
+        assert_eq!(
+            contents,
+            vec!["You are an expert on Rust.", "Ready.", "Why?"]
+        );
+    }
+
+    #[test]
+    fn test_few_shot_prompt_template_import() {
+        let json = format!(
+            r#"{{"lc":1,"type":"constructor","id":["langchain","prompts","few_shot","FewShotPromptTemplate"],
+               "kwargs":{{"examples":[{{"q":"2+2","a":"4"}},{{"q":"{{x}}","a":"?"}}],
+                 "example_prompt":{},"prefix":"Solve:","suffix":"Q: {{input}}\nA:","example_separator":"\n"}}}}"#,
+            prompt("Q: {q}\nA: {a}")
+        );
+
+        let few_shot = FewShotTemplate::from_langchain_json(&json).unwrap();
+        assert_eq!(few_shot.examples().len(), 2);
+        assert_eq!(
+            few_shot.format(&vars!(input = "3+3")).unwrap(),
+            "Solve:\nQ: 2+2\nA: 4\nQ: {x}\nA: ?\nQ: 3+3\nA:"
+        );
+    }
+
+    #[test]
+    fn test_unsupported_objects_are_rejected() {
+        let json = r#"{"lc":1,"type":"constructor","id":["langchain","prompts","chat","ChatPromptTemplate"],
+            "kwargs":{"messages":[{"lc":1,"type":"constructor","id":["x","RemoteTool"],"kwargs":{}}]}}"#;
+        assert!(matches!(
+            ChatTemplate::from_langchain_json(json),
+            Err(TemplateError::UnsupportedFormat(msg)) if msg.contains("RemoteTool")
+        ));
+        assert!(Template::from_langchain_json("not json").is_err());
+        assert!(ChatTemplate::from_langchain_json(&prompt("Hi")).is_err());
+    }
+}
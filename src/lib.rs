@@ -1,19 +1,44 @@
+pub use promptforge_syntax::detection;
+
 pub mod braces;
+pub use braces::BraceLiterals;
 
 pub mod is_even;
 pub use is_even::IsEven;
 
 pub mod placeholder;
 pub use placeholder::extract_placeholder_variable;
+pub use placeholder::extract_variable_defaults;
 pub use placeholder::extract_variables;
 pub use placeholder::is_valid_identifier;
 
+pub mod text;
+
+pub mod span;
+pub use span::SourceSpan;
+
 pub mod template_format;
 pub use template_format::merge_vars;
 pub use template_format::TemplateError;
 pub use template_format::TemplateFormat;
 
+pub mod ordering;
+
 pub mod vars;
+pub use promptforge_derive::{embed_prompts, PromptVars};
+pub use vars::{fields_cover_template, serialize_vars, PromptVars};
+
+pub mod batch;
+pub use batch::{format_rows, BatchSource, ValueType};
+
+pub mod format_options;
+pub use format_options::{
+    find_unfilled_placeholders, FormatOptions, OutputEncoding, RenderDeadline,
+    UnfilledPlaceholderPolicy,
+};
+
+pub mod filters;
+pub use filters::{FilterCall, FilterRegistry};
 
 pub mod formatting;
 pub use formatting::{Formattable, Templatable};
@@ -21,8 +46,17 @@ pub use formatting::{Formattable, Templatable};
 pub mod template;
 pub use template::Template;
 
+pub mod composed;
+pub use composed::ComposedTemplate;
+
+pub mod edit;
+pub use edit::{SourceNode, TemplateEditor};
+
 pub mod chat_template;
-pub use chat_template::ChatTemplate;
+pub use chat_template::{ChatTemplate, ChatTemplateBuilder, FormattedMessage};
+
+pub mod validation;
+pub use validation::{ConsistencyIssue, ConsistencyReport, MessageIssue, ValidationReport};
 
 pub mod message_like;
 pub use message_like::ArcMessageEnumExt;
@@ -31,18 +65,204 @@ pub use message_like::MessageLike;
 pub mod chats;
 
 pub mod role;
-pub use role::Role;
+pub use role::{Role, RoleAliases};
 
 pub mod messages_placeholder;
-pub use messages_placeholder::MessagesPlaceholder;
+pub use messages_placeholder::{MessagesPlaceholder, PlaceholderLimits, TruncationSide};
+
+pub mod chat_values;
+pub use chat_values::{ChatValue, ChatValues};
+
+pub mod history;
+pub use history::{ChatHistory, MessageSource, Summarizer};
 
 pub mod few_shot_template;
 pub use few_shot_template::FewShotTemplate;
 
 pub mod few_shot_chat_template;
-pub use few_shot_chat_template::FewShotChatTemplate;
+pub use few_shot_chat_template::{FewShotChatTemplate, NegativeExamplePolicy};
 
 pub mod examples;
 
+pub mod multi_turn_example;
+pub use multi_turn_example::{ExampleTurn, MultiTurnExample, ToolUseExample};
+
+pub mod tool_call_template;
+pub use tool_call_template::{ToolCallSpec, ToolCallTemplate};
+
+pub mod multimodal;
+pub use multimodal::{ContentPart, ImageUrl, MultimodalTemplate};
+
+pub mod message_metadata;
+pub use message_metadata::{CacheControl, MessageMetadata};
+
 pub mod few_shot_chat_template_config;
 pub use few_shot_chat_template_config::FewShotChatTemplateConfig;
+
+pub mod metrics;
+pub use metrics::{EstimatedTokenCounter, PromptMetrics, TokenCounter};
+
+pub mod tokens;
+pub use tokens::{BudgetedMessages, ElisionReport, MessageTokenEstimate, TokenBudget};
+
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+#[cfg(feature = "prometheus")]
+pub use prometheus::{MetricsSink, PrometheusRecorder};
+
+pub mod example_metadata;
+pub use example_metadata::{Difficulty, ExampleMetadata};
+
+pub mod example_selector;
+pub use example_selector::{ExampleSelector, MetadataFilterSelector, StratifiedExampleSelector};
+
+pub mod compression;
+pub use compression::{Compressor, ExtractiveCompressor, PromptCompression};
+
+pub mod components;
+
+pub mod anonymize;
+pub use anonymize::{AnonymizedExample, ExampleAnonymizer};
+
+pub mod hashing;
+pub use hashing::{CanonicalHash, TemplateHash};
+
+pub mod replay;
+pub use replay::{RenderLog, ReplayEntry, ReplayFailure, ReplayOptions, ReplayReport, Replayable};
+
+#[cfg(feature = "chaos")]
+pub mod chaos;
+#[cfg(feature = "chaos")]
+pub use chaos::FailureInjector;
+
+pub mod lint;
+pub use lint::{LintDiagnostic, LintProfile, LintSeverity, StyleRule};
+
+pub mod diagram;
+
+pub mod debug_renderer;
+pub use debug_renderer::{unified_diff, DebugRenderer};
+
+pub mod export;
+pub use export::{ExportedMessage, RoleMap, RoleMapping, ToolCall, ToolFunction};
+
+pub mod anthropic;
+pub use anthropic::anthropic_request;
+
+pub mod caching;
+pub use caching::{CacheHint, CacheHints, CacheReport, CachedExport};
+
+pub mod chunking;
+pub use chunking::{format_chunks, Chunk, ChunkSize, Chunker};
+pub mod pagination;
+pub use pagination::{ConversationPages, ConversationPaginator};
+
+#[cfg(feature = "openai")]
+pub mod openai;
+#[cfg(feature = "openai")]
+pub use openai::{OpenAiContent, OpenAiMessage, OpenAiRole};
+
+#[cfg(feature = "hf")]
+pub mod hf;
+#[cfg(feature = "hf")]
+pub use hf::HfChatTemplate;
+
+pub mod chatml;
+pub use chatml::render_chatml;
+
+#[cfg(feature = "yaml")]
+pub mod yaml;
+#[cfg(feature = "yaml")]
+pub use yaml::{from_yaml_str, to_yaml_string};
+
+pub mod llama;
+pub use llama::{render_llama2, render_llama3};
+
+pub mod renderer;
+pub use renderer::{
+    Anthropic, ChatMl, ChatRenderer, FormatStyle, Llama2, Llama3, OpenAiJson, PlainPrefix,
+};
+
+pub mod model_profile;
+pub use model_profile::ModelProfile;
+
+pub mod tenant;
+pub use tenant::{TenantContext, TenantDirectory};
+
+pub mod langchain;
+
+pub mod fixtures;
+
+pub mod conformance;
+pub use conformance::{ConformanceCase, ConformanceReport, ConformanceSuite};
+
+pub mod loader;
+pub use loader::{CancellationToken, FromSource, LoadResult, LoaderOptions};
+
+pub mod pack;
+pub use pack::{load_pack, EvalCase, PromptPack};
+
+pub mod library;
+pub use library::{LibraryItem, LibraryPrompt, PromptLibrary};
+
+pub mod constants;
+pub use constants::PromptConstants;
+
+pub mod registry;
+pub use registry::{
+    CompiledPrompt, PromptRegistry, ReadinessReport, RegisteredPrompt, WarmupFailure, WarmupStage,
+};
+
+pub mod registry_handle;
+pub use registry_handle::{RegistryEvent, RegistryEvents, RegistryHandle};
+
+#[cfg(feature = "watch")]
+pub mod watch;
+#[cfg(feature = "watch")]
+pub use watch::RegistryWatcher;
+
+pub mod pipeline;
+pub use pipeline::{PipelineConfig, PipelineStep, PromptPipeline};
+
+pub mod pool;
+pub use pool::RenderPool;
+
+pub mod encryption;
+pub use encryption::{Cipher, EncryptedLoader, Sensitive};
+
+pub mod normalization;
+pub use normalization::{NormalizationRules, NormalizeOptions, TextTransform};
+
+pub mod compiled;
+pub use compiled::{CompiledChatTemplate, CompiledTemplate};
+
+pub mod config;
+pub use config::{PromptForgeConfig, PromptForgeConfigBuilder};
+
+const fn assert_send_sync<T: Send + Sync>() {}
+
+const _: () = {
+    assert_send_sync::<Template>();
+    assert_send_sync::<ChatTemplate>();
+    assert_send_sync::<ComposedTemplate>();
+    assert_send_sync::<FewShotTemplate<Template>>();
+    assert_send_sync::<FewShotChatTemplate>();
+    assert_send_sync::<MessageLike>();
+    assert_send_sync::<MessagesPlaceholder>();
+    assert_send_sync::<TemplateError>();
+    assert_send_sync::<PromptCompression>();
+    assert_send_sync::<CompiledChatTemplate>();
+    assert_send_sync::<FormatOptions>();
+    assert_send_sync::<TokenBudget>();
+    assert_send_sync::<FilterRegistry>();
+    assert_send_sync::<DebugRenderer>();
+    assert_send_sync::<LoaderOptions>();
+    assert_send_sync::<PromptRegistry>();
+    assert_send_sync::<EncryptedLoader>();
+    assert_send_sync::<PromptPack>();
+    assert_send_sync::<ChatValues>();
+    assert_send_sync::<ChatHistory>();
+    assert_send_sync::<RenderPool>();
+    assert_send_sync::<PromptForgeConfig>();
+    assert_send_sync::<PromptLibrary>();
+};
@@ -0,0 +1,465 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{
+    BraceLiterals, ChatTemplate, FewShotChatTemplate, FewShotChatTemplateConfig, FewShotTemplate,
+    RegisteredPrompt, Templatable, Template, TemplateError,
+};
+
+pub const LIBRARY_EXTENSIONS: [&str; 4] = ["toml", "json", "yaml", "yml"];
+
+// Checked by `embed_prompts!` when it embeds YAML files, so a build without
+// the `yaml` feature fails instead of the first lookup.
+pub const YAML_SUPPORTED: bool = cfg!(feature = "yaml");
+
+#[derive(Debug, Clone)]
+pub enum LibraryPrompt {
+    Template(Template),
+    Chat(ChatTemplate),
+    FewShot(FewShotTemplate<Template>),
+    FewShotChat(FewShotChatTemplate),
+}
+
+pub trait LibraryItem: Sized {
+    fn from_prompt(prompt: &LibraryPrompt) -> Option<&Self>;
+}
+
+// Prompts keyed by their path relative to the library root, without the
+// extension and with `/` separators, e.g. `support/greeting`.
+#[derive(Debug, Clone, Default)]
+pub struct PromptLibrary {
+    prompts: BTreeMap<String, LibraryPrompt>,
+}
+
+fn parse_error(format: &str, error: impl std::fmt::Display) -> TemplateError {
+    TemplateError::MalformedTemplate(format!("Failed to parse {}: {}", format, error), None)
+}
+
+#[cfg(feature = "yaml")]
+fn parse_yaml(content: &str) -> Result<Value, TemplateError> {
+    crate::yaml::from_yaml_str(content)
+}
+
+#[cfg(not(feature = "yaml"))]
+fn parse_yaml(_: &str) -> Result<Value, TemplateError> {
+    Err(TemplateError::UnsupportedFormat(
+        "YAML prompt files need the 'yaml' feature".to_string(),
+    ))
+}
+
+impl LibraryPrompt {
+    pub fn parse(extension: &str, content: &str) -> Result<Self, TemplateError> {
+        Self::parse_with_literals(extension, content, &BraceLiterals::new())
+    }
+
+    // The literals are escaped in a plain template's source, as
+    // `Template::new_with_literals` does.
+    pub fn parse_with_literals(
+        extension: &str,
+        content: &str,
+        literals: &BraceLiterals,
+    ) -> Result<Self, TemplateError> {
+        let value = match extension.to_ascii_lowercase().as_str() {
+            "json" => serde_json::from_str(content).map_err(|e| parse_error("JSON", e))?,
+            "toml" => {
+                let table: toml::Table =
+                    toml::from_str(content).map_err(|e| parse_error("TOML", e))?;
+                serde_json::to_value(table).map_err(|e| parse_error("TOML", e))?
+            }
+            "yaml" | "yml" => parse_yaml(content)?,
+            other => {
+                return Err(TemplateError::UnsupportedFormat(format!(
+                    "Unsupported prompt file extension: {}",
+                    other
+                )))
+            }
+        };
+        Self::from_value_with_literals(value, literals)
+    }
+
+    pub fn from_value(value: Value) -> Result<Self, TemplateError> {
+        Self::from_value_with_literals(value, &BraceLiterals::new())
+    }
+
+    // The type is told apart by the keys each layout requires.
+    pub fn from_value_with_literals(
+        value: Value,
+        literals: &BraceLiterals,
+    ) -> Result<Self, TemplateError> {
+        let Value::Object(fields) = &value else {
+            return Err(TemplateError::MalformedTemplate(
+                "A prompt file must hold a table of fields".to_string(),
+                None,
+            ));
+        };
+        let has = |key: &str| fields.contains_key(key);
+
+        if has("example_prompt") {
+            serde_json::from_value(value)
+                .map(LibraryPrompt::FewShotChat)
+                .map_err(|e| parse_error("few-shot chat template", e))
+        } else if has("messages") && (has("prefix") || has("example_separator")) {
+            let config: FewShotChatTemplateConfig = serde_json::from_value(value)
+                .map_err(|e| parse_error("few-shot chat template", e))?;
+            FewShotChatTemplate::try_from(config).map(LibraryPrompt::FewShotChat)
+        } else if has("messages") {
+            serde_json::from_value(value)
+                .map(LibraryPrompt::Chat)
+                .map_err(|e| parse_error("chat template", e))
+        } else if has("examples") || has("example_separator") {
+            serde_json::from_value(value)
+                .map(LibraryPrompt::FewShot)
+                .map_err(|e| parse_error("few-shot template", e))
+        } else if has("template") {
+            // Rebuilt from its source so the Mustache and Jinja engines are ready.
+            let template: Template =
+                serde_json::from_value(value).map_err(|e| parse_error("template", e))?;
+            template
+                .with_source_and_literals(
+                    template.template(),
+                    template.template_format(),
+                    template.input_variables(),
+                    literals,
+                )
+                .map(LibraryPrompt::Template)
+        } else {
+            Err(TemplateError::MalformedTemplate(
+                "Cannot tell the prompt type; expected a 'template', 'messages', 'examples' or \
+                 'example_prompt' field"
+                    .to_string(),
+                None,
+            ))
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LibraryPrompt::Template(_) => "template",
+            LibraryPrompt::Chat(_) => "chat template",
+            LibraryPrompt::FewShot(_) => "few-shot template",
+            LibraryPrompt::FewShotChat(_) => "few-shot chat template",
+        }
+    }
+}
+
+impl LibraryItem for Template {
+    fn from_prompt(prompt: &LibraryPrompt) -> Option<&Self> {
+        match prompt {
+            LibraryPrompt::Template(template) => Some(template),
+            _ => None,
+        }
+    }
+}
+
+impl LibraryItem for ChatTemplate {
+    fn from_prompt(prompt: &LibraryPrompt) -> Option<&Self> {
+        match prompt {
+            LibraryPrompt::Chat(chat_template) => Some(chat_template),
+            _ => None,
+        }
+    }
+}
+
+impl LibraryItem for FewShotTemplate<Template> {
+    fn from_prompt(prompt: &LibraryPrompt) -> Option<&Self> {
+        match prompt {
+            LibraryPrompt::FewShot(few_shot) => Some(few_shot),
+            _ => None,
+        }
+    }
+}
+
+impl LibraryItem for FewShotChatTemplate {
+    fn from_prompt(prompt: &LibraryPrompt) -> Option<&Self> {
+        match prompt {
+            LibraryPrompt::FewShotChat(few_shot_chat) => Some(few_shot_chat),
+            _ => None,
+        }
+    }
+}
+
+// The registry only holds templates and chat templates.
+impl TryFrom<LibraryPrompt> for RegisteredPrompt {
+    type Error = TemplateError;
+
+    fn try_from(prompt: LibraryPrompt) -> Result<Self, Self::Error> {
+        match prompt {
+            LibraryPrompt::Template(template) => Ok(template.into()),
+            LibraryPrompt::Chat(chat_template) => Ok(chat_template.into()),
+            other => Err(TemplateError::MalformedTemplate(
+                format!("A {} cannot be registered as a prompt", other.kind()),
+                None,
+            )),
+        }
+    }
+}
+
+// `support/greeting.toml` under the root becomes `support/greeting`.
+pub fn prompt_name(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?.with_extension("");
+    let parts: Vec<&str> = relative
+        .components()
+        .map(|component| component.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+pub(crate) fn library_extension(path: &Path) -> Option<&str> {
+    let extension = path.extension()?.to_str()?;
+    LIBRARY_EXTENSIONS
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(extension))
+        .then_some(extension)
+}
+
+pub(crate) async fn prompt_files(root: &Path) -> Result<Vec<PathBuf>, TemplateError> {
+    let read_error = |dir: &Path, e: std::io::Error| {
+        TemplateError::MalformedTemplate(
+            format!("Failed to read prompt directory {}: {}", dir.display(), e),
+            None,
+        )
+    };
+
+    let mut files = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = fs::read_dir(&dir).await.map_err(|e| read_error(&dir, e))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| read_error(&dir, e))?
+        {
+            let path = entry.path();
+            let file_type = entry.file_type().await.map_err(|e| read_error(&path, e))?;
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if library_extension(&path).is_some() {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+impl PromptLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Every file is attempted; if any fail, the error lists each one with
+    // its path rather than stopping at the first.
+    pub async fn from_dir<P: AsRef<Path>>(root: P) -> Result<Self, TemplateError> {
+        let root = root.as_ref();
+        let mut library = PromptLibrary::new();
+        let mut failures = Vec::new();
+
+        for path in prompt_files(root).await? {
+            let name = prompt_name(root, &path).unwrap_or_else(|| path.display().to_string());
+            let extension = library_extension(&path).unwrap_or_default();
+            let loaded = match fs::read_to_string(&path).await {
+                Ok(content) => LibraryPrompt::parse(extension, &content),
+                Err(e) => Err(TemplateError::MalformedTemplate(
+                    format!("Failed to read prompt file: {}", e),
+                    None,
+                )),
+            };
+            let result = loaded.and_then(|prompt| library.insert(name, prompt));
+            if let Err(error) = result {
+                failures.push(format!("{}: {}", path.display(), error));
+            }
+        }
+
+        match failures.is_empty() {
+            true => Ok(library),
+            false => Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Failed to load {} prompt file(s) from {}:\n{}",
+                    failures.len(),
+                    root.display(),
+                    failures.join("\n")
+                ),
+                None,
+            )),
+        }
+    }
+
+    // Backs `embed_prompts!`, which reads each `(name, extension, content)`
+    // at build time and has already checked its templates with the same
+    // brace literals.
+    pub fn from_embedded(
+        files: &[(&str, &str, &str)],
+        literals: &BraceLiterals,
+    ) -> Result<Self, TemplateError> {
+        let mut library = PromptLibrary::new();
+        let mut failures = Vec::new();
+
+        for (name, extension, content) in files {
+            let result = LibraryPrompt::parse_with_literals(extension, content, literals)
+                .and_then(|prompt| library.insert(*name, prompt));
+            if let Err(error) = result {
+                failures.push(format!("{}: {}", name, error));
+            }
+        }
+
+        match failures.is_empty() {
+            true => Ok(library),
+            false => Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Failed to load {} embedded prompt file(s):\n{}",
+                    failures.len(),
+                    failures.join("\n")
+                ),
+                None,
+            )),
+        }
+    }
+
+    // Two files differing only in extension would claim the same name.
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        prompt: LibraryPrompt,
+    ) -> Result<(), TemplateError> {
+        let name = name.into();
+        if self.prompts.contains_key(&name) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Duplicate prompt name '{}'", name),
+                None,
+            ));
+        }
+        self.prompts.insert(name, prompt);
+        Ok(())
+    }
+
+    pub fn with_prompt(
+        mut self,
+        name: impl Into<String>,
+        prompt: LibraryPrompt,
+    ) -> Result<Self, TemplateError> {
+        self.insert(name, prompt)?;
+        Ok(self)
+    }
+
+    pub fn get<T: LibraryItem>(&self, name: &str) -> Option<&T> {
+        self.prompt(name).and_then(T::from_prompt)
+    }
+
+    // Like `get`, but says whether the prompt is missing or of another type.
+    pub fn require<T: LibraryItem>(&self, name: &str) -> Result<&T, TemplateError> {
+        let prompt = self.prompt(name).ok_or_else(|| {
+            TemplateError::MalformedTemplate(format!("Unknown prompt '{}'", name), None)
+        })?;
+        T::from_prompt(prompt).ok_or_else(|| {
+            TemplateError::MalformedTemplate(
+                format!(
+                    "Prompt '{}' is a {}, not the requested type",
+                    name,
+                    prompt.kind()
+                ),
+                None,
+            )
+        })
+    }
+
+    pub fn prompt(&self, name: &str) -> Option<&LibraryPrompt> {
+        self.prompts.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prompts.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prompts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Formattable};
+
+    async fn write_library(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = std::env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&root).await;
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).await.unwrap();
+            fs::write(path, content).await.unwrap();
+        }
+        root
+    }
+
+    #[tokio::test]
+    async fn test_from_dir_detects_prompt_types() {
+        let chat_toml = fs::read_to_string("tests/data/chat_template.toml")
+            .await
+            .unwrap();
+        let few_shot_toml = fs::read_to_string("tests/data/few_shot_template.toml")
+            .await
+            .unwrap();
+        let root = write_library(
+            "promptforge_library",
+            &[
+                ("support/greeting.toml", &chat_toml),
+                ("math/examples.toml", &few_shot_toml),
+                (
+                    "welcome.json",
+                    r#"{"template": "Hi {{name}}!", "template_format": "Mustache", "input_variables": ["name"]}"#,
+                ),
+                ("notes.md", "not a prompt"),
+            ],
+        )
+        .await;
+
+        let library = PromptLibrary::from_dir(&root).await.unwrap();
+        assert_eq!(
+            library.names().collect::<Vec<_>>(),
+            ["math/examples", "support/greeting", "welcome"]
+        );
+        let greeting: &ChatTemplate = library.get("support/greeting").unwrap();
+        assert_eq!(greeting.messages.len(), 3);
+        assert!(library
+            .get::<FewShotTemplate<Template>>("math/examples")
+            .is_some());
+        let welcome = library.require::<Template>("welcome").unwrap();
+        assert_eq!(welcome.format(&vars!(name = "Ada")).unwrap(), "Hi Ada!");
+
+        let err = library.require::<Template>("support/greeting").unwrap_err();
+        assert!(err.to_string().contains("is a chat template"), "{}", err);
+        assert!(library.get::<Template>("missing").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_from_dir_reports_every_bad_file() {
+        let root = write_library(
+            "promptforge_library_errors",
+            &[
+                ("good.json", r#"{"template": "Hi {name}", "template_format": "FmtString", "input_variables": ["name"]}"#),
+                ("broken.toml", "template = "),
+                ("nested/unknown.json", r#"{"title": "no prompt here"}"#),
+                ("good.toml", "template = \"Hi\"\ntemplate_format = \"PlainText\"\ninput_variables = []"),
+            ],
+        )
+        .await;
+
+        let err = PromptLibrary::from_dir(&root)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("Failed to load 3 prompt file(s)"), "{}", err);
+        assert!(err.contains("broken.toml: "), "{}", err);
+        assert!(err.contains("unknown.json: "), "{}", err);
+        assert!(err.contains("Duplicate prompt name 'good'"), "{}", err);
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+
+use crate::{ChatTemplate, ExportedMessage, Role, RoleMap, RoleMapping, TemplateError};
+
+pub const BOS: &str = "<s>";
+pub const EOS: &str = "</s>";
+pub const INST_START: &str = "[INST]";
+pub const INST_END: &str = "[/INST]";
+pub const SYS_START: &str = "<<SYS>>\n";
+pub const SYS_END: &str = "\n<</SYS>>\n\n";
+
+pub const BEGIN_OF_TEXT: &str = "<|begin_of_text|>";
+pub const START_HEADER: &str = "<|start_header_id|>";
+pub const END_HEADER: &str = "<|end_header_id|>";
+pub const EOT: &str = "<|eot_id|>";
+pub const LLAMA3_TOOL_ROLE: &str = "ipython";
+
+fn system_prompt(messages: &[ExportedMessage]) -> Option<String> {
+    let system: Vec<&str> = messages
+        .iter()
+        .filter(|message| message.role == "system")
+        .map(|message| message.content.as_str())
+        .collect();
+    (!system.is_empty()).then(|| system.join("\n\n"))
+}
+
+// Llama-2 has no system or tool turn of its own: the system prompt is folded
+// into the first `[INST]` block and consecutive messages from the same side
+// are merged so user and assistant strictly alternate.
+pub fn render_llama2(messages: &[ExportedMessage]) -> String {
+    let mut turns: Vec<(String, Option<String>)> = Vec::new();
+    for message in messages.iter().filter(|message| message.role != "system") {
+        let content = message.content.as_str();
+        match (message.role.as_str(), turns.last_mut()) {
+            ("assistant", Some((_, Some(reply)))) => {
+                reply.push_str("\n\n");
+                reply.push_str(content);
+            }
+            ("assistant", Some((_, reply @ None))) => *reply = Some(content.to_string()),
+            ("assistant", None) => turns.push((String::new(), Some(content.to_string()))),
+            (_, Some((user, None))) => {
+                user.push_str("\n\n");
+                user.push_str(content);
+            }
+            _ => turns.push((content.to_string(), None)),
+        }
+    }
+
+    let system = system_prompt(messages);
+    if turns.is_empty() && system.is_some() {
+        turns.push((String::new(), None));
+    }
+
+    let mut output = String::new();
+    for (index, (user, reply)) in turns.iter().enumerate() {
+        output.push_str(BOS);
+        output.push_str(INST_START);
+        output.push(' ');
+        if let (0, Some(system)) = (index, &system) {
+            output.push_str(SYS_START);
+            output.push_str(system);
+            output.push_str(SYS_END);
+        }
+        output.push_str(user.trim());
+        output.push(' ');
+        output.push_str(INST_END);
+        if let Some(reply) = reply {
+            output.push_str(&format!(" {} {}", reply.trim(), EOS));
+        }
+    }
+    output
+}
+
+// Llama-3 expects at most one system header, first in the conversation.
+pub fn render_llama3(messages: &[ExportedMessage], add_generation_prompt: bool) -> String {
+    let mut output = BEGIN_OF_TEXT.to_string();
+    let mut push_turn = |role: &str, content: &str| {
+        output.push_str(&format!(
+            "{}{}{}\n\n{}{}",
+            START_HEADER, role, END_HEADER, content, EOT
+        ));
+    };
+
+    if let Some(system) = system_prompt(messages) {
+        push_turn("system", &system);
+    }
+    for message in messages.iter().filter(|message| message.role != "system") {
+        push_turn(&message.role, &message.content);
+    }
+
+    if add_generation_prompt {
+        output.push_str(&format!("{}assistant{}\n\n", START_HEADER, END_HEADER));
+    }
+    output
+}
+
+pub(crate) fn llama3_role_map() -> RoleMap {
+    RoleMap::openai().map(Role::Tool, RoleMapping::new(LLAMA3_TOOL_ROLE))
+}
+
+impl ChatTemplate {
+    pub fn format_llama2(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let messages = self.export(variables, &RoleMap::text_only())?;
+        Ok(render_llama2(&messages))
+    }
+
+    pub fn format_llama3(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        let messages = self.export(variables, &llama3_role_map())?;
+        Ok(render_llama3(&messages, false))
+    }
+
+    pub fn format_llama3_for_generation(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        let messages = self.export(variables, &llama3_role_map())?;
+        Ok(render_llama3(&messages, true))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, vars};
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "Hi!",
+            Ai = "Hello.",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_format_llama2_multi_turn_with_system() {
+        let rendered = chat()
+            .format_llama2(&vars!(persona = "terse", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "<s>[INST] <<SYS>>\nYou are terse.\n<</SYS>>\n\nHi! [/INST] Hello. </s>\
+             <s>[INST] Why? [/INST]"
+        );
+    }
+
+    #[test]
+    fn test_format_llama2_merges_consecutive_turns() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            Human = "First.",
+            Human = "Second.",
+            System = "Late system note.",
+        ))
+        .unwrap();
+        assert_eq!(
+            chat_template.format_llama2(&vars!()).unwrap(),
+            "<s>[INST] <<SYS>>\nLate system note.\n<</SYS>>\n\nFirst.\n\nSecond. [/INST]"
+        );
+    }
+
+    #[test]
+    fn test_format_llama3() {
+        let variables = vars!(persona = "terse", question = "Why?");
+        let rendered = chat().format_llama3(&variables).unwrap();
+        assert_eq!(
+            rendered,
+            "<|begin_of_text|>\
+             <|start_header_id|>system<|end_header_id|>\n\nYou are terse.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nHi!<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\nHello.<|eot_id|>\
+             <|start_header_id|>user<|end_header_id|>\n\nWhy?<|eot_id|>"
+        );
+
+        let generation = chat().format_llama3_for_generation(&variables).unwrap();
+        assert!(generation.ends_with("<|start_header_id|>assistant<|end_header_id|>\n\n"));
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures::{stream, StreamExt};
+use tokio::{fs, sync::Notify};
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    BraceLiterals, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Formattable, MessageLike,
+    MessagesPlaceholder, PipelineConfig, PromptConstants, Templatable, Template, TemplateError,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationState>,
+}
+
+#[derive(Debug, Default)]
+struct CancellationState {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub async fn cancelled(&self) {
+        let notified = self.inner.notify.notified();
+        tokio::pin!(notified);
+        // Register before checking the flag so a cancel between the two is not missed.
+        notified.as_mut().enable();
+        if self.is_cancelled() {
+            return;
+        }
+        notified.await;
+    }
+}
+
+// Builds a prompt from the content of a file. Brace literals only matter
+// where a template format is detected from plain text; serialized prompts
+// record their format and already escaped text, so they parse as usual.
+pub trait FromSource: TryFrom<String, Error = TemplateError> {
+    fn from_source(source: String, _literals: &BraceLiterals) -> Result<Self, TemplateError> {
+        Self::try_from(source)
+    }
+}
+
+impl FromSource for Template {
+    fn from_source(source: String, literals: &BraceLiterals) -> Result<Self, TemplateError> {
+        Template::new_with_literals(&source, literals)
+    }
+}
+
+impl FromSource for ChatTemplate {}
+impl FromSource for FewShotChatTemplate {}
+impl<T> FromSource for FewShotTemplate<T> where T: Templatable + Formattable + DeserializeOwned {}
+impl FromSource for MessageLike {}
+impl FromSource for MessagesPlaceholder {}
+impl FromSource for PromptConstants {}
+impl FromSource for PipelineConfig {}
+
+#[derive(Debug, Clone)]
+pub struct LoaderOptions {
+    timeout: Option<Duration>,
+    cancellation: Option<CancellationToken>,
+    concurrency: usize,
+    brace_literals: BraceLiterals,
+}
+
+impl Default for LoaderOptions {
+    fn default() -> Self {
+        LoaderOptions {
+            timeout: None,
+            cancellation: None,
+            concurrency: Self::DEFAULT_CONCURRENCY,
+            brace_literals: BraceLiterals::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadResult<T> {
+    pub path: PathBuf,
+    pub result: Result<T, TemplateError>,
+}
+
+impl LoaderOptions {
+    pub const DEFAULT_CONCURRENCY: usize = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_brace_literals(mut self, brace_literals: BraceLiterals) -> Self {
+        self.brace_literals = brace_literals;
+        self
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    pub fn cancellation(&self) -> Option<&CancellationToken> {
+        self.cancellation.as_ref()
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+    }
+
+    pub fn brace_literals(&self) -> &BraceLiterals {
+        &self.brace_literals
+    }
+
+    pub async fn load<T, P>(&self, path: P) -> Result<T, TemplateError>
+    where
+        T: FromSource,
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let content = String::from_utf8(self.read_bytes(path).await?).map_err(|e| {
+            TemplateError::TomlDeserializationError(format!(
+                "Failed to read TOML file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+
+        T::from_source(content, &self.brace_literals)
+    }
+
+    pub async fn load_many<T, P, I>(&self, paths: I) -> Vec<LoadResult<T>>
+    where
+        T: FromSource,
+        P: AsRef<Path>,
+        I: IntoIterator<Item = P>,
+    {
+        stream::iter(paths)
+            .map(|path| async move {
+                let path = path.as_ref().to_path_buf();
+                let result = self.load(&path).await;
+                LoadResult { path, result }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
+
+    pub(crate) async fn read_bytes(&self, path: &Path) -> Result<Vec<u8>, TemplateError> {
+        match &self.cancellation {
+            Some(token) => tokio::select! {
+                biased;
+                _ = token.cancelled() => {
+                    Err(TemplateError::LoadCancelled(path.display().to_string()))
+                }
+                content = self.read_with_timeout(path) => content,
+            },
+            None => self.read_with_timeout(path).await,
+        }
+    }
+
+    async fn read_with_timeout(&self, path: &Path) -> Result<Vec<u8>, TemplateError> {
+        let read = fs::read(path);
+        let content = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, read).await.map_err(|_| {
+                TemplateError::LoadTimeout(format!(
+                    "{} after {}ms",
+                    path.display(),
+                    timeout.as_millis()
+                ))
+            })?,
+            None => read.await,
+        };
+
+        content.map_err(|e| {
+            TemplateError::TomlDeserializationError(format!(
+                "Failed to read TOML file {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ChatTemplate, Formattable, Template};
+
+    fn write_temp(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[tokio::test]
+    async fn test_load_many_keeps_order_and_reports_per_file() {
+        let good = PathBuf::from("tests/data/chat_template.toml");
+        let bad = write_temp("promptforge_loader_bad.toml", "messages = [");
+        let missing = std::env::temp_dir().join("promptforge_loader_missing.toml");
+
+        let options = LoaderOptions::new().with_concurrency(2);
+        let results = options
+            .load_many::<ChatTemplate, _, _>([&good, &bad, &missing])
+            .await;
+
+        assert_eq!(
+            results.iter().map(|r| &r.path).collect::<Vec<_>>(),
+            vec![&good, &bad, &missing]
+        );
+        assert_eq!(results[0].result.as_ref().unwrap().messages.len(), 3);
+        assert!(results[1].result.is_err());
+        assert!(matches!(
+            &results[2].result,
+            Err(TemplateError::TomlDeserializationError(msg)) if msg.contains("missing")
+        ));
+
+        std::fs::remove_file(bad).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_load_with_brace_literals() {
+        let path = write_temp("promptforge_loader_literals.txt", "Print {} for {{lang}}");
+
+        assert!(LoaderOptions::new()
+            .load::<Template, _>(&path)
+            .await
+            .is_err());
+        let template: Template = LoaderOptions::new()
+            .with_brace_literals(BraceLiterals::programming())
+            .load(&path)
+            .await
+            .unwrap();
+        assert_eq!(
+            template.format(&crate::vars!(lang = "Rust")).unwrap(),
+            "Print {} for Rust"
+        );
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_load_returns_error() {
+        let path = write_temp("promptforge_loader_cancel.txt", "Hi {name}");
+        let token = CancellationToken::new();
+        let options = LoaderOptions::new().with_cancellation(token.clone());
+
+        let template: Template = options.load(&path).await.unwrap();
+        assert_eq!(
+            template.format(&crate::vars!(name = "Ada")).unwrap(),
+            "Hi Ada"
+        );
+
+        token.cancel();
+        assert!(matches!(
+            options.load::<Template, _>(&path).await,
+            Err(TemplateError::LoadCancelled(_))
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_timeout_and_concurrency_options() {
+        let options = LoaderOptions::new()
+            .with_timeout(Duration::from_secs(5))
+            .with_concurrency(0);
+        assert_eq!(options.timeout(), Some(Duration::from_secs(5)));
+        assert_eq!(options.concurrency(), 1);
+
+        let token = CancellationToken::new();
+        let waiter = tokio::spawn({
+            let token = token.clone();
+            async move { token.cancelled().await }
+        });
+        token.cancel();
+        waiter.await.unwrap();
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,17 +1,35 @@
+use crate::few_shot_chat_template_config::MessageConfig;
+use crate::ordering::serialize_message;
 use crate::template::Template;
-use crate::{role::Role, FewShotChatTemplate};
-use crate::{MessagesPlaceholder, TemplateError};
+use crate::{
+    is_valid_identifier, Formattable, MessageMetadata, MessagesPlaceholder, MultimodalTemplate,
+    Templatable, TemplateError, TemplateFormat, ToolCallTemplate,
+};
+use crate::{role::Role, ChatTemplate, FewShotChatTemplate};
 use messageforge::{AiMessage, HumanMessage, MessageEnum, SystemMessage, ToolMessage};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
 pub enum MessageLike {
+    #[serde(serialize_with = "serialize_message")]
     BaseMessage(Arc<MessageEnum>),
     RolePromptTemplate(Role, Arc<Template>),
     Placeholder(MessagesPlaceholder),
     FewShotPrompt(Box<FewShotChatTemplate>), // Boxed to avoid recursive type
+    ToolCallTemplate(ToolCallTemplate),
+    Multimodal(MultimodalTemplate),
+    Annotated(Box<MessageLike>, MessageMetadata),
+    // One `role` message per element of the JSON array in `variable`.
+    ForEach {
+        variable: String,
+        item_template: Arc<Template>,
+        role: Role,
+    },
+    // A shared chat, such as a persona or safety prelude, included by name.
+    SubTemplate(String, Arc<ChatTemplate>),
 }
 
 impl MessageLike {
@@ -31,11 +49,130 @@ impl MessageLike {
         MessageLike::FewShotPrompt(Box::new(few_shot_prompt))
     }
 
+    pub fn tool_call_template(tool_call_template: ToolCallTemplate) -> Self {
+        MessageLike::ToolCallTemplate(tool_call_template)
+    }
+
+    pub fn multimodal(multimodal_template: MultimodalTemplate) -> Self {
+        MessageLike::Multimodal(multimodal_template)
+    }
+
+    pub fn for_each(
+        variable: &str,
+        role: Role,
+        item_template: Template,
+    ) -> Result<Self, TemplateError> {
+        role.ensure_message_role()?;
+        if !is_valid_identifier(variable) {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Invalid variable name: {}", variable),
+                None,
+            ));
+        }
+        Ok(MessageLike::ForEach {
+            variable: variable.to_string(),
+            item_template: Arc::new(item_template),
+            role,
+        })
+    }
+
+    pub fn sub_template(name: &str, chat_template: ChatTemplate) -> Result<Self, TemplateError> {
+        let message_like = MessageLike::SubTemplate(name.to_string(), Arc::new(chat_template));
+        message_like.check_includes(&mut Vec::new())?;
+        Ok(message_like)
+    }
+
+    // Sub-templates are shared by value, so a cycle shows up as a name that
+    // is included again somewhere inside itself.
+    pub(crate) fn check_includes<'a>(
+        &'a self,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<(), TemplateError> {
+        let MessageLike::SubTemplate(name, chat_template) = self.inner() else {
+            return Ok(());
+        };
+        if stack.contains(&name.as_str()) {
+            stack.push(name);
+            return Err(TemplateError::MalformedTemplate(
+                format!("Sub-template cycle: {}", stack.join(" -> ")),
+                None,
+            ));
+        }
+        if chat_template.prefill.is_some() {
+            return Err(TemplateError::MalformedTemplate(
+                format!("Sub-template '{}' cannot have an assistant prefill", name),
+                None,
+            ));
+        }
+
+        stack.push(name);
+        for message_like in &chat_template.messages {
+            message_like.check_includes(stack)?;
+        }
+        stack.pop();
+        Ok(())
+    }
+
+    // Replaces any metadata already attached rather than nesting it.
+    pub fn with_metadata(self, metadata: MessageMetadata) -> Self {
+        match self {
+            MessageLike::Annotated(message_like, _) => {
+                MessageLike::Annotated(message_like, metadata)
+            }
+            message_like => MessageLike::Annotated(Box::new(message_like), metadata),
+        }
+    }
+
+    pub fn metadata(&self) -> Option<&MessageMetadata> {
+        match self {
+            MessageLike::Annotated(_, metadata) => Some(metadata),
+            _ => None,
+        }
+    }
+
+    // The message itself, without its metadata.
+    pub fn inner(&self) -> &MessageLike {
+        match self {
+            MessageLike::Annotated(message_like, _) => message_like.inner(),
+            message_like => message_like,
+        }
+    }
+
+    // Interprets `source` the way `ChatTemplate::from_messages` does: a
+    // placeholder or few-shot definition for those roles, otherwise a template
+    // that collapses to a plain message when it has no variables.
+    pub fn from_role_source(role: Role, source: String) -> Result<Self, TemplateError> {
+        match role {
+            Role::Placeholder => Ok(MessageLike::placeholder(MessagesPlaceholder::try_from(
+                source,
+            )?)),
+            Role::FewShotPrompt => Ok(MessageLike::few_shot_prompt(FewShotChatTemplate::try_from(
+                source,
+            )?)),
+            _ => {
+                role.ensure_message_role()?;
+                let template = Template::from_template(&source)?;
+
+                if template.template_format() == TemplateFormat::PlainText {
+                    Ok(MessageLike::BaseMessage(role.to_message(&source)?))
+                } else {
+                    Ok(MessageLike::role_prompt_template(role, template))
+                }
+            }
+        }
+    }
+
+    pub fn from_config(config: MessageConfig) -> Result<Self, TemplateError> {
+        let role = Role::try_from(config.value.role.as_str())
+            .map_err(|_| TemplateError::InvalidRoleError)?;
+        Self::from_role_source(role, config.value.content)
+    }
+
     fn match_message_enum<T>(
         &self,
         extract_message: impl Fn(&MessageEnum) -> Option<&T>,
     ) -> Option<&T> {
-        if let MessageLike::BaseMessage(message_enum) = self {
+        if let MessageLike::BaseMessage(message_enum) = self.inner() {
             extract_message(message_enum)
         } else {
             None
@@ -59,6 +196,50 @@ impl MessageLike {
     }
 }
 
+// Each item is rendered with the outer variables plus `item` (the element,
+// as text) and its 1-based `index`; object items also bind their fields.
+pub(crate) fn format_for_each(
+    variable: &str,
+    item_template: &Template,
+    role: &Role,
+    variables: &HashMap<&str, &str>,
+) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+    let payload = variables
+        .get(variable)
+        .ok_or_else(|| TemplateError::MissingVariable(variable.to_string()))?;
+    let items: Vec<Value> = serde_json::from_str(payload).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("'{}' is not a JSON array: {}", variable, e), None)
+    })?;
+    let text = |value: &Value| match value {
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    };
+
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let mut bound = vec![
+                ("index".to_string(), (index + 1).to_string()),
+                ("item".to_string(), text(item)),
+            ];
+            if let Value::Object(fields) = item {
+                bound.extend(
+                    fields
+                        .iter()
+                        .map(|(name, value)| (name.clone(), text(value))),
+                );
+            }
+
+            let mut item_variables = variables.clone();
+            for (name, value) in &bound {
+                item_variables.insert(name, value);
+            }
+            Ok(role.to_message(&item_template.format(&item_variables)?)?)
+        })
+        .collect()
+}
+
 pub trait ArcMessageEnumExt {
     fn unwrap_enum(self) -> MessageEnum;
 }
@@ -74,7 +255,7 @@ impl TryFrom<String> for MessageLike {
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let json_value: serde_json::Value = serde_json::from_str(&value).map_err(|e| {
-            TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", e))
+            TemplateError::MalformedTemplate(format!("Failed to parse JSON: {}", e), None)
         })?;
 
         let message_like: MessageLike = match json_value.get("type").and_then(|t| t.as_str()) {
@@ -83,28 +264,28 @@ impl TryFrom<String> for MessageLike {
                     json_value["value"].clone(),
                 )
                 .map_err(|e| {
-                    TemplateError::MalformedTemplate(format!(
-                        "Failed to deserialize BaseMessage: {}",
-                        e
-                    ))
+                    TemplateError::MalformedTemplate(
+                        format!("Failed to deserialize BaseMessage: {}", e),
+                        None,
+                    )
                 })?;
                 MessageLike::BaseMessage(Arc::new(base_message))
             }
             Some("RolePromptTemplate") => {
                 let role = serde_json::from_value::<Role>(json_value["value"][0].clone()).map_err(
                     |e| {
-                        TemplateError::MalformedTemplate(format!(
-                            "Failed to deserialize Role: {}",
-                            e
-                        ))
+                        TemplateError::MalformedTemplate(
+                            format!("Failed to deserialize Role: {}", e),
+                            None,
+                        )
                     },
                 )?;
                 let template = serde_json::from_value::<Template>(json_value["value"][1].clone())
                     .map_err(|e| {
-                    TemplateError::MalformedTemplate(format!(
-                        "Failed to deserialize Template: {}",
-                        e
-                    ))
+                    TemplateError::MalformedTemplate(
+                        format!("Failed to deserialize Template: {}", e),
+                        None,
+                    )
                 })?;
                 MessageLike::RolePromptTemplate(role, Arc::new(template))
             }
@@ -112,10 +293,10 @@ impl TryFrom<String> for MessageLike {
                 let placeholder =
                     serde_json::from_value::<MessagesPlaceholder>(json_value["value"].clone())
                         .map_err(|e| {
-                            TemplateError::MalformedTemplate(format!(
-                                "Failed to deserialize Placeholder: {}",
-                                e
-                            ))
+                            TemplateError::MalformedTemplate(
+                                format!("Failed to deserialize Placeholder: {}", e),
+                                None,
+                            )
                         })?;
                 MessageLike::Placeholder(placeholder)
             }
@@ -123,16 +304,25 @@ impl TryFrom<String> for MessageLike {
                 let few_shot_prompt =
                     serde_json::from_value::<FewShotChatTemplate>(json_value["value"].clone())
                         .map_err(|e| {
-                            TemplateError::MalformedTemplate(format!(
-                                "Failed to deserialize FewShotPrompt: {}",
-                                e
-                            ))
+                            TemplateError::MalformedTemplate(
+                                format!("Failed to deserialize FewShotPrompt: {}", e),
+                                None,
+                            )
                         })?;
                 MessageLike::FewShotPrompt(Box::new(few_shot_prompt))
             }
+            Some("ToolCallTemplate" | "Multimodal" | "Annotated" | "ForEach" | "SubTemplate") => {
+                serde_json::from_value::<MessageLike>(json_value).map_err(|e| {
+                    TemplateError::MalformedTemplate(
+                        format!("Failed to deserialize MessageLike: {}", e),
+                        None,
+                    )
+                })?
+            }
             _ => {
                 return Err(TemplateError::MalformedTemplate(
                     "Unknown MessageLike type".to_string(),
+                    None,
                 ));
             }
         };
@@ -149,6 +339,28 @@ mod tests {
     use messageforge::{AiMessage, HumanMessage, SystemMessage};
     use messageforge::{BaseMessage as _, MessageType};
 
+    #[test]
+    fn test_from_config() {
+        let message_like =
+            MessageLike::from_config(MessageConfig::new("human", "Hello, {name}!")).unwrap();
+        assert!(matches!(
+            message_like,
+            MessageLike::RolePromptTemplate(Role::Human, _)
+        ));
+
+        let message_like = MessageLike::from_config(MessageConfig::new("ai", "Hi.")).unwrap();
+        assert_eq!(message_like.as_ai().unwrap().content(), "Hi.");
+
+        let message_like =
+            MessageLike::from_config(MessageConfig::new("placeholder", "{history}")).unwrap();
+        assert!(matches!(message_like, MessageLike::Placeholder(_)));
+
+        assert!(matches!(
+            MessageLike::from_config(MessageConfig::new("narrator", "Once")),
+            Err(TemplateError::InvalidRoleError)
+        ));
+    }
+
     #[test]
     fn test_from_base_message_human() {
         let human_message = HumanMessage::new("Hello, how are you?");
@@ -381,6 +593,33 @@ mod tests {
         assert_eq!(serialized, expected);
     }
 
+    #[test]
+    fn test_serialize_base_message_with_kwargs_is_stable() {
+        let mut ai_message = AiMessage::new("Calling tools.");
+        for key in ["zeta", "alpha", "mu", "beta", "omega", "kappa"] {
+            ai_message
+                .base
+                .additional_kwargs
+                .insert(key.to_string(), key.to_uppercase());
+        }
+        let message_like = MessageLike::base_message(ai_message.into());
+
+        let serialized = serde_json::to_string(&message_like).unwrap();
+        assert!(serialized.contains(
+            r#""additional_kwargs":{"alpha":"ALPHA","beta":"BETA","kappa":"KAPPA","mu":"MU","omega":"OMEGA","zeta":"ZETA"}"#
+        ));
+        assert_eq!(
+            serialized,
+            serde_json::to_string(&message_like.clone()).unwrap()
+        );
+
+        let deserialized: MessageLike = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(
+            deserialized.as_ai().unwrap().base.additional_kwargs.len(),
+            6
+        );
+    }
+
     #[test]
     fn test_deserialize_base_message() {
         let json_data = r#"
@@ -710,4 +949,43 @@ mod tests {
             panic!("Expected FewShotPrompt");
         }
     }
+
+    #[test]
+    fn test_for_each_serde() {
+        let message_like =
+            MessageLike::for_each("documents", Human, Template::new("Doc: {item}").unwrap())
+                .unwrap();
+        let serialized = serde_json::to_string(&message_like).unwrap();
+        assert!(serialized.starts_with(r#"{"type":"ForEach","value":{"variable":"documents""#));
+
+        let deserialized = MessageLike::try_from(serialized).unwrap();
+        let chat_template = ChatTemplate::from_message_likes(vec![deserialized]).unwrap();
+        assert_eq!(
+            chat_template
+                .format(&crate::vars!(documents = r#"["a", "b"]"#))
+                .unwrap(),
+            "human: Doc: a\nhuman: Doc: b"
+        );
+
+        let toml = r#"
+            [[messages]]
+            type = "ForEach"
+
+            [messages.value]
+            variable = "documents"
+            role = "Human"
+
+            [messages.value.item_template]
+            template = "Doc: {item}"
+            template_format = "FmtString"
+            input_variables = ["item"]
+        "#;
+        let from_toml = ChatTemplate::try_from(toml.to_string()).unwrap();
+        assert_eq!(
+            from_toml
+                .format(&crate::vars!(documents = r#"["c"]"#))
+                .unwrap(),
+            "human: Doc: c"
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use messageforge::{BaseMessage, BaseMessageFields, MessageEnum, ToolMessage};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CacheControl {
+    Ephemeral,
+}
+
+// Rides along with a message through rendering and export. `name` uses the
+// message's own name field; the cache marker and tags travel in
+// `additional_kwargs`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_control: Option<CacheControl>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tags: BTreeMap<String, String>,
+}
+
+impl MessageMetadata {
+    pub const CACHE_CONTROL_KEY: &'static str = "cache_control";
+    pub const TAGS_KEY: &'static str = "tags";
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn with_cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.cache_control = Some(cache_control);
+        self
+    }
+
+    pub fn with_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.tags.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.name.is_none() && self.cache_control.is_none() && self.tags.is_empty()
+    }
+
+    pub fn of(message: &MessageEnum) -> Self {
+        let additional_kwargs = message.additional_kwargs();
+        MessageMetadata {
+            name: message.name().map(str::to_string),
+            cache_control: additional_kwargs
+                .get(Self::CACHE_CONTROL_KEY)
+                .and_then(|json| serde_json::from_str(json).ok()),
+            tags: additional_kwargs
+                .get(Self::TAGS_KEY)
+                .and_then(|json| serde_json::from_str(json).ok())
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn apply(&self, message: Arc<MessageEnum>) -> Arc<MessageEnum> {
+        if self.is_empty() {
+            return message;
+        }
+
+        let mut message = Arc::unwrap_or_clone(message);
+        match &mut message {
+            MessageEnum::System(system) => self.apply_to_fields(&mut system.base),
+            MessageEnum::Human(human) => self.apply_to_fields(&mut human.base),
+            MessageEnum::Ai(ai) => self.apply_to_fields(&mut ai.base),
+            // Tool messages keep their fields private, so they are rebuilt.
+            MessageEnum::Tool(tool) => {
+                let mut fields = BaseMessageFields {
+                    content: tool.content().to_string(),
+                    example: tool.is_example(),
+                    message_type: *tool.message_type(),
+                    additional_kwargs: tool.additional_kwargs().clone(),
+                    response_metadata: tool.response_metadata().clone(),
+                    id: tool.id().map(str::to_string),
+                    name: tool.name().map(str::to_string),
+                };
+                self.apply_to_fields(&mut fields);
+                *tool = ToolMessage::new_with_base(
+                    tool.tool_call_id().to_string(),
+                    tool.artifact().clone(),
+                    tool.status().clone(),
+                    fields,
+                );
+            }
+        }
+        Arc::new(message)
+    }
+
+    fn apply_to_fields(&self, fields: &mut BaseMessageFields) {
+        if let Some(name) = &self.name {
+            fields.name = Some(name.clone());
+        }
+        if let Some(cache_control) = &self.cache_control {
+            fields.additional_kwargs.insert(
+                Self::CACHE_CONTROL_KEY.to_string(),
+                serde_json::to_string(cache_control).expect("cache control serializes"),
+            );
+        }
+        if !self.tags.is_empty() {
+            fields.additional_kwargs.insert(
+                Self::TAGS_KEY.to_string(),
+                serde_json::to_string(&self.tags).expect("tags serialize"),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::tool_message::ToolStatus;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_round_trips_through_messages() {
+        let metadata = MessageMetadata::new()
+            .with_name("alice")
+            .with_cache_control(CacheControl::Ephemeral)
+            .with_tag("source", "crm");
+
+        let tool = Arc::new(MessageEnum::Tool(ToolMessage::new(
+            "42",
+            "call_1".to_string(),
+            None,
+            ToolStatus::Success,
+        )));
+        let tagged = metadata.apply(tool);
+        assert_eq!(MessageMetadata::of(&tagged), metadata);
+        assert_eq!(tagged.content(), "42");
+        assert!(
+            matches!(tagged.as_ref(), MessageEnum::Tool(tool) if tool.tool_call_id() == "call_1")
+        );
+
+        assert_eq!(
+            serde_json::to_value(&metadata).unwrap(),
+            serde_json::json!({
+                "name": "alice",
+                "cache_control": {"type": "ephemeral"},
+                "tags": {"source": "crm"},
+            })
+        );
+    }
+}
@@ -1,12 +1,157 @@
-use serde::{Deserialize, Serialize};
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Arc,
+};
 
-use crate::{extract_placeholder_variable, TemplateError};
+use messageforge::{BaseMessage, MessageEnum};
+use serde::{
+    de::{self, DeserializeSeed, IgnoredAny, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+
+use crate::{
+    extract_placeholder_variable, EstimatedTokenCounter, MessageSource, Role, RoleAliases,
+    Summarizer, TemplateError, TokenCounter,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaceholderLimits {
+    pub max_bytes: usize,
+    pub max_messages: usize,
+}
+
+impl Default for PlaceholderLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: Self::DEFAULT_MAX_BYTES,
+            max_messages: Self::DEFAULT_MAX_MESSAGES,
+        }
+    }
+}
+
+impl PlaceholderLimits {
+    pub const DEFAULT_MAX_BYTES: usize = 8 * 1024 * 1024;
+    pub const DEFAULT_MAX_MESSAGES: usize = 10_000;
+
+    pub fn new(max_bytes: usize, max_messages: usize) -> Self {
+        Self {
+            max_bytes,
+            max_messages,
+        }
+    }
+
+    pub fn unlimited() -> Self {
+        Self::new(usize::MAX, usize::MAX)
+    }
+
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// Which end of an over-long history survives the `n_messages` and
+// `max_tokens` cut. `Tail` keeps the most recent turns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TruncationSide {
+    Head,
+    #[default]
+    Tail,
+}
+
+impl TruncationSide {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+// The counter is runtime-only configuration: it is not serialized and does
+// not take part in equality, so a deserialized placeholder with `max_tokens`
+// falls back to `EstimatedTokenCounter`.
+#[derive(Clone, Default)]
+struct CounterHook(Option<Arc<dyn TokenCounter>>);
+
+impl fmt::Debug for CounterHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            if self.0.is_some() {
+                "Custom"
+            } else {
+                "Estimated"
+            }
+        )
+    }
+}
+
+impl PartialEq for CounterHook {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for CounterHook {}
+
+// Like the counter, an attached message source is runtime-only state.
+#[derive(Clone, Default)]
+struct SourceHook(Option<Arc<dyn MessageSource>>);
+
+impl fmt::Debug for SourceHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", if self.0.is_some() { "Attached" } else { "None" })
+    }
+}
+
+impl PartialEq for SourceHook {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SourceHook {}
+
+#[derive(Clone, Default)]
+struct SummarizerHook(Option<(Arc<dyn Summarizer>, Role)>);
+
+impl fmt::Debug for SummarizerHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some((_, role)) => write!(f, "Summarize({})", role),
+            None => write!(f, "None"),
+        }
+    }
+}
+
+impl PartialEq for SummarizerHook {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for SummarizerHook {}
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MessagesPlaceholder {
     variable_name: String,
     optional: bool,
     n_messages: usize,
+    #[serde(default, skip_serializing_if = "PlaceholderLimits::is_default")]
+    limits: PlaceholderLimits,
+    #[serde(default, skip_serializing_if = "TruncationSide::is_default")]
+    truncation: TruncationSide,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<usize>,
+    #[serde(default, skip_serializing_if = "RoleAliases::is_empty")]
+    role_aliases: RoleAliases,
+    #[serde(skip)]
+    token_counter: CounterHook,
+    #[serde(skip)]
+    source: SourceHook,
+    #[serde(skip)]
+    summarizer: SummarizerHook,
 }
 
 impl MessagesPlaceholder {
@@ -25,9 +170,58 @@ impl MessagesPlaceholder {
             } else {
                 n_messages
             },
+            limits: PlaceholderLimits::default(),
+            truncation: TruncationSide::default(),
+            max_tokens: None,
+            role_aliases: RoleAliases::default(),
+            token_counter: CounterHook::default(),
+            source: SourceHook::default(),
+            summarizer: SummarizerHook::default(),
         }
     }
 
+    pub fn with_limits(mut self, limits: PlaceholderLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn with_truncation(mut self, truncation: TruncationSide) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    pub fn with_role_aliases(mut self, role_aliases: RoleAliases) -> Self {
+        self.role_aliases = role_aliases;
+        self
+    }
+
+    pub fn with_token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.token_counter = CounterHook(Some(Arc::new(counter)));
+        self
+    }
+
+    pub fn with_source(mut self, source: impl MessageSource + 'static) -> Self {
+        self.source = SourceHook(Some(Arc::new(source)));
+        self
+    }
+
+    // Messages cut by `n_messages` or `max_tokens` are handed to the summarizer
+    // and replaced by one `role` message on the truncated side. The summary
+    // itself is not counted against `max_tokens`.
+    pub fn with_summarizer(mut self, summarizer: impl Summarizer + 'static, role: Role) -> Self {
+        self.summarizer = SummarizerHook(Some((Arc::new(summarizer), role)));
+        self
+    }
+
+    pub fn has_source(&self) -> bool {
+        self.source.0.is_some()
+    }
+
     pub fn variable_name(&self) -> &str {
         &self.variable_name
     }
@@ -39,6 +233,238 @@ impl MessagesPlaceholder {
     pub fn n_messages(&self) -> usize {
         self.n_messages
     }
+
+    pub fn limits(&self) -> PlaceholderLimits {
+        self.limits
+    }
+
+    pub fn truncation(&self) -> TruncationSide {
+        self.truncation
+    }
+
+    pub fn max_tokens(&self) -> Option<usize> {
+        self.max_tokens
+    }
+
+    pub fn role_aliases(&self) -> &RoleAliases {
+        &self.role_aliases
+    }
+
+    // How many messages of `window`, counted from the kept side, fit the
+    // token budget.
+    fn within_token_budget(&self, window: &[Arc<MessageEnum>]) -> usize {
+        let Some(max_tokens) = self.max_tokens else {
+            return window.len();
+        };
+        let counter: &dyn TokenCounter = match &self.token_counter.0 {
+            Some(counter) => counter.as_ref(),
+            None => &EstimatedTokenCounter,
+        };
+
+        let mut used = 0;
+        let mut fits = |message: &&Arc<MessageEnum>| {
+            used += counter.count_tokens(message.content());
+            used <= max_tokens
+        };
+
+        match self.truncation {
+            TruncationSide::Head => window.iter().take_while(&mut fits).count(),
+            TruncationSide::Tail => window.iter().rev().take_while(&mut fits).count(),
+        }
+    }
+
+    fn apply_window(
+        &self,
+        mut messages: Vec<Arc<MessageEnum>>,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let total = messages.len();
+        let windowed = total.min(self.n_messages);
+        let (mut kept, overflow) = match self.truncation {
+            TruncationSide::Head => {
+                let keep = self.within_token_budget(&messages[..windowed]);
+                let overflow = messages.split_off(keep);
+                (messages, overflow)
+            }
+            TruncationSide::Tail => {
+                let keep = self.within_token_budget(&messages[total - windowed..]);
+                let kept = messages.split_off(total - keep);
+                (kept, messages)
+            }
+        };
+
+        if let Some((summarizer, role)) = &self.summarizer.0
+            && !overflow.is_empty()
+        {
+            let summary = role.render_message(&summarizer.summarize(&overflow)?)?;
+            match self.truncation {
+                TruncationSide::Head => kept.push(summary),
+                TruncationSide::Tail => kept.insert(0, summary),
+            }
+        }
+
+        Ok(kept)
+    }
+
+    // Optional placeholders only render nothing when their variable is absent;
+    // a provided value is parsed like any other placeholder's. An explicit
+    // variable takes precedence over an attached message source.
+    pub fn format_messages(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        match (variables.get(self.variable_name.as_str()), &self.source.0) {
+            (Some(payload), _) => self.parse_messages(payload),
+            (None, Some(source)) => self.select_messages(&source.messages()),
+            (None, None) if self.optional => Ok(Vec::new()),
+            (None, None) => Err(TemplateError::MissingVariable(self.variable_name.clone())),
+        }
+    }
+
+    // Applies the same limits and windowing as `parse_messages` to messages the
+    // caller already holds, skipping the JSON round trip.
+    pub fn select_messages(
+        &self,
+        messages: &[Arc<MessageEnum>],
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        if messages.len() > self.limits.max_messages {
+            return Err(TemplateError::PlaceholderLimitExceeded(format!(
+                "'{}' contains more than {} messages",
+                self.variable_name, self.limits.max_messages
+            )));
+        }
+
+        self.apply_window(messages.to_vec())
+    }
+
+    pub fn parse_messages(&self, payload: &str) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        if payload.len() > self.limits.max_bytes {
+            return Err(TemplateError::PlaceholderLimitExceeded(format!(
+                "'{}' payload is {} bytes, the limit is {} bytes",
+                self.variable_name,
+                payload.len(),
+                self.limits.max_bytes
+            )));
+        }
+
+        let exceeded = Cell::new(false);
+        // With a summarizer the overflow is needed, so nothing is windowed
+        // while decoding.
+        let visitor = MessagesVisitor {
+            n_messages: if self.summarizer.0.is_some() {
+                usize::MAX
+            } else {
+                self.n_messages
+            },
+            truncation: self.truncation,
+            max_messages: self.limits.max_messages,
+            role_aliases: &self.role_aliases,
+            exceeded: &exceeded,
+        };
+
+        let mut deserializer = serde_json::Deserializer::from_str(payload);
+        let messages = visitor
+            .deserialize(&mut deserializer)
+            .and_then(|messages| deserializer.end().map(|_| messages));
+
+        match messages {
+            Ok(messages) => self.apply_window(messages),
+            Err(_) if exceeded.get() => Err(TemplateError::PlaceholderLimitExceeded(format!(
+                "'{}' contains more than {} messages",
+                self.variable_name, self.limits.max_messages
+            ))),
+            Err(e) => Err(TemplateError::MalformedTemplate(
+                format!("Failed to deserialize placeholder: {}", e),
+                None,
+            )),
+        }
+    }
+}
+
+struct MessagesVisitor<'a> {
+    n_messages: usize,
+    truncation: TruncationSide,
+    max_messages: usize,
+    role_aliases: &'a RoleAliases,
+    exceeded: &'a Cell<bool>,
+}
+
+// Rewrites the `role` of each message to the name messageforge expects before
+// decoding it. OpenAI tool results carry no `status`, so one is assumed.
+struct MessageSeed<'a>(&'a RoleAliases);
+
+impl<'de> DeserializeSeed<'de> for MessageSeed<'_> {
+    type Value = MessageEnum;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let mut message = serde_json::Map::<String, Value>::deserialize(deserializer)?;
+        let role = message
+            .get("role")
+            .and_then(Value::as_str)
+            .and_then(|name| self.0.resolve(name).ok());
+
+        if let Some(role) = role {
+            message.insert("role".to_string(), Value::from(role.as_str()));
+            if role == Role::Tool {
+                message
+                    .entry("status")
+                    .or_insert_with(|| Value::from("Success"));
+            }
+        }
+
+        MessageEnum::deserialize(Value::Object(message)).map_err(de::Error::custom)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for MessagesVisitor<'_> {
+    type Value = Vec<Arc<MessageEnum>>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for MessagesVisitor<'_> {
+    type Value = Vec<Arc<MessageEnum>>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON array of messages")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut messages =
+            VecDeque::with_capacity(seq.size_hint().unwrap_or(0).min(self.n_messages));
+        let mut count = 0;
+
+        loop {
+            // Head stops decoding once full; Tail keeps a window of the last
+            // `n_messages`, evicting the oldest as newer ones arrive.
+            let more =
+                if messages.len() < self.n_messages || self.truncation == TruncationSide::Tail {
+                    match seq.next_element_seed(MessageSeed(self.role_aliases))? {
+                        Some(message) => {
+                            if messages.len() == self.n_messages {
+                                messages.pop_front();
+                            }
+                            messages.push_back(Arc::new(message));
+                            true
+                        }
+                        None => false,
+                    }
+                } else {
+                    seq.next_element::<IgnoredAny>()?.is_some()
+                };
+
+            if !more {
+                return Ok(messages.into());
+            }
+
+            count += 1;
+            if count > self.max_messages {
+                self.exceeded.set(true);
+                return Err(de::Error::custom("too many messages"));
+            }
+        }
+    }
 }
 
 impl TryFrom<&str> for MessagesPlaceholder {
@@ -117,7 +543,7 @@ mod tests {
         assert!(result.is_err());
         if let Err(e) = result {
             match e {
-                TemplateError::MalformedTemplate(msg) => {
+                TemplateError::MalformedTemplate(msg, _) => {
                     assert_eq!(
                         msg,
                         "Template must contain exactly one placeholder variable."
@@ -138,7 +564,7 @@ mod tests {
         assert!(result.is_err());
         if let Err(e) = result {
             match e {
-                TemplateError::MalformedTemplate(msg) => {
+                TemplateError::MalformedTemplate(msg, _) => {
                     assert_eq!(
                         msg,
                         "Template must contain exactly one placeholder variable."
@@ -159,7 +585,7 @@ mod tests {
         assert!(result.is_err());
         if let Err(e) = result {
             match e {
-                TemplateError::MalformedTemplate(msg) => {
+                TemplateError::MalformedTemplate(msg, _) => {
                     assert_eq!(
                         msg,
                         "Template must contain exactly one placeholder variable."
@@ -172,6 +598,219 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_messages_takes_last_n_by_default() {
+        let placeholder = MessagesPlaceholder::with_options("history".to_string(), false, 2);
+        let payload = r#"[
+            {"role": "human", "content": "one"},
+            {"role": "ai", "content": "two"},
+            {"role": "human", "content": "three"}
+        ]"#;
+
+        assert_eq!(placeholder.truncation(), TruncationSide::Tail);
+        let messages = placeholder.parse_messages(payload).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content(), "two");
+        assert_eq!(messages[1].content(), "three");
+    }
+
+    #[test]
+    fn test_parse_messages_takes_first_n_with_head() {
+        let placeholder = MessagesPlaceholder::with_options("history".to_string(), false, 2)
+            .with_truncation(TruncationSide::Head);
+        let payload = r#"[
+            {"role": "human", "content": "one"},
+            {"role": "ai", "content": "two"},
+            {"role": "human", "content": "three"}
+        ]"#;
+
+        let messages = placeholder.parse_messages(payload).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content(), "two");
+    }
+
+    #[test]
+    fn test_parse_messages_stops_at_token_budget() {
+        let payload = r#"[
+            {"role": "human", "content": "one two three"},
+            {"role": "ai", "content": "four five"},
+            {"role": "human", "content": "six"}
+        ]"#;
+        let placeholder = MessagesPlaceholder::new("history".to_string())
+            .with_max_tokens(5)
+            .with_token_counter(|text: &str| text.split_whitespace().count());
+
+        let messages = placeholder.parse_messages(payload).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content(), "four five");
+
+        let head = placeholder.clone().with_truncation(TruncationSide::Head);
+        let messages = head.parse_messages(payload).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[1].content(), "four five");
+
+        let estimated = MessagesPlaceholder::new("history".to_string()).with_max_tokens(2);
+        assert_eq!(estimated.parse_messages(payload).unwrap().len(), 1);
+
+        let serialized = serde_json::to_string(&placeholder).unwrap();
+        assert!(serialized.ends_with(r#""max_tokens":5}"#));
+        let deserialized: MessagesPlaceholder = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, placeholder);
+        assert_eq!(deserialized.max_tokens(), Some(5));
+    }
+
+    #[test]
+    fn test_summarizer_replaces_overflow() {
+        let payload = r#"[
+            {"role": "human", "content": "one"},
+            {"role": "ai", "content": "two"},
+            {"role": "human", "content": "three"}
+        ]"#;
+        let summarize = |overflow: &[Arc<MessageEnum>]| {
+            let contents: Vec<&str> = overflow.iter().map(|m| m.content()).collect();
+            Ok(format!("Earlier: {}", contents.join(", ")))
+        };
+        let placeholder = MessagesPlaceholder::with_options("history".to_string(), false, 1)
+            .with_summarizer(summarize, Role::System);
+
+        let messages = placeholder.parse_messages(payload).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[0].as_ref(), MessageEnum::System(_)));
+        assert_eq!(messages[0].content(), "Earlier: one, two");
+        assert_eq!(messages[1].content(), "three");
+
+        let head = placeholder
+            .with_truncation(TruncationSide::Head)
+            .with_summarizer(summarize, Role::Ai);
+        let messages = head.select_messages(&messages).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(messages[1].as_ref(), MessageEnum::Ai(_)));
+        assert_eq!(messages[1].content(), "Earlier: three");
+
+        let within = MessagesPlaceholder::new("history".to_string())
+            .with_summarizer(|_: &[Arc<MessageEnum>]| unreachable!(), Role::System);
+        assert_eq!(within.parse_messages(payload).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_summarizer_errors_propagate() {
+        let payload =
+            r#"[{"role": "human", "content": "one two"}, {"role": "ai", "content": "three"}]"#;
+        let failing = |_: &[Arc<MessageEnum>]| -> Result<String, TemplateError> {
+            Err(TemplateError::RuntimeError("summarizer down".to_string()))
+        };
+        let placeholder = MessagesPlaceholder::new("history".to_string())
+            .with_max_tokens(1)
+            .with_token_counter(|text: &str| text.split_whitespace().count())
+            .with_summarizer(failing, Role::System);
+        assert!(matches!(
+            placeholder.parse_messages(payload),
+            Err(TemplateError::RuntimeError(_))
+        ));
+
+        let placeholder = placeholder.with_summarizer(
+            |_: &[Arc<MessageEnum>]| Ok("summary".to_string()),
+            Role::Placeholder,
+        );
+        assert!(placeholder.parse_messages(payload).is_err());
+    }
+
+    #[test]
+    fn test_parse_messages_accepts_openai_roles() {
+        let payload = r#"[
+            {"role": "developer", "content": "Be terse."},
+            {"role": "user", "content": "Weather?"},
+            {"role": "assistant", "content": "Checking."},
+            {"role": "tool", "content": "Sunny", "tool_call_id": "call_1"},
+            {"role": "model", "content": "Sunny."}
+        ]"#;
+        let placeholder = MessagesPlaceholder::new("history".to_string());
+        assert!(placeholder.parse_messages(payload).is_err());
+
+        let placeholder =
+            placeholder.with_role_aliases(RoleAliases::new().with_alias("model", Role::Ai));
+        let messages = placeholder.parse_messages(payload).unwrap();
+        let roles: Vec<&str> = messages.iter().map(|m| m.message_type().as_str()).collect();
+        assert_eq!(roles, vec!["system", "human", "ai", "tool", "ai"]);
+
+        let serialized = serde_json::to_string(&placeholder).unwrap();
+        assert!(serialized.contains(r#""role_aliases":{"aliases":{"model":"Ai"}}"#));
+        let deserialized: MessagesPlaceholder = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.role_aliases(), placeholder.role_aliases());
+    }
+
+    #[test]
+    fn test_parse_messages_rejects_oversized_payload() {
+        let placeholder = MessagesPlaceholder::new("history".to_string())
+            .with_limits(PlaceholderLimits::new(16, 100));
+        let payload = r#"[{"role": "human", "content": "this is far too long"}]"#;
+
+        let error = placeholder.parse_messages(payload).unwrap_err();
+        assert!(matches!(error, TemplateError::PlaceholderLimitExceeded(_)));
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Placeholder limit exceeded: 'history' payload is {} bytes, the limit is 16 bytes",
+                payload.len()
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_messages_rejects_too_many_messages() {
+        let placeholder = MessagesPlaceholder::with_options("history".to_string(), false, 1)
+            .with_limits(PlaceholderLimits::new(usize::MAX, 2));
+        let payload = r#"[
+            {"role": "human", "content": "one"},
+            {"role": "ai", "content": "two"},
+            {"role": "human", "content": "three"}
+        ]"#;
+
+        let error = placeholder.parse_messages(payload).unwrap_err();
+        assert!(error.matches(&TemplateError::PlaceholderLimitExceeded(
+            "'history' contains more than 2 messages".to_string()
+        )));
+    }
+
+    #[test]
+    fn test_parse_messages_large_history() {
+        let message = r#"{"role": "human", "content": "hello"}"#;
+        let payload = format!("[{}]", vec![message; 5_000].join(","));
+        let placeholder = MessagesPlaceholder::with_options("history".to_string(), false, 3);
+
+        let messages = placeholder.parse_messages(&payload).unwrap();
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_messages_malformed_payload() {
+        let placeholder = MessagesPlaceholder::new("history".to_string());
+
+        for payload in ["not json", r#"{"role": "human"}"#, "[] trailing"] {
+            let error = placeholder.parse_messages(payload).unwrap_err();
+            assert!(
+                matches!(&error, TemplateError::MalformedTemplate(msg, _) if msg.starts_with("Failed to deserialize placeholder")),
+                "unexpected error for {:?}: {:?}",
+                payload,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_limits_skipped_in_serialization_by_default() {
+        let placeholder = MessagesPlaceholder::new("history".to_string());
+        assert_eq!(
+            serde_json::to_string(&placeholder).unwrap(),
+            r#"{"variable_name":"history","optional":false,"n_messages":100}"#
+        );
+
+        let limited = placeholder.with_limits(PlaceholderLimits::new(1024, 10));
+        let serialized = serde_json::to_string(&limited).unwrap();
+        let deserialized: MessagesPlaceholder = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.limits(), PlaceholderLimits::new(1024, 10));
+    }
+
     #[test]
     fn test_tryfrom_valid_optional_placeholder() {
         let template = "{history}";
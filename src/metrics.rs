@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptMetrics {
+    pub variable_count: usize,
+    pub message_count: usize,
+    pub few_shot_example_count: usize,
+    pub nesting_depth: usize,
+    pub estimated_tokens: usize,
+}
+
+pub const CHARS_PER_TOKEN: usize = 4;
+
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+pub trait TokenCounter: Send + Sync {
+    fn count_tokens(&self, text: &str) -> usize;
+}
+
+impl<F> TokenCounter for F
+where
+    F: Fn(&str) -> usize + Send + Sync,
+{
+    fn count_tokens(&self, text: &str) -> usize {
+        self(text)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EstimatedTokenCounter;
+
+impl TokenCounter for EstimatedTokenCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        estimate_tokens(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, FewShotPrompt, Human, Placeholder, System};
+    use crate::{chats, examples, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Template};
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abc"), 1);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("日本語です"), 2);
+    }
+
+    #[test]
+    fn test_template_metrics() {
+        let template = Template::new("Hello, {name}! Your order is {order_id}.").unwrap();
+        let metrics = template.metrics();
+
+        assert_eq!(metrics.variable_count, 2);
+        assert_eq!(metrics.message_count, 0);
+        assert_eq!(metrics.few_shot_example_count, 0);
+        assert_eq!(metrics.nesting_depth, 0);
+        assert_eq!(metrics.estimated_tokens, 10);
+    }
+
+    #[test]
+    fn test_chat_template_metrics() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful assistant.",
+            Placeholder = "{history}",
+            Human = "Hello {name}, today is {day}.",
+            Ai = "Hi {name}!",
+        ))
+        .unwrap();
+
+        let metrics = chat_template.metrics();
+        assert_eq!(metrics.variable_count, 3);
+        assert_eq!(metrics.message_count, 4);
+        assert_eq!(metrics.few_shot_example_count, 0);
+        assert_eq!(metrics.nesting_depth, 1);
+        assert_eq!(metrics.estimated_tokens, 7 + 8 + 3);
+    }
+
+    #[test]
+    fn test_chat_template_metrics_with_few_shot_prompt() {
+        let examples = examples!(
+            ("{input}: What is 2+2?", "{output}: 4"),
+            ("{input}: What is 2+3?", "{output}: 5")
+        );
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+        let few_shot_chat_template =
+            FewShotChatTemplate::new(FewShotTemplate::new(examples), example_prompt);
+
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are a helpful AI Assistant.",
+            FewShotPrompt = few_shot_chat_template.to_string(),
+            Human = "{input}",
+        ))
+        .unwrap();
+
+        let metrics = chat_template.metrics();
+        assert_eq!(metrics.variable_count, 1);
+        assert_eq!(metrics.message_count, 3);
+        assert_eq!(metrics.few_shot_example_count, 2);
+        assert_eq!(metrics.nesting_depth, 3);
+    }
+
+    #[test]
+    fn test_metrics_serialization() {
+        let metrics = Template::new("{a} {b}").unwrap().metrics();
+        let serialized = serde_json::to_string(&metrics).unwrap();
+        assert_eq!(
+            serialized,
+            r#"{"variable_count":2,"message_count":0,"few_shot_example_count":0,"nesting_depth":0,"estimated_tokens":2}"#
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::RoleMap;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelProfile {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    family: Option<String>,
+    #[serde(default)]
+    role_map: RoleMap,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    variables: BTreeMap<String, String>,
+}
+
+impl ModelProfile {
+    pub const MODEL_NAME_VARIABLE: &'static str = "model_name";
+
+    pub fn new(name: impl Into<String>) -> Self {
+        ModelProfile {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_family(mut self, family: impl Into<String>) -> Self {
+        self.family = Some(family.into());
+        self
+    }
+
+    pub fn with_role_map(mut self, role_map: RoleMap) -> Self {
+        self.role_map = role_map;
+        self
+    }
+
+    pub fn with_variable(mut self, name: impl Into<String>, value: impl ToString) -> Self {
+        self.variables.insert(name.into(), value.to_string());
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn family(&self) -> Option<&str> {
+        self.family.as_deref()
+    }
+
+    pub fn role_map(&self) -> &RoleMap {
+        &self.role_map
+    }
+
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&str> {
+        match self.variables.get(name) {
+            Some(value) => Some(value),
+            None if name == Self::MODEL_NAME_VARIABLE => Some(&self.name),
+            None => None,
+        }
+    }
+
+    pub fn resolve<'a>(
+        &'a self,
+        variables: &HashMap<&'a str, &'a str>,
+    ) -> HashMap<&'a str, &'a str> {
+        let mut resolved: HashMap<&str, &str> = self
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        resolved
+            .entry(Self::MODEL_NAME_VARIABLE)
+            .or_insert(self.name.as_str());
+        resolved.extend(variables.iter().map(|(&name, &value)| (name, value)));
+        resolved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vars;
+
+    #[test]
+    fn test_profile_variables() {
+        let profile = ModelProfile::new("gpt-4o").with_variable("max_words", 150);
+
+        assert_eq!(profile.variable("max_words"), Some("150"));
+        assert_eq!(profile.variable("model_name"), Some("gpt-4o"));
+        assert_eq!(profile.variable("temperature"), None);
+
+        let overridden = ModelProfile::new("gpt-4o").with_variable("model_name", "GPT-4o");
+        assert_eq!(overridden.variable("model_name"), Some("GPT-4o"));
+    }
+
+    #[test]
+    fn test_resolve_prefers_caller_values() {
+        let profile = ModelProfile::new("llama-3-8b").with_variable("max_words", 50);
+        let resolved = profile.resolve(&vars!(max_words = "20", question = "Why?"));
+
+        assert_eq!(resolved["max_words"], "20");
+        assert_eq!(resolved["model_name"], "llama-3-8b");
+        assert_eq!(resolved["question"], "Why?");
+    }
+
+    #[test]
+    fn test_profile_deserialization() {
+        let profile: ModelProfile = toml::from_str(
+            r#"
+            name = "small-local"
+            family = "llama"
+
+            [variables]
+            max_words = "40"
+
+            [role_map]
+            system = { role = "user", prefix = "Instructions: " }
+            human = { role = "user" }
+            ai = { role = "assistant" }
+            tool = { role = "user", prefix = "Tool result: " }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(profile.name(), "small-local");
+        assert_eq!(profile.family(), Some("llama"));
+        assert_eq!(profile.variable("max_words"), Some("40"));
+        assert_eq!(profile.role_map().system.role, "user");
+    }
+}
@@ -0,0 +1,365 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::{tool_message::ToolStatus, MessageEnum, ToolMessage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ChatTemplate, ExampleMetadata, Formattable, MessageLike, Role, Templatable, TemplateError,
+    ToolCall,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExampleTurn {
+    pub role: Role,
+    pub content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+}
+
+impl ExampleTurn {
+    pub const TOOL_CALL_ID_KEY: &'static str = "tool_call_id";
+
+    pub fn new(role: Role, content: impl Into<String>) -> Self {
+        ExampleTurn {
+            role,
+            content: content.into(),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+        }
+    }
+
+    pub fn human(content: impl Into<String>) -> Self {
+        Self::new(Role::Human, content)
+    }
+
+    pub fn ai(content: impl Into<String>) -> Self {
+        Self::new(Role::Ai, content)
+    }
+
+    pub fn system(content: impl Into<String>) -> Self {
+        Self::new(Role::System, content)
+    }
+
+    pub fn tool_call(content: impl Into<String>, tool_call_id: impl Into<String>) -> Self {
+        Self::new(Role::Ai, content).with_tool_call_id(tool_call_id)
+    }
+
+    pub fn tool_result(content: impl Into<String>, tool_call_id: impl Into<String>) -> Self {
+        Self::new(Role::Tool, content).with_tool_call_id(tool_call_id)
+    }
+
+    pub fn tool_use(tool_calls: Vec<ToolCall>) -> Self {
+        Self::new(Role::Ai, "").with_tool_calls(tool_calls)
+    }
+
+    pub fn with_tool_calls(mut self, tool_calls: Vec<ToolCall>) -> Self {
+        self.tool_calls = tool_calls;
+        self
+    }
+
+    pub fn with_tool_call_id(mut self, tool_call_id: impl Into<String>) -> Self {
+        self.tool_call_id = Some(tool_call_id.into());
+        self
+    }
+
+    fn to_message(&self, content: &str) -> Result<Arc<MessageEnum>, TemplateError> {
+        let message = match (&self.role, self.tool_call_id.as_deref()) {
+            (Role::Tool, Some(tool_call_id)) => MessageEnum::Tool(ToolMessage::new(
+                content,
+                tool_call_id.to_string(),
+                None,
+                ToolStatus::Success,
+            )),
+            (Role::Tool, None) => {
+                return Err(TemplateError::MalformedTemplate(
+                    "Tool turns in a multi-turn example need a tool_call_id".to_string(),
+                    None,
+                ))
+            }
+            (role, tool_call_id) => {
+                let mut message = Arc::unwrap_or_clone(role.render_message(content)?);
+                if let MessageEnum::Ai(ai) = &mut message {
+                    if let Some(tool_call_id) = tool_call_id {
+                        ai.base
+                            .additional_kwargs
+                            .insert(Self::TOOL_CALL_ID_KEY.to_string(), tool_call_id.to_string());
+                    }
+                    if !self.tool_calls.is_empty() {
+                        ai.base.additional_kwargs.insert(
+                            ToolCall::KWARGS_KEY.to_string(),
+                            ToolCall::to_kwarg(&self.tool_calls),
+                        );
+                    }
+                }
+                message
+            }
+        };
+
+        Ok(Arc::new(message))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MultiTurnExample {
+    turns: Vec<ExampleTurn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    example_prompt: Option<Arc<ChatTemplate>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<ExampleMetadata>,
+}
+
+impl MultiTurnExample {
+    pub fn new(turns: Vec<ExampleTurn>) -> Self {
+        MultiTurnExample {
+            turns,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_example_prompt(mut self, example_prompt: ChatTemplate) -> Self {
+        self.example_prompt = Some(Arc::new(example_prompt));
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: ExampleMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn turns(&self) -> &[ExampleTurn] {
+        &self.turns
+    }
+
+    pub fn example_prompt(&self) -> Option<&ChatTemplate> {
+        self.example_prompt.as_deref()
+    }
+
+    pub fn metadata(&self) -> Option<&ExampleMetadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn is_negative_example(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(ExampleMetadata::is_negative)
+    }
+
+    pub fn format_messages(
+        &self,
+        default_prompt: &ChatTemplate,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let example_prompt = self.example_prompt().unwrap_or(default_prompt);
+
+        self.turns
+            .iter()
+            .map(|turn| {
+                let template =
+                    example_prompt
+                        .messages
+                        .iter()
+                        .find_map(|message| match message.inner() {
+                            MessageLike::RolePromptTemplate(role, template)
+                                if *role == turn.role =>
+                            {
+                                Some(template)
+                            }
+                            _ => None,
+                        });
+
+                let content = match template {
+                    _ if !turn.tool_calls.is_empty() => turn.content.clone(),
+                    Some(template) => match template.input_variables().first() {
+                        Some(variable) => template
+                            .format(&HashMap::from([(variable.as_str(), turn.content.as_str())]))?,
+                        None => template.template().to_string(),
+                    },
+                    None => turn.content.clone(),
+                };
+
+                turn.to_message(&content)
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolUseExample {
+    request: String,
+    calls: Vec<(ToolCall, String)>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    answer: String,
+}
+
+impl ToolUseExample {
+    pub fn new(request: impl Into<String>) -> Self {
+        ToolUseExample {
+            request: request.into(),
+            calls: Vec::new(),
+            answer: String::new(),
+        }
+    }
+
+    pub fn with_call(mut self, call: ToolCall, result: impl Into<String>) -> Self {
+        self.calls.push((call, result.into()));
+        self
+    }
+
+    pub fn with_answer(mut self, answer: impl Into<String>) -> Self {
+        self.answer = answer.into();
+        self
+    }
+
+    pub fn request(&self) -> &str {
+        &self.request
+    }
+
+    pub fn calls(&self) -> &[(ToolCall, String)] {
+        &self.calls
+    }
+
+    pub fn answer(&self) -> &str {
+        &self.answer
+    }
+}
+
+impl From<ToolUseExample> for MultiTurnExample {
+    fn from(example: ToolUseExample) -> Self {
+        let mut turns = vec![ExampleTurn::human(example.request)];
+        if !example.calls.is_empty() {
+            turns.push(ExampleTurn::tool_use(
+                example.calls.iter().map(|(call, _)| call.clone()).collect(),
+            ));
+        }
+        for (call, result) in example.calls {
+            turns.push(ExampleTurn::tool_result(result, call.id));
+        }
+        if !example.answer.is_empty() {
+            turns.push(ExampleTurn::ai(example.answer));
+        }
+        MultiTurnExample::new(turns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::BaseMessage;
+
+    use super::*;
+    use crate::chats;
+    use crate::Role::{Ai, Human};
+
+    fn weather_example() -> MultiTurnExample {
+        MultiTurnExample::new(vec![
+            ExampleTurn::human("What's the weather in Paris?"),
+            ExampleTurn::tool_call(r#"get_weather({"city": "Paris"})"#, "call_1"),
+            ExampleTurn::tool_result("18°C, cloudy", "call_1"),
+            ExampleTurn::ai("It's 18°C and cloudy in Paris."),
+            ExampleTurn::human("And tomorrow?"),
+            ExampleTurn::ai("I can only check current conditions."),
+        ])
+    }
+
+    #[test]
+    fn test_multi_turn_example_repeats_example_prompt() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "Q: {input}", Ai = "A: {output}")).unwrap();
+        let messages = weather_example().format_messages(&example_prompt).unwrap();
+
+        assert_eq!(messages.len(), 6);
+        assert_eq!(messages[0].content(), "Q: What's the weather in Paris?");
+        assert_eq!(
+            messages[1].content(),
+            r#"A: get_weather({"city": "Paris"})"#
+        );
+        assert_eq!(messages[4].content(), "Q: And tomorrow?");
+
+        match messages[2].as_ref() {
+            MessageEnum::Tool(tool) => {
+                assert_eq!(tool.tool_call_id(), "call_1");
+                assert_eq!(tool.content(), "18°C, cloudy");
+            }
+            other => panic!("Expected a tool message, got {:?}", other),
+        }
+        match messages[1].as_ref() {
+            MessageEnum::Ai(ai) => assert_eq!(
+                ai.additional_kwargs().get(ExampleTurn::TOOL_CALL_ID_KEY),
+                Some(&"call_1".to_string())
+            ),
+            other => panic!("Expected an ai message, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_multi_turn_example_with_own_prompt() {
+        let default_prompt =
+            ChatTemplate::from_messages(chats!(Human = "Q: {input}", Ai = "A: {output}")).unwrap();
+        let example =
+            MultiTurnExample::new(vec![ExampleTurn::human("Hola"), ExampleTurn::ai("Hello")])
+                .with_example_prompt(
+                    ChatTemplate::from_messages(chats!(
+                        Human = "Spanish: {text}",
+                        Ai = "English: {text}"
+                    ))
+                    .unwrap(),
+                );
+
+        let messages = example.format_messages(&default_prompt).unwrap();
+        assert_eq!(messages[0].content(), "Spanish: Hola");
+        assert_eq!(messages[1].content(), "English: Hello");
+    }
+
+    #[test]
+    fn test_tool_use_example_exports_native_tool_messages() {
+        let example_prompt =
+            ChatTemplate::from_messages(chats!(Human = "Q: {input}", Ai = "A: {output}")).unwrap();
+        let example: MultiTurnExample = ToolUseExample::new("Weather in Paris?")
+            .with_call(
+                ToolCall::new(
+                    "call_1",
+                    "get_weather",
+                    &serde_json::json!({"city": "Paris"}),
+                ),
+                "18°C",
+            )
+            .with_answer("It's 18°C.")
+            .into();
+
+        let messages = example.format_messages(&example_prompt).unwrap();
+        let exported = crate::RoleMap::openai().export(&messages);
+        assert_eq!(
+            exported.iter().map(|m| m.role.as_str()).collect::<Vec<_>>(),
+            vec!["user", "assistant", "tool", "assistant"]
+        );
+        assert_eq!(exported[1].content, "");
+        assert_eq!(exported[1].tool_calls[0].function.name, "get_weather");
+        assert_eq!(exported[2].tool_call_id.as_deref(), Some("call_1"));
+        assert_eq!(exported[3].content, "A: It's 18°C.");
+    }
+
+    #[test]
+    fn test_tool_turn_requires_call_id() {
+        let example = MultiTurnExample::new(vec![ExampleTurn::new(Role::Tool, "42")]);
+        assert!(matches!(
+            example.format_messages(&ChatTemplate {
+                messages: vec![],
+                prefill: None,
+                sensitive: false,
+            }),
+            Err(TemplateError::MalformedTemplate(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_multi_turn_example_serialization_round_trip() {
+        let example = weather_example().with_metadata(ExampleMetadata::new().with_label("weather"));
+        let json = serde_json::to_string(&example).unwrap();
+        let deserialized: MultiTurnExample = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(deserialized.turns(), example.turns());
+        assert_eq!(deserialized.metadata(), example.metadata());
+        assert!(deserialized.example_prompt().is_none());
+        assert!(!json.contains("example_prompt"));
+    }
+}
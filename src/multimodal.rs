@@ -0,0 +1,238 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::MessageEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{Formattable, Role, Templatable, Template, TemplateError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+// Serializes in OpenAI's content-part shape; base64 images travel as data URLs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+impl ContentPart {
+    pub const KWARGS_KEY: &'static str = "content_parts";
+
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentPart::Text { text: text.into() }
+    }
+
+    pub fn image_url(url: impl Into<String>) -> Self {
+        ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: None,
+            },
+        }
+    }
+
+    pub fn image_base64(media_type: &str, data: &str) -> Self {
+        Self::image_url(format!("data:{};base64,{}", media_type, data))
+    }
+
+    pub fn with_detail(self, detail: impl Into<String>) -> Self {
+        match self {
+            ContentPart::ImageUrl { image_url } => ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    detail: Some(detail.into()),
+                    ..image_url
+                },
+            },
+            text => text,
+        }
+    }
+
+    // The media type and payload of a base64 data URL.
+    pub fn base64_data(&self) -> Option<(&str, &str)> {
+        let ContentPart::ImageUrl { image_url } = self else {
+            return None;
+        };
+        let (media_type, data) = image_url.url.strip_prefix("data:")?.split_once(',')?;
+        Some((media_type.strip_suffix(";base64")?, data))
+    }
+
+    pub fn from_kwargs(additional_kwargs: &HashMap<String, String>) -> Vec<ContentPart> {
+        additional_kwargs
+            .get(Self::KWARGS_KEY)
+            .and_then(|json| serde_json::from_str(json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn to_kwarg(parts: &[ContentPart]) -> String {
+        serde_json::to_string(parts).expect("content parts serialize")
+    }
+
+    fn sources(&self) -> Vec<&str> {
+        match self {
+            ContentPart::Text { text } => vec![text],
+            ContentPart::ImageUrl { image_url } => vec![&image_url.url],
+        }
+    }
+
+    fn render(&self, variables: &HashMap<&str, &str>) -> Result<Self, TemplateError> {
+        let render = |source: &str| Template::new(source)?.format(variables);
+        Ok(match self {
+            ContentPart::Text { text } => ContentPart::text(render(text)?),
+            ContentPart::ImageUrl { image_url } => ContentPart::ImageUrl {
+                image_url: ImageUrl {
+                    url: render(&image_url.url)?,
+                    detail: image_url.detail.clone(),
+                },
+            },
+        })
+    }
+}
+
+// A message whose content is a list of parts. The rendered text parts also
+// form the plain message content, so text-only targets still see them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MultimodalTemplate {
+    role: Role,
+    parts: Vec<ContentPart>,
+}
+
+impl MultimodalTemplate {
+    pub fn new(role: Role) -> Result<Self, TemplateError> {
+        role.ensure_message_role()?;
+        Ok(MultimodalTemplate {
+            role,
+            parts: Vec::new(),
+        })
+    }
+
+    pub fn with_part(mut self, part: ContentPart) -> Result<Self, TemplateError> {
+        for source in part.sources() {
+            Template::new(source)?;
+        }
+        self.parts.push(part);
+        Ok(self)
+    }
+
+    pub fn with_text(self, text: &str) -> Result<Self, TemplateError> {
+        self.with_part(ContentPart::text(text))
+    }
+
+    pub fn with_image_url(self, url: &str) -> Result<Self, TemplateError> {
+        self.with_part(ContentPart::image_url(url))
+    }
+
+    pub fn with_image_base64(self, media_type: &str, data: &str) -> Result<Self, TemplateError> {
+        self.with_part(ContentPart::image_base64(media_type, data))
+    }
+
+    pub fn role(&self) -> &Role {
+        &self.role
+    }
+
+    pub fn parts(&self) -> &[ContentPart] {
+        &self.parts
+    }
+
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for source in self.parts.iter().flat_map(ContentPart::sources) {
+            let Ok(template) = Template::new(source) else {
+                continue;
+            };
+            for var in template.input_variables() {
+                if !variables.contains(&var) {
+                    variables.push(var);
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn format_message(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Arc<MessageEnum>, TemplateError> {
+        let parts = self
+            .parts
+            .iter()
+            .map(|part| part.render(variables))
+            .collect::<Result<Vec<_>, _>>()?;
+        let text: Vec<&str> = parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                ContentPart::ImageUrl { .. } => None,
+            })
+            .collect();
+
+        let mut message = Arc::unwrap_or_clone(self.role.render_message(&text.join("\n"))?);
+        let additional_kwargs = match &mut message {
+            MessageEnum::System(system) => &mut system.base.additional_kwargs,
+            MessageEnum::Human(human) => &mut human.base.additional_kwargs,
+            MessageEnum::Ai(ai) => &mut ai.base.additional_kwargs,
+            MessageEnum::Tool(_) => {
+                unreachable!("tool roles are rejected by MultimodalTemplate::new")
+            }
+        };
+        additional_kwargs.insert(
+            ContentPart::KWARGS_KEY.to_string(),
+            ContentPart::to_kwarg(&parts),
+        );
+        Ok(Arc::new(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::BaseMessage;
+
+    use super::*;
+    use crate::vars;
+
+    #[test]
+    fn test_format_message_renders_parts() {
+        let template = MultimodalTemplate::new(Role::Human)
+            .unwrap()
+            .with_text("Describe {subject}.")
+            .unwrap()
+            .with_image_url("https://img.example/{id}.png")
+            .unwrap()
+            .with_image_base64("image/png", "{png}")
+            .unwrap();
+        assert_eq!(template.input_variables(), vec!["subject", "id", "png"]);
+
+        let message = template
+            .format_message(&vars!(subject = "the cat", id = "7", png = "iVBORw0"))
+            .unwrap();
+        assert_eq!(message.content(), "Describe the cat.");
+
+        let parts = ContentPart::from_kwargs(message.additional_kwargs());
+        assert_eq!(
+            serde_json::to_value(&parts).unwrap(),
+            serde_json::json!([
+                {"type": "text", "text": "Describe the cat."},
+                {"type": "image_url", "image_url": {"url": "https://img.example/7.png"}},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0"}},
+            ])
+        );
+        assert_eq!(parts[1].base64_data(), None);
+        assert_eq!(parts[2].base64_data(), Some(("image/png", "iVBORw0")));
+    }
+
+    #[test]
+    fn test_rejects_tool_role_and_bad_templates() {
+        assert!(matches!(
+            MultimodalTemplate::new(Role::Tool),
+            Err(TemplateError::ToolRoleNotSupported(_))
+        ));
+        assert!(MultimodalTemplate::new(Role::Human)
+            .unwrap()
+            .with_image_url("{unclosed")
+            .is_err());
+    }
+}
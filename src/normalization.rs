@@ -0,0 +1,377 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use lazy_static::lazy_static;
+use messageforge::{BaseMessage, MessageEnum, SystemMessage};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    braces::escape_braces, ChatTemplate, ExportedMessage, MessageLike, Role, Templatable, Template,
+    TemplateFormat,
+};
+
+lazy_static! {
+    static ref BUILTIN_RULES: NormalizationRules = NormalizationRules::new();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextTransform {
+    TrimStart,
+    TrimEnd,
+    EnsureTrailingNewline,
+    ReplaceSmartQuotes,
+    NormalizeLineEndings,
+}
+
+impl TextTransform {
+    pub fn apply(&self, text: &str) -> String {
+        match self {
+            TextTransform::TrimStart => text.trim_start().to_string(),
+            TextTransform::TrimEnd => text.trim_end().to_string(),
+            TextTransform::EnsureTrailingNewline if text.ends_with('\n') => text.to_string(),
+            TextTransform::EnsureTrailingNewline => format!("{}\n", text),
+            TextTransform::ReplaceSmartQuotes => text
+                .chars()
+                .map(|c| match c {
+                    '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{2032}' => '\'',
+                    '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{2033}' => '"',
+                    other => other,
+                })
+                .collect(),
+            TextTransform::NormalizeLineEndings => text.replace("\r\n", "\n").replace('\r', "\n"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NormalizationRules {
+    #[serde(flatten)]
+    rules: BTreeMap<String, Vec<TextTransform>>,
+}
+
+impl NormalizationRules {
+    pub fn new() -> Self {
+        let mut rules = Self::empty();
+        rules.add("llama", TextTransform::TrimStart);
+        rules.add("completion", TextTransform::EnsureTrailingNewline);
+        rules.add("ascii", TextTransform::ReplaceSmartQuotes);
+        rules
+    }
+
+    pub fn empty() -> Self {
+        Self::default()
+    }
+
+    pub fn builtin() -> &'static NormalizationRules {
+        &BUILTIN_RULES
+    }
+
+    pub fn add(&mut self, family: impl Into<String>, transform: TextTransform) -> &mut Self {
+        let transforms = self.rules.entry(family.into().to_lowercase()).or_default();
+        if !transforms.contains(&transform) {
+            transforms.push(transform);
+        }
+        self
+    }
+
+    pub fn remove(&mut self, family: &str) -> Option<Vec<TextTransform>> {
+        self.rules.remove(&family.to_lowercase())
+    }
+
+    pub fn transforms(&self, family: &str) -> &[TextTransform] {
+        self.rules
+            .get(&family.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn normalize(&self, family: &str, text: &str) -> String {
+        self.transforms(family)
+            .iter()
+            .fold(text.to_string(), |text, transform| transform.apply(&text))
+    }
+
+    pub fn normalize_messages(&self, family: &str, messages: &mut [ExportedMessage]) {
+        if self.transforms(family).is_empty() {
+            return;
+        }
+        for message in messages {
+            message.content = self.normalize(family, &message.content);
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizeOptions {
+    separator: String,
+    system_first: bool,
+}
+
+impl Default for NormalizeOptions {
+    fn default() -> Self {
+        NormalizeOptions {
+            separator: "\n\n".to_string(),
+            system_first: false,
+        }
+    }
+}
+
+impl NormalizeOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    // Hoists every system message ahead of the conversation, keeping their order.
+    pub fn with_system_first(mut self, system_first: bool) -> Self {
+        self.system_first = system_first;
+        self
+    }
+
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    pub fn system_first(&self) -> bool {
+        self.system_first
+    }
+}
+
+enum SystemSource<'a> {
+    Literal(&'a str),
+    Template(&'a Template),
+}
+
+// Only bare system messages whose source reads as `{var}` text can be merged;
+// annotated, tagged and Mustache or Jinja messages are left alone.
+fn system_source(message_like: &MessageLike) -> Option<SystemSource<'_>> {
+    match message_like {
+        MessageLike::BaseMessage(message)
+            if matches!(message.as_ref(), MessageEnum::System(_))
+                && message.additional_kwargs().is_empty() =>
+        {
+            Some(SystemSource::Literal(message.content()))
+        }
+        MessageLike::RolePromptTemplate(Role::System, template)
+            if matches!(
+                template.template_format(),
+                TemplateFormat::FmtString | TemplateFormat::PlainText
+            ) =>
+        {
+            Some(SystemSource::Template(template))
+        }
+        _ => None,
+    }
+}
+
+fn is_system(message_like: &MessageLike) -> bool {
+    match message_like.inner() {
+        MessageLike::BaseMessage(message) => matches!(message.as_ref(), MessageEnum::System(_)),
+        MessageLike::RolePromptTemplate(role, _) => *role == Role::System,
+        _ => false,
+    }
+}
+
+fn is_empty(message_like: &MessageLike) -> bool {
+    match message_like.inner() {
+        MessageLike::BaseMessage(message) => {
+            !matches!(message.as_ref(), MessageEnum::Tool(_))
+                && message.content().trim().is_empty()
+                && message.additional_kwargs().is_empty()
+        }
+        MessageLike::RolePromptTemplate(_, template) => template.template().trim().is_empty(),
+        _ => false,
+    }
+}
+
+fn merge_system(first: &MessageLike, second: &MessageLike, separator: &str) -> Option<MessageLike> {
+    let (first, second) = (system_source(first)?, system_source(second)?);
+    let separator = escape_braces(separator);
+
+    // The merged template is rebuilt from the one that carries partials or
+    // transforms, so the other must have none.
+    let (base, source, variables) = match (first, second) {
+        (SystemSource::Literal(first), SystemSource::Literal(second)) => {
+            let content = format!("{}{}{}", first, separator, second);
+            return Some(MessageLike::base_message(
+                SystemMessage::new(&content).into(),
+            ));
+        }
+        (SystemSource::Template(template), SystemSource::Literal(text)) => (
+            template,
+            format!(
+                "{}{}{}",
+                template.template(),
+                separator,
+                escape_braces(text)
+            ),
+            template.input_variables(),
+        ),
+        (SystemSource::Literal(text), SystemSource::Template(template)) => (
+            template,
+            format!(
+                "{}{}{}",
+                escape_braces(text),
+                separator,
+                template.template()
+            ),
+            template.input_variables(),
+        ),
+        (SystemSource::Template(first), SystemSource::Template(second)) => {
+            let plain = |template: &Template| {
+                template.partial_vars().is_empty() && template.transforms().is_empty()
+            };
+            let base = match (plain(first), plain(second)) {
+                (_, true) => first,
+                (true, false) => second,
+                (false, false) => return None,
+            };
+            let mut variables = first.input_variables();
+            for variable in second.input_variables() {
+                if !variables.contains(&variable) {
+                    variables.push(variable);
+                }
+            }
+            (
+                base,
+                format!("{}{}{}", first.template(), separator, second.template()),
+                variables,
+            )
+        }
+    };
+
+    let template = base
+        .with_source(&source, TemplateFormat::FmtString, variables)
+        .ok()?;
+    Some(MessageLike::RolePromptTemplate(
+        Role::System,
+        Arc::new(template),
+    ))
+}
+
+impl ChatTemplate {
+    pub fn normalize(&mut self) {
+        self.normalize_with_options(&NormalizeOptions::new());
+    }
+
+    // Drops empty messages, optionally hoists system messages, then merges
+    // each run of consecutive system messages into one.
+    pub fn normalize_with_options(&mut self, options: &NormalizeOptions) {
+        let mut messages: Vec<MessageLike> = std::mem::take(&mut self.messages)
+            .into_iter()
+            .filter(|message_like| !is_empty(message_like))
+            .collect();
+        if options.system_first {
+            let (system, rest): (Vec<_>, Vec<_>) = messages.into_iter().partition(is_system);
+            messages = system.into_iter().chain(rest).collect();
+        }
+
+        for message_like in messages {
+            if let Some(last) = self.messages.last_mut()
+                && let Some(merged) = merge_system(last, &message_like, &options.separator)
+            {
+                *last = merged;
+                continue;
+            }
+            self.messages.push(message_like);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_transforms() {
+        assert_eq!(TextTransform::TrimStart.apply("\n  Hi "), "Hi ");
+        assert_eq!(TextTransform::TrimEnd.apply(" Hi \n"), " Hi");
+        assert_eq!(TextTransform::EnsureTrailingNewline.apply("Hi"), "Hi\n");
+        assert_eq!(TextTransform::EnsureTrailingNewline.apply("Hi\n"), "Hi\n");
+        assert_eq!(
+            TextTransform::ReplaceSmartQuotes.apply("\u{201C}It\u{2019}s fine\u{201D}"),
+            "\"It's fine\""
+        );
+        assert_eq!(
+            TextTransform::NormalizeLineEndings.apply("a\r\nb\rc"),
+            "a\nb\nc"
+        );
+    }
+
+    #[test]
+    fn test_builtin_rules_by_family() {
+        let rules = NormalizationRules::builtin();
+        assert_eq!(rules.normalize("Llama", "  Hello"), "Hello");
+        assert_eq!(rules.normalize("completion", "Hello"), "Hello\n");
+        assert_eq!(rules.normalize("gpt", "  Hello"), "  Hello");
+    }
+
+    #[test]
+    fn test_extend_rules_table() {
+        let mut rules = NormalizationRules::new();
+        rules
+            .add("llama", TextTransform::ReplaceSmartQuotes)
+            .add("llama", TextTransform::TrimStart)
+            .add("mistral", TextTransform::TrimEnd);
+
+        assert_eq!(
+            rules.transforms("llama"),
+            &[TextTransform::TrimStart, TextTransform::ReplaceSmartQuotes]
+        );
+        assert_eq!(rules.normalize("llama", " \u{2018}hi\u{2019}"), "'hi'");
+        assert_eq!(rules.remove("mistral"), Some(vec![TextTransform::TrimEnd]));
+
+        let toml = "llama = [\"trim_start\", \"ensure_trailing_newline\"]";
+        let parsed: NormalizationRules = toml::from_str(toml).unwrap();
+        assert_eq!(parsed.normalize("llama", " Hi"), "Hi\n");
+    }
+
+    #[test]
+    fn test_normalize_merges_system_messages() {
+        use crate::Role::{Ai, Human, System};
+        use crate::{chats, vars, Formattable};
+
+        let chat = || {
+            let mut chat_template = ChatTemplate::from_messages(chats!(
+                System = "You are {persona}.",
+                Human = " ",
+                Human = "{question}",
+                Ai = "",
+                System = "Be brief.",
+            ))
+            .unwrap();
+            chat_template
+                .insert(
+                    1,
+                    MessageLike::base_message(SystemMessage::new(r"Use \{json\}.").into()),
+                )
+                .unwrap();
+            chat_template
+        };
+        let variables = vars!(persona = "a tutor", question = "Why?");
+
+        let mut normalized = chat();
+        normalized.normalize();
+        assert_eq!(normalized.messages.len(), 3);
+        assert_eq!(
+            normalized.format(&variables).unwrap(),
+            "system: You are a tutor.\n\nUse {json}.\nhuman: Why?\nsystem: Be brief."
+        );
+
+        let mut hoisted = chat();
+        hoisted.normalize_with_options(
+            &NormalizeOptions::new()
+                .with_system_first(true)
+                .with_separator("\n"),
+        );
+        assert_eq!(
+            hoisted.format(&variables).unwrap(),
+            "system: You are a tutor.\nUse {json}.\nBe brief.\nhuman: Why?"
+        );
+        assert_eq!(hoisted.input_variables(), vec!["persona", "question"]);
+    }
+}
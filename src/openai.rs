@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatTemplate, ContentPart, ExportedMessage, RoleMap, TemplateError, ToolCall};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OpenAiRole {
+    System,
+    User,
+    Assistant,
+    Tool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OpenAiContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenAiMessage {
+    pub role: OpenAiRole,
+    pub content: OpenAiContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_call_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tool_calls: Vec<ToolCall>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl TryFrom<ExportedMessage> for OpenAiMessage {
+    type Error = TemplateError;
+
+    fn try_from(message: ExportedMessage) -> Result<Self, Self::Error> {
+        let role = match message.role.as_str() {
+            "system" => OpenAiRole::System,
+            "user" => OpenAiRole::User,
+            "assistant" => OpenAiRole::Assistant,
+            "tool" => OpenAiRole::Tool,
+            _ => return Err(TemplateError::InvalidRoleError),
+        };
+
+        let content = match message.content_parts.is_empty() {
+            true => OpenAiContent::Text(message.content),
+            false => OpenAiContent::Parts(message.content_parts),
+        };
+        Ok(OpenAiMessage {
+            role,
+            content,
+            tool_call_id: message.tool_call_id,
+            tool_calls: message.tool_calls,
+            name: message.metadata.name,
+        })
+    }
+}
+
+impl ChatTemplate {
+    pub fn to_openai_typed(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Vec<OpenAiMessage>, TemplateError> {
+        self.export(variables, &RoleMap::openai())?
+            .into_iter()
+            .map(OpenAiMessage::try_from)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, vars, MessageMetadata};
+
+    #[test]
+    fn test_typed_openai_messages() {
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "{question}",
+            Ai = "Let me check.",
+        ))
+        .unwrap();
+
+        let messages = chat_template
+            .to_openai_typed(&vars!(persona = "a tutor", question = "Why?"))
+            .unwrap();
+        assert_eq!(
+            messages.iter().map(|m| m.role).collect::<Vec<_>>(),
+            vec![OpenAiRole::System, OpenAiRole::User, OpenAiRole::Assistant]
+        );
+        assert_eq!(
+            serde_json::to_string(&messages[1]).unwrap(),
+            r#"{"role":"user","content":"Why?"}"#
+        );
+    }
+
+    #[test]
+    fn test_unknown_exported_role_is_rejected() {
+        let exported = ExportedMessage {
+            role: "developer".to_string(),
+            content: "Hi".to_string(),
+            tool_call_id: None,
+            tool_calls: Vec::new(),
+            prefill: false,
+            content_parts: Vec::new(),
+            metadata: MessageMetadata::default(),
+        };
+        assert_eq!(
+            OpenAiMessage::try_from(exported),
+            Err(TemplateError::InvalidRoleError)
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use messageforge::{BaseMessage, MessageEnum};
+use serde::{Serialize, Serializer};
+
+// `serde_json::Value` objects are key-ordered, so routing a value through it
+// gives hash-map-backed fields a stable order when templates are written back.
+pub fn serialize_sorted<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize + ?Sized,
+    S: Serializer,
+{
+    serde_json::to_value(value)
+        .map_err(serde::ser::Error::custom)?
+        .serialize(serializer)
+}
+
+// Messages keep their declared field order unless `additional_kwargs` holds
+// more than one entry, the only case where the output could vary run to run.
+pub fn serialize_message<S: Serializer>(
+    message: &Arc<MessageEnum>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if message.additional_kwargs().len() > 1 {
+        serialize_sorted(message, serializer)
+    } else {
+        message.serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Holder {
+        #[serde(serialize_with = "serialize_sorted")]
+        map: HashMap<String, u32>,
+    }
+
+    #[test]
+    fn test_serialize_sorted_orders_keys() {
+        let map = ["zeta", "alpha", "mu", "beta", "omega", "kappa"]
+            .into_iter()
+            .enumerate()
+            .map(|(index, key)| (key.to_string(), index as u32))
+            .collect();
+
+        assert_eq!(
+            serde_json::to_string(&Holder { map }).unwrap(),
+            r#"{"map":{"alpha":1,"beta":3,"kappa":5,"mu":2,"omega":4,"zeta":0}}"#
+        );
+    }
+}
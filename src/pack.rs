@@ -0,0 +1,183 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+
+use crate::{
+    batch::Row, braces::escape_braces, merge_vars, BatchSource, FewShotChatTemplate, Formattable,
+    Template, TemplateError, TemplateFormat,
+};
+
+pub const PACK_PROMPT_FILE: &str = "prompt.toml";
+pub const PACK_EXAMPLES_FILE: &str = "examples.jsonl";
+pub const PACK_TOOLS_FILE: &str = "tools.json";
+pub const PACK_CASES_FILE: &str = "cases.toml";
+pub const PACK_README_FILE: &str = "README.md";
+
+// A directory bundling a few-shot chat prompt with everything needed to use
+// and check it. Only `prompt.toml` is required; the other files are optional.
+#[derive(Debug, Clone)]
+pub struct PromptPack {
+    pub template: FewShotChatTemplate,
+    pub tools: Vec<Value>,
+    pub cases: Vec<EvalCase>,
+    pub readme: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub expect_contains: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CasesFile {
+    #[serde(default)]
+    cases: Vec<EvalCase>,
+}
+
+impl EvalCase {
+    pub fn unmet_expectations<'a>(&'a self, output: &str) -> Vec<&'a str> {
+        self.expect_contains
+            .iter()
+            .filter(|expected| !output.contains(expected.as_str()))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+impl PromptPack {
+    // Cases supply the prompt's own variables; the role variables the examples
+    // are written against are filled in the same way `format_examples` does.
+    pub fn render_case(&self, case: &EvalCase) -> Result<String, TemplateError> {
+        let roles = self.template.example_role_variables();
+        let case_variables = case
+            .variables
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        let variables = merge_vars(&roles, &case_variables);
+        self.template.format(&variables)
+    }
+}
+
+pub async fn load_pack<P: AsRef<Path>>(dir: P) -> Result<PromptPack, TemplateError> {
+    let dir = dir.as_ref();
+    let mut template = FewShotChatTemplate::from_toml_file(dir.join(PACK_PROMPT_FILE)).await?;
+
+    if let Some(content) = read_optional(&dir.join(PACK_EXAMPLES_FILE)).await? {
+        let rows = BatchSource::new().parse_jsonl(&content)?;
+        let examples = rows
+            .iter()
+            .map(|row| example_from_row(&template, row))
+            .collect::<Result<Vec<_>, _>>()?;
+        template = template.extend_examples(examples);
+    }
+
+    let tools = match read_optional(&dir.join(PACK_TOOLS_FILE)).await? {
+        Some(content) => serde_json::from_str(&content).map_err(|e| {
+            TemplateError::MalformedTemplate(
+                format!("Failed to parse {}: {}", PACK_TOOLS_FILE, e),
+                None,
+            )
+        })?,
+        None => Vec::new(),
+    };
+
+    let cases = match read_optional(&dir.join(PACK_CASES_FILE)).await? {
+        Some(content) => {
+            toml::from_str::<CasesFile>(&content)
+                .map_err(|e| {
+                    TemplateError::TomlDeserializationError(format!(
+                        "Failed to parse {}: {}",
+                        PACK_CASES_FILE, e
+                    ))
+                })?
+                .cases
+        }
+        None => Vec::new(),
+    };
+
+    Ok(PromptPack {
+        template,
+        tools,
+        cases,
+        readme: read_optional(&dir.join(PACK_README_FILE)).await?,
+    })
+}
+
+// Lays a row out the way hand-written examples are: one `{variable}: value`
+// line per example-prompt variable, with the value's braces escaped.
+fn example_from_row(template: &FewShotChatTemplate, row: &Row) -> Result<Template, TemplateError> {
+    let variables = template.example_prompt().input_variables();
+    let lines = variables
+        .iter()
+        .map(|var| {
+            let value = row.get(var).ok_or_else(|| {
+                TemplateError::MissingVariable(format!(
+                    "Example in {} is missing '{}'",
+                    PACK_EXAMPLES_FILE, var
+                ))
+            })?;
+            Ok(format!("{{{}}}: {}", var, escape_braces(value)))
+        })
+        .collect::<Result<Vec<_>, TemplateError>>()?;
+
+    Template::new_with_config(
+        &lines.join("\n"),
+        Some(TemplateFormat::FmtString),
+        Some(variables),
+    )
+}
+
+async fn read_optional(path: &Path) -> Result<Option<String>, TemplateError> {
+    match fs::read_to_string(path).await {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(TemplateError::MalformedTemplate(
+            format!("Failed to read pack file {}: {}", path.display(), e),
+            None,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK_DIR: &str = "tests/data/prompt_pack";
+
+    #[tokio::test]
+    async fn test_load_pack_bundles_all_files() {
+        let pack = load_pack(PACK_DIR).await.unwrap();
+
+        assert_eq!(pack.template.examples().len(), 2);
+        assert_eq!(pack.tools.len(), 1);
+        assert_eq!(pack.tools[0]["function"]["name"], "calculator");
+        assert_eq!(pack.cases.len(), 2);
+        assert_eq!(pack.cases[0].variables["persona"], "friendly");
+        assert!(pack.readme.unwrap().starts_with("# Math tutor"));
+    }
+
+    #[tokio::test]
+    async fn test_render_case_includes_examples() {
+        let pack = load_pack(PACK_DIR).await.unwrap();
+
+        let output = pack.render_case(&pack.cases[0]).unwrap();
+        assert!(output.starts_with("You are a friendly math tutor."));
+        assert!(output.contains("human: What is {x} + 1 when x = 2?\nai: 3"));
+        assert!(pack.cases[0].unmet_expectations(&output).is_empty());
+
+        let output = pack.render_case(&pack.cases[1]).unwrap();
+        assert_eq!(pack.cases[1].unmet_expectations(&output), vec!["10"]);
+    }
+
+    #[tokio::test]
+    async fn test_load_pack_requires_prompt() {
+        assert!(load_pack("tests/data/missing_pack").await.is_err());
+    }
+}
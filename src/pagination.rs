@@ -0,0 +1,344 @@
+use std::{fmt, ops::Range, sync::Arc};
+
+use messageforge::MessageEnum;
+
+use crate::{
+    tokens::{message_tokens, TOKENS_PER_MESSAGE, TOKENS_PER_REPLY},
+    ChatTemplate, MessageLike, Templatable, Template, TemplateError, TokenCounter,
+};
+
+pub const PAGE_INDEX_VARIABLE: &str = "page_index";
+pub const PAGE_COUNT_VARIABLE: &str = "page_count";
+pub const DEFAULT_HISTORY_VARIABLE: &str = "history";
+pub const DEFAULT_SUMMARY_VARIABLE: &str = "summary";
+
+// Splits a conversation too long for any context window into sequential
+// prompts built from one frame. Each page's messages replace the frame's
+// history placeholder; the summary variable stays open so the caller can
+// feed the previous page's answer forward, except on the first page, which
+// gets the initial summary. A single message larger than the budget becomes
+// a page of its own.
+#[derive(Clone)]
+pub struct ConversationPaginator {
+    max_tokens: usize,
+    counter: Arc<dyn TokenCounter>,
+    history_variable: String,
+    summary_variable: String,
+    summary_tokens: usize,
+    initial_summary: String,
+}
+
+impl fmt::Debug for ConversationPaginator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConversationPaginator")
+            .field("max_tokens", &self.max_tokens)
+            .field("history_variable", &self.history_variable)
+            .field("summary_variable", &self.summary_variable)
+            .field("summary_tokens", &self.summary_tokens)
+            .field("initial_summary", &self.initial_summary)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConversationPages {
+    paginator: ConversationPaginator,
+    frame: ChatTemplate,
+    history: Vec<Arc<MessageEnum>>,
+    pages: Vec<Range<usize>>,
+    next: usize,
+}
+
+impl ConversationPaginator {
+    pub fn new(max_tokens: usize, counter: impl TokenCounter + 'static) -> Self {
+        ConversationPaginator {
+            max_tokens,
+            counter: Arc::new(counter),
+            history_variable: DEFAULT_HISTORY_VARIABLE.to_string(),
+            summary_variable: DEFAULT_SUMMARY_VARIABLE.to_string(),
+            summary_tokens: 0,
+            initial_summary: String::new(),
+        }
+    }
+
+    pub fn with_history_variable(mut self, variable: &str) -> Self {
+        self.history_variable = variable.to_string();
+        self
+    }
+
+    pub fn with_summary_variable(mut self, variable: &str) -> Self {
+        self.summary_variable = variable.to_string();
+        self
+    }
+
+    // Held back on every page for the summary the caller will fill in.
+    pub fn with_summary_tokens(mut self, summary_tokens: usize) -> Self {
+        self.summary_tokens = summary_tokens;
+        self
+    }
+
+    pub fn with_initial_summary(mut self, initial_summary: &str) -> Self {
+        self.initial_summary = initial_summary.to_string();
+        self
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    pub fn paginate(
+        &self,
+        frame: &ChatTemplate,
+        history: &[Arc<MessageEnum>],
+    ) -> Result<ConversationPages, TemplateError> {
+        if !frame
+            .messages
+            .iter()
+            .any(|message| self.is_history(message))
+        {
+            return Err(TemplateError::MalformedTemplate(
+                format!(
+                    "The pagination frame has no '{}' placeholder",
+                    self.history_variable
+                ),
+                None,
+            ));
+        }
+
+        let frame_tokens = self.frame_tokens(frame);
+        let available = self
+            .max_tokens
+            .checked_sub(frame_tokens)
+            .filter(|available| *available > 0)
+            .ok_or_else(|| {
+                TemplateError::MalformedTemplate(
+                    format!(
+                        "A budget of {} tokens leaves no room for history after the frame's {}",
+                        self.max_tokens, frame_tokens
+                    ),
+                    None,
+                )
+            })?;
+
+        let mut pages = Vec::new();
+        let (mut start, mut used) = (0, 0);
+        for (index, message) in history.iter().enumerate() {
+            let tokens = message_tokens(self.counter.as_ref(), message);
+            if index > start && used + tokens > available {
+                pages.push(start..index);
+                (start, used) = (index, 0);
+            }
+            used += tokens;
+        }
+        if start < history.len() {
+            pages.push(start..history.len());
+        }
+
+        Ok(ConversationPages {
+            paginator: self.clone(),
+            frame: frame.clone(),
+            history: history.to_vec(),
+            pages,
+            next: 0,
+        })
+    }
+
+    fn is_history(&self, message_like: &MessageLike) -> bool {
+        matches!(
+            message_like.inner(),
+            MessageLike::Placeholder(placeholder)
+                if placeholder.variable_name() == self.history_variable
+        )
+    }
+
+    // Only fixed text is counted; other message sources need their own margin.
+    fn frame_tokens(&self, frame: &ChatTemplate) -> usize {
+        let counter = self.counter.as_ref();
+        let messages: usize = frame
+            .messages
+            .iter()
+            .map(|message_like| match message_like.inner() {
+                MessageLike::BaseMessage(message) => message_tokens(counter, message),
+                MessageLike::RolePromptTemplate(role, template) => {
+                    TOKENS_PER_MESSAGE
+                        + counter.count_tokens(role.as_str())
+                        + counter.count_tokens(template.template())
+                }
+                _ => 0,
+            })
+            .sum();
+        let prefill = frame
+            .assistant_prefill()
+            .map_or(0, |prefill| counter.count_tokens(prefill.template()));
+
+        messages + prefill + self.summary_tokens + TOKENS_PER_REPLY
+    }
+}
+
+fn bind_template(template: &Template, bound: &[(&str, String)]) -> Template {
+    let mut template = template.clone();
+    for (name, value) in bound {
+        if template.input_variables().iter().any(|var| var == name) {
+            template.partial(name, value);
+        }
+    }
+    template
+}
+
+fn bind(message_like: &MessageLike, bound: &[(&str, String)]) -> MessageLike {
+    match message_like {
+        MessageLike::RolePromptTemplate(role, template) => {
+            MessageLike::role_prompt_template(role.clone(), bind_template(template, bound))
+        }
+        MessageLike::Annotated(message_like, metadata) => {
+            MessageLike::Annotated(Box::new(bind(message_like, bound)), metadata.clone())
+        }
+        other => other.clone(),
+    }
+}
+
+impl ConversationPages {
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.pages
+    }
+
+    fn page(&self, index: usize) -> ChatTemplate {
+        let mut bound = vec![
+            (PAGE_INDEX_VARIABLE, (index + 1).to_string()),
+            (PAGE_COUNT_VARIABLE, self.pages.len().to_string()),
+        ];
+        if index == 0 {
+            bound.push((
+                self.paginator.summary_variable.as_str(),
+                self.paginator.initial_summary.clone(),
+            ));
+        }
+
+        let mut messages = Vec::with_capacity(self.frame.messages.len() + self.pages[index].len());
+        for message_like in &self.frame.messages {
+            if !self.paginator.is_history(message_like) {
+                messages.push(bind(message_like, &bound));
+                continue;
+            }
+            messages.extend(
+                self.history[self.pages[index].clone()]
+                    .iter()
+                    .map(|message| {
+                        let page_message = MessageLike::BaseMessage(Arc::clone(message));
+                        match message_like.metadata() {
+                            Some(metadata) => page_message.with_metadata(metadata.clone()),
+                            None => page_message,
+                        }
+                    }),
+            );
+        }
+
+        ChatTemplate {
+            messages,
+            prefill: self
+                .frame
+                .prefill
+                .as_deref()
+                .map(|prefill| Arc::new(bind_template(prefill, &bound))),
+            sensitive: self.frame.sensitive,
+        }
+    }
+}
+
+impl Iterator for ConversationPages {
+    type Item = ChatTemplate;
+
+    fn next(&mut self) -> Option<ChatTemplate> {
+        if self.next >= self.pages.len() {
+            return None;
+        }
+        let page = self.page(self.next);
+        self.next += 1;
+        Some(page)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.pages.len() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for ConversationPages {}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::{AiMessage, HumanMessage};
+
+    use super::*;
+    use crate::Role::{Human, Placeholder, System};
+    use crate::{chats, vars, Formattable};
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    fn frame() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "Summary so far: {summary}",
+            Placeholder = "{history}",
+            Human = "Continue with part {page_index} of {page_count}.",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_paginates_history_with_carried_summary() {
+        let history: Vec<Arc<MessageEnum>> = (0..5)
+            .map(|turn| -> Arc<MessageEnum> {
+                let content = format!("turn {} has words", turn);
+                if turn % 2 == 0 {
+                    Arc::new(HumanMessage::new(&content).into())
+                } else {
+                    Arc::new(AiMessage::new(&content).into())
+                }
+            })
+            .collect();
+
+        // The frame costs 21 tokens and every turn 8, so two turns fit per page.
+        let pages = ConversationPaginator::new(37, words)
+            .with_initial_summary("nothing yet")
+            .paginate(&frame(), &history)
+            .unwrap();
+        assert_eq!(pages.ranges(), &[0..2, 2..4, 4..5]);
+        assert_eq!(pages.len(), 3);
+
+        let pages: Vec<ChatTemplate> = pages.collect();
+        assert_eq!(
+            pages[0].format(&vars!()).unwrap(),
+            "system: Summary so far: nothing yet\n\
+             human: turn 0 has words\n\
+             ai: turn 1 has words\n\
+             human: Continue with part 1 of 3."
+        );
+        assert_eq!(pages[2].input_variables(), vec!["summary"]);
+        assert_eq!(
+            pages[2].format(&vars!(summary = "four turns")).unwrap(),
+            "system: Summary so far: four turns\n\
+             human: turn 4 has words\n\
+             human: Continue with part 3 of 3."
+        );
+    }
+
+    #[test]
+    fn test_paginate_rejects_unusable_frames() {
+        let no_history = ChatTemplate::from_messages(chats!(System = "Hi")).unwrap();
+        assert!(ConversationPaginator::new(100, words)
+            .paginate(&no_history, &[])
+            .is_err());
+        assert!(ConversationPaginator::new(21, words)
+            .paginate(&frame(), &[])
+            .is_err());
+        assert_eq!(
+            ConversationPaginator::new(22, words)
+                .paginate(&frame(), &[])
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+}
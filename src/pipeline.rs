@@ -0,0 +1,305 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{Formattable, PromptRegistry, RegisteredPrompt, TemplateError};
+
+// `inputs` maps a template variable to the step whose output fills it;
+// variables it does not mention come from the pipeline's own inputs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineStep {
+    pub name: String,
+    pub template: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub inputs: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    #[serde(default)]
+    pub steps: Vec<PipelineStep>,
+}
+
+// Steps run in dependency order; among independent steps the declared order
+// is kept.
+#[derive(Debug, Clone)]
+pub struct PromptPipeline {
+    steps: Vec<PipelineStep>,
+    prompts: Vec<RegisteredPrompt>,
+}
+
+impl TryFrom<String> for PipelineConfig {
+    type Error = TemplateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        toml::from_str(&value).map_err(|e| {
+            TemplateError::TomlDeserializationError(format!("Failed to parse pipeline: {}", e))
+        })
+    }
+}
+
+impl PipelineConfig {
+    pub async fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        let content = fs::read_to_string(path).await.map_err(|e| {
+            TemplateError::TomlDeserializationError(format!("Failed to read TOML file: {}", e))
+        })?;
+        Self::try_from(content)
+    }
+}
+
+impl PromptPipeline {
+    pub fn new(config: PipelineConfig, registry: &PromptRegistry) -> Result<Self, TemplateError> {
+        let mut pending = config.steps;
+        for (index, step) in pending.iter().enumerate() {
+            if pending[..index].iter().any(|other| other.name == step.name) {
+                return Err(pipeline_error(format!(
+                    "Step '{}' is defined more than once",
+                    step.name
+                )));
+            }
+        }
+
+        let mut steps: Vec<PipelineStep> = Vec::with_capacity(pending.len());
+        let mut prompts = Vec::with_capacity(pending.len());
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|step| {
+                step.inputs
+                    .values()
+                    .all(|source| steps.iter().any(|done| &done.name == source))
+            });
+            let Some(index) = ready else {
+                return Err(blocked_error(&pending, &steps));
+            };
+
+            let step = pending.remove(index);
+            if registry.get(&step.template).is_none() {
+                return Err(pipeline_error(format!(
+                    "Step '{}' uses unknown template '{}'",
+                    step.name, step.template
+                )));
+            }
+            let prompt = registry.resolved(&step.template)?;
+            let variables = prompt.input_variables();
+            if let Some(variable) = step.inputs.keys().find(|var| !variables.contains(var)) {
+                return Err(pipeline_error(format!(
+                    "Step '{}' feeds '{}', which template '{}' does not use",
+                    step.name, variable, step.template
+                )));
+            }
+
+            prompts.push(prompt);
+            steps.push(step);
+        }
+
+        Ok(PromptPipeline { steps, prompts })
+    }
+
+    pub async fn from_toml_file<P: AsRef<Path>>(
+        path: P,
+        registry: &PromptRegistry,
+    ) -> Result<Self, TemplateError> {
+        Self::new(PipelineConfig::from_toml_file(path).await?, registry)
+    }
+
+    pub fn steps(&self) -> &[PipelineStep] {
+        &self.steps
+    }
+
+    // The variables callers must supply: everything no step output feeds.
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        for (step, prompt) in self.steps.iter().zip(&self.prompts) {
+            for var in prompt.input_variables() {
+                if !step.inputs.contains_key(&var) && !variables.contains(&var) {
+                    variables.push(var);
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn render_step(
+        &self,
+        name: &str,
+        variables: &HashMap<&str, &str>,
+        outputs: &BTreeMap<String, String>,
+    ) -> Result<String, TemplateError> {
+        let index = self
+            .steps
+            .iter()
+            .position(|step| step.name == name)
+            .ok_or_else(|| pipeline_error(format!("Unknown step '{}'", name)))?;
+        let step = &self.steps[index];
+
+        let mut merged = variables.clone();
+        for (variable, source) in &step.inputs {
+            let output = outputs.get(source).ok_or_else(|| {
+                TemplateError::MissingVariable(format!(
+                    "Output of step '{}' for '{}'",
+                    source, step.name
+                ))
+            })?;
+            merged.insert(variable.as_str(), output.as_str());
+        }
+
+        self.prompts[index].format(&merged)
+    }
+
+    // `call` receives each step's name and rendered prompt and returns the
+    // model output; outputs are keyed by step name.
+    pub async fn run<F, Fut>(
+        &self,
+        variables: &HashMap<&str, &str>,
+        mut call: F,
+    ) -> Result<BTreeMap<String, String>, TemplateError>
+    where
+        F: FnMut(&str, String) -> Fut,
+        Fut: Future<Output = Result<String, TemplateError>>,
+    {
+        let mut outputs = BTreeMap::new();
+        for step in &self.steps {
+            let prompt = self.render_step(&step.name, variables, &outputs)?;
+            let output = call(&step.name, prompt).await?;
+            outputs.insert(step.name.clone(), output);
+        }
+        Ok(outputs)
+    }
+}
+
+fn blocked_error(pending: &[PipelineStep], done: &[PipelineStep]) -> TemplateError {
+    let known = |name: &String| pending.iter().chain(done).any(|step| &step.name == name);
+    let unknown = pending.iter().find_map(|step| {
+        step.inputs
+            .values()
+            .find(|source| !known(source))
+            .map(|source| (step, source))
+    });
+
+    match unknown {
+        Some((step, source)) => pipeline_error(format!(
+            "Step '{}' reads the output of unknown step '{}'",
+            step.name, source
+        )),
+        None => {
+            let names: Vec<&str> = pending.iter().map(|step| step.name.as_str()).collect();
+            pipeline_error(format!("Steps form a cycle: {}", names.join(", ")))
+        }
+    }
+}
+
+fn pipeline_error(message: String) -> TemplateError {
+    TemplateError::MalformedTemplate(format!("Pipeline: {}", message), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Template};
+
+    fn registry() -> PromptRegistry {
+        PromptRegistry::new()
+            .with_prompt(
+                "outline",
+                Template::new("Outline an essay on {topic}.").unwrap(),
+            )
+            .with_prompt(
+                "draft",
+                Template::new("Write a {tone} essay from:\n{outline}").unwrap(),
+            )
+    }
+
+    const PIPELINE: &str = r#"
+        [[steps]]
+        name = "draft"
+        template = "draft"
+        inputs = { outline = "plan" }
+
+        [[steps]]
+        name = "plan"
+        template = "outline"
+    "#;
+
+    #[tokio::test]
+    async fn test_pipeline_runs_steps_in_dependency_order() {
+        let config = PipelineConfig::try_from(PIPELINE.to_string()).unwrap();
+        let pipeline = PromptPipeline::new(config, &registry()).unwrap();
+
+        let order: Vec<&str> = pipeline.steps().iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(order, vec!["plan", "draft"]);
+        assert_eq!(pipeline.input_variables(), vec!["topic", "tone"]);
+
+        let mut prompts = Vec::new();
+        let outputs = pipeline
+            .run(&vars!(topic = "tides", tone = "wry"), |step, prompt| {
+                prompts.push(prompt);
+                let output = format!("<{} output>", step);
+                async move { Ok(output) }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            prompts,
+            vec![
+                "Outline an essay on tides.",
+                "Write a wry essay from:\n<plan output>"
+            ]
+        );
+        assert_eq!(outputs["draft"], "<draft output>");
+    }
+
+    #[test]
+    fn test_pipeline_rejects_bad_wiring() {
+        let build = |toml: &str| {
+            PromptPipeline::new(PipelineConfig::try_from(toml.to_string())?, &registry())
+        };
+
+        let unknown_step = r#"
+            [[steps]]
+            name = "draft"
+            template = "draft"
+            inputs = { outline = "missing" }
+        "#;
+        let cycle = r#"
+            [[steps]]
+            name = "a"
+            template = "draft"
+            inputs = { outline = "b" }
+            [[steps]]
+            name = "b"
+            template = "draft"
+            inputs = { outline = "a" }
+        "#;
+        let unused = r#"
+            [[steps]]
+            name = "plan"
+            template = "outline"
+            [[steps]]
+            name = "draft"
+            template = "draft"
+            inputs = { topic = "plan" }
+        "#;
+
+        for (toml, expected) in [
+            (unknown_step, "unknown step 'missing'"),
+            (cycle, "cycle: a, b"),
+            (unused, "does not use"),
+            (
+                r#"steps = [{ name = "x", template = "nope" }]"#,
+                "unknown template",
+            ),
+        ] {
+            let error = build(toml).unwrap_err().to_string();
+            assert!(error.contains(expected), "{}", error);
+        }
+        assert!(matches!(
+            PipelineConfig::try_from("steps = 1".to_string()),
+            Err(TemplateError::TomlDeserializationError(_))
+        ));
+    }
+}
@@ -1,10 +1,16 @@
-use crate::{braces::has_multiple_words_between_braces, TemplateError};
+use crate::{
+    braces::{has_multiple_words_between_braces, mask_escaped_braces},
+    TemplateError,
+};
 use lazy_static::lazy_static;
 use regex::Regex;
 use std::collections::HashSet;
 
 lazy_static! {
     static ref IDENTIFIER_RE: Regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+    pub(crate) static ref FMTSTRING_PLACEHOLDER_RE: Regex =
+        Regex::new(r"\{([a-zA-Z_][a-zA-Z0-9_]*)(?::([^{}|]*))?((?:\|[^{}|]*)*)\}").unwrap();
+    pub(crate) static ref POSITIONAL_PLACEHOLDER_RE: Regex = Regex::new(r"\{(\d+)\}").unwrap();
 }
 
 pub fn is_valid_identifier(s: &str) -> bool {
@@ -13,11 +19,12 @@ pub fn is_valid_identifier(s: &str) -> bool {
 
 pub fn extract_variables(template: &str) -> Vec<&str> {
     let re = Regex::new(r"\{{1,2}([^}]+)\}{1,2}").unwrap();
+    let masked = mask_escaped_braces(template);
     let mut unique_vars = HashSet::new();
     let mut result = Vec::new();
 
-    for cap in re.captures_iter(template) {
-        let var = cap.get(1).unwrap().as_str().trim();
+    for cap in re.captures_iter(&masked) {
+        let (var, _) = split_default(&template[cap.get(1).unwrap().range()]);
         if is_valid_identifier(var)
             && !has_multiple_words_between_braces(var)
             && unique_vars.insert(var)
@@ -29,6 +36,51 @@ pub fn extract_variables(template: &str) -> Vec<&str> {
     result
 }
 
+// Indices of `{0}`-style placeholders, in order of first use.
+pub fn extract_positional(template: &str) -> Vec<usize> {
+    let masked = mask_escaped_braces(template);
+    let mut result = Vec::new();
+
+    for cap in POSITIONAL_PLACEHOLDER_RE.captures_iter(&masked) {
+        if let Ok(index) = cap[1].parse()
+            && !result.contains(&index)
+        {
+            result.push(index);
+        }
+    }
+
+    result
+}
+
+pub fn split_filters(placeholder: &str) -> (&str, Option<&str>) {
+    match placeholder.split_once('|') {
+        Some((head, filters)) => (head, Some(filters)),
+        None => (placeholder, None),
+    }
+}
+
+pub fn split_default(placeholder: &str) -> (&str, Option<&str>) {
+    let (placeholder, _) = split_filters(placeholder);
+    match placeholder.split_once(':') {
+        Some((name, default)) => (name.trim(), Some(default)),
+        None => (placeholder.trim(), None),
+    }
+}
+
+pub fn extract_variable_defaults(template: &str) -> Vec<(&str, &str)> {
+    let masked = mask_escaped_braces(template);
+    let mut seen = HashSet::new();
+
+    FMTSTRING_PLACEHOLDER_RE
+        .captures_iter(&masked)
+        .filter_map(|cap| {
+            let name = &template[cap.get(1)?.range()];
+            let default = &template[cap.get(2)?.range()];
+            seen.insert(name).then_some((name, default))
+        })
+        .collect()
+}
+
 pub fn extract_placeholder_variable(template: &str) -> Result<String, TemplateError> {
     let variables = extract_variables(template);
 
@@ -37,6 +89,7 @@ pub fn extract_placeholder_variable(template: &str) -> Result<String, TemplateEr
     } else {
         Err(TemplateError::MalformedTemplate(
             "Template must contain exactly one placeholder variable.".to_string(),
+            None,
         ))
     }
 }
@@ -100,4 +153,64 @@ mod tests {
         check_variables("{var_123}", vec!["var_123"]);
         check_variables("{var123}", vec!["var123"]);
     }
+
+    #[test]
+    fn test_extract_variables_with_defaults() {
+        check_variables("Hello {name:Anonymous}", vec!["name"]);
+        check_variables("{greeting:Hi} {name} {greeting}", vec!["greeting", "name"]);
+        check_variables("{name:}", vec!["name"]);
+        check_variables("{1name:Anonymous}", vec![]);
+    }
+
+    #[test]
+    fn test_extract_variable_defaults() {
+        assert_eq!(
+            extract_variable_defaults("{greeting:Hi there}, {name:Anonymous}! {mood}"),
+            vec![("greeting", "Hi there"), ("name", "Anonymous")]
+        );
+        assert_eq!(extract_variable_defaults("{name:}"), vec![("name", "")]);
+        assert!(extract_variable_defaults("{name} {{other}}").is_empty());
+    }
+
+    #[test]
+    fn test_split_default() {
+        assert_eq!(split_default("name"), ("name", None));
+        assert_eq!(split_default(" name "), ("name", None));
+        assert_eq!(split_default("name:Anonymous"), ("name", Some("Anonymous")));
+        assert_eq!(split_default("url:http://x"), ("url", Some("http://x")));
+        assert_eq!(split_default("name:anon|upper"), ("name", Some("anon")));
+        assert_eq!(split_default("name|upper"), ("name", None));
+    }
+
+    #[test]
+    fn test_extract_variables_skips_escaped_braces() {
+        check_variables(r#"\{"user": "{name}"\}"#, vec!["name"]);
+        check_variables(
+            r#"fn main() \{ println!("{greeting}"); \}"#,
+            vec!["greeting"],
+        );
+        check_variables(r"\{literal\} and {real}", vec!["real"]);
+        assert_eq!(
+            extract_variable_defaults(r"\{a:b\} {c:d}"),
+            vec![("c", "d")]
+        );
+    }
+
+    #[test]
+    fn test_split_filters() {
+        assert_eq!(split_filters("name"), ("name", None));
+        assert_eq!(
+            split_filters("bio|trim|truncate(100)"),
+            ("bio", Some("trim|truncate(100)"))
+        );
+    }
+
+    #[test]
+    fn test_extract_variables_with_filters() {
+        check_variables("{name|upper} {bio|truncate(100)}", vec!["name", "bio"]);
+        assert_eq!(
+            extract_variable_defaults("{name:anon|upper}"),
+            vec![("name", "anon")]
+        );
+    }
 }
@@ -0,0 +1,175 @@
+use std::sync::Arc;
+
+use tokio::{
+    sync::{OwnedSemaphorePermit, Semaphore},
+    task,
+};
+
+use crate::{
+    batch::Row, vars::borrow_vars, Formattable, Templatable, Template, TemplateError,
+    TemplateFormat,
+};
+
+// Bounds how many renders run at once. Submitting waits for a free slot, so
+// a burst of requests queues on the pool instead of piling onto the runtime.
+#[derive(Debug, Clone)]
+pub struct RenderPool {
+    permits: Arc<Semaphore>,
+    capacity: usize,
+}
+
+impl RenderPool {
+    pub const DEFAULT_CAPACITY: usize = 64;
+
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        RenderPool {
+            permits: Arc::new(Semaphore::new(capacity)),
+            capacity,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.capacity - self.permits.available_permits()
+    }
+
+    // Renders on tokio's blocking pool, which suits any template whose cost is
+    // unknown.
+    pub async fn submit<T>(&self, template: Arc<T>, variables: Row) -> Result<String, TemplateError>
+    where
+        T: Formattable + Send + Sync + 'static,
+    {
+        let permit = self.acquire().await?;
+        render_blocking(permit, move || template.format(&borrow_vars(&variables))).await
+    }
+
+    // Plain and `{var}` templates render inline; Mustache and Jinja2 renders
+    // move off the async workers.
+    pub async fn submit_template(
+        &self,
+        template: Arc<Template>,
+        variables: Row,
+    ) -> Result<String, TemplateError> {
+        let permit = self.acquire().await?;
+        match template.template_format() {
+            TemplateFormat::FmtString | TemplateFormat::PlainText => {
+                let rendered = template.format(&borrow_vars(&variables));
+                drop(permit);
+                rendered
+            }
+            TemplateFormat::Mustache | TemplateFormat::Jinja2 => {
+                render_blocking(permit, move || template.format(&borrow_vars(&variables))).await
+            }
+        }
+    }
+
+    pub async fn submit_all<T>(
+        &self,
+        template: Arc<T>,
+        rows: Vec<Row>,
+    ) -> Vec<Result<String, TemplateError>>
+    where
+        T: Formattable + Send + Sync + 'static,
+    {
+        let renders = rows
+            .into_iter()
+            .map(|row| self.submit(Arc::clone(&template), row));
+        futures::future::join_all(renders).await
+    }
+
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, TemplateError> {
+        Arc::clone(&self.permits)
+            .acquire_owned()
+            .await
+            .map_err(|e| TemplateError::RuntimeError(format!("Render pool closed: {}", e)))
+    }
+}
+
+impl Default for RenderPool {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CAPACITY)
+    }
+}
+
+async fn render_blocking(
+    permit: OwnedSemaphorePermit,
+    render: impl FnOnce() -> Result<String, TemplateError> + Send + 'static,
+) -> Result<String, TemplateError> {
+    task::spawn_blocking(move || {
+        let rendered = render();
+        drop(permit);
+        rendered
+    })
+    .await
+    .map_err(|e| TemplateError::RuntimeError(format!("Render task failed: {}", e)))?
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::vars;
+
+    fn row(variables: &HashMap<&str, &str>) -> Row {
+        variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_submit_renders_every_format() {
+        let pool = RenderPool::new(2);
+        let variables = row(&vars!(name = "Ada"));
+
+        for source in [
+            "Hi {name}",
+            "Hi {{name}}",
+            "Hi {{ name }}{% if true %}!{% endif %}",
+        ] {
+            let template = Arc::new(Template::new(source).unwrap());
+            let rendered = pool
+                .submit_template(Arc::clone(&template), variables.clone())
+                .await
+                .unwrap();
+            assert!(rendered.starts_with("Hi Ada"), "{}", rendered);
+            assert_eq!(
+                pool.submit(template, variables.clone()).await.unwrap(),
+                rendered
+            );
+        }
+        assert_eq!(pool.in_flight(), 0);
+
+        let missing = pool
+            .submit(Arc::new(Template::new("{missing}").unwrap()), Row::new())
+            .await;
+        assert!(matches!(missing, Err(TemplateError::MissingVariable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_pool_bounds_concurrent_renders() {
+        let pool = RenderPool::new(1);
+        let held = Arc::clone(&pool.permits).acquire_owned().await.unwrap();
+        assert_eq!(pool.in_flight(), 1);
+
+        let template = Arc::new(Template::new("{n}").unwrap());
+        let rows: Vec<Row> = (0..4)
+            .map(|n| Row::from([("n".to_string(), n.to_string())]))
+            .collect();
+        let renders = pool.submit_all(template, rows);
+        tokio::pin!(renders);
+
+        let waited = tokio::time::timeout(std::time::Duration::from_millis(20), &mut renders).await;
+        assert!(waited.is_err());
+
+        drop(held);
+        let rendered: Vec<String> = renders.await.into_iter().map(Result::unwrap).collect();
+        assert_eq!(rendered, vec!["0", "1", "2", "3"]);
+        assert_eq!(RenderPool::new(0).capacity(), 1);
+    }
+}
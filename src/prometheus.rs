@@ -0,0 +1,227 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    sync::Mutex,
+    time::Instant,
+};
+
+use crate::{metrics::estimate_tokens, Formattable, TemplateError};
+
+pub const RENDERS_TOTAL: &str = "promptforge_renders_total";
+pub const RENDER_ERRORS_TOTAL: &str = "promptforge_render_errors_total";
+pub const RENDER_DURATION_SECONDS: &str = "promptforge_render_duration_seconds";
+pub const RENDERED_TOKENS: &str = "promptforge_rendered_tokens";
+
+pub const DURATION_BUCKETS: &[f64] = &[0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0];
+pub const TOKEN_BUCKETS: &[f64] = &[16.0, 64.0, 256.0, 1024.0, 4096.0, 16384.0];
+
+// promptforge does not depend on the `metrics` crate. The sink mirrors its
+// facade instead, so forwarding to `metrics::counter!`/`histogram!` is an
+// adapter of a few lines on the caller's side.
+pub trait MetricsSink: Send + Sync {
+    fn increment_counter(&self, name: &'static str, template: &str, value: u64);
+    fn record_histogram(&self, name: &'static str, template: &str, value: f64);
+}
+
+pub fn instrumented_format<T: Formattable + ?Sized>(
+    sink: &dyn MetricsSink,
+    label: &str,
+    template: &T,
+    variables: &HashMap<&str, &str>,
+) -> Result<String, TemplateError> {
+    let started = Instant::now();
+    let result = template.format(variables);
+    sink.record_histogram(
+        RENDER_DURATION_SECONDS,
+        label,
+        started.elapsed().as_secs_f64(),
+    );
+
+    match &result {
+        Ok(output) => {
+            sink.increment_counter(RENDERS_TOTAL, label, 1);
+            sink.record_histogram(RENDERED_TOKENS, label, estimate_tokens(output) as f64);
+        }
+        Err(_) => sink.increment_counter(RENDER_ERRORS_TOTAL, label, 1),
+    }
+    result
+}
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, bounds: &[f64], value: f64) {
+        self.buckets.resize(bounds.len(), 0);
+        for (bucket, bound) in self.buckets.iter_mut().zip(bounds) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct PrometheusRecorder {
+    counters: Mutex<BTreeMap<(&'static str, String), u64>>,
+    histograms: Mutex<BTreeMap<(&'static str, String), Histogram>>,
+}
+
+impl PrometheusRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn format<T: Formattable + ?Sized>(
+        &self,
+        label: &str,
+        template: &T,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        instrumented_format(self, label, template, variables)
+    }
+
+    pub fn counter(&self, name: &str, template: &str) -> u64 {
+        self.counters
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|((metric, label), _)| *metric == name && label == template)
+            .map_or(0, |(_, value)| *value)
+    }
+
+    pub fn render(&self) -> String {
+        let mut output = String::new();
+        let counters = self.counters.lock().unwrap();
+        let histograms = self.histograms.lock().unwrap();
+
+        let mut described = None;
+        for ((name, template), value) in counters.iter() {
+            if described != Some(*name) {
+                let _ = writeln!(output, "# TYPE {} counter", name);
+                described = Some(*name);
+            }
+            let _ = writeln!(
+                output,
+                "{}{{template=\"{}\"}} {}",
+                name,
+                escape(template),
+                value
+            );
+        }
+
+        let mut described = None;
+        for ((name, template), histogram) in histograms.iter() {
+            if described != Some(*name) {
+                let _ = writeln!(output, "# TYPE {} histogram", name);
+                described = Some(*name);
+            }
+            let template = escape(template);
+            for (bound, count) in bounds_for(name).iter().zip(&histogram.buckets) {
+                let _ = writeln!(
+                    output,
+                    "{}_bucket{{template=\"{}\",le=\"{}\"}} {}",
+                    name, template, bound, count
+                );
+            }
+            let _ = writeln!(
+                output,
+                "{}_bucket{{template=\"{}\",le=\"+Inf\"}} {}",
+                name, template, histogram.count
+            );
+            let _ = writeln!(
+                output,
+                "{}_sum{{template=\"{}\"}} {}",
+                name, template, histogram.sum
+            );
+            let _ = writeln!(
+                output,
+                "{}_count{{template=\"{}\"}} {}",
+                name, template, histogram.count
+            );
+        }
+
+        output
+    }
+}
+
+impl MetricsSink for PrometheusRecorder {
+    fn increment_counter(&self, name: &'static str, template: &str, value: u64) {
+        *self
+            .counters
+            .lock()
+            .unwrap()
+            .entry((name, template.to_string()))
+            .or_default() += value;
+    }
+
+    fn record_histogram(&self, name: &'static str, template: &str, value: f64) {
+        self.histograms
+            .lock()
+            .unwrap()
+            .entry((name, template.to_string()))
+            .or_default()
+            .observe(bounds_for(name), value);
+    }
+}
+
+fn bounds_for(name: &str) -> &'static [f64] {
+    match name {
+        RENDERED_TOKENS => TOKEN_BUCKETS,
+        _ => DURATION_BUCKETS,
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, Template};
+
+    #[test]
+    fn test_recorder_counts_renders_and_errors() {
+        let recorder = PrometheusRecorder::new();
+        let template = Template::new("Hello {name}, welcome aboard!").unwrap();
+
+        recorder
+            .format("greeting", &template, &vars!(name = "Ada"))
+            .unwrap();
+        recorder
+            .format("greeting", &template, &vars!(name = "Bob"))
+            .unwrap();
+        assert!(recorder.format("greeting", &template, &vars!()).is_err());
+
+        assert_eq!(recorder.counter(RENDERS_TOTAL, "greeting"), 2);
+        assert_eq!(recorder.counter(RENDER_ERRORS_TOTAL, "greeting"), 1);
+        assert_eq!(recorder.counter(RENDERS_TOTAL, "other"), 0);
+    }
+
+    #[test]
+    fn test_render_exposition_format() {
+        let recorder = PrometheusRecorder::new();
+        recorder.increment_counter(RENDERS_TOTAL, "a\"b", 3);
+        recorder.record_histogram(RENDERED_TOKENS, "summary", 100.0);
+
+        let text = recorder.render();
+        assert!(text.contains("# TYPE promptforge_renders_total counter\n"));
+        assert!(text.contains("promptforge_renders_total{template=\"a\\\"b\"} 3\n"));
+        assert!(
+            text.contains("promptforge_rendered_tokens_bucket{template=\"summary\",le=\"64\"} 0\n")
+        );
+        assert!(text
+            .contains("promptforge_rendered_tokens_bucket{template=\"summary\",le=\"256\"} 1\n"));
+        assert!(text.contains("promptforge_rendered_tokens_count{template=\"summary\"} 1\n"));
+    }
+}
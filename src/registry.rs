@@ -0,0 +1,448 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use serde::Serialize;
+
+use crate::{
+    encryption::Sensitive,
+    lint::{lint_chat, lint_template, LintProfile},
+    vars::borrow_vars,
+    ChatTemplate, CompiledChatTemplate, CompiledTemplate, Formattable, LintDiagnostic,
+    PromptConstants, Templatable, Template, TemplateError,
+};
+
+#[derive(Debug, Clone)]
+pub enum RegisteredPrompt {
+    Template(Arc<Template>),
+    Chat(ChatTemplate),
+}
+
+impl From<Template> for RegisteredPrompt {
+    fn from(template: Template) -> Self {
+        RegisteredPrompt::Template(Arc::new(template))
+    }
+}
+
+impl From<ChatTemplate> for RegisteredPrompt {
+    fn from(chat_template: ChatTemplate) -> Self {
+        RegisteredPrompt::Chat(chat_template)
+    }
+}
+
+impl RegisteredPrompt {
+    pub fn input_variables(&self) -> Vec<String> {
+        match self {
+            RegisteredPrompt::Template(template) => template.input_variables(),
+            RegisteredPrompt::Chat(chat_template) => chat_template.input_variables(),
+        }
+    }
+
+    pub fn lint(&self, profile: Option<LintProfile>) -> Vec<LintDiagnostic> {
+        match (self, profile) {
+            (RegisteredPrompt::Template(template), Some(profile)) => {
+                profile.lint_template(template)
+            }
+            (RegisteredPrompt::Template(template), None) => lint_template(template),
+            (RegisteredPrompt::Chat(chat_template), Some(profile)) => {
+                profile.lint_chat(chat_template)
+            }
+            (RegisteredPrompt::Chat(chat_template), None) => lint_chat(chat_template),
+        }
+    }
+
+    pub fn compile(&self) -> Result<CompiledPrompt, TemplateError> {
+        match self {
+            RegisteredPrompt::Template(template) => {
+                template.compile().map(CompiledPrompt::Template)
+            }
+            RegisteredPrompt::Chat(chat_template) => {
+                chat_template.compile().map(CompiledPrompt::Chat)
+            }
+        }
+    }
+}
+
+impl Formattable for RegisteredPrompt {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        match self {
+            RegisteredPrompt::Template(template) => template.format(variables),
+            RegisteredPrompt::Chat(chat_template) => chat_template.format(variables),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CompiledPrompt {
+    Template(CompiledTemplate),
+    Chat(CompiledChatTemplate),
+}
+
+impl Formattable for CompiledPrompt {
+    fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+        match self {
+            CompiledPrompt::Template(template) => template.format(variables),
+            CompiledPrompt::Chat(chat_template) => chat_template.format(variables),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmupStage {
+    Compile,
+    Render,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct WarmupFailure {
+    pub prompt: String,
+    pub stage: WarmupStage,
+    pub error: TemplateError,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReadinessReport {
+    pub compiled: usize,
+    pub rendered: usize,
+    pub failures: Vec<WarmupFailure>,
+}
+
+impl ReadinessReport {
+    pub fn is_ready(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    pub fn failures_for(&self, prompt: &str) -> Vec<&WarmupFailure> {
+        self.failures
+            .iter()
+            .filter(|failure| failure.prompt == prompt)
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptRegistry {
+    prompts: BTreeMap<String, RegisteredPrompt>,
+    samples: HashMap<String, HashMap<String, String>>,
+    compiled: HashMap<String, CompiledPrompt>,
+    lint_profile: Option<LintProfile>,
+    constants: PromptConstants,
+}
+
+impl PromptRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        prompt: impl Into<RegisteredPrompt>,
+    ) -> Option<RegisteredPrompt> {
+        let name = name.into();
+        self.compiled.remove(&name);
+        self.prompts.insert(name, prompt.into())
+    }
+
+    // Installs a prompt together with its compiled form, so a reader never
+    // falls back to an uncompiled render in between.
+    pub(crate) fn install(
+        &mut self,
+        name: String,
+        prompt: RegisteredPrompt,
+        compiled: CompiledPrompt,
+    ) -> Option<RegisteredPrompt> {
+        let previous = self.register(name.clone(), prompt);
+        self.compiled.insert(name, compiled);
+        previous
+    }
+
+    // Sensitive prompts were decrypted in memory and must never be written
+    // back to disk; see `Sensitive`.
+    pub fn register_sensitive(
+        &mut self,
+        name: impl Into<String>,
+        prompt: impl Into<RegisteredPrompt>,
+    ) -> Option<RegisteredPrompt> {
+        let mut prompt = prompt.into();
+        prompt.mark_sensitive();
+        self.register(name, prompt)
+    }
+
+    pub fn with_lint_profile(mut self, profile: LintProfile) -> Self {
+        self.lint_profile = Some(profile);
+        self
+    }
+
+    pub fn lint_profile(&self) -> Option<LintProfile> {
+        self.lint_profile
+    }
+
+    // Prompts without findings are left out of the report.
+    pub fn lint(&self) -> BTreeMap<String, Vec<LintDiagnostic>> {
+        self.prompts
+            .iter()
+            .map(|(name, prompt)| (name.clone(), prompt.lint(self.lint_profile)))
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .collect()
+    }
+
+    // Compiled prompts were built against the old constants, so they are dropped.
+    pub fn set_constants(&mut self, constants: PromptConstants) {
+        self.compiled.clear();
+        self.constants = constants;
+    }
+
+    pub fn with_constants(mut self, constants: PromptConstants) -> Self {
+        self.set_constants(constants);
+        self
+    }
+
+    pub fn constants(&self) -> &PromptConstants {
+        &self.constants
+    }
+
+    pub fn is_sensitive(&self, name: &str) -> bool {
+        self.prompts.get(name).is_some_and(Sensitive::is_sensitive)
+    }
+
+    pub fn with_prompt(
+        mut self,
+        name: impl Into<String>,
+        prompt: impl Into<RegisteredPrompt>,
+    ) -> Self {
+        self.register(name, prompt);
+        self
+    }
+
+    pub fn set_sample(&mut self, name: impl Into<String>, variables: &HashMap<&str, &str>) {
+        let variables = variables
+            .iter()
+            .map(|(&key, &value)| (key.to_string(), value.to_string()))
+            .collect();
+        self.samples.insert(name.into(), variables);
+    }
+
+    pub fn with_sample(mut self, name: impl Into<String>, variables: &HashMap<&str, &str>) -> Self {
+        self.set_sample(name, variables);
+        self
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<RegisteredPrompt> {
+        self.compiled.remove(name);
+        self.samples.remove(name);
+        self.prompts.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RegisteredPrompt> {
+        self.prompts.get(name)
+    }
+
+    // The registered prompt with every `{@const.name}` filled in.
+    pub fn resolved(&self, name: &str) -> Result<RegisteredPrompt, TemplateError> {
+        match self.prompts.get(name) {
+            Some(prompt) => self.constants.resolve_prompt(prompt),
+            None => Err(TemplateError::MalformedTemplate(
+                format!("No prompt registered under '{}'", name),
+                None,
+            )),
+        }
+    }
+
+    pub fn compiled(&self, name: &str) -> Option<&CompiledPrompt> {
+        self.compiled.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.prompts.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.prompts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.prompts.is_empty()
+    }
+
+    pub fn format(
+        &self,
+        name: &str,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        self.constants.check_values(variables)?;
+        match self.compiled.get(name) {
+            Some(compiled) => compiled.format(variables),
+            None => self.resolved(name)?.format(variables),
+        }
+    }
+
+    pub fn warmup(&mut self) -> ReadinessReport {
+        let mut report = ReadinessReport::default();
+        self.compiled.clear();
+
+        for (name, prompt) in &self.prompts {
+            let compiled = match self
+                .constants
+                .resolve_prompt(prompt)
+                .and_then(|prompt| prompt.compile())
+            {
+                Ok(compiled) => compiled,
+                Err(error) => {
+                    report.failures.push(WarmupFailure {
+                        prompt: name.clone(),
+                        stage: WarmupStage::Compile,
+                        error,
+                    });
+                    continue;
+                }
+            };
+            report.compiled += 1;
+
+            if let Some(sample) = self.samples.get(name) {
+                let sample = borrow_vars(sample);
+                let rendered = self
+                    .constants
+                    .check_values(&sample)
+                    .and_then(|_| compiled.format(&sample));
+                match rendered {
+                    Ok(_) => report.rendered += 1,
+                    Err(error) => report.failures.push(WarmupFailure {
+                        prompt: name.clone(),
+                        stage: WarmupStage::Render,
+                        error,
+                    }),
+                }
+            }
+
+            self.compiled.insert(name.clone(), compiled);
+        }
+
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{chats, vars, Role::Human, Role::System};
+
+    fn registry() -> PromptRegistry {
+        PromptRegistry::new()
+            .with_prompt("greet", Template::new("Hi {name}!").unwrap())
+            .with_prompt(
+                "support",
+                ChatTemplate::from_messages(chats!(
+                    System = "You work for {company}.",
+                    Human = "{question}",
+                ))
+                .unwrap(),
+            )
+            .with_sample("greet", &vars!(name = "Ada"))
+    }
+
+    #[test]
+    fn test_warmup_compiles_and_renders_samples() {
+        let mut registry = registry();
+        let report = registry.warmup();
+
+        assert!(report.is_ready());
+        assert_eq!((report.compiled, report.rendered), (2, 1));
+        assert!(registry.compiled("support").is_some());
+        assert_eq!(
+            registry.format("greet", &vars!(name = "Bob")).unwrap(),
+            "Hi Bob!"
+        );
+        assert!(registry.format("missing", &vars!()).is_err());
+    }
+
+    #[test]
+    fn test_warmup_reports_render_failures_per_prompt() {
+        let mut registry = registry().with_sample("support", &vars!(company = "Acme"));
+        let report = registry.warmup();
+
+        assert!(!report.is_ready());
+        assert_eq!(report.compiled, 2);
+        assert_eq!(
+            report.failures_for("support"),
+            vec![&WarmupFailure {
+                prompt: "support".to_string(),
+                stage: WarmupStage::Render,
+                error: TemplateError::MissingVariable(
+                    "Variable 'question' is missing. Expected: [\"question\"], but received: \
+                     [\"company\"]"
+                        .to_string()
+                ),
+            }]
+        );
+        assert!(report.failures_for("greet").is_empty());
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["failures"][0]["stage"], "render");
+        assert_eq!(json["failures"][0]["error"]["kind"], "missing_variable");
+    }
+
+    #[test]
+    fn test_register_replaces_and_invalidates_compiled() {
+        let mut registry = registry();
+        registry.warmup();
+
+        let previous = registry.register("greet", Template::new("Hello {name}").unwrap());
+        assert!(previous.is_some());
+        assert!(registry.compiled("greet").is_none());
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            vec!["greet", "support"]
+        );
+        assert!(registry.remove("support").is_some());
+        assert_eq!(registry.len(), 1);
+    }
+
+    #[test]
+    fn test_lint_uses_registry_profile() {
+        let registry =
+            registry().with_prompt("shout", Template::new("DO NOT EVER guess {x}").unwrap());
+        assert_eq!(registry.lint().len(), 0);
+
+        let registry = registry.with_lint_profile(LintProfile::OpenaiBestPractices);
+        let report = registry.lint();
+        assert_eq!(
+            report.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["shout"]
+        );
+        assert_eq!(report["shout"][0].rule, crate::lint::SHOUTING);
+    }
+
+    #[test]
+    fn test_constants_resolve_and_validate_enums() {
+        let constants = PromptConstants::new()
+            .with_constant("product_name", "Acme")
+            .with_enum("tone", ["formal", "casual"]);
+        let mut registry = PromptRegistry::new()
+            .with_prompt(
+                "pitch",
+                Template::new("A {tone} pitch for {@const.product_name}.").unwrap(),
+            )
+            .with_constants(constants)
+            .with_sample("pitch", &vars!(tone = "loud"));
+
+        assert_eq!(
+            registry.format("pitch", &vars!(tone = "formal")).unwrap(),
+            "A formal pitch for Acme."
+        );
+        assert!(matches!(
+            registry.format("pitch", &vars!(tone = "loud")),
+            Err(TemplateError::InvalidValue(_))
+        ));
+
+        let report = registry.warmup();
+        assert_eq!(report.compiled, 1);
+        assert_eq!(report.failures[0].stage, WarmupStage::Render);
+        assert_eq!(
+            registry.format("pitch", &vars!(tone = "casual")).unwrap(),
+            "A casual pitch for Acme."
+        );
+    }
+}
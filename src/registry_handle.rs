@@ -0,0 +1,290 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use futures::Stream;
+use tokio::sync::broadcast::{
+    self,
+    error::{RecvError, TryRecvError},
+};
+
+use crate::{CanonicalHash, PromptRegistry, RegisteredPrompt, TemplateError, TemplateHash};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryEvent {
+    // `previous` is `None` for a new prompt and `current` is `None` once it
+    // has been removed.
+    Changed {
+        name: String,
+        version: u64,
+        previous: Option<TemplateHash>,
+        current: Option<TemplateHash>,
+    },
+    // The subscriber fell behind and missed this many events, so anything it
+    // cached should be dropped wholesale.
+    Lagged(u64),
+    // A watched file could not be loaded; the prompt it held, if any, is kept.
+    ReloadFailed {
+        path: PathBuf,
+        error: TemplateError,
+    },
+}
+
+#[derive(Debug)]
+pub struct RegistryEvents {
+    receiver: broadcast::Receiver<RegistryEvent>,
+}
+
+impl RegistryEvents {
+    pub async fn recv(&mut self) -> Option<RegistryEvent> {
+        match self.receiver.recv().await {
+            Ok(event) => Some(event),
+            Err(RecvError::Lagged(missed)) => Some(RegistryEvent::Lagged(missed)),
+            Err(RecvError::Closed) => None,
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Option<RegistryEvent> {
+        match self.receiver.try_recv() {
+            Ok(event) => Some(event),
+            Err(TryRecvError::Lagged(missed)) => Some(RegistryEvent::Lagged(missed)),
+            Err(TryRecvError::Empty | TryRecvError::Closed) => None,
+        }
+    }
+
+    // Ends once every handle to the registry has been dropped.
+    pub fn into_stream(self) -> impl Stream<Item = RegistryEvent> {
+        futures::stream::unfold(self, |mut events| async move {
+            events.recv().await.map(|event| (event, events))
+        })
+    }
+}
+
+#[derive(Debug)]
+struct HandleState {
+    registry: PromptRegistry,
+    versions: HashMap<String, u64>,
+}
+
+// A shared registry whose prompts can be replaced one at a time while other
+// threads keep rendering. Every change bumps that prompt's version and is
+// published to subscribers.
+#[derive(Debug, Clone)]
+pub struct RegistryHandle {
+    state: Arc<RwLock<HandleState>>,
+    events: broadcast::Sender<RegistryEvent>,
+}
+
+impl RegistryHandle {
+    pub const EVENT_CAPACITY: usize = 64;
+
+    pub fn new(registry: PromptRegistry) -> Self {
+        let versions = registry.names().map(|name| (name.to_string(), 1)).collect();
+        RegistryHandle {
+            state: Arc::new(RwLock::new(HandleState { registry, versions })),
+            events: broadcast::channel(Self::EVENT_CAPACITY).0,
+        }
+    }
+
+    pub fn subscribe(&self) -> RegistryEvents {
+        RegistryEvents {
+            receiver: self.events.subscribe(),
+        }
+    }
+
+    // The new prompt is compiled before the lock is taken; if that fails the
+    // old prompt stays in place and no version is spent.
+    pub fn swap(
+        &self,
+        name: impl Into<String>,
+        prompt: impl Into<RegisteredPrompt>,
+    ) -> Result<u64, TemplateError> {
+        let (name, prompt) = (name.into(), prompt.into());
+        let compiled = self
+            .read()
+            .registry
+            .constants()
+            .resolve_prompt(&prompt)?
+            .compile()?;
+        let current = prompt.template_hash();
+
+        let mut state = self.write();
+        let previous = state
+            .registry
+            .install(name.clone(), prompt, compiled)
+            .map(|previous| previous.template_hash());
+        let version = state.bump(&name);
+        self.publish(name, version, previous, Some(current));
+        Ok(version)
+    }
+
+    pub fn remove(&self, name: &str) -> Option<RegisteredPrompt> {
+        let mut state = self.write();
+        let removed = state.registry.remove(name)?;
+        let version = state.bump(name);
+        self.publish(
+            name.to_string(),
+            version,
+            Some(removed.template_hash()),
+            None,
+        );
+        Some(removed)
+    }
+
+    pub fn version(&self, name: &str) -> Option<u64> {
+        let state = self.read();
+        state
+            .registry
+            .get(name)
+            .and(state.versions.get(name).copied())
+    }
+
+    pub fn get(&self, name: &str) -> Option<RegisteredPrompt> {
+        self.read().registry.get(name).cloned()
+    }
+
+    pub fn format(
+        &self,
+        name: &str,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        self.read().registry.format(name, variables)
+    }
+
+    pub fn with_registry<R>(&self, read: impl FnOnce(&PromptRegistry) -> R) -> R {
+        read(&self.read().registry)
+    }
+
+    pub(crate) fn notify(&self, event: RegistryEvent) {
+        // Having no subscribers is not an error.
+        let _ = self.events.send(event);
+    }
+
+    // Sent while the write lock is held, so events arrive in version order.
+    fn publish(
+        &self,
+        name: String,
+        version: u64,
+        previous: Option<TemplateHash>,
+        current: Option<TemplateHash>,
+    ) {
+        self.notify(RegistryEvent::Changed {
+            name,
+            version,
+            previous,
+            current,
+        });
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, HandleState> {
+        self.state
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, HandleState> {
+        self.state
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl HandleState {
+    fn bump(&mut self, name: &str) -> u64 {
+        let version = self.versions.entry(name.to_string()).or_default();
+        *version += 1;
+        *version
+    }
+}
+
+impl From<PromptRegistry> for RegistryHandle {
+    fn from(registry: PromptRegistry) -> Self {
+        Self::new(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use futures::StreamExt;
+
+    use super::*;
+    use crate::{vars, Template};
+
+    fn handle() -> RegistryHandle {
+        PromptRegistry::new()
+            .with_prompt("greet", Template::new("Hi {name}!").unwrap())
+            .into()
+    }
+
+    #[tokio::test]
+    async fn test_swap_bumps_version_and_notifies() {
+        let handle = handle();
+        let mut events = handle.subscribe();
+        let old_hash = handle.get("greet").unwrap().template_hash();
+
+        let new = Template::new("Hello {name}.").unwrap();
+        let new_hash = new.template_hash();
+        assert_eq!(handle.swap("greet", new).unwrap(), 2);
+        assert_eq!(
+            handle.format("greet", &vars!(name = "Ada")).unwrap(),
+            "Hello Ada."
+        );
+        assert!(handle.with_registry(|registry| registry.compiled("greet").is_some()));
+        assert_eq!(
+            events.recv().await,
+            Some(RegistryEvent::Changed {
+                name: "greet".to_string(),
+                version: 2,
+                previous: Some(old_hash),
+                current: Some(new_hash),
+            })
+        );
+
+        let broken = Template::new("{@const.missing} {name}").unwrap();
+        assert!(handle.swap("greet", broken).is_err());
+        assert_eq!(handle.version("greet"), Some(2));
+        assert_eq!(events.try_recv(), None);
+
+        assert!(handle.remove("greet").is_some());
+        assert_eq!(handle.version("greet"), None);
+        drop(handle);
+        let rest: Vec<RegistryEvent> = events.into_stream().collect().await;
+        assert!(matches!(
+            rest.as_slice(),
+            [RegistryEvent::Changed {
+                version: 3,
+                current: None,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn test_swap_while_rendering_concurrently() {
+        let handle = handle();
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = handle.clone();
+                thread::spawn(move || {
+                    for _ in 0..200 {
+                        let rendered = handle.format("greet", &vars!(name = "Ada")).unwrap();
+                        assert!(rendered == "Hi Ada!" || rendered == "Yo Ada!");
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..50 {
+            handle
+                .swap("greet", Template::new("Yo {name}!").unwrap())
+                .unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(handle.version("greet"), Some(51));
+    }
+}
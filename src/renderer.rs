@@ -0,0 +1,300 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::{BaseMessage, MessageEnum, MessageType};
+
+use crate::{
+    anthropic_request, render_chatml, render_llama2, render_llama3, ChatTemplate,
+    FewShotChatTemplate, Role, RoleMap, TemplateError,
+};
+
+pub trait ChatRenderer: Send + Sync {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError>;
+}
+
+// The historical `ChatTemplate::format` output: one "role: content" line per message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PlainPrefix;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChatMl {
+    pub add_generation_prompt: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OpenAiJson {
+    pub role_map: RoleMap,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Anthropic;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Llama2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Llama3 {
+    pub add_generation_prompt: bool,
+}
+
+// Per-`MessageType` line prefixes and the separator placed between messages.
+// The default reproduces `PlainPrefix`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FormatStyle {
+    human: String,
+    ai: String,
+    system: String,
+    chat: String,
+    tool: String,
+    separator: String,
+}
+
+impl Default for FormatStyle {
+    fn default() -> Self {
+        FormatStyle {
+            human: "human: ".to_string(),
+            ai: "ai: ".to_string(),
+            system: "system: ".to_string(),
+            chat: String::new(),
+            tool: String::new(),
+            separator: "\n".to_string(),
+        }
+    }
+}
+
+impl FormatStyle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_prefix(mut self, message_type: MessageType, prefix: &str) -> Self {
+        *self.prefix_mut(message_type) = prefix.to_string();
+        self
+    }
+
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    pub fn prefix(&self, message_type: MessageType) -> &str {
+        match message_type {
+            MessageType::Human => &self.human,
+            MessageType::Ai => &self.ai,
+            MessageType::System => &self.system,
+            MessageType::Chat => &self.chat,
+            MessageType::Tool => &self.tool,
+        }
+    }
+
+    pub fn separator(&self) -> &str {
+        &self.separator
+    }
+
+    fn prefix_mut(&mut self, message_type: MessageType) -> &mut String {
+        match message_type {
+            MessageType::Human => &mut self.human,
+            MessageType::Ai => &mut self.ai,
+            MessageType::System => &mut self.system,
+            MessageType::Chat => &mut self.chat,
+            MessageType::Tool => &mut self.tool,
+        }
+    }
+
+    pub fn join(&self, messages: &[Arc<MessageEnum>]) -> String {
+        messages
+            .iter()
+            .map(|message| match Role::of(message) {
+                Role::Custom(name) => format!("{}: {}", name, message.content()),
+                _ => format!(
+                    "{}{}",
+                    self.prefix(*message.message_type()),
+                    message.content()
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join(&self.separator)
+    }
+}
+
+pub(crate) fn join_messages(messages: &[Arc<MessageEnum>]) -> String {
+    FormatStyle::default().join(messages)
+}
+
+impl ChatRenderer for FormatStyle {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(self.join(messages))
+    }
+}
+
+impl ChatRenderer for PlainPrefix {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(join_messages(messages))
+    }
+}
+
+impl ChatMl {
+    pub fn for_generation() -> Self {
+        ChatMl {
+            add_generation_prompt: true,
+        }
+    }
+}
+
+impl ChatRenderer for ChatMl {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(render_chatml(
+            &RoleMap::openai().export(messages),
+            self.add_generation_prompt,
+        ))
+    }
+}
+
+impl OpenAiJson {
+    pub fn new() -> Self {
+        OpenAiJson {
+            role_map: RoleMap::openai(),
+        }
+    }
+}
+
+impl ChatRenderer for OpenAiJson {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        serde_json::to_string(&self.role_map.export(messages)).map_err(|e| {
+            TemplateError::MalformedTemplate(format!("Failed to serialize messages: {}", e), None)
+        })
+    }
+}
+
+impl ChatRenderer for Anthropic {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(anthropic_request(&RoleMap::openai().export(messages))?.to_string())
+    }
+}
+
+impl ChatRenderer for Llama2 {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(render_llama2(&RoleMap::text_only().export(messages)))
+    }
+}
+
+impl ChatRenderer for Llama3 {
+    fn render(&self, messages: &[Arc<MessageEnum>]) -> Result<String, TemplateError> {
+        Ok(render_llama3(
+            &crate::llama::llama3_role_map().export(messages),
+            self.add_generation_prompt,
+        ))
+    }
+}
+
+impl ChatTemplate {
+    pub fn format_with_renderer(
+        &self,
+        variables: &HashMap<&str, &str>,
+        renderer: &dyn ChatRenderer,
+    ) -> Result<String, TemplateError> {
+        renderer.render(&self.format_messages(variables)?)
+    }
+
+    pub fn format_styled(
+        &self,
+        variables: &HashMap<&str, &str>,
+        style: &FormatStyle,
+    ) -> Result<String, TemplateError> {
+        Ok(style.join(&self.format_messages(variables)?))
+    }
+}
+
+impl FewShotChatTemplate {
+    pub fn format_with_renderer(
+        &self,
+        renderer: &dyn ChatRenderer,
+    ) -> Result<String, TemplateError> {
+        renderer.render(&self.format_messages()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, examples, vars, FewShotTemplate, Formattable};
+
+    fn chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(System = "You are {persona}.", Human = "{question}"))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_plain_prefix_matches_format() {
+        let variables = vars!(persona = "terse", question = "Why?");
+        assert_eq!(
+            chat()
+                .format_with_renderer(&variables, &PlainPrefix)
+                .unwrap(),
+            chat().format(&variables).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_styled() {
+        let variables = vars!(persona = "terse", question = "Why?");
+        let style = FormatStyle::new()
+            .with_prefix(MessageType::System, "")
+            .with_prefix(MessageType::Human, "User: ")
+            .with_prefix(MessageType::Ai, "Assistant: ")
+            .with_separator("\n\n");
+
+        assert_eq!(
+            chat().format_styled(&variables, &style).unwrap(),
+            "You are terse.\n\nUser: Why?"
+        );
+        assert_eq!(style.prefix(MessageType::Tool), "");
+        assert_eq!(
+            chat()
+                .format_styled(&variables, &FormatStyle::default())
+                .unwrap(),
+            chat().format(&variables).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_renderers_share_formatted_messages() {
+        let variables = vars!(persona = "terse", question = "Why?");
+        let renderers: Vec<(Box<dyn ChatRenderer>, &str)> = vec![
+            (
+                Box::new(ChatMl::for_generation()),
+                "<|im_start|>assistant\n",
+            ),
+            (
+                Box::new(OpenAiJson::new()),
+                r#"{"role":"user","content":"Why?"}]"#,
+            ),
+            (Box::new(Anthropic), r#""system":"You are terse.""#),
+            (Box::new(Llama2), "You are terse.\n<</SYS>>\n\nWhy? [/INST]"),
+            (
+                Box::new(Llama3::default()),
+                "user<|end_header_id|>\n\nWhy?<|eot_id|>",
+            ),
+        ];
+
+        for (renderer, expected) in renderers {
+            let rendered = chat()
+                .format_with_renderer(&variables, renderer.as_ref())
+                .unwrap();
+            assert!(rendered.contains(expected), "{}", rendered);
+        }
+    }
+
+    #[test]
+    fn test_few_shot_chat_template_with_renderer() {
+        let few_shot = FewShotChatTemplate::new(
+            FewShotTemplate::new(examples!(("{input}: 2+2?", "{output}: 4"))),
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap(),
+        );
+
+        assert_eq!(
+            few_shot.format_with_renderer(&ChatMl::default()).unwrap(),
+            "<|im_start|>user\n2+2?<|im_end|>\n<|im_start|>assistant\n4<|im_end|>\n"
+        );
+    }
+}
@@ -0,0 +1,259 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    CanonicalHash, FormatOptions, Formattable, OutputEncoding, TemplateError,
+    UnfilledPlaceholderPolicy,
+};
+
+pub trait Replayable: Formattable + CanonicalHash {}
+
+impl<T: Formattable + CanonicalHash> Replayable for T {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayOptions {
+    #[serde(default)]
+    pub unfilled_placeholders: UnfilledPlaceholderPolicy,
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+}
+
+impl From<&FormatOptions> for ReplayOptions {
+    fn from(options: &FormatOptions) -> Self {
+        ReplayOptions {
+            unfilled_placeholders: options.unfilled_placeholders(),
+            output_encoding: options.output_encoding(),
+        }
+    }
+}
+
+impl From<ReplayOptions> for FormatOptions {
+    fn from(options: ReplayOptions) -> Self {
+        FormatOptions::new()
+            .with_unfilled_placeholders(options.unfilled_placeholders)
+            .with_output_encoding(options.output_encoding)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayEntry {
+    pub template: String,
+    pub variables: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "is_default_options")]
+    pub options: ReplayOptions,
+    pub output_digest: String,
+}
+
+fn is_default_options(options: &ReplayOptions) -> bool {
+    *options == ReplayOptions::default()
+}
+
+fn digest_output(output: &str) -> String {
+    Sha256::digest(output.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
+pub enum ReplayFailure {
+    UnknownTemplate(String),
+    OutputChanged(String),
+    Error(TemplateError),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ReplayReport {
+    pub verified: usize,
+    pub failures: Vec<(usize, ReplayFailure)>,
+}
+
+impl ReplayReport {
+    pub fn is_identical(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RenderLog {
+    entries: Vec<ReplayEntry>,
+}
+
+impl RenderLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> &[ReplayEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Only the digest of the output is kept so logs of large prompts stay small.
+    pub fn record<T: Replayable + ?Sized>(
+        &mut self,
+        template: &T,
+        variables: &HashMap<&str, &str>,
+        options: &FormatOptions,
+    ) -> Result<String, TemplateError> {
+        let output = template.format_with_options(variables, options)?;
+        self.entries.push(ReplayEntry {
+            template: template.template_hash().to_string(),
+            variables: variables
+                .iter()
+                .map(|(&key, &value)| (key.to_string(), value.to_string()))
+                .collect(),
+            options: ReplayOptions::from(options),
+            output_digest: digest_output(&output),
+        });
+        Ok(output)
+    }
+
+    pub fn to_jsonl(&self) -> String {
+        self.entries
+            .iter()
+            .map(|entry| serde_json::to_string(entry).expect("replay entries serialize") + "\n")
+            .collect()
+    }
+
+    pub fn from_jsonl(log: &str) -> Result<Self, TemplateError> {
+        let entries = log
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    TemplateError::MalformedTemplate(
+                        format!("Invalid render log entry on line {}: {}", index + 1, e),
+                        None,
+                    )
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(RenderLog { entries })
+    }
+
+    pub fn replay(&self, templates: &[&dyn Replayable]) -> ReplayReport {
+        let by_hash: HashMap<String, &dyn Replayable> = templates
+            .iter()
+            .map(|&template| (template.template_hash().to_string(), template))
+            .collect();
+
+        let mut report = ReplayReport::default();
+        for (index, entry) in self.entries.iter().enumerate() {
+            let Some(template) = by_hash.get(&entry.template) else {
+                report.failures.push((
+                    index,
+                    ReplayFailure::UnknownTemplate(entry.template.clone()),
+                ));
+                continue;
+            };
+
+            let variables: HashMap<&str, &str> = entry
+                .variables
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            let options = FormatOptions::from(entry.options);
+            match template.format_with_options(&variables, &options) {
+                Ok(output) if digest_output(&output) == entry.output_digest => report.verified += 1,
+                Ok(output) => report
+                    .failures
+                    .push((index, ReplayFailure::OutputChanged(digest_output(&output)))),
+                Err(error) => report.failures.push((index, ReplayFailure::Error(error))),
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, System};
+    use crate::{chats, vars, ChatTemplate, Template};
+
+    #[test]
+    fn test_record_and_replay_round_trip() {
+        let greeting = Template::new("Hello {name}!").unwrap();
+        let chat = ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let mut log = RenderLog::new();
+        log.record(&greeting, &vars!(name = "Ada"), &FormatOptions::new())
+            .unwrap();
+        log.record(
+            &chat,
+            &vars!(persona = "kind", question = "a b"),
+            &FormatOptions::new().with_output_encoding(OutputEncoding::Url),
+        )
+        .unwrap();
+        assert!(log
+            .record(&greeting, &vars!(), &FormatOptions::new())
+            .is_err());
+        assert_eq!(log.len(), 2);
+
+        let parsed = RenderLog::from_jsonl(&log.to_jsonl()).unwrap();
+        assert_eq!(parsed, log);
+
+        let report = parsed.replay(&[&greeting, &chat]);
+        assert!(report.is_identical());
+        assert_eq!(report.verified, 2);
+    }
+
+    #[test]
+    fn test_replay_reports_unknown_templates_and_bad_lines() {
+        let mut log = RenderLog::new();
+        log.record(
+            &Template::new("Hi {name}").unwrap(),
+            &vars!(name = "Bob"),
+            &FormatOptions::new(),
+        )
+        .unwrap();
+
+        let edited = Template::new("Hi {name}!").unwrap();
+        let report = log.replay(&[&edited]);
+        assert_eq!(report.verified, 0);
+        assert!(matches!(
+            report.failures[0],
+            (0, ReplayFailure::UnknownTemplate(_))
+        ));
+
+        assert!(RenderLog::from_jsonl("{\"template\": 1}\n").is_err());
+    }
+
+    #[test]
+    fn test_replay_detects_changed_output() {
+        let template = Template::new("Hi {name}").unwrap();
+        let mut log = RenderLog::new();
+        log.record(&template, &vars!(name = "Bob"), &FormatOptions::new())
+            .unwrap();
+
+        let tampered = log
+            .to_jsonl()
+            .replace(&log.entries()[0].output_digest, "00");
+        let report = RenderLog::from_jsonl(&tampered)
+            .unwrap()
+            .replay(&[&template]);
+        assert_eq!(
+            report.failures,
+            vec![(
+                0,
+                ReplayFailure::OutputChanged(log.entries()[0].output_digest.clone())
+            )]
+        );
+    }
+}
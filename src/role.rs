@@ -1,9 +1,11 @@
-use std::{convert::TryFrom, fmt, sync::Arc};
+use std::{collections::BTreeMap, convert::TryFrom, fmt, sync::Arc};
 
 use messageforge::{AiMessage, HumanMessage, MessageEnum, SystemMessage};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+use crate::TemplateError;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
 pub enum Role {
     System,
     Human,
@@ -11,6 +13,14 @@ pub enum Role {
     Tool,
     Placeholder,
     FewShotPrompt,
+    Custom(String),
+}
+
+// Extra role names to accept when loading messages, on top of the ones
+// `Role::try_from` already knows. Lookups are case-insensitive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleAliases {
+    aliases: BTreeMap<String, Role>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
@@ -28,10 +38,23 @@ impl TryFrom<&str> for Role {
     type Error = InvalidRoleError;
 
     fn try_from(role: &str) -> Result<Self, Self::Error> {
+        // Custom roles are opted into explicitly so a misspelt built-in role
+        // still fails to parse.
+        if let Some(prefix) = role.get(..Role::CUSTOM_PREFIX.len())
+            && prefix.eq_ignore_ascii_case(Role::CUSTOM_PREFIX)
+        {
+            let name = &role[Role::CUSTOM_PREFIX.len()..];
+            return match name.is_empty() {
+                true => Err(InvalidRoleError),
+                false => Ok(Role::custom(name)),
+            };
+        }
+
         match role.to_lowercase().as_str() {
-            "system" => Ok(Role::System),
-            "human" => Ok(Role::Human),
-            "ai" => Ok(Role::Ai),
+            // OpenAI names, so captured API histories load as-is.
+            "system" | "developer" => Ok(Role::System),
+            "human" | "user" => Ok(Role::Human),
+            "ai" | "assistant" => Ok(Role::Ai),
             "tool" => Ok(Role::Tool),
             "placeholder" => Ok(Role::Placeholder),
             "fewshotprompt" => Ok(Role::FewShotPrompt),
@@ -40,7 +63,58 @@ impl TryFrom<&str> for Role {
     }
 }
 
+impl RoleAliases {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_alias(mut self, alias: &str, role: Role) -> Self {
+        self.aliases.insert(alias.to_lowercase(), role);
+        self
+    }
+
+    pub fn resolve(&self, name: &str) -> Result<Role, InvalidRoleError> {
+        match self.aliases.get(&name.to_lowercase()) {
+            Some(role) => Ok(role.clone()),
+            None => Role::try_from(name),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty()
+    }
+
+    // In alias order, lowercased.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Role)> {
+        self.aliases
+            .iter()
+            .map(|(alias, role)| (alias.as_str(), role))
+    }
+}
+
 impl Role {
+    pub const CUSTOM_PREFIX: &'static str = "custom:";
+    pub const CUSTOM_ROLE_KEY: &'static str = "role";
+
+    pub fn custom(name: &str) -> Self {
+        Role::Custom(name.to_string())
+    }
+
+    // Custom-role messages travel as human messages tagged through
+    // `additional_kwargs`, since messageforge has no free-form message type.
+    pub fn of(message: &MessageEnum) -> Self {
+        match message {
+            MessageEnum::System(_) => Role::System,
+            MessageEnum::Human(human) => match human.additional_kwargs().get(Self::CUSTOM_ROLE_KEY)
+            {
+                Some(name) => Role::Custom(name.clone()),
+                None => Role::Human,
+            },
+            MessageEnum::Ai(_) => Role::Ai,
+            MessageEnum::Tool(_) => Role::Tool,
+        }
+    }
+
     pub fn as_str(&self) -> &str {
         match self {
             Role::System => "system",
@@ -49,19 +123,52 @@ impl Role {
             Role::Tool => "tool",
             Role::Placeholder => "placeholder",
             Role::FewShotPrompt => "fewshotprompt",
+            Role::Custom(name) => name,
         }
     }
 
-    pub fn to_message(self, content: &str) -> Result<Arc<MessageEnum>, InvalidRoleError> {
+    pub fn to_message(&self, content: &str) -> Result<Arc<MessageEnum>, InvalidRoleError> {
         let message_enum = match self {
             Role::System => MessageEnum::System(SystemMessage::new(content)),
             Role::Human => MessageEnum::Human(HumanMessage::new(content)),
             Role::Ai => MessageEnum::Ai(AiMessage::new(content)),
+            Role::Custom(name) => {
+                let mut human = HumanMessage::new(content);
+                human
+                    .base
+                    .additional_kwargs
+                    .insert(Self::CUSTOM_ROLE_KEY.to_string(), name.clone());
+                MessageEnum::Human(human)
+            }
             _ => return Err(InvalidRoleError),
         };
 
         Ok(Arc::new(message_enum))
     }
+
+    // Placeholders and few-shot prompts expand to a list of messages, and a
+    // tool message cannot be built without the id of the call it answers, so
+    // neither can back a single templated message.
+    pub fn ensure_message_role(&self) -> Result<(), TemplateError> {
+        match self {
+            Role::System | Role::Human | Role::Ai | Role::Custom(_) => Ok(()),
+            Role::Placeholder | Role::FewShotPrompt => {
+                Err(TemplateError::PlaceholderNotAllowedHere(format!(
+                    "'{}' expands to a list of messages and cannot be a single templated message",
+                    self
+                )))
+            }
+            Role::Tool => Err(TemplateError::ToolRoleNotSupported(
+                "tool messages need a tool_call_id; use ToolUseExample or a prebuilt ToolMessage"
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub fn render_message(&self, content: &str) -> Result<Arc<MessageEnum>, TemplateError> {
+        self.ensure_message_role()?;
+        Ok(self.to_message(content)?)
+    }
 }
 
 impl fmt::Display for Role {
@@ -109,6 +216,43 @@ mod tests {
         assert!(Role::try_from("invalid").is_err());
     }
 
+    #[test]
+    fn test_openai_role_names() {
+        assert_eq!(Role::try_from("user").unwrap(), Role::Human);
+        assert_eq!(Role::try_from("Assistant").unwrap(), Role::Ai);
+        assert_eq!(Role::try_from("developer").unwrap(), Role::System);
+    }
+
+    #[test]
+    fn test_role_aliases_resolve() {
+        let aliases = RoleAliases::new()
+            .with_alias("Model", Role::Ai)
+            .with_alias("user", Role::System);
+
+        assert_eq!(aliases.resolve("model").unwrap(), Role::Ai);
+        assert_eq!(aliases.resolve("USER").unwrap(), Role::System);
+        assert_eq!(aliases.resolve("assistant").unwrap(), Role::Ai);
+        assert!(aliases.resolve("bot").is_err());
+        assert!(RoleAliases::new().is_empty());
+    }
+
+    #[test]
+    fn test_custom_role() {
+        let critic = Role::try_from("Custom:Critic").unwrap();
+        assert_eq!(critic, Role::custom("Critic"));
+        assert_eq!(critic.to_string(), "Critic");
+        assert!(Role::try_from("custom:").is_err());
+        assert!(Role::try_from("critic").is_err());
+
+        let message = critic.render_message("Too vague.").unwrap();
+        assert_eq!(message.content(), "Too vague.");
+        assert_eq!(Role::of(&message), critic);
+        assert_eq!(
+            Role::of(&Role::Human.to_message("Hi").unwrap()),
+            Role::Human
+        );
+    }
+
     #[test]
     fn test_system_message_creation() {
         test_message_creation(Role::System, "This is a system message.");
@@ -134,6 +278,23 @@ mod tests {
         test_invalid_message_creation(Role::Placeholder, "This is a placeholder message.");
     }
 
+    #[test]
+    fn test_render_message_targeted_errors() {
+        assert!(Role::Human.render_message("Hi").is_ok());
+        assert!(matches!(
+            Role::Placeholder.render_message("{history}"),
+            Err(TemplateError::PlaceholderNotAllowedHere(_))
+        ));
+        assert!(matches!(
+            Role::FewShotPrompt.ensure_message_role(),
+            Err(TemplateError::PlaceholderNotAllowedHere(_))
+        ));
+        assert!(matches!(
+            Role::Tool.render_message("42"),
+            Err(TemplateError::ToolRoleNotSupported(_))
+        ));
+    }
+
     #[test]
     fn test_invalid_role_message() {
         let result = Role::try_from("invalid");
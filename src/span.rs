@@ -0,0 +1,114 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    detection::line_column,
+    text::length::{last_graphemes, truncate_graphemes},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourceSpan {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub snippet: String,
+}
+
+impl SourceSpan {
+    pub const SNIPPET_RADIUS: usize = 20;
+
+    pub fn locate(source: &str, offset: usize) -> Self {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[offset..]
+            .find('\n')
+            .map_or(source.len(), |i| offset + i);
+        let before = &source[line_start..offset];
+        let after = &source[offset..line_end];
+
+        let mut snippet = last_graphemes(before, Self::SNIPPET_RADIUS).to_string();
+        snippet.push_str(truncate_graphemes(after, Self::SNIPPET_RADIUS));
+
+        let (line, column) = line_column(source, offset);
+        SourceSpan {
+            offset,
+            line,
+            column,
+            snippet: snippet.trim_end_matches('\r').to_string(),
+        }
+    }
+
+    pub fn from_line_column(source: &str, line: usize, column: usize) -> Self {
+        let line_start: usize = source
+            .split_inclusive('\n')
+            .take(line.saturating_sub(1))
+            .map(str::len)
+            .sum();
+        let column_offset: usize = source[line_start..]
+            .chars()
+            .take(column.saturating_sub(1))
+            .map(char::len_utf8)
+            .sum();
+        Self::locate(source, line_start + column_offset)
+    }
+}
+
+impl fmt::Display for SourceSpan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} near `{}`",
+            self.line, self.column, self.snippet
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_in_multiline_source() {
+        let source = "You are a helper.\nAnswer {question politely.\nBye";
+        let span = SourceSpan::locate(source, source.find("{question").unwrap());
+
+        assert_eq!(span.offset, 25);
+        assert_eq!(span.line, 2);
+        assert_eq!(span.column, 8);
+        assert_eq!(span.snippet, "Answer {question politely.");
+        assert_eq!(
+            span.to_string(),
+            "line 2, column 8 near `Answer {question politely.`"
+        );
+    }
+
+    #[test]
+    fn test_snippet_is_clipped_around_offset() {
+        let source = format!("{}{{oops{}", "a".repeat(50), "b".repeat(50));
+        let span = SourceSpan::locate(&source, 50);
+
+        assert_eq!(span.column, 51);
+        assert_eq!(
+            span.snippet,
+            format!("{}{{oops{}", "a".repeat(20), "b".repeat(15))
+        );
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        let source = "héllo {";
+        let span = SourceSpan::locate(source, source.len() - 1);
+        assert_eq!(span.column, 7);
+        assert_eq!(SourceSpan::from_line_column(source, 1, 7), span);
+        assert_eq!(SourceSpan::locate(source, 100).offset, source.len());
+
+        let emoji = format!("{}{{", "👍🏽".repeat(30));
+        let span = SourceSpan::locate(&emoji, emoji.len() - 1);
+        assert_eq!(span.snippet, format!("{}{{", "👍🏽".repeat(20)));
+    }
+}
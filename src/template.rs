@@ -1,12 +1,28 @@
 use handlebars::Handlebars;
+use minijinja::{Environment, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-
+use std::sync::Arc;
+
+use crate::braces::{escape_braces, mask_escaped_braces, unescape_braces, BraceLiterals};
+use crate::compiled::CompiledTemplate;
+use crate::config::PromptForgeConfig;
+use crate::detection::{check_source, DetectionError};
+use crate::encryption::refuse_sensitive;
+use crate::example_metadata::ExampleMetadata;
+use crate::filters::{FilterCall, FilterRegistry};
+use crate::format_options::{DeadlineWriter, FormatOptions, RenderDeadline};
 use crate::formatting::{Formattable, Templatable};
-use crate::placeholder::extract_variables;
-use crate::template_format::{
-    detect_template, merge_vars, validate_template, TemplateError, TemplateFormat,
+use crate::metrics::{estimate_tokens, PromptMetrics};
+use crate::model_profile::ModelProfile;
+use crate::ordering::serialize_sorted;
+use crate::placeholder::{
+    extract_positional, extract_variable_defaults, extract_variables, FMTSTRING_PLACEHOLDER_RE,
+    POSITIONAL_PLACEHOLDER_RE,
 };
+use crate::span::SourceSpan;
+use crate::template_format::{detection_error, merge_vars, TemplateError, TemplateFormat};
+use crate::vars::{borrow_vars, serialize_vars, PromptVars};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Template {
@@ -15,12 +31,37 @@ pub struct Template {
     input_variables: Vec<String>,
     #[serde(skip, default)]
     handlebars: Option<Handlebars<'static>>,
-    #[serde(skip)]
+    #[serde(skip, default)]
+    jinja: Option<Arc<Environment<'static>>>,
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
     partials: HashMap<String, String>,
+    // Variable name to a filter chain applied to its value before rendering,
+    // in every template format.
+    #[serde(
+        default,
+        skip_serializing_if = "HashMap::is_empty",
+        serialize_with = "serialize_sorted"
+    )]
+    transforms: HashMap<String, String>,
+    #[serde(skip)]
+    filters: Option<Arc<FilterRegistry>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<ExampleMetadata>,
+    #[serde(
+        skip_deserializing,
+        skip_serializing_if = "std::ops::Not::not",
+        serialize_with = "refuse_sensitive"
+    )]
+    pub(crate) sensitive: bool,
 }
 
 impl Template {
     pub const MUSTACHE_TEMPLATE: &'static str = "mustache_template";
+    pub const JINJA2_TEMPLATE: &'static str = "jinja2_template";
 
     pub fn new(tmpl: &str) -> Result<Self, TemplateError> {
         Self::new_with_config(tmpl, None, None)
@@ -31,13 +72,44 @@ impl Template {
         template_format: Option<TemplateFormat>,
         input_variables: Option<Vec<String>>,
     ) -> Result<Self, TemplateError> {
-        validate_template(tmpl)?;
+        Self::build(
+            tmpl,
+            template_format,
+            input_variables,
+            &BraceLiterals::new(),
+        )
+    }
+
+    pub fn new_with_literals(tmpl: &str, literals: &BraceLiterals) -> Result<Self, TemplateError> {
+        Self::build(tmpl, None, None, literals)
+    }
+
+    fn build(
+        tmpl: &str,
+        template_format: Option<TemplateFormat>,
+        input_variables: Option<Vec<String>>,
+        literals: &BraceLiterals,
+    ) -> Result<Self, TemplateError> {
+        // Literals are only escaped for detection and `{var}` rendering;
+        // Mustache leaves single braces alone, so it keeps the source as is.
+        let source = tmpl;
+        let escaped = literals.escape(source);
+        let tmpl = escaped.as_ref();
+
+        let template_format = match check_source(tmpl, template_format.as_ref().map(Into::into)) {
+            Ok(detected) => TemplateFormat::from(detected),
+            Err(DetectionError::Unsupported) => {
+                return Err(TemplateError::UnsupportedFormat(
+                    "Unable to detect template format".into(),
+                ))
+            }
+            Err(error) => return Err(detection_error(tmpl, error)),
+        };
+
+        if template_format == TemplateFormat::Jinja2 {
+            return Self::new_jinja2(source, input_variables);
+        }
 
-        let template_format = template_format
-            .or_else(|| detect_template(tmpl).ok())
-            .ok_or_else(|| {
-                TemplateError::UnsupportedFormat("Unable to detect template format".into())
-            })?;
         let input_variables = input_variables.unwrap_or_else(|| {
             extract_variables(tmpl)
                 .into_iter()
@@ -45,11 +117,11 @@ impl Template {
                 .collect()
         });
 
-        let handlebars = if template_format == TemplateFormat::Mustache {
-            let handle = Self::initialize_handlebars(tmpl)?;
-            Some(handle)
+        let (tmpl, handlebars) = if template_format == TemplateFormat::Mustache {
+            let handle = Self::initialize_handlebars(source)?;
+            (source, Some(handle))
         } else {
-            None
+            (tmpl, None)
         };
 
         Ok(Template {
@@ -57,7 +129,42 @@ impl Template {
             template_format,
             input_variables,
             handlebars,
+            jinja: None,
             partials: HashMap::new(),
+            transforms: HashMap::new(),
+            filters: None,
+            metadata: None,
+            sensitive: false,
+        })
+    }
+
+    fn new_jinja2(tmpl: &str, input_variables: Option<Vec<String>>) -> Result<Self, TemplateError> {
+        let environment = Self::initialize_jinja(tmpl)?;
+
+        let input_variables = match input_variables {
+            Some(input_variables) => input_variables,
+            None => {
+                let template = environment
+                    .get_template(Self::JINJA2_TEMPLATE)
+                    .map_err(|e| TemplateError::MalformedTemplate(e.to_string(), None))?;
+                let mut variables: Vec<String> =
+                    template.undeclared_variables(false).into_iter().collect();
+                variables.sort();
+                variables
+            }
+        };
+
+        Ok(Template {
+            template: tmpl.to_string(),
+            template_format: TemplateFormat::Jinja2,
+            input_variables,
+            handlebars: None,
+            jinja: Some(Arc::new(environment)),
+            partials: HashMap::new(),
+            transforms: HashMap::new(),
+            filters: None,
+            metadata: None,
+            sensitive: false,
         })
     }
 
@@ -79,71 +186,523 @@ impl Template {
         &self.partials
     }
 
+    pub fn partial_format(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Template, TemplateError> {
+        match self.template_format {
+            TemplateFormat::FmtString => {}
+            TemplateFormat::PlainText => return Ok(self.clone()),
+            _ => {
+                return Err(TemplateError::UnsupportedFormat(
+                    "Partial formatting is only supported for FmtString templates".to_string(),
+                ))
+            }
+        }
+
+        let transformed = self.apply_transforms(variables)?;
+        let masked = mask_escaped_braces(&self.template);
+        let mut result = String::with_capacity(self.template.len());
+        let mut last = 0;
+
+        for cap in FMTSTRING_PLACEHOLDER_RE.captures_iter(&masked) {
+            let (whole, name) = (
+                cap.get(0).unwrap(),
+                &self.template[cap.get(1).unwrap().range()],
+            );
+            let Some(&value) = variables.get(name) else {
+                continue;
+            };
+            if !self.input_variables.iter().any(|var| var == name) {
+                continue;
+            }
+            let value = transformed.get(name).map_or(value, String::as_str);
+
+            let value = match cap.get(3) {
+                Some(filters) if !filters.is_empty() => {
+                    self.apply_filters(&self.template[filters.range()], value, filters.start())?
+                }
+                _ => value.to_string(),
+            };
+
+            result.push_str(&self.template[last..whole.start()]);
+            result.push_str(&escape_braces(&value));
+            last = whole.end();
+        }
+        result.push_str(&self.template[last..]);
+
+        let remaining = self
+            .input_variables
+            .iter()
+            .filter(|var| !variables.contains_key(var.as_str()))
+            .cloned()
+            .collect();
+
+        let mut template =
+            Template::new_with_config(&result, Some(TemplateFormat::FmtString), Some(remaining))?;
+        template.partials = self
+            .partials
+            .iter()
+            .filter(|(name, _)| !variables.contains_key(name.as_str()))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        template.transforms = self
+            .transforms
+            .iter()
+            .filter(|(name, _)| !variables.contains_key(name.as_str()))
+            .map(|(name, chain)| (name.clone(), chain.clone()))
+            .collect();
+        template.filters = self.filters.clone();
+        template.metadata = self.metadata.clone();
+        Ok(template)
+    }
+
+    pub(crate) fn with_source(
+        &self,
+        source: &str,
+        template_format: TemplateFormat,
+        input_variables: Vec<String>,
+    ) -> Result<Template, TemplateError> {
+        self.with_source_and_literals(
+            source,
+            template_format,
+            input_variables,
+            &BraceLiterals::new(),
+        )
+    }
+
+    pub(crate) fn with_source_and_literals(
+        &self,
+        source: &str,
+        template_format: TemplateFormat,
+        input_variables: Vec<String>,
+        literals: &BraceLiterals,
+    ) -> Result<Template, TemplateError> {
+        let mut template = Template::build(
+            source,
+            Some(template_format),
+            Some(input_variables),
+            literals,
+        )?;
+        template.partials = self
+            .partials
+            .iter()
+            .filter(|(name, _)| template.input_variables.contains(name))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        template.transforms = self
+            .transforms
+            .iter()
+            .filter(|(name, _)| template.input_variables.contains(name))
+            .map(|(name, chain)| (name.clone(), chain.clone()))
+            .collect();
+        template.filters = self.filters.clone();
+        template.metadata = self.metadata.clone();
+        Ok(template)
+    }
+
+    pub fn with_transform(mut self, variable: &str, chain: &str) -> Result<Self, TemplateError> {
+        FilterCall::parse_chain(chain)?;
+        self.transforms
+            .insert(variable.to_string(), chain.to_string());
+        Ok(self)
+    }
+
+    pub fn transforms(&self) -> &HashMap<String, String> {
+        &self.transforms
+    }
+
+    fn apply_transforms(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<HashMap<String, String>, TemplateError> {
+        self.transforms
+            .iter()
+            .filter_map(|(name, chain)| Some((name, chain, *variables.get(name.as_str())?)))
+            .map(|(name, chain, value)| {
+                FilterCall::parse_chain(chain)
+                    .and_then(|calls| self.filters().apply_chain(&calls, value))
+                    .map(|value| (name.clone(), value))
+            })
+            .collect()
+    }
+
+    pub fn with_filters(mut self, filters: FilterRegistry) -> Self {
+        self.filters = Some(Arc::new(filters));
+        self
+    }
+
+    // A registry set on the template itself takes precedence.
+    pub(crate) fn or_filters(mut self, filters: &Arc<FilterRegistry>) -> Self {
+        self.filters.get_or_insert_with(|| Arc::clone(filters));
+        self
+    }
+
+    pub fn filters(&self) -> &FilterRegistry {
+        self.filters
+            .as_deref()
+            .unwrap_or_else(|| FilterRegistry::builtin())
+    }
+
+    pub fn with_metadata(mut self, metadata: ExampleMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn metadata(&self) -> Option<&ExampleMetadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn is_negative_example(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .is_some_and(ExampleMetadata::is_negative)
+    }
+
+    pub fn variable_defaults(&self) -> HashMap<&str, &str> {
+        if self.template_format != TemplateFormat::FmtString {
+            return HashMap::new();
+        }
+
+        extract_variable_defaults(&self.template)
+            .into_iter()
+            .filter(|(name, _)| self.input_variables.iter().any(|var| var == name))
+            .collect()
+    }
+
+    pub fn required_variables(&self) -> Vec<String> {
+        let defaults = self.variable_defaults();
+        self.input_variables
+            .iter()
+            .filter(|var| !defaults.contains_key(var.as_str()))
+            .cloned()
+            .collect()
+    }
+
+    pub fn positional_count(&self) -> usize {
+        match self.template_format {
+            TemplateFormat::FmtString | TemplateFormat::PlainText => {
+                extract_positional(&self.template)
+                    .into_iter()
+                    .max()
+                    .map_or(0, |index| index + 1)
+            }
+            TemplateFormat::Mustache | TemplateFormat::Jinja2 => 0,
+        }
+    }
+
+    // Fills `{0}`, `{1}`, ... in order, like Python's `str.format`; extra
+    // arguments are ignored. Named placeholders must already be covered by
+    // partials or defaults.
+    pub fn format_args(&self, args: &[&str]) -> Result<String, TemplateError> {
+        if !matches!(
+            self.template_format,
+            TemplateFormat::FmtString | TemplateFormat::PlainText
+        ) {
+            return Err(TemplateError::UnsupportedFormat(format!(
+                "Positional arguments need a {{var}} template, not {:?}",
+                self.template_format
+            )));
+        }
+
+        let count = self.positional_count();
+        let named: Vec<String> = self
+            .required_variables()
+            .into_iter()
+            .filter(|var| !self.partials.contains_key(var))
+            .collect();
+        match (named.is_empty(), count) {
+            (true, _) => {}
+            (false, 0) => {
+                return Err(TemplateError::MalformedTemplate(
+                    format!(
+                        "Named placeholders need format(), not format_args(): {}",
+                        named.join(", ")
+                    ),
+                    None,
+                ));
+            }
+            (false, _) => {
+                return Err(TemplateError::MalformedTemplate(
+                    format!(
+                        "Template mixes positional and named placeholders: {}",
+                        named.join(", ")
+                    ),
+                    None,
+                ));
+            }
+        }
+        if args.len() < count {
+            return Err(TemplateError::MissingVariable(format!(
+                "Positional argument {} is missing; the template takes {}",
+                args.len(),
+                count
+            )));
+        }
+
+        let masked = mask_escaped_braces(&self.template);
+        let mut source = String::with_capacity(self.template.len());
+        let mut last = 0;
+        for cap in POSITIONAL_PLACEHOLDER_RE.captures_iter(&masked) {
+            let whole = cap.get(0).unwrap();
+            let Some(arg) = cap[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| args.get(index))
+            else {
+                continue;
+            };
+            source.push_str(&self.template[last..whole.start()]);
+            source.push_str(&escape_braces(arg));
+            last = whole.end();
+        }
+        source.push_str(&self.template[last..]);
+
+        self.with_source(
+            &source,
+            TemplateFormat::FmtString,
+            self.input_variables.clone(),
+        )?
+        .format(&HashMap::new())
+    }
+
+    pub fn format_with<T: Serialize + ?Sized>(&self, ctx: &T) -> Result<String, TemplateError> {
+        let variables = serialize_vars(ctx)?;
+        self.format(&borrow_vars(&variables))
+    }
+
+    pub fn format_vars<V: PromptVars>(&self, vars: V) -> Result<String, TemplateError> {
+        let variables = vars.into_vars();
+        self.format(&borrow_vars(&variables))
+    }
+
+    pub fn format_for(
+        &self,
+        variables: &HashMap<&str, &str>,
+        profile: &ModelProfile,
+    ) -> Result<String, TemplateError> {
+        self.format(&profile.resolve(variables))
+    }
+
+    pub fn compile(&self) -> Result<CompiledTemplate, TemplateError> {
+        CompiledTemplate::new(self)
+    }
+
+    pub fn metrics(&self) -> PromptMetrics {
+        PromptMetrics {
+            variable_count: self.input_variables.len(),
+            estimated_tokens: estimate_tokens(&self.template),
+            ..Default::default()
+        }
+    }
+
     fn initialize_handlebars(tmpl: &str) -> Result<Handlebars<'static>, TemplateError> {
         let mut handlebars = Handlebars::new();
         handlebars
             .register_template_string(Self::MUSTACHE_TEMPLATE, tmpl)
             .map_err(|e| {
-                TemplateError::MalformedTemplate(format!("Failed to register template: {}", e))
+                let span = e
+                    .pos()
+                    .map(|(line, column)| SourceSpan::from_line_column(tmpl, line, column));
+                registration_error(format!("Failed to register template: {}", e), span)
             })?;
         Ok(handlebars)
     }
 
+    fn initialize_jinja(tmpl: &str) -> Result<Environment<'static>, TemplateError> {
+        let mut environment = Environment::new();
+        environment
+            .add_template_owned(Self::JINJA2_TEMPLATE, tmpl.to_string())
+            .map_err(|e| {
+                let span = e
+                    .range()
+                    .map(|range| SourceSpan::locate(tmpl, range.start))
+                    .or_else(|| {
+                        e.line()
+                            .map(|line| SourceSpan::from_line_column(tmpl, line, 1))
+                    });
+                registration_error(format!("Failed to register template: {}", e), span)
+            })?;
+        Ok(environment)
+    }
+
     fn validate_variables(
         &self,
         variables: &std::collections::HashMap<&str, &str>,
     ) -> Result<(), TemplateError> {
-        for var in &self.input_variables {
-            let has_key = variables.contains_key(var.as_str());
-            if !has_key {
-                return Err(TemplateError::MissingVariable(format!(
-                    "Variable '{}' is missing. Expected: {:?}, but received: {:?}",
-                    var,
-                    self.input_variables,
-                    variables.keys().collect::<Vec<_>>()
-                )));
-            }
-        }
-        Ok(())
+        let defaults = self.variable_defaults();
+        let missing: Vec<&String> = self
+            .input_variables
+            .iter()
+            .filter(|var| {
+                !variables.contains_key(var.as_str()) && !defaults.contains_key(var.as_str())
+            })
+            .collect();
+
+        check_missing(&self.input_variables, &missing, variables)
     }
 
     fn format_fmtstring(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
-        let mut result = self.template.clone();
-
-        for var in &self.input_variables {
-            let placeholder = format!("{{{}}}", var);
+        let defaults = self.variable_defaults();
+        let masked = mask_escaped_braces(&self.template);
+        let mut result = String::with_capacity(self.template.len());
+        let mut last = 0;
+
+        for cap in FMTSTRING_PLACEHOLDER_RE.captures_iter(&masked) {
+            let (whole, name) = (
+                cap.get(0).unwrap(),
+                &self.template[cap.get(1).unwrap().range()],
+            );
+            if !self.input_variables.iter().any(|var| var == name) {
+                continue;
+            }
 
-            if let Some(value) = variables.get(var.as_str()) {
-                result = result.replace(&placeholder, value);
-            } else {
-                return Err(TemplateError::MissingVariable(var.clone()));
+            let value = variables
+                .get(name)
+                .copied()
+                .or_else(|| cap.get(2).map(|default| &self.template[default.range()]))
+                .or_else(|| defaults.get(name).copied())
+                .ok_or_else(|| TemplateError::MissingVariable(name.to_string()))?;
+
+            result.push_str(&unescape_braces(&self.template[last..whole.start()]));
+            match cap.get(3) {
+                Some(filters) if !filters.is_empty() => result.push_str(&self.apply_filters(
+                    &self.template[filters.range()],
+                    value,
+                    filters.start(),
+                )?),
+                _ => result.push_str(value),
             }
+            last = whole.end();
         }
 
+        result.push_str(&unescape_braces(&self.template[last..]));
         Ok(result)
     }
 
-    fn format_mustache(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
-        match &self.handlebars {
-            None => Err(TemplateError::UnsupportedFormat(
+    fn apply_filters(
+        &self,
+        chain: &str,
+        value: &str,
+        offset: usize,
+    ) -> Result<String, TemplateError> {
+        FilterCall::parse_chain(chain)
+            .and_then(|calls| self.filters().apply_chain(&calls, value))
+            .map_err(|e| e.at(&self.template, offset))
+    }
+
+    fn format_until(
+        &self,
+        variables: &HashMap<&str, &str>,
+        deadline: Option<RenderDeadline>,
+    ) -> Result<String, TemplateError> {
+        let mut merged_variables = merge_vars(&self.partials, variables);
+        let transformed = self.apply_transforms(&merged_variables)?;
+        merged_variables.extend(
+            transformed
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        self.validate_variables(&merged_variables)?;
+
+        match self.template_format {
+            TemplateFormat::FmtString => self.format_fmtstring(&merged_variables),
+            TemplateFormat::Mustache => self.format_mustache(&merged_variables, deadline),
+            TemplateFormat::Jinja2 => self.format_jinja2(&merged_variables, deadline),
+            TemplateFormat::PlainText => Ok(unescape_braces(&self.template).into_owned()),
+        }
+    }
+
+    fn format_mustache(
+        &self,
+        variables: &HashMap<&str, &str>,
+        deadline: Option<RenderDeadline>,
+    ) -> Result<String, TemplateError> {
+        let Some(handlebars) = &self.handlebars else {
+            return Err(TemplateError::UnsupportedFormat(
                 "Handlebars not initialized".to_string(),
-            )),
-            Some(handlebars) => handlebars
+            ));
+        };
+
+        let Some(deadline) = deadline else {
+            return handlebars
                 .render(Self::MUSTACHE_TEMPLATE, variables)
-                .map_err(TemplateError::RuntimeError),
+                .map_err(TemplateError::from);
+        };
+
+        let mut writer = DeadlineWriter::new(deadline);
+        match handlebars.render_to_write(Self::MUSTACHE_TEMPLATE, variables, &mut writer) {
+            Ok(()) => writer.into_string(),
+            Err(_) if deadline.is_expired() => Err(deadline.error()),
+            Err(e) => Err(TemplateError::from(e)),
+        }
+    }
+
+    fn format_jinja2(
+        &self,
+        variables: &HashMap<&str, &str>,
+        deadline: Option<RenderDeadline>,
+    ) -> Result<String, TemplateError> {
+        let environment = match &self.jinja {
+            Some(environment) => Arc::clone(environment),
+            None => Arc::new(Self::initialize_jinja(&self.template)?),
+        };
+
+        let context = Value::from_pairs(variables.iter().map(|(&k, &v)| (k, v)));
+        let template = environment
+            .get_template(Self::JINJA2_TEMPLATE)
+            .map_err(|e| TemplateError::RuntimeError(e.to_string()))?;
+
+        let Some(deadline) = deadline else {
+            return template
+                .render(context)
+                .map_err(|e| TemplateError::RuntimeError(e.to_string()));
+        };
+
+        let mut writer = DeadlineWriter::new(deadline);
+        match template.render_captured_to(context, &mut writer) {
+            Ok(_) => writer.into_string(),
+            Err(_) if deadline.is_expired() => Err(deadline.error()),
+            Err(e) => Err(TemplateError::RuntimeError(e.to_string())),
         }
     }
 }
 
+fn registration_error(message: String, span: Option<SourceSpan>) -> TemplateError {
+    match span {
+        Some(span) => TemplateError::MalformedTemplate(message, Some(span)),
+        None => TemplateError::MalformedTemplate(message, None),
+    }
+}
+
 impl Formattable for Template {
     fn format(&self, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
-        let merged_variables = merge_vars(&self.partials, variables);
-        self.validate_variables(&merged_variables)?;
+        self.format_until(variables, None)
+    }
 
-        match self.template_format {
-            TemplateFormat::FmtString => self.format_fmtstring(&merged_variables),
-            TemplateFormat::Mustache => self.format_mustache(&merged_variables),
-            TemplateFormat::PlainText => Ok(self.template.clone()),
+    fn format_with_options(
+        &self,
+        variables: &HashMap<&str, &str>,
+        options: &FormatOptions,
+    ) -> Result<String, TemplateError> {
+        let deadline = options.deadline();
+        let output = self.format_until(variables, deadline)?;
+        if let Some(deadline) = deadline {
+            deadline.check()?;
+        }
+        options.finish(output)
+    }
+
+    fn format_with_config(
+        &self,
+        variables: &HashMap<&str, &str>,
+        config: Option<&PromptForgeConfig>,
+    ) -> Result<String, TemplateError> {
+        match config {
+            Some(config) => config
+                .prepare_template(self, variables)?
+                .format_with_options(variables, config.format_options()),
+            None => self.format(variables),
         }
     }
 }
@@ -170,6 +729,27 @@ impl TryFrom<String> for Template {
     }
 }
 
+// Shared with `CompiledTemplate` so both forms report missing variables the
+// same way: one missing name is described in full, several are listed.
+pub(crate) fn check_missing(
+    input_variables: &[String],
+    missing: &[&String],
+    variables: &HashMap<&str, &str>,
+) -> Result<(), TemplateError> {
+    match missing {
+        [] => Ok(()),
+        [var] => Err(TemplateError::MissingVariable(format!(
+            "Variable '{}' is missing. Expected: {:?}, but received: {:?}",
+            var,
+            input_variables,
+            variables.keys().collect::<Vec<_>>()
+        ))),
+        _ => Err(TemplateError::MissingVariables(
+            missing.iter().map(|var| var.to_string()).collect(),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -206,15 +786,27 @@ mod tests {
     fn test_prompt_template_new_error() {
         let mixed_template = "Tell me a {adjective} joke about {{content}}.";
         let tmpl_err = Template::new(mixed_template).unwrap_err();
-        assert!(matches!(tmpl_err, TemplateError::MalformedTemplate(_)));
+        assert!(matches!(
+            tmpl_err,
+            TemplateError::MalformedTemplate(_, Some(_))
+        ));
+        assert_eq!(tmpl_err.span().unwrap().column, 34);
 
         let malformed_fmtstring = "Tell me a {adjective joke about {content}.";
         let tmpl_err = Template::new(malformed_fmtstring).unwrap_err();
-        assert!(matches!(tmpl_err, TemplateError::MalformedTemplate(_)));
+        assert!(matches!(
+            tmpl_err,
+            TemplateError::MalformedTemplate(_, Some(_))
+        ));
+        assert_eq!(tmpl_err.span().unwrap().column, 11);
 
         let malformed_mustache = "Tell me a {{adjective joke about {{content}}.";
         let tmpl_err = Template::new(malformed_mustache).unwrap_err();
-        assert!(matches!(tmpl_err, TemplateError::MalformedTemplate(_)));
+        assert!(matches!(
+            tmpl_err,
+            TemplateError::MalformedTemplate(_, Some(_))
+        ));
+        assert_eq!(tmpl_err.span().unwrap().column, 11);
     }
 
     #[test]
@@ -250,6 +842,315 @@ mod tests {
         assert!(matches!(result, TemplateError::MissingVariable(_)));
     }
 
+    #[test]
+    fn test_fmtstring_defaults() {
+        let tmpl = Template::new("Hello, {name:Anonymous}! Welcome to {place}.").unwrap();
+        assert_eq!(tmpl.template_format, TemplateFormat::FmtString);
+        assert_eq!(tmpl.input_variables(), vec!["name", "place"]);
+        assert_eq!(tmpl.required_variables(), vec!["place"]);
+        assert_eq!(
+            tmpl.variable_defaults(),
+            HashMap::from([("name", "Anonymous")])
+        );
+
+        assert_eq!(
+            tmpl.format(&vars!(place = "Rustville")).unwrap(),
+            "Hello, Anonymous! Welcome to Rustville."
+        );
+        assert_eq!(
+            tmpl.format(&vars!(name = "Alice", place = "Rustville"))
+                .unwrap(),
+            "Hello, Alice! Welcome to Rustville."
+        );
+        assert!(matches!(
+            tmpl.format(&vars!(name = "Alice")),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_fmtstring_defaults_with_partials_and_repeats() {
+        let mut tmpl = Template::new("{name:Dear friend}, {name} and {mood:}").unwrap();
+        assert_eq!(tmpl.template_format, TemplateFormat::FmtString);
+        assert_eq!(
+            tmpl.format(&vars!()).unwrap(),
+            "Dear friend, Dear friend and "
+        );
+
+        tmpl.partial("name", "Bob");
+        assert_eq!(tmpl.format(&vars!()).unwrap(), "Bob, Bob and ");
+        assert_eq!(
+            tmpl.format(&vars!(name = "Eve", mood = "happy")).unwrap(),
+            "Eve, Eve and happy"
+        );
+    }
+
+    #[test]
+    fn test_fmtstring_filters() {
+        let tmpl = Template::new("{name|trim|upper}: {bio|truncate(12, ...)}").unwrap();
+        assert_eq!(tmpl.template_format, TemplateFormat::FmtString);
+        assert_eq!(tmpl.input_variables(), vec!["name", "bio"]);
+
+        let formatted = tmpl
+            .format(&vars!(
+                name = "  ada ",
+                bio = "Wrote the first published algorithm."
+            ))
+            .unwrap();
+        assert_eq!(formatted, "ADA: Wrote the fi...");
+
+        let tmpl = Template::new("Hello, {name:stranger|upper}!").unwrap();
+        assert_eq!(tmpl.format(&vars!()).unwrap(), "Hello, STRANGER!");
+
+        let tmpl = Template::new("Hi\n{name|shout}").unwrap();
+        assert!(matches!(
+            tmpl.format(&vars!(name = "Ada")),
+            Err(TemplateError::MalformedTemplate(msg, Some(span)))
+                if msg == "Unknown filter: shout" && (span.line, span.column) == (2, 6)
+        ));
+    }
+
+    #[test]
+    fn test_new_with_literals() {
+        let literals = BraceLiterals::programming().with_literal("{a, b}");
+        assert!(Template::new("Print {} for {{lang}}").is_err());
+        assert!(Template::new("Destructure {a, b} from {value}").is_err());
+
+        let template = Template::new_with_literals("Print {} for {{lang}}", &literals).unwrap();
+        assert_eq!(template.template_format(), TemplateFormat::Mustache);
+        assert_eq!(
+            template.format(&vars!(lang = "Rust")).unwrap(),
+            "Print {} for Rust"
+        );
+
+        let template =
+            Template::new_with_literals("Destructure {a, b} from {value}", &literals).unwrap();
+        assert_eq!(template.template_format(), TemplateFormat::FmtString);
+        assert_eq!(template.input_variables(), vec!["value"]);
+        assert_eq!(
+            template.format(&vars!(value = "pair")).unwrap(),
+            "Destructure {a, b} from pair"
+        );
+    }
+
+    #[test]
+    fn test_format_args() {
+        let template = Template::new("Translate \"{0}\" into {1}, then {0} again.").unwrap();
+        assert_eq!(template.positional_count(), 2);
+        assert!(template.input_variables().is_empty());
+        assert_eq!(
+            template
+                .format_args(&["hi {there}", "French", "extra"])
+                .unwrap(),
+            "Translate \"hi {there}\" into French, then hi {there} again."
+        );
+        assert!(matches!(
+            template.format_args(&["hi"]),
+            Err(TemplateError::MissingVariable(_))
+        ));
+
+        let mut mixed = Template::new("{greeting}, {0}! Literal \\{1\\}.").unwrap();
+        assert_eq!(mixed.positional_count(), 1);
+        assert!(matches!(
+            mixed.format_args(&["Ada"]),
+            Err(TemplateError::MalformedTemplate(message, _))
+                if message == "Template mixes positional and named placeholders: greeting"
+        ));
+        mixed.partial("greeting", "Hello");
+        assert_eq!(
+            mixed.format_args(&["Ada"]).unwrap(),
+            "Hello, Ada! Literal {1}."
+        );
+
+        assert!(Template::new("Hi {name}")
+            .unwrap()
+            .format_args(&[])
+            .is_err());
+    }
+
+    #[test]
+    fn test_fmtstring_escaped_braces() {
+        let tmpl = Template::new(r#"Reply as JSON: \{"greeting": "{greeting}"\}"#).unwrap();
+        assert_eq!(tmpl.template_format, TemplateFormat::FmtString);
+        assert_eq!(tmpl.input_variables(), vec!["greeting"]);
+        assert_eq!(
+            tmpl.format(&vars!(greeting = "Hello")).unwrap(),
+            r#"Reply as JSON: {"greeting": "Hello"}"#
+        );
+
+        let tmpl = Template::new(r"fn main() \{ println!(); \}").unwrap();
+        assert_eq!(tmpl.template_format, TemplateFormat::PlainText);
+        assert_eq!(tmpl.format(&vars!()).unwrap(), "fn main() { println!(); }");
+
+        let tmpl = Template::new(r"\{name\} is replaced by {name}").unwrap();
+        assert_eq!(
+            tmpl.format(&vars!(name = "Ada")).unwrap(),
+            "{name} is replaced by Ada"
+        );
+
+        assert!(Template::new(r"Unbalanced \{ but {open").is_err());
+    }
+
+    #[test]
+    fn test_format_with_serialize_context() {
+        #[derive(Serialize)]
+        struct Order {
+            name: String,
+            order_id: u64,
+        }
+
+        let tmpl = Template::new("Hello, {name}! Your order number is {order_id}.").unwrap();
+        let order = Order {
+            name: "Alice".to_string(),
+            order_id: 12345,
+        };
+        assert_eq!(
+            tmpl.format_with(&order).unwrap(),
+            "Hello, Alice! Your order number is 12345."
+        );
+
+        let tmpl = Template::new("{name} ordered {missing}").unwrap();
+        assert!(matches!(
+            tmpl.format_with(&order),
+            Err(TemplateError::MissingVariable(_))
+        ));
+        assert!(tmpl.format_with(&42).is_err());
+    }
+
+    #[test]
+    fn test_partial_format_returns_remaining_template() {
+        let tmpl = Template::new("{greeting|upper}, {name}! Your code is \\{{code}\\}.").unwrap();
+
+        let stage_one = tmpl.partial_format(&vars!(greeting = "hello")).unwrap();
+        assert_eq!(
+            stage_one.template(),
+            "HELLO, {name}! Your code is \\{{code}\\}."
+        );
+        assert_eq!(stage_one.input_variables(), vec!["name", "code"]);
+
+        let stage_two = stage_one
+            .partial_format(&vars!(name = "{admin}", unknown = "ignored"))
+            .unwrap();
+        assert_eq!(stage_two.input_variables(), vec!["code"]);
+        assert_eq!(
+            stage_two.format(&vars!(code = "42")).unwrap(),
+            "HELLO, {admin}! Your code is {42}."
+        );
+
+        let done = stage_two.partial_format(&vars!(code = "7")).unwrap();
+        assert!(done.input_variables().is_empty());
+        assert_eq!(
+            done.format(&vars!()).unwrap(),
+            "HELLO, {admin}! Your code is {7}."
+        );
+    }
+
+    #[test]
+    fn test_partial_format_keeps_defaults_and_partials() {
+        let mut tmpl = Template::new("{tone:neutral} reply to {user} in {language}").unwrap();
+        tmpl.partial("language", "French");
+
+        let partial = tmpl.partial_format(&vars!(user = "Ada")).unwrap();
+        assert_eq!(
+            partial.template(),
+            "{tone:neutral} reply to Ada in {language}"
+        );
+        assert_eq!(partial.partial_vars().get("language").unwrap(), "French");
+        assert_eq!(
+            partial.format(&vars!()).unwrap(),
+            "neutral reply to Ada in French"
+        );
+
+        let mustache = Template::new("Hello {{name}}").unwrap();
+        assert!(matches!(
+            mustache.partial_format(&vars!(name = "Ada")),
+            Err(TemplateError::UnsupportedFormat(_))
+        ));
+    }
+
+    #[test]
+    fn test_missing_variables_are_aggregated() {
+        let tmpl =
+            Template::new("{greeting}, {name}! Your order {order_id} ships {eta:soon}.").unwrap();
+
+        let err = tmpl.format(&vars!(greeting = "Hi")).unwrap_err();
+        assert!(matches!(
+            &err,
+            TemplateError::MissingVariables(names) if *names == ["name", "order_id"]
+        ));
+        assert_eq!(err.to_string(), "Missing variables: name, order_id");
+
+        assert!(matches!(
+            tmpl.format(&vars!(greeting = "Hi", name = "Ada")),
+            Err(TemplateError::MissingVariable(msg)) if msg.contains("'order_id'")
+        ));
+
+        let compiled = tmpl.compile().unwrap();
+        assert!(matches!(
+            compiled.format(&vars!(greeting = "Hi")),
+            Err(TemplateError::MissingVariables(names)) if names == ["name", "order_id"]
+        ));
+        assert!(matches!(
+            compiled.format(&vars!(greeting = "Hi", name = "Ada")),
+            Err(TemplateError::MissingVariable(msg))
+                if msg.contains("'order_id'") && msg.contains("Expected")
+        ));
+        let repeated = Template::new("{name} and {name}")
+            .unwrap()
+            .compile()
+            .unwrap();
+        assert!(matches!(
+            repeated.format(&vars!()),
+            Err(TemplateError::MissingVariable(msg)) if msg.contains("'name'")
+        ));
+
+        let mustache = Template::new("{{a}} {{b}} {{c}}").unwrap();
+        assert!(matches!(
+            mustache.format(&vars!(b = "2")),
+            Err(TemplateError::MissingVariables(names)) if names == ["a", "c"]
+        ));
+    }
+
+    #[test]
+    fn test_format_for_model_profile() {
+        let tmpl =
+            Template::new("You are {model_name}. Answer in at most {max_words} words.").unwrap();
+        let profile = ModelProfile::new("gpt-4o-mini").with_variable("max_words", 100);
+
+        assert_eq!(
+            tmpl.format_for(&vars!(), &profile).unwrap(),
+            "You are gpt-4o-mini. Answer in at most 100 words."
+        );
+        assert_eq!(
+            tmpl.format_for(&vars!(max_words = "10"), &profile).unwrap(),
+            "You are gpt-4o-mini. Answer in at most 10 words."
+        );
+        assert!(tmpl
+            .format_for(&vars!(), &ModelProfile::new("bare"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_fmtstring_custom_filters() {
+        let mut filters = FilterRegistry::new();
+        filters.register("shout", |value, _| Ok(format!("{}!", value.to_uppercase())));
+
+        let tmpl = Template::new("{greeting|shout} {name}")
+            .unwrap()
+            .with_filters(filters);
+        assert_eq!(
+            tmpl.format(&vars!(greeting = "hi", name = "Ada")).unwrap(),
+            "HI! Ada"
+        );
+        assert_eq!(
+            tmpl.compile()
+                .unwrap()
+                .format(&vars!(greeting = "hi", name = "Ada"))
+                .unwrap(),
+            "HI! Ada"
+        );
+    }
+
     #[test]
     fn test_format_mustache_success() {
         let tmpl = Template::new("Hello, {{name}}!").unwrap();
@@ -492,8 +1393,9 @@ mod tests {
 
         let template = Template::try_from(invalid_template.clone());
         assert!(template.is_err());
-        if let Err(TemplateError::MalformedTemplate(msg)) = template {
-            println!("{}", msg);
+        if let Err(TemplateError::MalformedTemplate(msg, Some(span))) = template {
+            assert_eq!(msg, "unclosed brace");
+            assert_eq!(span.snippet, "Hello, {name!");
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
         }
@@ -505,10 +1407,134 @@ mod tests {
 
         let template = Template::try_from(mixed_format_template.clone());
         assert!(template.is_err());
-        if let Err(TemplateError::MalformedTemplate(msg)) = template {
-            println!("{}", msg);
+        if let Err(TemplateError::MalformedTemplate(msg, Some(span))) = template {
+            assert_eq!(msg, "mixed single and double braces");
+            assert_eq!(span.offset, 18);
         } else {
             panic!("Expected TemplateError::MalformedTemplate");
         }
     }
+
+    #[test]
+    fn test_jinja2_template_detection_and_variables() {
+        let tmpl =
+            Template::new("{% if formal %}Dear {{ name }},{% else %}Hi {{ name }}!{% endif %}")
+                .unwrap();
+
+        assert_eq!(tmpl.template_format, TemplateFormat::Jinja2);
+        assert_eq!(tmpl.input_variables, vec!["formal", "name"]);
+    }
+
+    #[test]
+    fn test_jinja2_formatting() {
+        let tmpl = Template::new(
+            "{% for word in words %}[{{ word | upper }}]{% endfor %} {# comment #}{{ name }}",
+        )
+        .unwrap();
+        let formatted = tmpl.format(&vars!(words = "ab", name = "Alice")).unwrap();
+        assert_eq!(formatted, "[A][B] Alice");
+
+        let tmpl = Template::new("{% if formal %}Dear {{ name }}{% endif %}").unwrap();
+        assert_eq!(
+            tmpl.format(&vars!(formal = "yes", name = "Bob")).unwrap(),
+            "Dear Bob"
+        );
+        assert_eq!(tmpl.format(&vars!(formal = "", name = "Bob")).unwrap(), "");
+
+        let err = tmpl.format(&vars!(name = "Bob")).unwrap_err();
+        assert!(matches!(err, TemplateError::MissingVariable(_)));
+    }
+
+    #[test]
+    fn test_jinja2_explicit_format_and_serialization() {
+        let tmpl = Template::new_with_config(
+            "Hello {{ name | title }}!",
+            Some(TemplateFormat::Jinja2),
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            tmpl.format(&vars!(name = "ada lovelace")).unwrap(),
+            "Hello Ada Lovelace!"
+        );
+
+        let serialized = serde_json::to_string(&tmpl).unwrap();
+        let deserialized: Template = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.template_format, TemplateFormat::Jinja2);
+        assert_eq!(
+            deserialized.format(&vars!(name = "ada lovelace")).unwrap(),
+            "Hello Ada Lovelace!"
+        );
+    }
+
+    #[test]
+    fn test_render_timeout() {
+        use std::time::Duration;
+
+        let mustache = Template::new("Hello {{name}}!").unwrap();
+        let jinja = Template::new_with_config(
+            "{% for i in range(100000) %}{{ name }}{% endfor %}",
+            Some(TemplateFormat::Jinja2),
+            Some(vec!["name".to_string()]),
+        )
+        .unwrap();
+        let expired = FormatOptions::new().with_render_timeout(Duration::ZERO);
+        let generous = FormatOptions::new().with_render_timeout(Duration::from_secs(60));
+
+        for template in [&mustache, &jinja] {
+            let err = template
+                .format_with_options(&vars!(name = "Ada"), &expired)
+                .unwrap_err();
+            assert!(matches!(err, TemplateError::Timeout(_)), "{:?}", err);
+        }
+        assert_eq!(
+            mustache
+                .format_with_options(&vars!(name = "Ada"), &generous)
+                .unwrap(),
+            "Hello Ada!"
+        );
+        assert_eq!(
+            jinja
+                .format_with_options(&vars!(name = "a"), &generous)
+                .unwrap()
+                .len(),
+            100000
+        );
+        assert!(matches!(
+            mustache.format_with_options(&vars!(), &generous),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+
+    #[test]
+    fn test_partials_serialize_in_key_order() {
+        let mut tmpl = Template::new("{greeting}, {name}! Today is {day}.").unwrap();
+        tmpl.partial("name", "Ada")
+            .partial("day", "Monday")
+            .partial("greeting", "Hi");
+
+        let serialized = serde_json::to_string(&tmpl).unwrap();
+        assert!(serialized.contains(r#""partials":{"day":"Monday","greeting":"Hi","name":"Ada"}"#));
+        assert_eq!(
+            toml::to_string(&tmpl).unwrap(),
+            toml::to_string(&tmpl.clone()).unwrap()
+        );
+
+        let deserialized: Template = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized.partial_vars(), tmpl.partial_vars());
+        assert_eq!(
+            deserialized.format(&vars!()).unwrap(),
+            "Hi, Ada! Today is Monday."
+        );
+        assert!(!serde_json::to_string(&Template::new("{a}").unwrap())
+            .unwrap()
+            .contains("partials"));
+    }
+
+    #[test]
+    fn test_jinja2_malformed_template() {
+        let err = Template::new("{% if name %}Hello").unwrap_err();
+        assert!(matches!(err, TemplateError::MalformedTemplate(_, Some(_))));
+        assert_eq!(err.span().unwrap().line, 1);
+    }
 }
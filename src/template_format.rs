@@ -5,21 +5,47 @@ use handlebars::RenderError;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    braces::{
-        count_left_braces, count_right_braces, has_multiple_words_between_braces, has_no_braces,
-        has_only_double_braces, has_only_single_braces,
-    },
+    detection::{check_braces, check_source, classify, DetectedFormat, DetectionError},
     role::InvalidRoleError,
+    span::SourceSpan,
 };
 
-#[derive(Debug)]
+pub use crate::detection::{
+    is_fmtstring, is_jinja2, is_mustache, is_plain_text, is_valid_template,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", content = "detail", rename_all = "snake_case")]
 pub enum TemplateError {
-    MalformedTemplate(String),
+    // The span, when known, points at the offending text in the source.
+    #[serde(serialize_with = "serialize_malformed")]
+    MalformedTemplate(String, Option<SourceSpan>),
     UnsupportedFormat(String),
     MissingVariable(String),
-    RuntimeError(RenderError),
+    MissingVariables(Vec<String>),
+    RuntimeError(String),
     InvalidRoleError,
     TomlDeserializationError(String),
+    PlaceholderLimitExceeded(String),
+    UnfilledPlaceholder(String),
+    LoadTimeout(String),
+    LoadCancelled(String),
+    PlaceholderNotAllowedHere(String),
+    ToolRoleNotSupported(String),
+    Timeout(String),
+    InvalidValue(String),
+}
+
+// Without a span the detail stays a plain message, as for other variants.
+fn serialize_malformed<S: serde::Serializer>(
+    message: &String,
+    span: &Option<SourceSpan>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match span {
+        Some(span) => (message, span).serialize(serializer),
+        None => message.serialize(serializer),
+    }
 }
 
 impl From<InvalidRoleError> for TemplateError {
@@ -30,7 +56,7 @@ impl From<InvalidRoleError> for TemplateError {
 
 impl From<RenderError> for TemplateError {
     fn from(err: RenderError) -> Self {
-        TemplateError::RuntimeError(err)
+        TemplateError::RuntimeError(err.to_string())
     }
 }
 
@@ -43,14 +69,34 @@ impl From<TomlError> for TemplateError {
 impl std::fmt::Display for TemplateError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            TemplateError::MalformedTemplate(msg) => write!(f, "Malformed template: {}", msg),
+            TemplateError::MalformedTemplate(msg, None) => write!(f, "Malformed template: {}", msg),
+            TemplateError::MalformedTemplate(msg, Some(span)) => {
+                write!(f, "Malformed template at {}: {}", span, msg)
+            }
             TemplateError::UnsupportedFormat(msg) => write!(f, "Unsupported format: {}", msg),
             TemplateError::MissingVariable(msg) => write!(f, "Missing variable: {}", msg),
+            TemplateError::MissingVariables(names) => {
+                write!(f, "Missing variables: {}", names.join(", "))
+            }
             TemplateError::RuntimeError(err) => write!(f, "Render error: {}", err),
             TemplateError::InvalidRoleError => write!(f, "Invalid role error"),
             TemplateError::TomlDeserializationError(msg) => {
                 write!(f, "TOML deserialization error: {}", msg)
             }
+            TemplateError::PlaceholderLimitExceeded(msg) => {
+                write!(f, "Placeholder limit exceeded: {}", msg)
+            }
+            TemplateError::UnfilledPlaceholder(msg) => write!(f, "Unfilled placeholder: {}", msg),
+            TemplateError::LoadTimeout(msg) => write!(f, "Load timed out: {}", msg),
+            TemplateError::LoadCancelled(msg) => write!(f, "Load cancelled: {}", msg),
+            TemplateError::PlaceholderNotAllowedHere(msg) => {
+                write!(f, "Placeholder not allowed here: {}", msg)
+            }
+            TemplateError::ToolRoleNotSupported(msg) => {
+                write!(f, "Tool role not supported: {}", msg)
+            }
+            TemplateError::Timeout(msg) => write!(f, "Render timed out: {}", msg),
+            TemplateError::InvalidValue(msg) => write!(f, "Invalid value: {}", msg),
         }
     }
 }
@@ -58,10 +104,30 @@ impl std::fmt::Display for TemplateError {
 impl std::error::Error for TemplateError {}
 
 impl TemplateError {
+    pub fn at(self, source: &str, offset: usize) -> Self {
+        match self {
+            TemplateError::MalformedTemplate(msg, None) => {
+                TemplateError::MalformedTemplate(msg, Some(SourceSpan::locate(source, offset)))
+            }
+            other => other,
+        }
+    }
+
+    pub fn span(&self) -> Option<&SourceSpan> {
+        match self {
+            TemplateError::MalformedTemplate(_, span) => span.as_ref(),
+            _ => None,
+        }
+    }
+
     pub fn matches(&self, other: &TemplateError) -> bool {
         match (self, other) {
             (TemplateError::MissingVariable(a), TemplateError::MissingVariable(b)) => a == b,
-            (TemplateError::MalformedTemplate(a), TemplateError::MalformedTemplate(b)) => a == b,
+            (TemplateError::MissingVariables(a), TemplateError::MissingVariables(b)) => a == b,
+            (
+                TemplateError::MalformedTemplate(a, a_span),
+                TemplateError::MalformedTemplate(b, b_span),
+            ) => a == b && a_span == b_span,
             (TemplateError::UnsupportedFormat(a), TemplateError::UnsupportedFormat(b)) => a == b,
             (TemplateError::RuntimeError(_), TemplateError::RuntimeError(_)) => true,
             (TemplateError::InvalidRoleError, TemplateError::InvalidRoleError) => true,
@@ -69,6 +135,24 @@ impl TemplateError {
                 TemplateError::TomlDeserializationError(a),
                 TemplateError::TomlDeserializationError(b),
             ) => a == b,
+            (
+                TemplateError::PlaceholderLimitExceeded(a),
+                TemplateError::PlaceholderLimitExceeded(b),
+            ) => a == b,
+            (TemplateError::UnfilledPlaceholder(a), TemplateError::UnfilledPlaceholder(b)) => {
+                a == b
+            }
+            (TemplateError::LoadTimeout(a), TemplateError::LoadTimeout(b)) => a == b,
+            (TemplateError::LoadCancelled(a), TemplateError::LoadCancelled(b)) => a == b,
+            (
+                TemplateError::PlaceholderNotAllowedHere(a),
+                TemplateError::PlaceholderNotAllowedHere(b),
+            ) => a == b,
+            (TemplateError::ToolRoleNotSupported(a), TemplateError::ToolRoleNotSupported(b)) => {
+                a == b
+            }
+            (TemplateError::Timeout(a), TemplateError::Timeout(b)) => a == b,
+            (TemplateError::InvalidValue(a), TemplateError::InvalidValue(b)) => a == b,
             _ => false,
         }
     }
@@ -79,6 +163,7 @@ pub enum TemplateFormat {
     PlainText,
     FmtString,
     Mustache,
+    Jinja2,
 }
 
 impl TemplateFormat {
@@ -87,25 +172,34 @@ impl TemplateFormat {
             TemplateFormat::FmtString => "FmtString",
             TemplateFormat::Mustache => "Mustache",
             TemplateFormat::PlainText => "PlainText",
+            TemplateFormat::Jinja2 => "Jinja2",
         }
     }
     pub fn from_template(template: &str) -> Result<Self, TemplateError> {
-        if !is_valid_template(template) {
-            return Err(TemplateError::MalformedTemplate(
-                "Malformed template".to_string(),
-            ));
+        check_source(template, None)
+            .map(TemplateFormat::from)
+            .map_err(|error| detection_error(template, error))
+    }
+}
+
+impl From<DetectedFormat> for TemplateFormat {
+    fn from(format: DetectedFormat) -> Self {
+        match format {
+            DetectedFormat::PlainText => TemplateFormat::PlainText,
+            DetectedFormat::FmtString => TemplateFormat::FmtString,
+            DetectedFormat::Mustache => TemplateFormat::Mustache,
+            DetectedFormat::Jinja2 => TemplateFormat::Jinja2,
         }
+    }
+}
 
-        if is_fmtstring(template) {
-            Ok(TemplateFormat::FmtString)
-        } else if is_mustache(template) {
-            Ok(TemplateFormat::Mustache)
-        } else if is_plain_text(template) {
-            Ok(TemplateFormat::PlainText)
-        } else {
-            Err(TemplateError::UnsupportedFormat(
-                "Unsupported template format".to_string(),
-            ))
+impl From<&TemplateFormat> for DetectedFormat {
+    fn from(format: &TemplateFormat) -> Self {
+        match format {
+            TemplateFormat::PlainText => DetectedFormat::PlainText,
+            TemplateFormat::FmtString => DetectedFormat::FmtString,
+            TemplateFormat::Mustache => DetectedFormat::Mustache,
+            TemplateFormat::Jinja2 => DetectedFormat::Jinja2,
         }
     }
 }
@@ -118,6 +212,7 @@ impl TryFrom<&str> for TemplateFormat {
             "fmtstring" => Ok(TemplateFormat::FmtString),
             "mustache" => Ok(TemplateFormat::Mustache),
             "plaintext" => Ok(TemplateFormat::PlainText),
+            "jinja2" | "jinja" => Ok(TemplateFormat::Jinja2),
             _ => Err(TemplateError::UnsupportedFormat(
                 "Unsupported template format".to_string(),
             )),
@@ -125,45 +220,29 @@ impl TryFrom<&str> for TemplateFormat {
     }
 }
 
-pub fn is_plain_text(s: &str) -> bool {
-    has_no_braces(s)
-}
-
-pub fn is_mustache(s: &str) -> bool {
-    has_only_double_braces(s) && !has_multiple_words_between_braces(s)
-}
-
-pub fn is_fmtstring(s: &str) -> bool {
-    has_only_single_braces(s) && !has_multiple_words_between_braces(s)
-}
-
-pub fn is_valid_template(s: &str) -> bool {
-    if has_no_braces(s) {
-        return true;
+pub(crate) fn detection_error(source: &str, error: DetectionError) -> TemplateError {
+    match error {
+        DetectionError::Malformed(Some((offset, reason))) => TemplateError::MalformedTemplate(
+            reason.to_string(),
+            Some(SourceSpan::locate(source, offset)),
+        ),
+        DetectionError::Malformed(None) => {
+            TemplateError::MalformedTemplate(source.to_string(), None)
+        }
+        DetectionError::Unsupported => {
+            TemplateError::UnsupportedFormat("Unsupported template format".to_string())
+        }
     }
-
-    count_left_braces(s) == count_right_braces(s)
-        && (has_only_double_braces(s) || has_only_single_braces(s))
 }
 
 pub fn validate_template(s: &str) -> Result<(), TemplateError> {
-    if !is_valid_template(s) {
-        return Err(TemplateError::MalformedTemplate(s.to_string()));
-    }
-
-    Ok(())
+    check_braces(s).map_err(|error| detection_error(s, error))
 }
 
 pub fn detect_template(s: &str) -> Result<TemplateFormat, TemplateError> {
-    if is_plain_text(s) {
-        Ok(TemplateFormat::PlainText)
-    } else if is_mustache(s) {
-        Ok(TemplateFormat::Mustache)
-    } else if is_fmtstring(s) {
-        Ok(TemplateFormat::FmtString)
-    } else {
-        Err(TemplateError::UnsupportedFormat(s.to_string()))
-    }
+    classify(s)
+        .map(TemplateFormat::from)
+        .map_err(|_| TemplateError::UnsupportedFormat(s.to_string()))
 }
 
 pub fn merge_vars<'a>(
@@ -265,6 +344,27 @@ mod tests {
             .matches(&TemplateError::UnsupportedFormat("{var words}".to_string())));
     }
 
+    #[test]
+    fn test_escaped_braces_validation_and_detection() {
+        assert!(validate_template(r"A lone \{ brace").is_ok());
+        assert!(validate_template(r#"\{"key": {value}\}"#).is_ok());
+        assert!(validate_template(r"\{{var}").is_ok());
+        assert!(validate_template(r"{{var}\}").is_err());
+
+        assert_eq!(
+            detect_template(r"Only \{escaped\} braces").unwrap(),
+            TemplateFormat::PlainText
+        );
+        assert_eq!(
+            detect_template(r#"\{"key": {value}\}"#).unwrap(),
+            TemplateFormat::FmtString
+        );
+        assert_eq!(
+            TemplateFormat::from_template(r#"\{"key": {value}\}"#).unwrap(),
+            TemplateFormat::FmtString
+        );
+    }
+
     #[test]
     fn test_validate_template() {
         assert!(validate_template("{var}").is_ok());
@@ -273,21 +373,42 @@ mod tests {
         assert!(validate_template("This is a {{valid}} Mustache template").is_ok());
         assert!(validate_template("No placeholders here").is_ok());
 
-        assert!(validate_template("{{var}")
-            .unwrap_err()
-            .matches(&TemplateError::MalformedTemplate("{{var}".to_string())));
+        assert!(validate_template("{{var}").unwrap_err().matches(
+            &TemplateError::MalformedTemplate(
+                "mismatched brace widths".to_string(),
+                Some(SourceSpan::locate("{{var}", 0))
+            )
+        ));
 
-        assert!(validate_template("{var}}")
-            .unwrap_err()
-            .matches(&TemplateError::MalformedTemplate("{var}}".to_string())));
+        assert!(validate_template("{var}}").unwrap_err().matches(
+            &TemplateError::MalformedTemplate(
+                "mismatched brace widths".to_string(),
+                Some(SourceSpan::locate("{var}}", 0))
+            )
+        ));
 
         assert!(validate_template("{var} words {{another}}")
             .unwrap_err()
             .matches(&TemplateError::MalformedTemplate(
-                "{var} words {{another}}".to_string()
+                "mixed single and double braces".to_string(),
+                Some(SourceSpan::locate("{var} words {{another}}", 12))
             )));
     }
 
+    #[test]
+    fn test_validate_template_reports_location() {
+        let template = "You are a support agent.\nCustomer: {name}\nIssue: {issue\nReply politely.";
+        let err = validate_template(template).unwrap_err();
+
+        let span = err.span().unwrap();
+        assert_eq!((span.line, span.column), (3, 8));
+        assert_eq!(span.snippet, "Issue: {issue");
+        assert_eq!(
+            err.to_string(),
+            "Malformed template at line 3, column 8 near `Issue: {issue`: unclosed brace"
+        );
+    }
+
     #[test]
     fn test_from_template_format() {
         assert_eq!(
@@ -307,8 +428,9 @@ mod tests {
 
         let result = TemplateFormat::from_template("{name {{other}}");
         match result {
-            Err(TemplateError::MalformedTemplate(msg)) => {
-                assert_eq!(msg, "Malformed template".to_string());
+            Err(TemplateError::MalformedTemplate(msg, Some(span))) => {
+                assert_eq!(msg, "unclosed brace".to_string());
+                assert_eq!(span.offset, 0);
             }
             _ => panic!("Expected MalformedTemplate error"),
         }
@@ -442,4 +564,43 @@ mod tests {
         assert_eq!(merged.get("day"), Some(&"Sunday"));
         assert_eq!(merged.len(), 2);
     }
+
+    #[test]
+    fn test_template_error_eq_and_clone() {
+        let err = TemplateError::MissingVariables(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(err.clone(), err);
+        assert_ne!(err, TemplateError::MissingVariable("a".to_string()));
+
+        let render_err: TemplateError =
+            RenderError::from(handlebars::RenderErrorReason::Other("boom".to_string())).into();
+        assert_eq!(render_err, TemplateError::RuntimeError("boom".to_string()));
+    }
+
+    #[test]
+    fn test_template_error_serialize() {
+        let err = TemplateError::MalformedTemplate(
+            "unclosed brace".to_string(),
+            Some(SourceSpan::locate("Hi {name", 3)),
+        );
+        assert_eq!(
+            serde_json::to_string(&TemplateError::MalformedTemplate("bad".to_string(), None))
+                .unwrap(),
+            r#"{"kind":"malformed_template","detail":"bad"}"#
+        );
+        assert_eq!(
+            serde_json::to_value(&err).unwrap(),
+            serde_json::json!({
+                "kind": "malformed_template",
+                "detail": ["unclosed brace", {"offset": 3, "line": 1, "column": 4, "snippet": "Hi {name"}],
+            })
+        );
+        assert_eq!(
+            serde_json::to_string(&TemplateError::InvalidRoleError).unwrap(),
+            r#"{"kind":"invalid_role_error"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&TemplateError::RuntimeError("boom".to_string())).unwrap(),
+            r#"{"kind":"runtime_error","detail":"boom"}"#
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use messageforge::{BaseMessage, MessageEnum, SystemMessage};
+use serde::{Deserialize, Serialize};
+
+use crate::{ChatTemplate, RoleMap, TemplateError};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantContext {
+    id: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    partials: BTreeMap<String, String>,
+    #[serde(default)]
+    role_labels: RoleMap,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    prelude: Option<String>,
+}
+
+impl TenantContext {
+    pub fn new(id: impl Into<String>) -> Self {
+        TenantContext {
+            id: id.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_partial(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.partials.insert(name.into(), value.into());
+        self
+    }
+
+    pub fn with_role_labels(mut self, role_labels: RoleMap) -> Self {
+        self.role_labels = role_labels;
+        self
+    }
+
+    pub fn with_prelude(mut self, prelude: impl Into<String>) -> Self {
+        self.prelude = Some(prelude.into());
+        self
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn partials(&self) -> &BTreeMap<String, String> {
+        &self.partials
+    }
+
+    pub fn role_labels(&self) -> &RoleMap {
+        &self.role_labels
+    }
+
+    pub fn prelude(&self) -> Option<&str> {
+        self.prelude.as_deref()
+    }
+
+    pub fn resolve<'a>(
+        &'a self,
+        variables: &HashMap<&'a str, &'a str>,
+    ) -> HashMap<&'a str, &'a str> {
+        let mut resolved: HashMap<&str, &str> = self
+            .partials
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        resolved.extend(variables.iter().map(|(&name, &value)| (name, value)));
+        resolved
+    }
+
+    // The prelude is merged into a leading system message rather than added as a
+    // second one, since several providers reject multiple system messages.
+    pub fn apply_prelude(&self, messages: &mut Vec<Arc<MessageEnum>>) {
+        let Some(prelude) = &self.prelude else {
+            return;
+        };
+
+        match messages.first().map(|message| message.as_ref()) {
+            Some(MessageEnum::System(system)) => {
+                let merged = format!("{}\n\n{}", prelude, system.content());
+                messages[0] = Arc::new(MessageEnum::System(SystemMessage::new(&merged)));
+            }
+            _ => messages.insert(
+                0,
+                Arc::new(MessageEnum::System(SystemMessage::new(prelude))),
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TenantDirectory {
+    #[serde(flatten)]
+    tenants: HashMap<String, TenantContext>,
+}
+
+impl TenantDirectory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, tenant: TenantContext) -> Option<TenantContext> {
+        self.tenants.insert(tenant.id.clone(), tenant)
+    }
+
+    pub fn with_tenant(mut self, tenant: TenantContext) -> Self {
+        self.insert(tenant);
+        self
+    }
+
+    pub fn get(&self, id: &str) -> Option<&TenantContext> {
+        self.tenants.get(id)
+    }
+
+    pub fn resolve(&self, id: &str) -> Result<&TenantContext, TemplateError> {
+        self.get(id).ok_or_else(|| {
+            TemplateError::MalformedTemplate(format!("Unknown tenant '{}'", id), None)
+        })
+    }
+
+    pub fn format(
+        &self,
+        id: &str,
+        chat_template: &ChatTemplate,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        chat_template.format_for_tenant(self.resolve(id)?, variables)
+    }
+}
+
+impl ChatTemplate {
+    pub fn format_messages_for_tenant(
+        &self,
+        tenant: &TenantContext,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Vec<Arc<MessageEnum>>, TemplateError> {
+        let mut messages = self.format_messages(&tenant.resolve(variables))?;
+        tenant.apply_prelude(&mut messages);
+        Ok(messages)
+    }
+
+    pub fn format_for_tenant(
+        &self,
+        tenant: &TenantContext,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<String, TemplateError> {
+        let messages = self.format_messages_for_tenant(tenant, variables)?;
+        Ok(tenant
+            .role_labels
+            .export(&messages)
+            .into_iter()
+            .map(|message| format!("{}: {}", message.role, message.content))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Human, System};
+    use crate::{chats, vars, Role, RoleMapping};
+
+    fn support_chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are the {product} assistant.",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_format_for_tenant_applies_partials_prelude_and_labels() {
+        let tenant = TenantContext::new("acme")
+            .with_partial("product", "Acme Cloud")
+            .with_prelude("Never discuss pricing.")
+            .with_role_labels(RoleMap::new().map(Role::Human, RoleMapping::new("Customer")));
+
+        assert_eq!(
+            support_chat()
+                .format_for_tenant(&tenant, &vars!(question = "How do I reset?"))
+                .unwrap(),
+            "system: Never discuss pricing.\n\nYou are the Acme Cloud assistant.\n\
+             Customer: How do I reset?"
+        );
+    }
+
+    #[test]
+    fn test_runtime_vars_override_partials_and_prelude_without_system() {
+        let tenant = TenantContext::new("globex")
+            .with_partial("question", "default question")
+            .with_prelude("Be formal.");
+        let chat = ChatTemplate::from_messages(chats!(Human = "{question}")).unwrap();
+
+        let messages = chat
+            .format_messages_for_tenant(&tenant, &vars!(question = "Hi"))
+            .unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].content(), "Be formal.");
+        assert_eq!(messages[1].content(), "Hi");
+    }
+
+    #[test]
+    fn test_directory_resolves_by_id() {
+        let directory = TenantDirectory::new()
+            .with_tenant(TenantContext::new("acme").with_partial("product", "Acme"));
+
+        assert_eq!(
+            directory
+                .format("acme", &support_chat(), &vars!(question = "Hi"))
+                .unwrap(),
+            "system: You are the Acme assistant.\nhuman: Hi"
+        );
+        assert!(directory.resolve("initech").is_err());
+
+        let parsed: TenantDirectory =
+            toml::from_str("[acme]\nid = \"acme\"\nprelude = \"Be nice.\"\n").unwrap();
+        assert_eq!(parsed.resolve("acme").unwrap().prelude(), Some("Be nice."));
+    }
+}
@@ -0,0 +1,4 @@
+pub mod datetime;
+mod grapheme_tables;
+pub mod length;
+pub mod number;
@@ -0,0 +1,306 @@
+// Just enough ISO 8601 and strftime for rendering timestamps into prompts:
+// fixed UTC offsets only, English month and day names.
+
+use crate::TemplateError;
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp {
+    // Seconds since the Unix epoch, in UTC.
+    pub seconds: i64,
+    pub offset_minutes: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Civil {
+    year: i64,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    weekday: usize,
+}
+
+fn invalid(value: &str) -> TemplateError {
+    TemplateError::InvalidValue(format!("'{}' is not an ISO 8601 timestamp", value))
+}
+
+fn number(digits: &str, value: &str) -> Result<u32, TemplateError> {
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid(value));
+    }
+    digits.parse().map_err(|_| invalid(value))
+}
+
+// Howard Hinnant's days_from_civil.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Accepts `Z`, `UTC`, `+HH:MM`, `+HHMM` and `+HH`.
+pub fn parse_offset(offset: &str) -> Option<i32> {
+    if offset == "Z" || offset.eq_ignore_ascii_case("utc") {
+        return Some(0);
+    }
+    let (sign, rest) = match offset.as_bytes().first()? {
+        b'+' => (1, &offset[1..]),
+        b'-' => (-1, &offset[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((hours, minutes)) => (hours, minutes),
+        None if rest.len() == 4 => rest.split_at(2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().ok().filter(|h| (0..24).contains(h))?;
+    let minutes: i32 = minutes.parse().ok().filter(|m| (0..60).contains(m))?;
+    Some(sign * (hours * 60 + minutes))
+}
+
+impl Timestamp {
+    // A date, optionally followed by `T` (or a space) and a time with an
+    // offset. Timestamps without an offset are taken as UTC.
+    pub fn parse(value: &str) -> Result<Self, TemplateError> {
+        let trimmed = value.trim();
+        let (date, time) = match trimmed.find(['T', 't', ' ']) {
+            Some(index) => (&trimmed[..index], Some(&trimmed[index + 1..])),
+            None => (trimmed, None),
+        };
+
+        let mut parts = date.splitn(3, '-');
+        let (year, month, day) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day)) if year.len() == 4 => (
+                number(year, value)? as i64,
+                number(month, value)?,
+                number(day, value)?,
+            ),
+            _ => return Err(invalid(value)),
+        };
+        if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+            return Err(invalid(value));
+        }
+
+        let (mut seconds_of_day, mut offset_minutes) = (0, 0);
+        if let Some(time) = time {
+            let (clock, offset) = match time.find(['Z', 'z', '+', '-']) {
+                Some(index) => (&time[..index], &time[index..]),
+                None => (time, "Z"),
+            };
+            offset_minutes =
+                parse_offset(&offset.to_ascii_uppercase()).ok_or_else(|| invalid(value))?;
+
+            let clock = clock.split_once('.').map_or(clock, |(whole, _)| whole);
+            let mut fields = clock.split(':');
+            let hour = number(fields.next().unwrap_or_default(), value)?;
+            let minute = number(fields.next().ok_or_else(|| invalid(value))?, value)?;
+            let second = fields.next().map_or(Ok(0), |s| number(s, value))?;
+            if fields.next().is_some() || hour > 23 || minute > 59 || second > 60 {
+                return Err(invalid(value));
+            }
+            seconds_of_day = (hour * 3600 + minute * 60 + second) as i64;
+        }
+
+        Ok(Timestamp {
+            seconds: days_from_civil(year, month, day) * 86400 + seconds_of_day
+                - offset_minutes as i64 * 60,
+            offset_minutes,
+        })
+    }
+
+    pub fn with_offset(self, offset_minutes: i32) -> Self {
+        Timestamp {
+            seconds: self.seconds,
+            offset_minutes,
+        }
+    }
+
+    fn civil(&self) -> Civil {
+        let local = self.seconds + self.offset_minutes as i64 * 60;
+        let (days, seconds_of_day) = (local.div_euclid(86400), local.rem_euclid(86400));
+        let (year, month, day) = civil_from_days(days);
+        Civil {
+            year,
+            month,
+            day,
+            hour: (seconds_of_day / 3600) as u32,
+            minute: (seconds_of_day / 60 % 60) as u32,
+            second: (seconds_of_day % 60) as u32,
+            weekday: (days + 4).rem_euclid(7) as usize,
+        }
+    }
+
+    fn offset(&self, separator: &str) -> String {
+        let sign = if self.offset_minutes < 0 { '-' } else { '+' };
+        let minutes = self.offset_minutes.abs();
+        format!(
+            "{}{:02}{}{:02}",
+            sign,
+            minutes / 60,
+            separator,
+            minutes % 60
+        )
+    }
+
+    // Supports %Y %y %m %d %e %H %I %M %S %p %B %b %A %a %Z %z and %%; a `-`
+    // flag (`%-d`) drops the zero padding.
+    pub fn format(&self, pattern: &str) -> Result<String, TemplateError> {
+        let civil = self.civil();
+        let mut out = String::with_capacity(pattern.len() * 2);
+        let mut chars = pattern.chars();
+
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            let mut spec = chars.next();
+            let pad = spec != Some('-');
+            if !pad {
+                spec = chars.next();
+            }
+            let padded = |value: u32| {
+                if pad {
+                    format!("{:02}", value)
+                } else {
+                    value.to_string()
+                }
+            };
+            let hour12 = match civil.hour % 12 {
+                0 => 12,
+                hour => hour,
+            };
+
+            match spec {
+                Some('Y') => out.push_str(&civil.year.to_string()),
+                Some('y') => out.push_str(&format!("{:02}", civil.year.rem_euclid(100))),
+                Some('m') => out.push_str(&padded(civil.month)),
+                Some('d') => out.push_str(&padded(civil.day)),
+                Some('e') => out.push_str(&format!("{:>2}", civil.day)),
+                Some('H') => out.push_str(&padded(civil.hour)),
+                Some('I') => out.push_str(&padded(hour12)),
+                Some('M') => out.push_str(&padded(civil.minute)),
+                Some('S') => out.push_str(&padded(civil.second)),
+                Some('p') => out.push_str(if civil.hour < 12 { "AM" } else { "PM" }),
+                Some('B') => out.push_str(MONTHS[civil.month as usize - 1]),
+                Some('b') => out.push_str(&MONTHS[civil.month as usize - 1][..3]),
+                Some('A') => out.push_str(WEEKDAYS[civil.weekday]),
+                Some('a') => out.push_str(&WEEKDAYS[civil.weekday][..3]),
+                Some('Z') if self.offset_minutes == 0 => out.push_str("UTC"),
+                Some('Z') => out.push_str(&self.offset(":")),
+                Some('z') => out.push_str(&self.offset("")),
+                Some('%') => out.push('%'),
+                Some(other) => {
+                    return Err(TemplateError::MalformedTemplate(
+                        format!("Unsupported date format specifier: %{}", other),
+                        None,
+                    ));
+                }
+                None => {
+                    return Err(TemplateError::MalformedTemplate(
+                        "Date format ends with a bare %".to_string(),
+                        None,
+                    ));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_format_timestamps() {
+        let timestamp = Timestamp::parse("2024-02-29T18:05:09.123+02:00").unwrap();
+        assert_eq!(timestamp.seconds, 1709222709);
+        assert_eq!(
+            timestamp.format("%A, %B %-d %Y at %I:%M %p (%Z)").unwrap(),
+            "Thursday, February 29 2024 at 06:05 PM (+02:00)"
+        );
+        assert_eq!(
+            timestamp
+                .with_offset(-300)
+                .format("%a %b %e %H:%M:%S %z")
+                .unwrap(),
+            "Thu Feb 29 11:05:09 -0500"
+        );
+        assert_eq!(
+            Timestamp::parse("1969-12-31")
+                .unwrap()
+                .format("%d/%m/%y %Z")
+                .unwrap(),
+            "31/12/69 UTC"
+        );
+
+        for bad in [
+            "2023-02-29",
+            "yesterday",
+            "2024-01-01T25:00",
+            "2024-01-01T10:00+9:99",
+        ] {
+            assert!(
+                matches!(Timestamp::parse(bad), Err(TemplateError::InvalidValue(_))),
+                "{}",
+                bad
+            );
+        }
+        assert!(timestamp.format("%Q").is_err());
+    }
+}
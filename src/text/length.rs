@@ -0,0 +1,201 @@
+use std::cmp::Ordering;
+
+use super::grapheme_tables::{EXTENDED_PICTOGRAPHIC, GRAPHEME_BREAKS};
+
+// Unicode extended grapheme clusters (UAX #29, rules GB3 to GB13) over the
+// Grapheme_Cluster_Break tables generated into `grapheme_tables.rs`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GraphemeBreak {
+    Other,
+    Cr,
+    Lf,
+    Control,
+    Extend,
+    Zwj,
+    RegionalIndicator,
+    Prepend,
+    SpacingMark,
+    L,
+    V,
+    T,
+    Lv,
+    Lvt,
+}
+
+fn find_range<T>(ranges: &[T], c: u32, bounds: impl Fn(&T) -> (u32, u32)) -> Option<&T> {
+    ranges
+        .binary_search_by(|range| {
+            let (start, end) = bounds(range);
+            if end < c {
+                Ordering::Less
+            } else if start > c {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .ok()
+        .map(|index| &ranges[index])
+}
+
+fn grapheme_break(c: char) -> GraphemeBreak {
+    const HANGUL_SYLLABLES: std::ops::RangeInclusive<u32> = 0xAC00..=0xD7A3;
+    const HANGUL_T_COUNT: u32 = 28;
+
+    let c = c as u32;
+    if HANGUL_SYLLABLES.contains(&c) {
+        return match (c - HANGUL_SYLLABLES.start()) % HANGUL_T_COUNT {
+            0 => GraphemeBreak::Lv,
+            _ => GraphemeBreak::Lvt,
+        };
+    }
+    find_range(GRAPHEME_BREAKS, c, |&(start, end, _)| (start, end))
+        .map_or(GraphemeBreak::Other, |&(_, _, class)| class)
+}
+
+fn is_extended_pictographic(c: char) -> bool {
+    find_range(EXTENDED_PICTOGRAPHIC, c as u32, |&range| range).is_some()
+}
+
+#[derive(Debug, Clone)]
+pub struct Graphemes<'a> {
+    text: &'a str,
+}
+
+impl<'a> Iterator for Graphemes<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        use GraphemeBreak::*;
+
+        let mut chars = self.text.char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+        let mut previous = grapheme_break(first);
+        // GB11 and GB12/13 look further back than one character: whether the
+        // text so far ends in `ExtPict Extend*` (or that plus a ZWJ), and how
+        // many regional indicators it ends with.
+        let mut emoji = is_extended_pictographic(first);
+        let mut emoji_zwj = false;
+        let mut regional_run = usize::from(previous == RegionalIndicator);
+
+        for (index, c) in chars {
+            let next = grapheme_break(c);
+            let pictographic = is_extended_pictographic(c);
+            let joins = match (previous, next) {
+                (Cr, Lf) => true,
+                (Cr | Lf | Control, _) | (_, Cr | Lf | Control) => false,
+                (L, L | V | Lv | Lvt) | (Lv | V, V | T) | (Lvt | T, T) => true,
+                (_, Extend | Zwj | SpacingMark) | (Prepend, _) => true,
+                (Zwj, _) => emoji_zwj && pictographic,
+                (RegionalIndicator, RegionalIndicator) => regional_run % 2 == 1,
+                _ => false,
+            };
+            if !joins {
+                break;
+            }
+            emoji_zwj = emoji && next == Zwj;
+            emoji = pictographic || (emoji && next == Extend);
+            regional_run = match next {
+                RegionalIndicator => regional_run + 1,
+                _ => 0,
+            };
+            previous = next;
+            end = index + c.len_utf8();
+        }
+
+        let (grapheme, rest) = self.text.split_at(end);
+        self.text = rest;
+        Some(grapheme)
+    }
+}
+
+pub fn graphemes(text: &str) -> Graphemes<'_> {
+    Graphemes { text }
+}
+
+pub fn grapheme_len(text: &str) -> usize {
+    if text.is_ascii() && !text.contains("\r\n") {
+        return text.len();
+    }
+    graphemes(text).count()
+}
+
+pub fn truncate_graphemes(text: &str, max: usize) -> &str {
+    let end: usize = graphemes(text).take(max).map(str::len).sum();
+    &text[..end]
+}
+
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    let mut end = 0;
+    for grapheme in graphemes(text) {
+        if end + grapheme.len() > max_bytes {
+            break;
+        }
+        end += grapheme.len();
+    }
+    &text[..end]
+}
+
+pub fn last_graphemes(text: &str, max: usize) -> &str {
+    let total = grapheme_len(text);
+    let skip: usize = graphemes(text)
+        .take(total.saturating_sub(max))
+        .map(str::len)
+        .sum();
+    &text[skip..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grapheme_len_multi_byte() {
+        assert_eq!(grapheme_len("hello"), 5);
+        assert_eq!(grapheme_len("héllo"), 5);
+        assert_eq!(grapheme_len("he\u{301}llo"), 5);
+        assert_eq!(grapheme_len("日本語のテキスト"), 8);
+        assert_eq!(grapheme_len("👍🏽 ok"), 4);
+        assert_eq!(grapheme_len("👨‍👩‍👧‍👦"), 1);
+        assert_eq!(grapheme_len("🇯🇵🇫🇷"), 2);
+        assert_eq!(grapheme_len("❤️"), 1);
+        assert_eq!(grapheme_len("a\r\nb"), 3);
+        assert_eq!(grapheme_len(""), 0);
+    }
+
+    #[test]
+    fn test_truncate_never_splits_clusters() {
+        assert_eq!(truncate_graphemes("👨‍👩‍👧‍👦 family", 1), "👨‍👩‍👧‍👦");
+        assert_eq!(truncate_graphemes("he\u{301}llo", 2), "he\u{301}");
+        assert_eq!(truncate_graphemes("日本語", 10), "日本語");
+        assert_eq!(truncate_bytes("日本語", 7), "日本");
+        assert_eq!(truncate_bytes("👍🏽!", 5), "");
+        assert_eq!(truncate_bytes("👍🏽!", 9), "👍🏽!");
+        assert_eq!(last_graphemes("ab🇯🇵🇫🇷", 2), "🇯🇵🇫🇷");
+        assert_eq!(last_graphemes("ab", 5), "ab");
+    }
+
+    #[test]
+    fn test_indic_clusters_keep_their_marks() {
+        // Spacing vowel signs (Bengali, Tamil) and viramas stay with their base.
+        assert_eq!(grapheme_len("কি"), 1);
+        assert_eq!(grapheme_len("கி"), 1);
+        assert_eq!(grapheme_len("नमस्ते"), 4);
+        assert_eq!(truncate_graphemes("কিx", 1), "কি");
+        assert_eq!(truncate_graphemes("தமிழ்", 2), "தமி");
+        assert_eq!(last_graphemes("xகி", 1), "கி");
+        assert_eq!(truncate_bytes("কিx", 3), "");
+        // A prepended Arabic number sign joins the digit after it.
+        assert_eq!(grapheme_len("\u{600}1"), 1);
+        assert_eq!(grapheme_len("한국어"), 3);
+        assert_eq!(grapheme_len("\u{1100}\u{1161}\u{11A8}"), 1);
+    }
+
+    #[test]
+    fn test_graphemes_iterator() {
+        let clusters: Vec<&str> = graphemes("ñ👍🏽\r\n").collect();
+        assert_eq!(clusters, vec!["ñ", "👍🏽", "\r\n"]);
+    }
+}
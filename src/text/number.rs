@@ -0,0 +1,104 @@
+use crate::TemplateError;
+
+// Group and decimal separators, looked up by full locale and then language.
+fn separators(locale: &str) -> Option<(&'static str, &'static str)> {
+    let locale = locale.replace('_', "-").to_ascii_lowercase();
+    match locale.as_str() {
+        "de-ch" | "it-ch" | "fr-ch" => return Some(("'", ".")),
+        "pt-pt" => return Some(("\u{a0}", ",")),
+        _ => {}
+    }
+    match locale.split('-').next().unwrap_or_default() {
+        "en" | "ja" | "zh" | "ko" | "he" | "th" | "hi" => Some((",", ".")),
+        "de" | "es" | "it" | "nl" | "pt" | "id" | "tr" | "da" | "el" => Some((".", ",")),
+        "fr" | "ru" | "pl" | "sv" | "nb" | "no" | "fi" | "cs" | "sk" | "uk" | "hu" => {
+            Some(("\u{a0}", ","))
+        }
+        _ => None,
+    }
+}
+
+fn group(digits: &str, separator: &str) -> String {
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3 * separator.len());
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push_str(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+// Keeps the digits as given unless `decimals` asks for rounding, so large
+// integers and exact decimals are not distorted by a float round trip.
+pub fn format_number(
+    value: &str,
+    locale: &str,
+    decimals: Option<usize>,
+) -> Result<String, TemplateError> {
+    let (group_separator, decimal_separator) = separators(locale).ok_or_else(|| {
+        TemplateError::MalformedTemplate(format!("Unsupported number locale: {}", locale), None)
+    })?;
+    let trimmed = value.trim();
+    let number: f64 = trimmed
+        .parse()
+        .ok()
+        .filter(|number: &f64| number.is_finite())
+        .ok_or_else(|| TemplateError::InvalidValue(format!("'{}' is not a number", value)))?;
+
+    let plain = trimmed
+        .trim_start_matches(['-', '+'])
+        .bytes()
+        .all(|b| b.is_ascii_digit() || b == b'.');
+    let digits = match decimals {
+        Some(decimals) => format!("{:.*}", decimals, number),
+        None if plain => trimmed.trim_start_matches('+').to_string(),
+        None => number.to_string(),
+    };
+
+    let (sign, digits) = match digits.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", digits.as_str()),
+    };
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits, None),
+    };
+    let integer = if integer.is_empty() { "0" } else { integer };
+
+    let mut out = format!("{}{}", sign, group(integer, group_separator));
+    if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+        out.push_str(decimal_separator);
+        out.push_str(fraction);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_number_by_locale() {
+        assert_eq!(
+            format_number("1234567.891", "en-US", None).unwrap(),
+            "1,234,567.891"
+        );
+        assert_eq!(
+            format_number("1234567.891", "de", Some(2)).unwrap(),
+            "1.234.567,89"
+        );
+        assert_eq!(
+            format_number("-9876543", "fr_FR", None).unwrap(),
+            "-9\u{a0}876\u{a0}543"
+        );
+        assert_eq!(format_number("1e6", "de-CH", None).unwrap(), "1'000'000");
+        assert_eq!(format_number(".5", "en", None).unwrap(), "0.5");
+        assert_eq!(format_number("999", "en", None).unwrap(), "999");
+        assert!(matches!(
+            format_number("12 apples", "en", None),
+            Err(TemplateError::InvalidValue(_))
+        ));
+        assert!(format_number("12", "xx", None).is_err());
+    }
+}
@@ -0,0 +1,385 @@
+use std::{collections::HashMap, fmt, sync::Arc};
+
+use messageforge::{BaseMessage, MessageEnum};
+
+use crate::{
+    text::length::{grapheme_len, truncate_graphemes},
+    ChatTemplate, Formattable, Role, RoleMap, Template, TemplateError, TokenCounter,
+};
+
+// Every count here is an estimate. promptforge ships no tokenizer, so the
+// text is measured by whatever `TokenCounter` the caller passes (wrap a BPE
+// such as tiktoken's `encode(..).len()` in a closure to match a model), and
+// chat-completion APIs wrap every message in role/delimiter tokens and prime
+// the reply with a few more; these follow the published OpenAI accounting,
+// which providers do not guarantee.
+pub const TOKENS_PER_MESSAGE: usize = 3;
+pub const TOKENS_PER_REPLY: usize = 3;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MessageTokenEstimate {
+    pub messages: Vec<usize>,
+    pub reply_overhead: usize,
+    pub total: usize,
+}
+
+#[derive(Clone)]
+pub struct TokenBudget {
+    max_tokens: usize,
+    counter: Arc<dyn TokenCounter>,
+    keep_system: bool,
+    keep_last_human: bool,
+    truncate: bool,
+}
+
+impl fmt::Debug for TokenBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenBudget")
+            .field("max_tokens", &self.max_tokens)
+            .field("keep_system", &self.keep_system)
+            .field("keep_last_human", &self.keep_last_human)
+            .field("truncate", &self.truncate)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ElisionReport {
+    pub dropped: Vec<usize>,
+    pub truncated: Vec<usize>,
+    pub tokens_before: usize,
+    pub tokens_after: usize,
+    pub max_tokens: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BudgetedMessages {
+    pub messages: Vec<Arc<MessageEnum>>,
+    pub report: ElisionReport,
+}
+
+pub(crate) fn message_tokens(counter: &dyn TokenCounter, message: &MessageEnum) -> usize {
+    let exported = RoleMap::openai().export_message(message);
+    TOKENS_PER_MESSAGE
+        + counter.count_tokens(&exported.role)
+        + counter.count_tokens(&exported.content)
+}
+
+impl MessageTokenEstimate {
+    pub fn fits(&self, budget: usize) -> bool {
+        self.total <= budget
+    }
+}
+
+impl TokenBudget {
+    pub fn new(max_tokens: usize, counter: impl TokenCounter + 'static) -> Self {
+        TokenBudget {
+            max_tokens,
+            counter: Arc::new(counter),
+            keep_system: true,
+            keep_last_human: true,
+            truncate: false,
+        }
+    }
+
+    pub fn with_keep_system(mut self, keep_system: bool) -> Self {
+        self.keep_system = keep_system;
+        self
+    }
+
+    pub fn with_keep_last_human(mut self, keep_last_human: bool) -> Self {
+        self.keep_last_human = keep_last_human;
+        self
+    }
+
+    pub fn with_truncation(mut self, truncate: bool) -> Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    fn message_tokens(&self, message: &MessageEnum) -> usize {
+        message_tokens(self.counter.as_ref(), message)
+    }
+
+    fn is_protected(&self, messages: &[Arc<MessageEnum>], index: usize) -> bool {
+        match messages[index].as_ref() {
+            MessageEnum::System(_) => self.keep_system,
+            MessageEnum::Human(_) => {
+                self.keep_last_human
+                    && !messages[index + 1..]
+                        .iter()
+                        .any(|message| matches!(message.as_ref(), MessageEnum::Human(_)))
+            }
+            _ => false,
+        }
+    }
+
+    // Longest prefix of the message whose cost drops by at least `excess`,
+    // or `None` when nothing of the content would survive.
+    fn truncate_message(&self, message: &MessageEnum, excess: usize) -> Option<Arc<MessageEnum>> {
+        let role = match message {
+            MessageEnum::System(_) => Role::System,
+            MessageEnum::Human(_) => Role::Human,
+            MessageEnum::Ai(_) => Role::Ai,
+            MessageEnum::Tool(_) => return None,
+        };
+        let content = message.content();
+        let target = self.counter.count_tokens(content).checked_sub(excess)?;
+
+        let (mut low, mut high) = (0, grapheme_len(content));
+        while low < high {
+            let mid = (low + high).div_ceil(2);
+            if self.counter.count_tokens(truncate_graphemes(content, mid)) <= target {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+
+        let truncated = truncate_graphemes(content, low).trim_end();
+        if truncated.is_empty() {
+            return None;
+        }
+        role.to_message(truncated).ok()
+    }
+}
+
+impl ElisionReport {
+    pub fn within_budget(&self) -> bool {
+        self.tokens_after <= self.max_tokens
+    }
+
+    pub fn is_elided(&self) -> bool {
+        !self.dropped.is_empty() || !self.truncated.is_empty()
+    }
+}
+
+impl Template {
+    pub fn estimate_tokens(
+        &self,
+        variables: &HashMap<&str, &str>,
+        encoder: &dyn TokenCounter,
+    ) -> Result<usize, TemplateError> {
+        Ok(encoder.count_tokens(&self.format(variables)?))
+    }
+}
+
+impl ChatTemplate {
+    pub fn estimate_tokens_messages(
+        &self,
+        variables: &HashMap<&str, &str>,
+        encoder: &dyn TokenCounter,
+    ) -> Result<MessageTokenEstimate, TemplateError> {
+        let messages: Vec<usize> = self
+            .export(variables, &RoleMap::openai())?
+            .iter()
+            .map(|message| {
+                TOKENS_PER_MESSAGE
+                    + encoder.count_tokens(&message.role)
+                    + encoder.count_tokens(&message.content)
+            })
+            .collect();
+
+        Ok(MessageTokenEstimate {
+            total: messages.iter().sum::<usize>() + TOKENS_PER_REPLY,
+            reply_overhead: TOKENS_PER_REPLY,
+            messages,
+        })
+    }
+
+    // Elides oldest-first: unprotected messages are truncated when that alone
+    // brings the prompt within budget and truncation is enabled, else dropped.
+    pub fn format_messages_within(
+        &self,
+        variables: &HashMap<&str, &str>,
+        budget: &TokenBudget,
+    ) -> Result<BudgetedMessages, TemplateError> {
+        let messages = self.format_messages(variables)?;
+        let mut costs: Vec<usize> = messages
+            .iter()
+            .map(|message| budget.message_tokens(message))
+            .collect();
+        let tokens_before = costs.iter().sum::<usize>() + TOKENS_PER_REPLY;
+
+        let mut kept: Vec<Option<Arc<MessageEnum>>> = messages.iter().cloned().map(Some).collect();
+        let mut report = ElisionReport {
+            tokens_before,
+            tokens_after: tokens_before,
+            max_tokens: budget.max_tokens,
+            ..ElisionReport::default()
+        };
+
+        for index in 0..messages.len() {
+            if report.tokens_after <= budget.max_tokens {
+                break;
+            }
+            if budget.is_protected(&messages, index) {
+                continue;
+            }
+
+            let excess = report.tokens_after - budget.max_tokens;
+            let truncated = budget
+                .truncate
+                .then(|| budget.truncate_message(&messages[index], excess))
+                .flatten();
+
+            match truncated {
+                Some(message) => {
+                    let cost = budget.message_tokens(&message);
+                    report.tokens_after = report.tokens_after - costs[index] + cost;
+                    costs[index] = cost;
+                    kept[index] = Some(message);
+                    report.truncated.push(index);
+                }
+                None => {
+                    report.tokens_after -= costs[index];
+                    kept[index] = None;
+                    report.dropped.push(index);
+                }
+            }
+        }
+
+        Ok(BudgetedMessages {
+            messages: kept.into_iter().flatten().collect(),
+            report,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Role::{Ai, Human, Placeholder, System};
+    use crate::{chats, vars, EstimatedTokenCounter};
+
+    fn words(text: &str) -> usize {
+        text.split_whitespace().count()
+    }
+
+    #[test]
+    fn test_template_estimate_tokens() {
+        let template = Template::new("Summarize {doc} in {n} words.").unwrap();
+        let variables = vars!(doc = "the quarterly report", n = "ten");
+
+        assert_eq!(template.estimate_tokens(&variables, &words).unwrap(), 7);
+        assert_eq!(
+            template
+                .estimate_tokens(&variables, &EstimatedTokenCounter)
+                .unwrap(),
+            11
+        );
+        assert!(template.estimate_tokens(&vars!(), &words).is_err());
+    }
+
+    #[test]
+    fn test_chat_estimate_tokens_messages() {
+        let chat = ChatTemplate::from_messages(chats!(
+            System = "You are {persona}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+
+        let count = chat
+            .estimate_tokens_messages(
+                &vars!(persona = "very terse", question = "Why is the sky blue?"),
+                &words,
+            )
+            .unwrap();
+
+        assert_eq!(count.messages, vec![3 + 1 + 4, 3 + 1 + 5]);
+        assert_eq!(count.reply_overhead, TOKENS_PER_REPLY);
+        assert_eq!(count.total, 8 + 9 + 3);
+        assert!(count.fits(20));
+        assert!(!count.fits(19));
+    }
+
+    fn history_chat() -> ChatTemplate {
+        ChatTemplate::from_messages(chats!(
+            System = "You are terse.",
+            Placeholder = "{history}",
+            Human = "{question}",
+        ))
+        .unwrap()
+    }
+
+    fn history() -> String {
+        serde_json::json!([
+            {"role": "human", "content": "one two three four"},
+            {"role": "ai", "content": "five six seven"},
+            {"role": "human", "content": "eight nine"},
+            {"role": "ai", "content": "ten"},
+        ])
+        .to_string()
+    }
+
+    #[test]
+    fn test_format_messages_within_drops_oldest_history() {
+        let history = history();
+        let variables = vars!(history = history.as_str(), question = "Why?");
+        // Per message: 3 overhead + 1 role word + content words.
+        let budget = TokenBudget::new(30, words);
+
+        let result = history_chat()
+            .format_messages_within(&variables, &budget)
+            .unwrap();
+
+        assert_eq!(result.report.tokens_before, 7 + 8 + 7 + 6 + 5 + 5 + 3);
+        assert_eq!(result.report.dropped, vec![1, 2]);
+        assert!(result.report.truncated.is_empty());
+        assert_eq!(result.report.tokens_after, 7 + 6 + 5 + 5 + 3);
+        assert!(result.report.within_budget());
+
+        let contents: Vec<&str> = result.messages.iter().map(|m| m.content()).collect();
+        assert_eq!(
+            contents,
+            vec!["You are terse.", "eight nine", "ten", "Why?"]
+        );
+    }
+
+    #[test]
+    fn test_format_messages_within_truncates_and_keeps_protected() {
+        let history = history();
+        let variables = vars!(history = history.as_str(), question = "Why?");
+
+        let result = history_chat()
+            .format_messages_within(
+                &variables,
+                &TokenBudget::new(38, words).with_truncation(true),
+            )
+            .unwrap();
+        assert_eq!(result.report.truncated, vec![1]);
+        assert_eq!(result.messages[1].content(), "one");
+        assert_eq!(result.report.tokens_after, 38);
+
+        let result = history_chat()
+            .format_messages_within(&variables, &TokenBudget::new(5, words))
+            .unwrap();
+        assert_eq!(result.report.dropped, vec![1, 2, 3, 4]);
+        assert_eq!(result.messages.len(), 2);
+        assert!(!result.report.within_budget());
+
+        let result = history_chat()
+            .format_messages_within(
+                &variables,
+                &TokenBudget::new(5, words).with_keep_system(false),
+            )
+            .unwrap();
+        assert_eq!(result.report.dropped, vec![0, 1, 2, 3, 4]);
+        assert_eq!(result.messages[0].content(), "Why?");
+    }
+
+    #[test]
+    fn test_format_messages_within_noop_when_fits() {
+        let chat = ChatTemplate::from_messages(chats!(Human = "{question}", Ai = "ok")).unwrap();
+        let result = chat
+            .format_messages_within(&vars!(question = "Why?"), &TokenBudget::new(100, words))
+            .unwrap();
+
+        assert!(!result.report.is_elided());
+        assert_eq!(result.messages.len(), 2);
+    }
+}
@@ -0,0 +1,198 @@
+use std::{collections::HashMap, sync::Arc};
+
+use messageforge::{AiMessage, MessageEnum};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Formattable, Templatable, Template, TemplateError, ToolCall};
+
+// Every string in `arguments` is a template; rendered values are placed into
+// the JSON as strings, so they never need escaping by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallSpec {
+    pub id: String,
+    pub name: String,
+    pub arguments: Value,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolCallTemplate {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    content: String,
+    calls: Vec<ToolCallSpec>,
+}
+
+impl ToolCallTemplate {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_content(mut self, content: &str) -> Result<Self, TemplateError> {
+        Template::new(content)?;
+        self.content = content.to_string();
+        Ok(self)
+    }
+
+    pub fn with_call(
+        mut self,
+        id: &str,
+        name: &str,
+        arguments: Value,
+    ) -> Result<Self, TemplateError> {
+        let call = ToolCallSpec {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments,
+        };
+        for source in call_sources(&call) {
+            Template::new(source)?;
+        }
+        self.calls.push(call);
+        Ok(self)
+    }
+
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    pub fn calls(&self) -> &[ToolCallSpec] {
+        &self.calls
+    }
+
+    pub fn input_variables(&self) -> Vec<String> {
+        let mut variables: Vec<String> = Vec::new();
+        let sources =
+            std::iter::once(self.content.as_str()).chain(self.calls.iter().flat_map(call_sources));
+        for source in sources {
+            let Ok(template) = Template::new(source) else {
+                continue;
+            };
+            for var in template.input_variables() {
+                if !variables.contains(&var) {
+                    variables.push(var);
+                }
+            }
+        }
+        variables
+    }
+
+    pub fn format_message(
+        &self,
+        variables: &HashMap<&str, &str>,
+    ) -> Result<Arc<MessageEnum>, TemplateError> {
+        let calls = self
+            .calls
+            .iter()
+            .map(|call| {
+                Ok(ToolCall::new(
+                    render(&call.id, variables)?,
+                    render(&call.name, variables)?,
+                    &render_value(&call.arguments, variables)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, TemplateError>>()?;
+
+        let mut ai = AiMessage::new(&render(&self.content, variables)?);
+        if !calls.is_empty() {
+            ai.base
+                .additional_kwargs
+                .insert(ToolCall::KWARGS_KEY.to_string(), ToolCall::to_kwarg(&calls));
+        }
+        Ok(Arc::new(MessageEnum::Ai(ai)))
+    }
+}
+
+fn call_sources(call: &ToolCallSpec) -> Vec<&str> {
+    let mut sources = vec![call.id.as_str(), call.name.as_str()];
+    collect_strings(&call.arguments, &mut sources);
+    sources
+}
+
+fn collect_strings<'a>(value: &'a Value, sources: &mut Vec<&'a str>) {
+    match value {
+        Value::String(source) => sources.push(source),
+        Value::Array(items) => items.iter().for_each(|item| collect_strings(item, sources)),
+        Value::Object(fields) => fields
+            .values()
+            .for_each(|field| collect_strings(field, sources)),
+        _ => {}
+    }
+}
+
+fn render(source: &str, variables: &HashMap<&str, &str>) -> Result<String, TemplateError> {
+    Template::new(source)?.format(variables)
+}
+
+fn render_value(value: &Value, variables: &HashMap<&str, &str>) -> Result<Value, TemplateError> {
+    Ok(match value {
+        Value::String(source) => Value::String(render(source, variables)?),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| render_value(item, variables))
+                .collect::<Result<_, _>>()?,
+        ),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| Ok((key.clone(), render_value(field, variables)?)))
+                .collect::<Result<_, TemplateError>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use messageforge::BaseMessage;
+    use serde_json::json;
+
+    use super::*;
+    use crate::vars;
+
+    fn weather() -> ToolCallTemplate {
+        ToolCallTemplate::new()
+            .with_content("Checking {city}.")
+            .unwrap()
+            .with_call(
+                "call_{n}",
+                "get_weather",
+                json!({"city": "{city}", "units": ["{units}"], "days": 3}),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_format_message_renders_tool_calls() {
+        let template = weather();
+        assert_eq!(template.input_variables(), vec!["city", "n", "units"]);
+
+        let message = template
+            .format_message(&vars!(city = "Oslo \"centre\"", n = "1", units = "metric"))
+            .unwrap();
+        assert_eq!(message.content(), "Checking Oslo \"centre\".");
+
+        let MessageEnum::Ai(ai) = message.as_ref() else {
+            panic!("expected an AI message");
+        };
+        let calls = ToolCall::from_kwargs(ai.additional_kwargs());
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].id, "call_1");
+        assert_eq!(calls[0].function.name, "get_weather");
+        assert_eq!(
+            calls[0].arguments().unwrap(),
+            json!({"city": "Oslo \"centre\"", "units": ["metric"], "days": 3})
+        );
+    }
+
+    #[test]
+    fn test_invalid_and_missing_variables() {
+        assert!(ToolCallTemplate::new()
+            .with_call("call_1", "lookup", json!({"q": "{unclosed"}))
+            .is_err());
+        assert!(matches!(
+            weather().format_message(&vars!(city = "Oslo")),
+            Err(TemplateError::MissingVariable(_))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+use serde::Serialize;
+
+use crate::TemplateError;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MessageIssue {
+    pub index: usize,
+    pub error: TemplateError,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub missing: Vec<String>,
+    pub unused: Vec<String>,
+    pub malformed: Vec<MessageIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.malformed.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<Self, TemplateError> {
+        if let Some(issue) = self.malformed.first() {
+            return Err(issue.error.clone());
+        }
+        match self.missing.len() {
+            0 => Ok(self),
+            1 => Err(TemplateError::MissingVariable(self.missing[0].clone())),
+            _ => Err(TemplateError::MissingVariables(self.missing)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConsistencyIssue {
+    RoleSequence {
+        example: usize,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    FieldLabels {
+        example: usize,
+        message: usize,
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+    FinalTurnLabels {
+        expected: Vec<String>,
+        found: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConsistencyReport {
+    pub examples: usize,
+    pub issues: Vec<ConsistencyIssue>,
+}
+
+impl ConsistencyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
@@ -1,3 +1,12 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::TemplateError;
+
+pub use promptforge_syntax::variables::fields_cover_template;
+
 #[macro_export]
 macro_rules! vars {
     () => {
@@ -15,9 +24,84 @@ macro_rules! vars {
     };
 }
 
+pub trait PromptVars {
+    const FIELDS: &'static [&'static str];
+
+    fn into_vars(self) -> HashMap<String, String>;
+}
+
+pub fn serialize_vars<T: Serialize + ?Sized>(
+    ctx: &T,
+) -> Result<HashMap<String, String>, TemplateError> {
+    let value = serde_json::to_value(ctx).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("Failed to serialize format context: {}", e), None)
+    })?;
+
+    let Value::Object(fields) = value else {
+        return Err(TemplateError::MalformedTemplate(
+            "Format context must serialize to a map of variables".to_string(),
+            None,
+        ));
+    };
+
+    Ok(fields
+        .into_iter()
+        .filter_map(|(name, value)| match value {
+            Value::Null => None,
+            Value::String(text) => Some((name, text)),
+            other => Some((name, other.to_string())),
+        })
+        .collect())
+}
+
+pub(crate) fn borrow_vars(variables: &HashMap<String, String>) -> HashMap<&str, &str> {
+    variables
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_vars() {
+        #[derive(Serialize)]
+        struct Context<'a> {
+            name: &'a str,
+            age: u32,
+            premium: bool,
+            nickname: Option<&'a str>,
+            tags: Vec<&'a str>,
+        }
+
+        let variables = serialize_vars(&Context {
+            name: "Ada",
+            age: 36,
+            premium: true,
+            nickname: None,
+            tags: vec!["math", "poetry"],
+        })
+        .unwrap();
+
+        assert_eq!(variables.len(), 4);
+        assert_eq!(variables["name"], "Ada");
+        assert_eq!(variables["age"], "36");
+        assert_eq!(variables["premium"], "true");
+        assert_eq!(variables["tags"], r#"["math","poetry"]"#);
+        assert!(!variables.contains_key("nickname"));
+    }
+
+    #[test]
+    fn test_serialize_vars_rejects_non_maps() {
+        assert!(matches!(
+            serialize_vars(&json!(["not", "a", "map"])),
+            Err(TemplateError::MalformedTemplate(_, _))
+        ));
+        assert!(serialize_vars("text").is_err());
+    }
 
     #[test]
     fn test_empty_prompt_vars() {
@@ -0,0 +1,426 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tokio::{fs, task::JoinHandle};
+
+use crate::{
+    library::{library_extension, prompt_files, prompt_name},
+    LibraryPrompt, RegisteredPrompt, RegistryEvent, RegistryHandle, TemplateError,
+};
+
+// Polls a prompt directory and applies each change to the registry through
+// `swap` and `remove`, so subscribers see the usual `Changed` events. A file
+// that fails to load is reported once as `ReloadFailed`, and the prompt it
+// held stays in place until it loads again or the file is deleted. Contents
+// are compared rather than timestamps, which can be too coarse to notice two
+// quick edits. Every tick reads every prompt file in full, so each interval
+// costs O(files × bytes) of reads; pick the interval with that in mind.
+//
+// A change is applied once two consecutive polls read the same content, so
+// a read that catches a file half-written is never loaded or reported. A
+// change therefore lands one interval after it is first seen. Writers should
+// still replace files atomically, by writing a temporary file and renaming it
+// into place, so a slow write cannot be read twice in the same torn state.
+#[derive(Debug)]
+pub struct RegistryWatcher {
+    task: JoinHandle<()>,
+}
+
+#[derive(Debug)]
+struct WatchState {
+    handle: RegistryHandle,
+    root: PathBuf,
+    // Path to the content last seen and the name it was registered under.
+    files: HashMap<PathBuf, (String, String)>,
+    // Path to the content, or read error, of its last reported failure.
+    failed: HashMap<PathBuf, String>,
+    // Path to the changed content, or read error, read by the last poll.
+    pending: HashMap<PathBuf, String>,
+}
+
+impl RegistryWatcher {
+    pub fn stop(self) {
+        self.task.abort();
+    }
+
+    pub fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
+impl Drop for RegistryWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl RegistryHandle {
+    // The initial load is strict: every file must load, or the error lists
+    // each one that did not and nothing is watched.
+    pub async fn watch<P: AsRef<Path>>(
+        &self,
+        root: P,
+        interval: Duration,
+    ) -> Result<RegistryWatcher, TemplateError> {
+        let mut state = WatchState {
+            handle: self.clone(),
+            root: root.as_ref().to_path_buf(),
+            files: HashMap::new(),
+            failed: HashMap::new(),
+            pending: HashMap::new(),
+        };
+
+        let failures: Vec<String> = state
+            .poll(false)
+            .await?
+            .into_iter()
+            .map(|(path, error)| format!("{}: {}", path.display(), error))
+            .collect();
+        if !failures.is_empty() {
+            return Err(TemplateError::MalformedTemplate(
+                format!(
+                    "Failed to load {} prompt file(s) from {}:\n{}",
+                    failures.len(),
+                    state.root.display(),
+                    failures.join("\n")
+                ),
+                None,
+            ));
+        }
+
+        let task = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval);
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                match state.poll(true).await {
+                    Ok(failures) => {
+                        for (path, error) in failures {
+                            state
+                                .handle
+                                .notify(RegistryEvent::ReloadFailed { path, error });
+                        }
+                    }
+                    Err(error) => state.handle.notify(RegistryEvent::ReloadFailed {
+                        path: state.root.clone(),
+                        error,
+                    }),
+                }
+            }
+        });
+        Ok(RegistryWatcher { task })
+    }
+}
+
+impl WatchState {
+    // Applies whatever changed since the last poll and returns the files
+    // that failed to load. With `debounce`, a change is only acted on once
+    // the previous poll read the same thing.
+    async fn poll(
+        &mut self,
+        debounce: bool,
+    ) -> Result<Vec<(PathBuf, TemplateError)>, TemplateError> {
+        let mut failures = Vec::new();
+        let mut seen = BTreeMap::new();
+        let listed = prompt_files(&self.root).await?;
+
+        for path in listed.iter().cloned() {
+            let Some(name) = prompt_name(&self.root, &path) else {
+                continue;
+            };
+            let content = match fs::read_to_string(&path).await {
+                Ok(content) => content,
+                // Removed between listing and reading; the next poll sees it gone.
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                // The file is still there, so the prompt it held is kept.
+                Err(e) => {
+                    seen.entry(name).or_insert_with(|| path.clone());
+                    let error = format!("Failed to read prompt file: {}", e);
+                    if debounce && !self.settled(&path, &error) {
+                        continue;
+                    }
+                    self.fail(&mut failures, path, error.clone(), || {
+                        TemplateError::MalformedTemplate(error, None)
+                    });
+                    continue;
+                }
+            };
+
+            if let Some(other) = seen.insert(name.clone(), path.clone()) {
+                let error = || {
+                    TemplateError::MalformedTemplate(
+                        format!(
+                            "Duplicate prompt name '{}', already loaded from {}",
+                            name,
+                            other.display()
+                        ),
+                        None,
+                    )
+                };
+                self.fail(&mut failures, path, content, error);
+                seen.insert(name, other);
+                continue;
+            }
+            if self
+                .files
+                .get(&path)
+                .is_some_and(|(previous, _)| *previous == content)
+            {
+                self.failed.remove(&path);
+                self.pending.remove(&path);
+                continue;
+            }
+            if debounce && !self.settled(&path, &content) {
+                continue;
+            }
+
+            let extension = library_extension(&path).unwrap_or_default();
+            let loaded = LibraryPrompt::parse(extension, &content)
+                .and_then(RegisteredPrompt::try_from)
+                .and_then(|prompt| self.handle.swap(name.clone(), prompt));
+            match loaded {
+                Ok(_) => {
+                    self.failed.remove(&path);
+                    self.files.insert(path, (content, name));
+                }
+                Err(error) => self.fail(&mut failures, path, content, || error),
+            }
+        }
+
+        self.failed.retain(|path, _| listed.contains(path));
+        self.pending.retain(|path, _| listed.contains(path));
+        let removed: Vec<PathBuf> = self
+            .files
+            .keys()
+            .filter(|path| !seen.values().any(|seen_path| seen_path == *path))
+            .cloned()
+            .collect();
+        for path in removed {
+            // A duplicate keeps the name another file now holds.
+            if let Some((_, name)) = self.files.remove(&path)
+                && !seen.contains_key(&name)
+            {
+                self.handle.remove(&name);
+            }
+        }
+        Ok(failures)
+    }
+
+    // Whether the previous poll read the same content, or read error, as
+    // this one.
+    fn settled(&mut self, path: &Path, content: &str) -> bool {
+        match self.pending.get(path) {
+            Some(previous) if previous == content => true,
+            _ => {
+                self.pending.insert(path.to_path_buf(), content.to_string());
+                false
+            }
+        }
+    }
+
+    // A file that fails again with the same content, or the same read
+    // error, was already reported.
+    fn fail(
+        &mut self,
+        failures: &mut Vec<(PathBuf, TemplateError)>,
+        path: PathBuf,
+        content: String,
+        error: impl FnOnce() -> TemplateError,
+    ) {
+        if self.failed.get(&path) != Some(&content) {
+            failures.push((path.clone(), error()));
+            self.failed.insert(path, content);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{vars, PromptRegistry};
+
+    async fn next_event(events: &mut crate::RegistryEvents) -> RegistryEvent {
+        tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("the watcher reported the change")
+            .unwrap()
+    }
+
+    // Replaces the file atomically, as the watcher expects of writers.
+    async fn replace(path: &Path, contents: impl AsRef<[u8]>) {
+        let staged = path.with_extension("tmp");
+        fs::write(&staged, contents).await.unwrap();
+        fs::rename(&staged, path).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_reloads_changed_files() {
+        let root = std::env::temp_dir().join("promptforge_watch");
+        let _ = fs::remove_dir_all(&root).await;
+        fs::create_dir_all(root.join("support")).await.unwrap();
+        let greet = root.join("support/greet.json");
+        let template = |source: &str| {
+            format!(
+                r#"{{"template": "{}", "template_format": "FmtString", "input_variables": ["name"]}}"#,
+                source
+            )
+        };
+        fs::write(&greet, template("Hi {name}!")).await.unwrap();
+
+        let handle = RegistryHandle::new(PromptRegistry::new());
+        let mut events = handle.subscribe();
+        let watcher = handle
+            .watch(&root, Duration::from_millis(10))
+            .await
+            .unwrap();
+        assert_eq!(
+            handle
+                .format("support/greet", &vars!(name = "Ada"))
+                .unwrap(),
+            "Hi Ada!"
+        );
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::Changed { version: 1, .. }
+        ));
+
+        replace(&greet, template("Yo {name}!")).await;
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::Changed { version: 2, .. }
+        ));
+        assert_eq!(
+            handle
+                .format("support/greet", &vars!(name = "Ada"))
+                .unwrap(),
+            "Yo Ada!"
+        );
+
+        replace(&greet, "{ not json").await;
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::ReloadFailed { path, .. } if path == greet
+        ));
+        assert_eq!(handle.version("support/greet"), Some(2));
+
+        fs::remove_file(&greet).await.unwrap();
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::Changed { current: None, .. }
+        ));
+        assert!(handle.get("support/greet").is_none());
+        assert!(watcher.is_running());
+        watcher.stop();
+    }
+
+    #[tokio::test]
+    async fn test_watch_reports_each_failure_once() {
+        let root = std::env::temp_dir().join("promptforge_watch_failures");
+        let _ = fs::remove_dir_all(&root).await;
+        fs::create_dir_all(&root).await.unwrap();
+        let greet = root.join("greet.json");
+        fs::write(&greet, r#"{"template": "Hi {name}!", "template_format": "FmtString", "input_variables": ["name"]}"#)
+            .await
+            .unwrap();
+
+        let handle = RegistryHandle::new(PromptRegistry::new());
+        let watcher = handle
+            .watch(&root, Duration::from_millis(10))
+            .await
+            .unwrap();
+        let mut events = handle.subscribe();
+        let settle = || tokio::time::sleep(Duration::from_millis(100));
+
+        // Invalid UTF-8 fails to read; the prompt must survive it.
+        replace(&greet, [0xFF, 0xFE]).await;
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::ReloadFailed { path, .. } if path == greet
+        ));
+        settle().await;
+        assert!(events.try_recv().is_none());
+        assert!(handle.get("greet").is_some());
+
+        replace(&greet, "{ not json").await;
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::ReloadFailed { path, .. } if path == greet
+        ));
+        settle().await;
+        assert!(events.try_recv().is_none());
+
+        replace(&greet, r#"{"template": "Hi {name}!", "template_format": "FmtString", "input_variables": ["name"]}"#)
+            .await;
+        let duplicate = root.join("greet.toml");
+        replace(&duplicate, "template = \"Yo {name}!\"\ntemplate_format = \"FmtString\"\ninput_variables = [\"name\"]")
+            .await;
+        assert!(matches!(
+            next_event(&mut events).await,
+            RegistryEvent::ReloadFailed { path, .. } if path == duplicate
+        ));
+        settle().await;
+        assert!(events.try_recv().is_none());
+        assert_eq!(
+            handle.format("greet", &vars!(name = "Ada")).unwrap(),
+            "Hi Ada!"
+        );
+        watcher.stop();
+    }
+
+    #[tokio::test]
+    async fn test_poll_waits_for_a_stable_read() {
+        let root = std::env::temp_dir().join("promptforge_watch_torn");
+        let _ = fs::remove_dir_all(&root).await;
+        fs::create_dir_all(&root).await.unwrap();
+        let greet = root.join("greet.toml");
+        let source = "template = \"Hi {name}!\"\ntemplate_format = \"FmtString\"\ninput_variables = [\"name\"]";
+        fs::write(&greet, source).await.unwrap();
+
+        let mut state = WatchState {
+            handle: RegistryHandle::new(PromptRegistry::new()),
+            root: root.clone(),
+            files: HashMap::new(),
+            failed: HashMap::new(),
+            pending: HashMap::new(),
+        };
+        assert!(state.poll(false).await.unwrap().is_empty());
+
+        // A read that catches the file half-written is held back, and never
+        // reported once the write completes.
+        fs::write(&greet, "template = \"Yo {na").await.unwrap();
+        assert!(state.poll(true).await.unwrap().is_empty());
+        fs::write(&greet, source.replace("Hi", "Yo")).await.unwrap();
+        assert!(state.poll(true).await.unwrap().is_empty());
+        assert_eq!(state.handle.version("greet"), Some(1));
+        assert!(state.poll(true).await.unwrap().is_empty());
+        assert_eq!(
+            state.handle.format("greet", &vars!(name = "Ada")).unwrap(),
+            "Yo Ada!"
+        );
+
+        fs::write(&greet, "template = ").await.unwrap();
+        assert!(state.poll(true).await.unwrap().is_empty());
+        assert_eq!(state.poll(true).await.unwrap().len(), 1);
+        assert!(state.poll(true).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_watch_rejects_bad_initial_load() {
+        let root = std::env::temp_dir().join("promptforge_watch_bad");
+        let _ = fs::remove_dir_all(&root).await;
+        fs::create_dir_all(&root).await.unwrap();
+        fs::write(root.join("broken.toml"), "template = ")
+            .await
+            .unwrap();
+
+        let handle = RegistryHandle::new(PromptRegistry::new());
+        let err = handle
+            .watch(&root, Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("broken.toml"), "{}", err);
+    }
+}
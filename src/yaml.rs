@@ -0,0 +1,418 @@
+// The block-style subset of YAML that prompt repositories use: mappings,
+// sequences, plain scalars (which may continue on more-indented lines),
+// single-line quoted scalars, `|` and `>` block scalars, single-line flow
+// sequences and the empty mapping `{}`. Anchors, aliases, tags, flow
+// mappings, block scalar indentation indicators, `?` complex keys, tab
+// indentation, multiple documents, and quoted scalars or flow sequences
+// spanning lines are rejected with an error naming the construct rather than
+// guessed at.
+
+use std::path::Path;
+
+use promptforge_syntax::yaml as parser;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{Map, Value};
+use tokio::fs;
+
+use crate::{
+    ChatTemplate, FewShotChatTemplate, FewShotTemplate, Formattable, Templatable, Template,
+    TemplateError,
+};
+
+const INDENT: usize = 2;
+
+pub fn to_yaml_string<T: Serialize + ?Sized>(value: &T) -> Result<String, TemplateError> {
+    let value = serde_json::to_value(value).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("YAML serialization error: {}", e), None)
+    })?;
+    let mut out = String::new();
+    match &value {
+        Value::Object(map) if !map.is_empty() => write_mapping(map, 0, false, &mut out),
+        Value::Array(items) if !items.is_empty() => write_sequence(items, 0, &mut out),
+        scalar => {
+            out.push_str(&inline(scalar));
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+pub fn from_yaml_str<T: DeserializeOwned>(yaml: &str) -> Result<T, TemplateError> {
+    let value =
+        parser::parse(yaml).map_err(|message| TemplateError::MalformedTemplate(message, None))?;
+    serde_json::from_value(value).map_err(|e| {
+        TemplateError::MalformedTemplate(format!("YAML deserialization error: {}", e), None)
+    })
+}
+
+async fn read_yaml_file<P: AsRef<Path>>(path: P) -> Result<String, TemplateError> {
+    fs::read_to_string(path).await.map_err(|e| {
+        TemplateError::MalformedTemplate(format!("Failed to read YAML file: {}", e), None)
+    })
+}
+
+fn pad(indent: usize, out: &mut String) {
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+fn is_plain(text: &str) -> bool {
+    !text.is_empty()
+        && text.trim() == text
+        && !text.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !text.chars().any(char::is_control)
+        && matches!(parser::resolve_plain(text), Value::String(_))
+}
+
+fn inline(value: &Value) -> String {
+    match value {
+        Value::String(text) if is_plain(text) => text.clone(),
+        Value::Array(items) if items.is_empty() => "[]".to_string(),
+        Value::Object(map) if map.is_empty() => "{}".to_string(),
+        // JSON's escapes are all valid in a double-quoted YAML scalar.
+        value => value.to_string(),
+    }
+}
+
+// A literal block keeps multi-line prompts readable; it needs a first line
+// that does not start with whitespace so the indentation can be detected.
+fn write_block_literal(text: &str, indent: usize, out: &mut String) -> bool {
+    if !text.contains('\n')
+        || text.starts_with([' ', '\t', '\n'])
+        || text.chars().any(|c| c.is_control() && c != '\n')
+    {
+        return false;
+    }
+
+    let trailing = text.len() - text.trim_end_matches('\n').len();
+    let (chomp, body) = match trailing {
+        0 => ("-", text),
+        1 => ("", &text[..text.len() - 1]),
+        _ => ("+", &text[..text.len() - 1]),
+    };
+    out.push_str(" |");
+    out.push_str(chomp);
+    out.push('\n');
+    for line in body.split('\n') {
+        if !line.is_empty() {
+            pad(indent, out);
+            out.push_str(line);
+        }
+        out.push('\n');
+    }
+    true
+}
+
+// Writes whatever follows `key:` or `-` on the current line.
+fn write_value(value: &Value, indent: usize, out: &mut String) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            write_mapping(map, indent + INDENT, false, out);
+        }
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            write_sequence(items, indent + INDENT, out);
+        }
+        Value::String(text) if write_block_literal(text, indent + INDENT, out) => {}
+        scalar => {
+            out.push(' ');
+            out.push_str(&inline(scalar));
+            out.push('\n');
+        }
+    }
+}
+
+fn write_mapping(map: &Map<String, Value>, indent: usize, first_inline: bool, out: &mut String) {
+    for (index, (key, value)) in map.iter().enumerate() {
+        if index > 0 || !first_inline {
+            pad(indent, out);
+        }
+        out.push_str(&inline(&Value::String(key.clone())));
+        out.push(':');
+        write_value(value, indent, out);
+    }
+}
+
+fn write_sequence(items: &[Value], indent: usize, out: &mut String) {
+    for item in items {
+        pad(indent, out);
+        out.push('-');
+        match item {
+            Value::Object(map) if !map.is_empty() => {
+                out.push(' ');
+                write_mapping(map, indent + INDENT, true, out);
+            }
+            item => write_value(item, indent, out),
+        }
+    }
+}
+
+impl Template {
+    // Rebuilt from its source so the Mustache and Jinja engines are ready.
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, TemplateError> {
+        let template: Template = from_yaml_str(yaml)?;
+        template.with_source(
+            template.template(),
+            template.template_format(),
+            template.input_variables(),
+        )
+    }
+
+    pub async fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        Self::from_yaml_str(&read_yaml_file(path).await?)
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, TemplateError> {
+        to_yaml_string(self)
+    }
+}
+
+impl ChatTemplate {
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, TemplateError> {
+        from_yaml_str(yaml)
+    }
+
+    pub async fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        Self::from_yaml_str(&read_yaml_file(path).await?)
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, TemplateError> {
+        to_yaml_string(self)
+    }
+}
+
+impl<T> FewShotTemplate<T>
+where
+    T: Templatable + Formattable + Serialize + DeserializeOwned,
+{
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, TemplateError> {
+        from_yaml_str(yaml)
+    }
+
+    pub async fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        Self::from_yaml_str(&read_yaml_file(path).await?)
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, TemplateError> {
+        to_yaml_string(self)
+    }
+}
+
+impl FewShotChatTemplate {
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, TemplateError> {
+        from_yaml_str(yaml)
+    }
+
+    pub async fn from_yaml_file<P: AsRef<Path>>(path: P) -> Result<Self, TemplateError> {
+        Self::from_yaml_str(&read_yaml_file(path).await?)
+    }
+
+    pub fn to_yaml_string(&self) -> Result<String, TemplateError> {
+        to_yaml_string(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::Role::{Ai, Human, System};
+    use crate::{chats, examples, vars};
+
+    #[test]
+    fn test_parses_block_yaml() {
+        let yaml = r#"
+---
+# A prompt file
+name: summarize   # trailing comment
+version: 2
+ratio: 0.5
+enabled: true
+missing: ~
+tags: [docs, "a, b", 3]
+limits:
+  max: 10
+  label: 'it''s'
+system: |
+  You are terse.
+
+  Never apologise.
+note: >-
+  folded
+  text
+steps:
+  - plain item
+  - role: human
+    content: "Hi {name}\n"
+  -
+    - nested
+"#;
+        let value: Value = from_yaml_str(yaml).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "name": "summarize",
+                "version": 2,
+                "ratio": 0.5,
+                "enabled": true,
+                "missing": null,
+                "tags": ["docs", "a, b", 3],
+                "limits": {"max": 10, "label": "it's"},
+                "system": "You are terse.\n\nNever apologise.\n",
+                "note": "folded text",
+                "steps": [
+                    "plain item",
+                    {"role": "human", "content": "Hi {name}\n"},
+                    ["nested"]
+                ]
+            })
+        );
+
+        for bad in [
+            "a: 1\na: 2",
+            "key: &anchor x",
+            "a: 1\n---\nb: 2",
+            "a: \"open",
+        ] {
+            assert!(from_yaml_str::<Value>(bad).is_err(), "{}", bad);
+        }
+    }
+
+    fn assert_rejects(yaml: &str, construct: &str) {
+        let error = from_yaml_str::<Value>(yaml).unwrap_err();
+        assert!(
+            matches!(&error, TemplateError::MalformedTemplate(message, None)
+                if message.contains(&format!("{} are not supported", construct))),
+            "{}: {}",
+            yaml,
+            error
+        );
+    }
+
+    #[test]
+    fn test_rejects_anchors() {
+        assert_rejects("base: &defaults x\nother: y", "anchors");
+        assert_rejects("tags: [a, &first b]", "anchors");
+        assert_rejects("base: x\nother: *defaults", "aliases");
+    }
+
+    #[test]
+    fn test_rejects_flow_mappings() {
+        assert_rejects("limits: {max: 10}", "flow mappings");
+        assert_rejects("items: [{a: 1}]", "flow mappings");
+    }
+
+    #[test]
+    fn test_rejects_block_scalar_indentation_indicators() {
+        assert_rejects(
+            "system: |2\n    indented",
+            "block scalar indentation indicators",
+        );
+        assert_rejects("note: >-1\n folded", "block scalar indentation indicators");
+    }
+
+    #[test]
+    fn test_rejects_tags() {
+        assert_rejects("count: !!int 3", "tags");
+        assert_rejects("!custom key: value", "tags");
+    }
+
+    #[test]
+    fn test_folds_multi_line_plain_scalars() {
+        let yaml = "
+t: plain
+  continued
+    further
+
+  after a blank   # comment
+steps:
+  - first
+    item
+  - second
+template: Hi {name},
+  how are you?
+";
+        let value: Value = from_yaml_str(yaml).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "t": "plain continued further\nafter a blank",
+                "steps": ["first item", "second"],
+                "template": "Hi {name}, how are you?"
+            })
+        );
+
+        let template = Template::from_yaml_str(
+            "template: Hello {name},\n  welcome aboard.\ntemplate_format: FmtString\ninput_variables: [name]",
+        )
+        .unwrap();
+        assert_eq!(
+            template.format(&vars!(name = "Ada")).unwrap(),
+            "Hello Ada, welcome aboard."
+        );
+
+        for bad in ["t: plain # comment\n  continued", "t: \"open\n  quote\""] {
+            assert!(from_yaml_str::<Value>(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_round_trips_every_template_type() {
+        let value = json!({
+            "text": "Line one\n  indented\n\nend\n\n",
+            "strip": "no newline\nat end",
+            "quoted": ["{name}", "- dash", "true", "12", "", " padded", "a: b", "tab\tin"],
+            "empty": {"list": [], "map": {}},
+            "nested": [[1, 2], {"k": null}]
+        });
+        let yaml = to_yaml_string(&value).unwrap();
+        assert_eq!(from_yaml_str::<Value>(&yaml).unwrap(), value);
+
+        let mut template = Template::new("Hi {{name}}, you are {{age}}.").unwrap();
+        template.partial("age", "30");
+        let restored = Template::from_yaml_str(&template.to_yaml_string().unwrap()).unwrap();
+        assert_eq!(
+            restored.format(&vars!(name = "Ada")).unwrap(),
+            "Hi Ada, you are 30."
+        );
+
+        let chat_template = ChatTemplate::from_messages(chats!(
+            System = "Be brief.\nAnswer in {language}.",
+            Human = "{question}",
+        ))
+        .unwrap();
+        let restored =
+            ChatTemplate::from_yaml_str(&chat_template.to_yaml_string().unwrap()).unwrap();
+        let variables = vars!(language = "French", question = "Why?");
+        assert_eq!(
+            restored.format(&variables).unwrap(),
+            chat_template.format(&variables).unwrap()
+        );
+
+        let few_shot = FewShotTemplate::new(examples!(("Q: 2+2?", "A: 4"), ("Q: 3+3?", "A: 6")));
+        let restored: FewShotTemplate<Template> =
+            FewShotTemplate::from_yaml_str(&few_shot.to_yaml_string().unwrap()).unwrap();
+        assert_eq!(
+            restored.format(&vars!()).unwrap(),
+            few_shot.format(&vars!()).unwrap()
+        );
+
+        let few_shot_chat = FewShotChatTemplate::new(
+            few_shot,
+            ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap(),
+        );
+        let restored =
+            FewShotChatTemplate::from_yaml_str(&few_shot_chat.to_yaml_string().unwrap()).unwrap();
+        assert_eq!(
+            restored.format_examples().unwrap(),
+            few_shot_chat.format_examples().unwrap()
+        );
+    }
+}
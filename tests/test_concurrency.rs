@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::thread;
+
+use promptforge::Role::{Ai, FewShotPrompt, Human, System};
+use promptforge::{
+    chats, examples, ChatTemplate, FewShotChatTemplate, FewShotTemplate, Formattable, Template,
+};
+
+const THREADS: usize = 8;
+const ITERATIONS: usize = 100;
+
+fn stress<T, F>(shared: Arc<T>, check: F)
+where
+    T: Send + Sync + 'static,
+    F: Fn(&T, usize, usize) + Send + Sync + Copy + 'static,
+{
+    let handles: Vec<_> = (0..THREADS)
+        .map(|thread_id| {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || {
+                for iteration in 0..ITERATIONS {
+                    check(&shared, thread_id, iteration);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().expect("formatting thread panicked");
+    }
+}
+
+#[test]
+fn test_concurrent_fmtstring_format() {
+    let template = Arc::new(Template::new("Hello, {name}! You are caller {id}.").unwrap());
+
+    stress(template, |template, thread_id, iteration| {
+        let name = format!("thread-{}", thread_id);
+        let id = iteration.to_string();
+        let mut variables = HashMap::new();
+        variables.insert("name", name.as_str());
+        variables.insert("id", id.as_str());
+
+        let output = template.format(&variables).unwrap();
+        assert_eq!(output, format!("Hello, {}! You are caller {}.", name, id));
+    });
+}
+
+#[test]
+fn test_concurrent_mustache_format() {
+    let template = Arc::new(Template::new("Hello, {{name}}! You are caller {{id}}.").unwrap());
+
+    stress(template, |template, thread_id, iteration| {
+        let name = format!("thread-{}", thread_id);
+        let id = iteration.to_string();
+        let mut variables = HashMap::new();
+        variables.insert("name", name.as_str());
+        variables.insert("id", id.as_str());
+
+        let output = template.format(&variables).unwrap();
+        assert_eq!(output, format!("Hello, {}! You are caller {}.", name, id));
+    });
+}
+
+#[test]
+fn test_concurrent_chat_template_format() {
+    let examples = examples!(
+        ("{input}: What is 2+2?", "{output}: 4"),
+        ("{input}: What is 2+3?", "{output}: 5")
+    );
+    let example_prompt =
+        ChatTemplate::from_messages(chats!(Human = "{input}", Ai = "{output}")).unwrap();
+    let few_shot = FewShotChatTemplate::new(FewShotTemplate::new(examples), example_prompt);
+
+    let chat_template = Arc::new(
+        ChatTemplate::from_messages(chats!(
+            System = "You are a helpful AI Assistant.",
+            FewShotPrompt = few_shot.to_string(),
+            Human = "{input}",
+        ))
+        .unwrap(),
+    );
+
+    stress(chat_template, |chat_template, thread_id, iteration| {
+        let input = format!("What is {}+{}?", thread_id, iteration);
+        let mut variables = HashMap::new();
+        variables.insert("input", input.as_str());
+
+        let output = chat_template.format(&variables).unwrap();
+        let expected = format!(
+            "system: You are a helpful AI Assistant.\n\
+             human: What is 2+2?\nai: 4\nhuman: What is 2+3?\nai: 5\nhuman: {}",
+            input
+        );
+        assert_eq!(output, expected);
+    });
+}
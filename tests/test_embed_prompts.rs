@@ -0,0 +1,74 @@
+use promptforge::{
+    embed_prompts, ChatTemplate, Formattable, PromptLibrary, Template, TemplateError,
+};
+
+#[test]
+fn test_embedded_library_holds_every_file() -> Result<(), TemplateError> {
+    let library: &'static PromptLibrary = embed_prompts!("tests/data/embedded")?;
+    assert_eq!(
+        library.names().collect::<Vec<_>>(),
+        ["support/triage", "welcome"]
+    );
+
+    let welcome = library.require::<Template>("welcome")?;
+    let vars = [("name", "Ada"), ("product", "Forge")]
+        .into_iter()
+        .collect();
+    assert_eq!(welcome.format(&vars)?, "Hi Ada, welcome to Forge!");
+    Ok(())
+}
+
+#[test]
+fn test_embedded_chat_template_formats() {
+    let triage: &ChatTemplate = embed_prompts!("tests/data/embedded")
+        .unwrap()
+        .get("support/triage")
+        .unwrap();
+    let vars = [("team", "billing"), ("ticket", "Refund please")]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        triage.format(&vars).unwrap(),
+        "system: You triage tickets for billing.\nhuman: Refund please"
+    );
+}
+
+#[test]
+fn test_embedded_brace_literals_render_as_written() {
+    let snippet: &Template = embed_prompts!(
+        "tests/data/embedded_literals",
+        literals = [programming, "{{"]
+    )
+    .unwrap()
+    .get("snippet")
+    .unwrap();
+    let vars = [("name", "Ada")].into_iter().collect();
+    assert_eq!(
+        snippet.format(&vars).unwrap(),
+        "Hi Ada, Rust writes a literal brace as {{ in format strings"
+    );
+}
+
+#[cfg(feature = "yaml")]
+#[test]
+fn test_embedded_yaml_prompts() {
+    let summary: &Template = embed_prompts!("tests/data/embedded_yaml")
+        .unwrap()
+        .get("summary")
+        .unwrap();
+    let vars = [("text", "the report"), ("words", "ten")]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        summary.format(&vars).unwrap(),
+        "Summarize the report in ten words."
+    );
+}
+
+#[test]
+fn test_embedded_library_reports_load_failures() {
+    // The braces pair up, so the build accepts the file, but the Jinja
+    // block is never closed.
+    let error = embed_prompts!("tests/data/embedded_unloadable").unwrap_err();
+    assert!(error.to_string().contains("greeting"), "{}", error);
+}
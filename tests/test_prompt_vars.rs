@@ -0,0 +1,60 @@
+use promptforge::{PromptVars, Template};
+
+const GREETING: &str = "Hello {name}, your order {order_id} ships {eta:soon}.";
+
+#[derive(PromptVars)]
+#[prompt(template = GREETING)]
+struct Greeting<'a> {
+    name: &'a str,
+    order_id: u32,
+}
+
+#[derive(PromptVars)]
+#[prompt(template = "Summarize {document} for {audience}.")]
+struct Summary {
+    document: String,
+    #[prompt(rename = "audience")]
+    reader: String,
+}
+
+#[test]
+fn test_derived_fields_and_into_vars() {
+    assert_eq!(Greeting::FIELDS, &["name", "order_id"]);
+
+    let vars = Greeting {
+        name: "Ada",
+        order_id: 42,
+    }
+    .into_vars();
+    assert_eq!(vars["name"], "Ada");
+    assert_eq!(vars["order_id"], "42");
+}
+
+#[test]
+fn test_format_vars_with_const_template() {
+    let tmpl = Template::new(GREETING).unwrap();
+    let greeting = Greeting {
+        name: "Ada",
+        order_id: 42,
+    };
+
+    assert_eq!(
+        tmpl.format_vars(greeting).unwrap(),
+        "Hello Ada, your order 42 ships soon."
+    );
+}
+
+#[test]
+fn test_renamed_field() {
+    assert_eq!(Summary::FIELDS, &["document", "audience"]);
+
+    let tmpl = Template::new("Summarize {document} for {audience}.").unwrap();
+    let summary = Summary {
+        document: "the report".to_string(),
+        reader: "executives".to_string(),
+    };
+    assert_eq!(
+        tmpl.format_vars(summary).unwrap(),
+        "Summarize the report for executives."
+    );
+}